@@ -0,0 +1,120 @@
+//! Editor-only notes placed on annotation layers
+//!
+//! Annotations are the notes & markup left on a map while designing it, such
+//! as "bridge goes here" or "needs more trees".  They are saved with the map,
+//! but left out of every export.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::map::{self, Location};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup)
+            .add_system(update_annotation_marker);
+    }
+}
+
+/// a note placed on a hex of an annotation layer
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub location: Location,
+    pub text: String,
+    /// color of the marker drawn for the note
+    pub color: Color,
+}
+
+impl Annotation {
+    pub fn new(location: Location, text: impl Into<String>) -> Self {
+        Self {
+            location,
+            text: text.into(),
+            color: Color::YELLOW,
+        }
+    }
+}
+
+/// mesh drawn for every annotation
+#[derive(Resource)]
+struct MarkerMesh(Handle<Mesh>);
+
+/// marker drawn above an annotation, with the color it is drawn in
+#[derive(Component, Debug)]
+struct Marker(Color);
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(MarkerMesh(
+        meshes.add(Mesh::from(shape::Box::new(0.3, 0.05, 0.3))),
+    ));
+}
+
+/// move & recolor the marker of annotations that changed
+///
+/// Markers of the same color share one material, which is dropped once the
+/// annotations change and none of them uses the color any more.
+fn update_annotation_marker(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut colors: Local<HashMap<[u32; 4], Handle<StandardMaterial>>>,
+    marker: Res<MarkerMesh>,
+    map: Query<&map::Map>,
+    mut annotations: Query<
+        (Entity, &Annotation, &mut Transform, Option<&Children>),
+        Changed<Annotation>,
+    >,
+    mut markers: Query<(&mut Marker, &mut Handle<StandardMaterial>)>,
+    all: Query<&Annotation>,
+) {
+    let Ok(map) = map.get_single() else { return };
+    if annotations.is_empty() {
+        return;
+    }
+    for (entity, annotation, mut transform, children) in &mut annotations {
+        transform.translation = map.translation(annotation.location);
+        let material = colors
+            .entry(annotation.color.as_rgba_f32().map(f32::to_bits))
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: annotation.color,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone();
+
+        // only the color of an existing marker can change
+        let existing = children
+            .iter()
+            .flat_map(|children| children.iter())
+            .find(|child| markers.contains(**child));
+        if let Some(child) = existing {
+            let (mut color, mut handle) = markers.get_mut(*child).unwrap();
+            if color.0 != annotation.color {
+                color.0 = annotation.color;
+                *handle = material;
+            }
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Marker(annotation.color),
+                PbrBundle {
+                    mesh: marker.0.clone(),
+                    material,
+                    transform: Transform::from_xyz(0.0, 0.6, 0.0),
+                    ..default()
+                },
+            ));
+        });
+    }
+
+    // drop the materials of colors no annotation uses any more
+    let used: HashSet<[u32; 4]> = all
+        .iter()
+        .map(|annotation| annotation.color.as_rgba_f32().map(f32::to_bits))
+        .collect();
+    colors.retain(|color, _| used.contains(color));
+}
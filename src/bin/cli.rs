@@ -0,0 +1,203 @@
+//! command-line tools for working with maps outside of the editor
+use anyhow::{Context, Result};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+use hex_sandbox::{
+    export,
+    persistence::{binary, schema, FileFormat, MapFormat},
+    scatter,
+};
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "cli", about = "hex sandbox map tools")]
+enum Cli {
+    /// export a map to a single glTF scene
+    ExportGltf {
+        /// map to export
+        #[structopt(parse(from_os_str))]
+        map: PathBuf,
+        /// output path; writes binary glTF for `.glb`, otherwise `.gltf` with
+        /// a `.bin` buffer alongside it
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// directory tile paths are relative to; defaults to the bevy asset
+        /// directory
+        #[structopt(long, parse(from_os_str))]
+        assets: Option<PathBuf>,
+    },
+    /// export a top-down schematic of a map as an SVG
+    ExportSvg {
+        /// map to export
+        #[structopt(parse(from_os_str))]
+        map: PathBuf,
+        /// output path
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// do not label tiles with their names
+        #[structopt(long)]
+        no_names: bool,
+        /// label tiles with their locations
+        #[structopt(long)]
+        coordinates: bool,
+        /// do not draw the hex grid
+        #[structopt(long)]
+        no_grid: bool,
+        /// pixels per world unit
+        #[structopt(long, default_value = "32")]
+        scale: f32,
+    },
+    /// convert a map between RON, JSON, & binary formats; the formats are
+    /// determined by the file extensions
+    Convert {
+        /// map to convert
+        #[structopt(parse(from_os_str))]
+        input: PathBuf,
+        /// output path; `.ron`, `.json`, or `.hexmap`
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// do not compress binary output
+        #[structopt(long)]
+        uncompressed: bool,
+    },
+    /// scatter decoration tiles onto a layer using a saved preset, or rules
+    /// from a file
+    Scatter {
+        /// map to decorate
+        #[structopt(parse(from_os_str))]
+        map: PathBuf,
+        /// output path; may be the same as the map
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+        /// layer containing the tiles the rules match
+        #[structopt(long)]
+        source: String,
+        /// layer the decorations are placed on; created if it doesn't exist
+        #[structopt(long)]
+        target: String,
+        /// name of a saved preset
+        #[structopt(long, required_unless = "rules")]
+        preset: Option<String>,
+        /// file containing the rules of one preset, in RON or JSON
+        #[structopt(long, parse(from_os_str), conflicts_with = "preset")]
+        rules: Option<PathBuf>,
+        /// presets file; defaults to the one in the user library
+        #[structopt(long, parse(from_os_str))]
+        presets: Option<PathBuf>,
+        /// seed to use instead of the seed saved with the rules
+        #[structopt(long)]
+        seed: Option<u64>,
+    },
+    /// write the JSON Schemas for the map & tileset formats
+    Schema {
+        /// directory to write the schemas to
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+    },
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    match Cli::from_args() {
+        Cli::ExportGltf {
+            map,
+            output,
+            assets,
+        } => {
+            let map_format = MapFormat::load(&map).context(format!("load map {:?}", map))?;
+            let assets = assets.unwrap_or_else(export::asset_dir);
+            export::gltf::export(&map_format, &assets, &output)
+                .context(format!("export {:?}", output))?;
+        }
+        Cli::ExportSvg {
+            map,
+            output,
+            no_names,
+            coordinates,
+            no_grid,
+            scale,
+        } => {
+            let map_format = MapFormat::load(&map).context(format!("load map {:?}", map))?;
+            let options = export::svg::SvgOptions {
+                tile_names: !no_names,
+                coordinates,
+                grid: !no_grid,
+                scale,
+            };
+            export::svg::export(&map_format, &options, &output)
+                .context(format!("export {:?}", output))?;
+        }
+        Cli::Convert {
+            input,
+            output,
+            uncompressed,
+        } => {
+            let map_format = MapFormat::load(&input).context(format!("load map {:?}", input))?;
+            if uncompressed && FileFormat::from_path(&output) == FileFormat::Binary {
+                let f = File::create(&output).context(format!("create {:?}", output))?;
+                let mut writer = BufWriter::new(f);
+                binary::write(&map_format, &mut writer, binary::Compression::None)
+                    .context(format!("write {:?}", output))?;
+                writer.flush()?;
+            } else {
+                map_format
+                    .save(&output)
+                    .context(format!("write {:?}", output))?;
+            }
+        }
+        Cli::Scatter {
+            map,
+            output,
+            source,
+            target,
+            preset,
+            rules,
+            presets,
+            seed,
+        } => {
+            let mut scatter = match (preset, rules) {
+                (_, Some(path)) => {
+                    let buf = std::fs::read_to_string(&path).context(format!("read {:?}", path))?;
+                    FileFormat::from_path(&path)
+                        .deserialize::<scatter::Scatter>(&buf)
+                        .context(format!("parse {:?}", path))?
+                }
+                (Some(name), None) => {
+                    let path = presets.unwrap_or_else(scatter::presets_path);
+                    let presets = scatter::Presets::load(&path)?;
+                    presets
+                        .get(&name)
+                        .context(format!("preset not found in {:?}: {}", path, name))?
+                        .clone()
+                }
+                (None, None) => unreachable!("structopt requires --preset or --rules"),
+            };
+            if let Some(seed) = seed {
+                scatter.seed = seed;
+            }
+
+            let mut map_format = MapFormat::load(&map).context(format!("load map {:?}", map))?;
+            let count = scatter
+                .apply(&mut map_format, &source, &target)
+                .context(format!("scatter onto layer {}", target))?;
+            map_format
+                .save(&output)
+                .context(format!("write {:?}", output))?;
+            println!("placed {} tiles on layer {}", count, target);
+        }
+        Cli::Schema { dir } => {
+            std::fs::create_dir_all(&dir).context(format!("create {:?}", dir))?;
+            for (name, schema) in schema::schemas() {
+                let path = dir.join(name);
+                let buf = serde_json::to_string_pretty(&schema)? + "\n";
+                std::fs::write(&path, buf).context(format!("write {:?}", path))?;
+            }
+        }
+    }
+    Ok(())
+}
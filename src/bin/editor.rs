@@ -11,7 +11,11 @@ use bevy_mod_picking::prelude::*;
 use bevy_mod_sysfail::macros::*;
 use leafwing_input_manager::prelude::*;
 
-use hex_sandbox::{file_picker, map, persistence, prelude::*, tileset};
+use hex_sandbox::{
+    annotation, brush, export, file_picker, map, object, persistence, prelude::*, region, replace,
+    scatter, selection, stamp, tiled, tileset, tool, watch,
+};
+use std::collections::{HashMap, HashSet};
 
 mod editor_ui;
 use editor_ui as ui;
@@ -37,9 +41,21 @@ fn main() -> Result<()> {
     .add_plugin(hex_sandbox::thumbnail_render::Plugin)
     .add_plugin(tileset::Plugin)
     .add_plugin(map::Plugin)
+    .add_plugin(object::Plugin)
+    .add_plugin(annotation::Plugin)
+    .add_plugin(region::Plugin)
     .add_plugin(persistence::Plugin)
+    .add_plugin(stamp::Plugin)
+    .add_plugin(tool::Plugin)
+    .add_plugin(watch::Plugin)
     .insert_resource(EditorState::default())
     .insert_resource(TileSelection::default())
+    .insert_resource(StampLibrary::default())
+    .insert_resource(StampBrush::default())
+    .insert_resource(ToolState::default())
+    .insert_resource(MapSelection::default())
+    .insert_resource(brush::RandomBrush::default())
+    .insert_resource(EditHistory::default())
     .add_event::<PickerEvent>()
     .add_event::<EditorUiEvent>()
     .add_event::<MapCursorMoveEvent>()
@@ -56,10 +72,27 @@ fn main() -> Result<()> {
         handle_ui_events,
         handle_picker_events,
         handle_map_cursor_events,
+        eyedropper.after(handle_input),
+        apply_tool.after(handle_input),
         hex_sandbox::ui::draw_confirmation_dialog::<EditorUiEvent>,
         // update_cursor,
         update_cursor_model,
         map_loaded,
+    ))
+    .add_systems((
+        load_stamp_library,
+        update_stamp_preview,
+        place_stamp.after(handle_input),
+        remove_stamp_tilesets,
+        place_object.after(handle_input),
+        place_annotation.after(handle_input),
+        capture_stamp.after(handle_input),
+        update_selection_outline.after(apply_tool),
+        apply_region_tool.after(handle_input),
+        follow_active_layer,
+        remove_scatter_preview,
+        watch_map_file,
+        tileset_reloaded,
     ));
 
     // XXX to help debug leafwing/egui ordering issue
@@ -191,13 +224,44 @@ fn setup(
         OnPointer::<Move>::send_event::<MapCursorMoveEvent>(),
     ));
 
-    // and a cursor
-    commands.spawn((
-        Name::new("map_cursor"),
-        MapCursor::default(),
-        tileset::TileTransform::default(),
-        SpatialBundle::default(),
-    ));
+    // hex used to preview tiles the active tool will erase
+    commands.insert_resource(ToolPreviewAssets {
+        mesh: meshes.add(Mesh::from(shape::RegularPolygon::new(0.9, 6))),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.9, 0.1, 0.1, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        select_material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.3, 0.7, 1.0, 0.8),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+        selection_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(1.0, 0.8, 0.1),
+            unlit: true,
+            ..default()
+        }),
+    });
+
+    // and a cursor; it and the tool previews are children of the cursor root
+    // so they are drawn at the height of the active layer
+    commands
+        .spawn((
+            Name::new("cursor_root"),
+            CursorRoot,
+            SpatialBundle::default(),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                Name::new("map_cursor"),
+                MapCursor::default(),
+                tileset::TileTransform::default(),
+                SpatialBundle::default(),
+            ));
+        });
 }
 
 fn inspector_enabled(state: Res<EditorState>) -> bool {
@@ -207,18 +271,39 @@ fn inspector_enabled(state: Res<EditorState>) -> bool {
 #[derive(Resource, Debug)]
 struct EditorState {
     // UI elements
-    inspector: bool,           // display World Inspector
-    right_panel: bool,         // display left panel
-    egui_visuals_window: bool, // display egui visuals window
-    properties_window: bool,   // show the properties window
-    egui_debug: bool,          // show the egui debugging window
-    new_tileset_window: bool,  // show create tileset window
+    inspector: bool,                           // display World Inspector
+    right_panel: bool,                         // display left panel
+    egui_visuals_window: bool,                 // display egui visuals window
+    properties_window: bool,                   // show the properties window
+    egui_debug: bool,                          // show the egui debugging window
+    new_tileset_window: bool,                  // show create tileset window
+    import_layers: Option<std::path::PathBuf>, // show import layers window for map
+    stamp_capture: bool,                       // dragging on the map selects a stamp
+    new_stamp: Option<Vec<Entity>>,            // show save stamp window for tiles
+    find_replace_window: bool,                 // show find & replace window
+    objects_window: bool,                      // show the objects window
+    annotations_window: bool,                  // show the annotations window
+    placed_tiles_window: bool,                 // show the placed tiles window
+    random_brush_window: bool,                 // show the random brush window
+    scatter_window: bool,                      // show the scatter window
+    property_schema_window: Option<Entity>,    // show property schema window for tileset
+    // show the tileset from folder window; the tileset to rescan, or None for a new tileset
+    tileset_folder: Option<(Option<Entity>, std::path::PathBuf)>,
+    // show the results of the last folder scan
+    folder_scan: Option<(Entity, tileset::FolderScan)>,
+    // ask to reload the map changed on disk; the path & a summary of the changes
+    map_changed: Option<(std::path::PathBuf, Vec<String>)>,
+    // show the remove dialog for a tileset or some of its tiles
+    remove_tiles: Option<TileRemoval>,
 
     //editor state
     map_path: Option<std::path::PathBuf>, // current loaded map path
     unsaved_changes: bool,                // tracks if there are unsaved changes
     active_layer: Option<Entity>,         // selected layer in the ui
     active_tileset: Option<Entity>,       // active tileset
+    active_object: Option<Entity>,        // object shown in the objects window
+    active_annotation: Option<Entity>,    // note shown in the annotations window
+    active_region: Option<Entity>,        // region edited by the map tools
 }
 
 impl Default for EditorState {
@@ -230,9 +315,26 @@ impl Default for EditorState {
             properties_window: true,
             egui_debug: false,
             new_tileset_window: false,
+            import_layers: None,
+            stamp_capture: false,
+            new_stamp: None,
+            find_replace_window: false,
+            objects_window: false,
+            annotations_window: false,
+            placed_tiles_window: false,
+            random_brush_window: false,
+            scatter_window: false,
+            property_schema_window: None,
+            tileset_folder: None,
+            folder_scan: None,
+            map_changed: None,
+            remove_tiles: None,
             map_path: None,
             active_tileset: None,
             active_layer: None,
+            active_object: None,
+            active_annotation: None,
+            active_region: None,
             unsaved_changes: false,
         }
     }
@@ -254,6 +356,8 @@ enum EditorSelection {
 #[derive(Resource, Default, Debug)]
 struct TileSelection {
     tiles: std::collections::HashSet<tileset::TileRef>,
+    /// tile the TilePicker should scroll to the next time it is drawn
+    scroll_to: Option<tileset::TileRef>,
 }
 
 impl TileSelection {
@@ -268,8 +372,56 @@ enum EditorUiEvent {
     MapClose,
     MapSave(std::path::PathBuf),
     MapLoad(std::path::PathBuf),
+    /// the open map file was changed by another program
+    MapChanged(std::path::PathBuf),
+    /// discard the open map and load it again from disk
+    MapReload(std::path::PathBuf),
     MapSaveAs,
-    DeleteTileset(Entity),
+    MapImportTmx {
+        tmx: std::path::PathBuf,
+        mapping: std::path::PathBuf,
+    },
+    MapImportLayers {
+        path: std::path::PathBuf,
+        offset: map::Location,
+        rotation: tileset::TileRotation,
+    },
+    MapExportGltf(std::path::PathBuf),
+    MapExportSvg(std::path::PathBuf),
+    /// remove tiles from a tileset, deleting the placed tiles using them, or
+    /// reassigning them to the tiles with the same name in another tileset
+    RemoveTiles {
+        removal: TileRemoval,
+        reassign: Option<Entity>,
+    },
+    /// tiles that are no longer in a tileset, such as after reloading the
+    /// tileset file; forget them wherever they are used
+    TilesRemoved(TileRemoval),
+    DuplicateLayer(Entity),
+    DeleteLayer(Entity),
+    DeleteObject(Entity),
+    DeleteRegion(Entity),
+    StampSelect(std::path::PathBuf),
+    StampDeselect,
+    StampSave {
+        name: String,
+        tiles: Vec<Entity>,
+    },
+    SelectionRotate(tileset::TileRotation),
+    SelectionDelete,
+    SelectionMoveToLayer(Entity),
+    SelectionClear,
+    Undo,
+    Redo,
+    ReplaceTiles {
+        replace: replace::TileReplace,
+        scope: ReplaceScope,
+    },
+    ScatterTiles {
+        scatter: scatter::Scatter,
+        source: Entity,
+        target: Entity,
+    },
     // UpdateSelection(EditorSelection),
     RedrawMapTiles,
 }
@@ -299,8 +451,18 @@ enum PickerEvent {
     },
     MapSave(Option<std::path::PathBuf>),
     MapLoad(Option<std::path::PathBuf>),
+    TmxImport(Option<std::path::PathBuf>),
+    TmxMapping {
+        tmx: std::path::PathBuf,
+        mapping: Option<std::path::PathBuf>,
+    },
+    ImportLayers(Option<std::path::PathBuf>),
+    GltfExport(Option<std::path::PathBuf>),
+    SvgExport(Option<std::path::PathBuf>),
     TilesetImport(Option<Vec<std::path::PathBuf>>),
     TilesetExport(Entity, Option<std::path::PathBuf>),
+    /// folder of models to create a tileset from, or to rescan into a tileset
+    TilesetFolder(Option<Entity>, Option<std::path::PathBuf>),
 }
 
 impl file_picker::PickerEvent for PickerEvent {
@@ -314,18 +476,132 @@ impl file_picker::PickerEvent for PickerEvent {
             },
             MapSave(_) => MapSave(Some(result[0].clone())),
             MapLoad(_) => MapLoad(Some(result[0].clone())),
+            TmxImport(_) => TmxImport(Some(result[0].clone())),
+            TmxMapping { ref tmx, .. } => TmxMapping {
+                tmx: tmx.clone(),
+                mapping: Some(result[0].clone()),
+            },
+            ImportLayers(_) => ImportLayers(Some(result[0].clone())),
+            GltfExport(_) => GltfExport(Some(result[0].clone())),
+            SvgExport(_) => SvgExport(Some(result[0].clone())),
             TilesetImport(_) => TilesetImport(Some(result)),
             TilesetExport(t, _) => TilesetExport(t, Some(result[0].clone())),
+            TilesetFolder(t, _) => TilesetFolder(t, Some(result[0].clone())),
         };
     }
 }
 
+/// stamps loaded from the stamp library directory
+#[derive(Resource, Debug)]
+struct StampLibrary {
+    dir: std::path::PathBuf,
+    stamps: Vec<LibraryStamp>,
+    dirty: bool, // reload the library
+}
+
+impl Default for StampLibrary {
+    fn default() -> Self {
+        Self {
+            dir: stamp::library_dir(),
+            stamps: Vec::new(),
+            dirty: true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LibraryStamp {
+    path: std::path::PathBuf,
+    stamp: stamp::Stamp,
+    image: Handle<Image>,
+    texture: egui::TextureId,
+}
+
+/// stamp used as the cursor brush; tile locations are relative to the cursor
+#[derive(Resource, Default, Debug)]
+struct StampBrush {
+    path: Option<std::path::PathBuf>,
+    tiles: Vec<(
+        map::Location,
+        tileset::TileRef,
+        tileset::TileRotation,
+        tileset::TileOverrides,
+    )>,
+    /// tilesets added to the map to preview the stamp; removed again by
+    /// `remove_stamp_tilesets` unless the stamp is placed
+    tilesets: Vec<Entity>,
+}
+
+/// tile showing where a tile from the `StampBrush` will be placed; the value
+/// is the index into `StampBrush.tiles`
+#[derive(Component, Debug)]
+struct StampPreview(usize);
+
+/// tiles on the map selected by the selection tools
+#[derive(Resource, Default, Debug)]
+struct MapSelection {
+    tiles: HashSet<Entity>,
+}
+
+/// tiles searched by find & replace
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ReplaceScope {
+    #[default]
+    ActiveLayer,
+    AllLayers,
+    Selection,
+}
+
+impl ReplaceScope {
+    fn label(&self) -> &'static str {
+        match self {
+            ReplaceScope::ActiveLayer => "Active Layer",
+            ReplaceScope::AllLayers => "All Layers",
+            ReplaceScope::Selection => "Selection",
+        }
+    }
+}
+
+/// tiles to remove from a tileset; every tile when `tiles` is None, which also
+/// removes the tileset
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TileRemoval {
+    tileset: Entity,
+    tiles: Option<Vec<tileset::TileId>>,
+}
+
+impl TileRemoval {
+    fn removes(&self, tile_ref: &tileset::TileRef) -> bool {
+        tile_ref.tileset == self.tileset
+            && match &self.tiles {
+                Some(tiles) => tiles.contains(&tile_ref.tile),
+                None => true,
+            }
+    }
+
+    /// the tiles removed from `tileset`
+    fn tile_ids(&self, tileset: &tileset::Tileset) -> Vec<tileset::TileId> {
+        match &self.tiles {
+            Some(tiles) => tiles.clone(),
+            None => tileset.tile_order.clone(),
+        }
+    }
+}
+
+/// outline drawn around the tiles in the `MapSelection`
+#[derive(Component, Debug)]
+struct SelectionOutline;
+
 #[derive(Component)]
 struct MainCamera;
 
 #[derive(Component)]
 struct GridSelectionPlane;
 
+/// parent of the cursor & tool previews; raised to the active layer height
+#[derive(Component)]
+struct CursorRoot;
+
 #[derive(Component, Default, Debug, Reflect)]
 struct MapCursor {
     position: Vec3,
@@ -347,8 +623,36 @@ pub enum InputActions {
     CameraControl,
     TileRotateCW,
     TileRotateCCW,
+    ToolPaint,
+    ToolErase,
+    ToolLine,
+    ToolRectangle,
+    ToolRadius,
+    ToolFill,
+    ToolSelectBox,
+    ToolSelectLasso,
+    ToolSelectRadius,
+    ToolSelectSame,
+    ToolMove,
+    SelectionDelete,
+    SelectionClear,
 }
 
+/// hotkey actions for the built-in tools, and the name of the tool
+const TOOL_ACTIONS: [(InputActions, &str); 11] = [
+    (InputActions::ToolPaint, "Paint"),
+    (InputActions::ToolErase, "Erase"),
+    (InputActions::ToolLine, "Line"),
+    (InputActions::ToolRectangle, "Rectangle"),
+    (InputActions::ToolRadius, "Radius"),
+    (InputActions::ToolFill, "Fill"),
+    (InputActions::ToolSelectBox, "Select Box"),
+    (InputActions::ToolSelectLasso, "Lasso"),
+    (InputActions::ToolSelectRadius, "Select Radius"),
+    (InputActions::ToolSelectSame, "Select Same"),
+    (InputActions::ToolMove, "Move"),
+];
+
 #[rustfmt::skip]
 fn input_map() -> InputMap<InputActions> {
     InputMap::default()
@@ -362,6 +666,19 @@ fn input_map() -> InputMap<InputActions> {
         .insert(KeyCode::Space, InputActions::CameraPan)
         .insert(KeyCode::Q, InputActions::TileRotateCW)
         .insert(KeyCode::E, InputActions::TileRotateCCW)
+        .insert(KeyCode::B, InputActions::ToolPaint)
+        .insert(KeyCode::X, InputActions::ToolErase)
+        .insert(KeyCode::L, InputActions::ToolLine)
+        .insert(KeyCode::R, InputActions::ToolRectangle)
+        .insert(KeyCode::C, InputActions::ToolRadius)
+        .insert(KeyCode::F, InputActions::ToolFill)
+        .insert(KeyCode::S, InputActions::ToolSelectBox)
+        .insert(KeyCode::A, InputActions::ToolSelectLasso)
+        .insert(KeyCode::O, InputActions::ToolSelectRadius)
+        .insert(KeyCode::T, InputActions::ToolSelectSame)
+        .insert(KeyCode::V, InputActions::ToolMove)
+        .insert(KeyCode::Delete, InputActions::SelectionDelete)
+        .insert(KeyCode::Escape, InputActions::SelectionClear)
         .build()
 }
 
@@ -370,6 +687,9 @@ fn handle_input(
     mut cursor: Query<&mut tileset::TileTransform, With<MapCursor>>,
     mut camera: Query<(&mut Rig, &mut Projection, &Transform), With<MainCamera>>,
     mut egui_contexts: EguiContexts,
+    mut tools: ResMut<tool::ToolRegistry>,
+    selection: Res<MapSelection>,
+    mut ui_events: EventWriter<EditorUiEvent>,
 ) {
     let actions = action_state.single();
     let (mut rig, mut projection, transform) = camera.single_mut();
@@ -424,13 +744,41 @@ fn handle_input(
         projection.scale = (projection.scale * (1.0 - scale * 0.005)).clamp(0.001, 15.0);
     }
 
+    // rotate the selected tiles if there are any, otherwise the cursor tile
     let mut tile_transform = cursor.single_mut();
     if actions.just_pressed(InputActions::TileRotateCW) {
-        tile_transform.rotation = tile_transform.rotation.clockwise();
+        if selection.tiles.is_empty() {
+            tile_transform.rotation = tile_transform.rotation.clockwise();
+        } else {
+            ui_events.send(EditorUiEvent::SelectionRotate(
+                tileset::TileRotation::Clockwise60,
+            ));
+        }
     }
 
     if actions.just_pressed(InputActions::TileRotateCCW) {
-        tile_transform.rotation = tile_transform.rotation.counter_clockwise();
+        if selection.tiles.is_empty() {
+            tile_transform.rotation = tile_transform.rotation.counter_clockwise();
+        } else {
+            ui_events.send(EditorUiEvent::SelectionRotate(
+                tileset::TileRotation::CounterClockwise60,
+            ));
+        }
+    }
+
+    if !selection.tiles.is_empty() {
+        if actions.just_pressed(InputActions::SelectionDelete) {
+            ui_events.send(EditorUiEvent::SelectionDelete);
+        }
+        if actions.just_pressed(InputActions::SelectionClear) {
+            ui_events.send(EditorUiEvent::SelectionClear);
+        }
+    }
+
+    for (action, name) in TOOL_ACTIONS {
+        if actions.just_pressed(action) {
+            tools.select(name);
+        }
     }
 }
 
@@ -467,8 +815,45 @@ fn handle_ui_events(world: &mut World) {
             }
             MapSave(path) => run_system(world, path.clone(), save_map),
             MapLoad(path) => run_system(world, path.clone(), load_map),
+            MapChanged(path) => check_map_file(world, path),
+            MapReload(path) => {
+                run_system(world, (), close_map);
+                world.resource_mut::<EditorState>().map_path = Some(path.clone());
+                run_system(world, path, load_map);
+            }
+            MapImportTmx { tmx, mapping } => {
+                run_system(world, (), close_map);
+                run_system(world, (tmx, mapping), import_tmx);
+            }
+            MapImportLayers {
+                path,
+                offset,
+                rotation,
+            } => run_system(world, (path, offset, rotation), import_layers),
+            MapExportGltf(path) => run_system(world, path, export_gltf),
+            MapExportSvg(path) => run_system(world, path, export_svg),
             RedrawMapTiles => run_system(world, (), redraw_map_tiles),
-            DeleteTileset(entity) => run_system(world, entity, remove_tileset),
+            RemoveTiles { removal, reassign } => remove_tiles(world, &removal, reassign),
+            TilesRemoved(removal) => forget_tiles(world, &removal, &HashSet::new()),
+            DuplicateLayer(entity) => duplicate_layer(world, entity),
+            DeleteLayer(entity) => run_system(world, entity, remove_layer),
+            DeleteObject(entity) => run_system(world, entity, remove_object),
+            DeleteRegion(entity) => run_system(world, entity, remove_region),
+            StampSelect(path) => select_stamp(world, path),
+            StampDeselect => run_system(world, (), deselect_stamp),
+            StampSave { name, tiles } => save_stamp(world, name, tiles),
+            SelectionRotate(rotation) => run_system(world, rotation, rotate_selection),
+            SelectionDelete => run_system(world, (), delete_selection),
+            SelectionMoveToLayer(layer) => run_system(world, layer, move_selection_to_layer),
+            SelectionClear => world.resource_mut::<MapSelection>().tiles.clear(),
+            Undo => run_system(world, false, undo_stroke),
+            Redo => run_system(world, true, undo_stroke),
+            ReplaceTiles { replace, scope } => replace_tiles(world, &replace, scope),
+            ScatterTiles {
+                scatter,
+                source,
+                target,
+            } => scatter_tiles(world, &scatter, source, target),
         }
     }
 
@@ -491,15 +876,157 @@ fn save_map(
     state.unsaved_changes = false;
 }
 
-fn load_map(In(path): In<std::path::PathBuf>, mut commands: Commands) {
+fn export_gltf(
+    In(path): In<std::path::PathBuf>,
+    mut commands: Commands,
+    map: Query<Entity, With<map::Map>>,
+) {
+    let Ok(entity) = map.get_single() else {
+        warn!("no map loaded");
+        return;
+    };
+    info!("export map to {}", path.to_string_lossy());
+    commands.add(persistence::SaveMapCommand::with_writer(
+        path,
+        entity,
+        |path, map| export::gltf::export(map, &export::asset_dir(), path),
+    ));
+}
+
+fn export_svg(
+    In(path): In<std::path::PathBuf>,
+    mut commands: Commands,
+    map: Query<Entity, With<map::Map>>,
+) {
+    let Ok(entity) = map.get_single() else {
+        warn!("no map loaded");
+        return;
+    };
+    info!("export map to {}", path.to_string_lossy());
+    commands.add(persistence::SaveMapCommand::with_writer(
+        path,
+        entity,
+        |path, map| export::svg::export(map, &export::svg::SvgOptions::default(), path),
+    ));
+}
+
+fn load_map(
+    In(path): In<std::path::PathBuf>,
+    mut commands: Commands,
+    mut history: ResMut<EditHistory>,
+) {
     info!("load map {}", path.to_string_lossy());
+    *history = EditHistory::default();
     commands.spawn(persistence::MapImporter::new(path));
 }
 
+/// compare the map file changed on disk with the open map, and ask the user
+/// to reload it if they differ; saving the map also changes the file
+fn check_map_file(world: &mut World, path: std::path::PathBuf) {
+    let mut query = world.query_filtered::<Entity, With<map::Map>>();
+    let Ok(root) = query.get_single(world) else { return };
+    let current = match persistence::MapFormat::try_new(world, root) {
+        Ok(map) => map,
+        Err(e) => {
+            warn!("failed to compare map with {:?}: {:?}", path, e);
+            return;
+        }
+    };
+    // the file may be read while it's being written; it is reported as
+    // changed again when the write finishes
+    let changed = match persistence::MapFormat::load(&path) {
+        Ok(map) => map,
+        Err(e) => {
+            debug!("failed to load changed map {:?}: {:?}", path, e);
+            return;
+        }
+    };
+
+    let diff = persistence::diff::MapDiff::new(&current, &changed);
+    let mut state = world.resource_mut::<EditorState>();
+    if diff.is_empty() {
+        state.map_changed = None;
+        return;
+    }
+    info!("map {:?} changed on disk: {:?}", path, diff);
+    state.map_changed = Some((path, diff.summary()));
+}
+
+/// watch the open map file for changes made by other programs
+fn watch_map_file(
+    state: Res<EditorState>,
+    mut watcher: ResMut<watch::FileWatcher>,
+    mut events: EventReader<watch::FileChanged>,
+    mut editor_events: EventWriter<EditorUiEvent>,
+) {
+    let Some(map_path) = &state.map_path else {
+        events.clear();
+        return;
+    };
+    if state.is_changed() {
+        watcher.watch(map_path);
+    }
+    for watch::FileChanged(path) in events.iter() {
+        if path == map_path {
+            editor_events.send(EditorUiEvent::MapChanged(path.clone()));
+        }
+    }
+}
+
+/// tilesets reloaded from their files differ from the tilesets in the saved
+/// map, and may no longer have the tiles used by the brushes & undo history
+fn tileset_reloaded(
+    mut state: ResMut<EditorState>,
+    mut events: EventReader<watch::TilesetReloaded>,
+    mut editor_events: EventWriter<EditorUiEvent>,
+) {
+    for watch::TilesetReloaded { tileset, merge } in events.iter() {
+        state.unsaved_changes = true;
+        if !merge.removed.is_empty() {
+            editor_events.send(EditorUiEvent::TilesRemoved(TileRemoval {
+                tileset: *tileset,
+                tiles: Some(merge.removed.clone()),
+            }));
+        }
+    }
+}
+
+fn import_tmx(
+    In((tmx, mapping)): In<(std::path::PathBuf, std::path::PathBuf)>,
+    mut commands: Commands,
+) {
+    info!(
+        "import Tiled map {} using GID mapping {}",
+        tmx.to_string_lossy(),
+        mapping.to_string_lossy()
+    );
+    commands.spawn(tiled::importer(tmx, mapping));
+}
+
+fn import_layers(
+    In((path, offset, rotation)): In<(std::path::PathBuf, map::Location, tileset::TileRotation)>,
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    map: Query<Entity, With<map::Map>>,
+) {
+    let Ok(entity) = map.get_single() else {
+        warn!("no map loaded");
+        return;
+    };
+    info!("import layers from map {}", path.to_string_lossy());
+    commands.spawn(persistence::LayerImporter::new(
+        path, entity, offset, rotation,
+    ));
+    state.unsaved_changes = true;
+}
+
 fn close_map(
     mut commands: Commands,
     mut state: ResMut<EditorState>,
     mut tile_selection: ResMut<TileSelection>,
+    mut brush: ResMut<StampBrush>,
+    mut selection: ResMut<MapSelection>,
+    mut history: ResMut<EditHistory>,
     map: Query<Entity, With<map::Map>>,
     cursor: Query<Entity, With<MapCursor>>,
 ) {
@@ -515,6 +1042,9 @@ fn close_map(
         .remove::<(tileset::TileRef, Handle<Scene>)>()
         .despawn_descendants();
     tile_selection.tiles.clear();
+    *brush = StampBrush::default();
+    selection.tiles.clear();
+    *history = EditHistory::default();
 
     if let Ok(entity) = map.get_single() {
         commands.entity(entity).despawn_recursive();
@@ -523,6 +1053,11 @@ fn close_map(
     state.unsaved_changes = false;
     state.active_tileset = None;
     state.active_layer = None;
+    state.active_object = None;
+    state.active_annotation = None;
+    state.active_region = None;
+    state.stamp_capture = false;
+    state.new_stamp = None;
 }
 
 fn create_map(mut commands: Commands, mut state: ResMut<EditorState>) {
@@ -572,181 +1107,2693 @@ fn map_loaded(
     }
 }
 
-fn remove_tileset(
-    In(tileset_id): In<Entity>,
-    mut state: ResMut<EditorState>,
-    mut commands: Commands,
-    tilesets: Query<Entity, With<tileset::Tileset>>,
-) {
-    commands.entity(tileset_id).despawn_recursive();
-    state.active_tileset = tilesets.iter().find(|entity| *entity != tileset_id);
+/// tiles on the map layers using a tile being removed
+fn placed_tiles_using(world: &mut World, removal: &TileRemoval) -> Vec<(Entity, tileset::TileRef)> {
+    let mut layers = world.query::<&map::Layer>();
+    let mut tiles = world.query_filtered::<(Entity, &tileset::TileRef, &Parent), (
+        Without<MapCursor>,
+        Without<StampPreview>,
+        Without<ToolPreview>,
+    )>();
+    tiles
+        .iter(world)
+        .filter(|(_, tile_ref, parent)| {
+            removal.removes(tile_ref) && layers.get(world, parent.get()).is_ok()
+        })
+        .map(|(entity, tile_ref, _)| (entity, *tile_ref))
+        .collect()
 }
 
-fn redraw_map_tiles(
-    mut commands: Commands,
-    tile_selection: Res<TileSelection>,
-    tiles: Query<(
-        Entity,
-        &tileset::TileRef,
-        &tileset::TileTransform,
-        &map::Location,
-    )>,
-    tilesets: Query<&tileset::Tileset>,
-    map: Query<&map::Map>,
-) {
-    let Ok(map) = map.get_single() else { return; };
-    for (entity, tile_ref, tile_transform, location) in &tiles {
-        if !tile_selection.tiles.contains(tile_ref) {
-            continue;
-        }
-        let Ok(tileset) = tilesets.get(tile_ref.tileset) else {
-            warn!("unknown tileset {:?} in entity {:?}",
-                tile_ref.tileset, entity);
-            continue;
-        };
+/// remove a tileset, or some of its tiles; the placed tiles using them are
+/// reassigned to the tile with the same name in the `reassign` tileset, or
+/// deleted if there is none
+fn remove_tiles(world: &mut World, removal: &TileRemoval, reassign: Option<Entity>) {
+    let Some(tileset) = world.get::<tileset::Tileset>(removal.tileset) else {
+        warn!("failed to get Tileset {:?}", removal.tileset);
+        return;
+    };
+    let tiles = removal.tile_ids(tileset);
+    // no matching names; every placed tile is deleted
+    let replace = reassign
+        .and_then(|to| replace::TileReplace::tiles_by_name(world, removal.tileset, &tiles, to).ok())
+        .unwrap_or_default();
 
-        let bundle = tileset::TileBundle::new(
-            map,
-            *location,
-            tile_transform.clone(),
-            tileset,
-            tile_ref.tileset,
-            tile_ref.tile,
+    let mut reassign = Vec::new();
+    let mut deleted = HashSet::new();
+    for (entity, tile_ref) in placed_tiles_using(world, removal) {
+        match replace.mapping.get(&tile_ref) {
+            Some(to) => {
+                let transform = world.get::<tileset::TileTransform>(entity).cloned();
+                reassign.push((entity, *to, transform.unwrap_or_default()));
+            }
+            None => {
+                world.entity_mut(entity).despawn_recursive();
+                deleted.insert(entity);
+            }
+        }
+    }
+    // tiles that would overlap other tiles once reassigned are deleted too
+    let count = reassign.len();
+    let overlapping = change_tiles(world, reassign);
+    let reassigned = count - overlapping.len();
+    if !overlapping.is_empty() {
+        warn!(
+            "{} placed tiles deleted; their new tiles would overlap other tiles",
+            overlapping.len()
         );
-        commands.entity(entity).insert(bundle);
     }
-}
-
-fn handle_picker_events(
-    mut commands: Commands,
-    mut picker_events: EventReader<PickerEvent>,
-    mut state: ResMut<EditorState>,
-    mut tilesets: Query<&mut tileset::Tileset>,
-    mut editor_events: EventWriter<EditorUiEvent>,
-    map: Query<Entity, With<map::Map>>,
-) {
-    for event in picker_events.iter() {
-        match event {
-            PickerEvent::AddTiles { tileset_id, files } => {
-                let Ok(mut tileset) = tilesets.get_mut(*tileset_id) else { continue };
-                let Some(paths) = files else { continue };
-                for path in paths {
-                    tileset.add_tile(path.clone());
-                }
-                state.unsaved_changes = true;
-            }
-            PickerEvent::MapSave(path) => {
-                let Some(path) = path else { continue };
-                if state.map_path.is_none() {
-                    state.map_path = Some(path.clone());
-                }
+    for entity in overlapping {
+        world.entity_mut(entity).despawn_recursive();
+        deleted.insert(entity);
+    }
+    info!(
+        "removing {} tiles from {:?}; {} placed tiles reassigned, {} deleted",
+        tiles.len(),
+        removal.tileset,
+        reassigned,
+        deleted.len()
+    );
+    forget_tiles(world, removal, &deleted);
 
-                editor_events.send(EditorUiEvent::MapSave(path.clone()));
+    match &removal.tiles {
+        Some(tiles) => {
+            let mut tileset = world.get_mut::<tileset::Tileset>(removal.tileset).unwrap();
+            tileset.remove_tiles(tiles);
+        }
+        None => {
+            world.entity_mut(removal.tileset).despawn_recursive();
+            let mut tilesets = world.query_filtered::<Entity, With<tileset::Tileset>>();
+            let active_tileset = tilesets.iter(world).next();
+            let mut state = world.resource_mut::<EditorState>();
+            state.active_tileset = active_tileset;
+            if state.property_schema_window == Some(removal.tileset) {
+                state.property_schema_window = None;
             }
-            PickerEvent::MapLoad(path) => {
-                let Some(path) = path else { continue };
-                if state.map_path.is_none() {
-                    state.map_path = Some(path.clone());
-                }
+        }
+    }
+    world.resource_mut::<EditorState>().unsaved_changes = true;
+}
 
-                editor_events.send(EditorUiEvent::MapLoad(path.clone()));
-            }
-            PickerEvent::TilesetImport(paths) => {
-                let Some(paths) = paths else { continue };
-                let Ok(map) = map.get_single() else {
-                    error!("no map found; not loading tileset");
-                    continue;
-                };
-                commands.entity(map).with_children(|map| {
-                    for path in paths {
-                        let id = map.spawn(tileset::TilesetImporter::new(path.clone())).id();
-                        state.active_tileset = Some(id);
-                    }
-                });
-            }
-            PickerEvent::TilesetExport(tileset_id, path) => {
-                let Some(path) = path else { continue };
-                let Ok(tileset) = tilesets.get(*tileset_id) else {
-                    warn!("tileset not found: {:?}", event);
-                    continue;
-                };
-                commands.spawn(tileset::TilesetExporter::new(path.clone(), tileset.clone()));
-            }
+/// forget removed tiles everywhere they may be used, along with the `deleted`
+/// placed tiles
+fn forget_tiles(world: &mut World, removal: &TileRemoval, deleted: &HashSet<Entity>) {
+    world
+        .resource_mut::<MapSelection>()
+        .tiles
+        .retain(|entity| !deleted.contains(entity));
+    let mut tile_selection = world.resource_mut::<TileSelection>();
+    tile_selection
+        .tiles
+        .retain(|tile_ref| !removal.removes(tile_ref));
+    if matches!(&tile_selection.scroll_to, Some(tile_ref) if removal.removes(tile_ref)) {
+        tile_selection.scroll_to = None;
+    }
+    let mut random_brush = world.resource_mut::<brush::RandomBrush>();
+    random_brush
+        .weights
+        .retain(|tile_ref, _| !removal.removes(tile_ref));
+    let mut stamp_brush = world.resource_mut::<StampBrush>();
+    if stamp_brush
+        .tiles
+        .iter()
+        .any(|(_, tile_ref, _, _)| removal.removes(tile_ref))
+    {
+        *stamp_brush = StampBrush::default();
+    }
+    let mut cursor = world.query_filtered::<(Entity, &tileset::TileRef), With<MapCursor>>();
+    if let Ok((cursor, tile_ref)) = cursor.get_single(world) {
+        if removal.removes(tile_ref) {
+            world
+                .entity_mut(cursor)
+                .remove::<(tileset::TileRef, Handle<Scene>)>()
+                .despawn_descendants();
         }
     }
-    picker_events.clear();
+    let mut history = world.resource_mut::<EditHistory>();
+    if history.uses_tiles(removal) {
+        info!("clearing the undo history; it uses removed tiles");
+        *history = EditHistory::default();
+    }
 }
 
-#[sysfail(log)]
-fn handle_map_cursor_events(
-    mut commands: Commands,
-    mut events: EventReader<MapCursorMoveEvent>,
-    state: Res<EditorState>,
-    map: Query<&map::Map>,
-    buttons: Res<Input<MouseButton>>,
-    cursor: Query<(Entity, &tileset::TileRef, &tileset::TileTransform), With<MapCursor>>,
+/// copy a layer and its tiles, placing the copy directly above the layer
+fn duplicate_layer(world: &mut World, layer_id: Entity) {
+    let Some(layer) = world.get::<map::Layer>(layer_id) else {
+        warn!("failed to get Layer {:?}", layer_id);
+        return;
+    };
+    let Some(map) = world.get::<Parent>(layer_id).map(|p| p.get()) else { return };
+    let copy = map::Layer {
+        name: format!("{} copy", layer.name),
+        order: layer.order + 1,
+        visible: layer.visible,
+        locked: layer.locked,
+        opacity: layer.opacity,
+        y_offset: layer.y_offset,
+        kind: layer.kind,
+        ..default()
+    };
+
+    // make room for the copy above the layer
+    let mut layers = world.query::<(&mut map::Layer, &Parent)>();
+    for (mut layer, parent) in layers.iter_mut(world) {
+        if parent.get() == map && layer.order >= copy.order {
+            layer.order += 1;
+        }
+    }
+
+    let mut query = world.query::<(
+        &map::Location,
+        &tileset::TileRef,
+        &tileset::TileTransform,
+        Option<&tileset::TileOverrides>,
+    )>();
+    let tiles: Vec<_> = world
+        .get::<Children>(layer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| query.get(world, *child).ok())
+        .map(|(location, tile_ref, transform, overrides)| {
+            (*location, *tile_ref, transform.clone(), overrides.copied())
+        })
+        .collect();
+    let mut query = world.query::<(&map::Edge, &tileset::TileRef, &tileset::TileTransform)>();
+    let edges: Vec<_> = world
+        .get::<Children>(layer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| query.get(world, *child).ok())
+        .map(|(edge, tile_ref, transform)| (*edge, *tile_ref, transform.clone()))
+        .collect();
+    let mut query = world.query::<(&map::Vertex, &tileset::TileRef, &tileset::TileTransform)>();
+    let vertices: Vec<_> = world
+        .get::<Children>(layer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| query.get(world, *child).ok())
+        .map(|(vertex, tile_ref, transform)| (*vertex, *tile_ref, transform.clone()))
+        .collect();
+    let mut query = world.query::<(Entity, &object::MapObject)>();
+    let objects: Vec<_> = world
+        .get::<Children>(layer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| query.get(world, *child).ok())
+        .map(|(entity, object)| (entity, object.clone()))
+        .collect();
+    let mut query = world.query::<&annotation::Annotation>();
+    let annotations: Vec<_> = world
+        .get::<Children>(layer_id)
+        .into_iter()
+        .flatten()
+        .filter_map(|child| query.get(world, *child).ok())
+        .cloned()
+        .collect();
+
+    let copy_id = world
+        .spawn((
+            Name::new(format!("layer: {}", copy.name)),
+            copy,
+            SpatialBundle::default(),
+        ))
+        .with_children(|layer| {
+            for (location, tile_ref, transform, overrides) in tiles {
+                let mut tile = layer.spawn((location, tile_ref, transform));
+                tile.insert(SpatialBundle::default());
+                if let Some(overrides) = overrides {
+                    tile.insert(overrides);
+                }
+            }
+            for (edge, tile_ref, transform) in edges {
+                layer.spawn((edge, tile_ref, transform, SpatialBundle::default()));
+            }
+            for (vertex, tile_ref, transform) in vertices {
+                layer.spawn((vertex, tile_ref, transform, SpatialBundle::default()));
+            }
+            for annotation in annotations {
+                layer.spawn((
+                    Name::new("annotation"),
+                    annotation,
+                    SpatialBundle::default(),
+                ));
+            }
+        })
+        .id();
+    world.entity_mut(map).add_child(copy_id);
+
+    // references between objects in the layer point to the copied objects
+    let copies: HashMap<Entity, Entity> = objects
+        .iter()
+        .map(|(entity, _)| (*entity, world.spawn_empty().id()))
+        .collect();
+    for (entity, mut object) in objects {
+        for property in object.properties.values_mut() {
+            if let object::Property::Object(target) = property {
+                *target = copies.get(target).copied().unwrap_or(*target);
+            }
+        }
+        world
+            .entity_mut(copies[&entity])
+            .insert((
+                Name::new(format!("object: {}", object.name)),
+                object,
+                SpatialBundle::default(),
+            ))
+            .set_parent(copy_id);
+    }
+
+    let mut state = world.resource_mut::<EditorState>();
+    state.active_layer = Some(copy_id);
+    state.unsaved_changes = true;
+}
+
+fn remove_layer(
+    In(layer_id): In<Entity>,
+    mut state: ResMut<EditorState>,
+    mut selection: ResMut<MapSelection>,
+    mut commands: Commands,
+    layers: Query<(Entity, &map::Layer)>,
+    tiles: Query<&Parent, With<tileset::TileRef>>,
+    objects: Query<&Parent, With<object::MapObject>>,
+    annotations: Query<&Parent, With<annotation::Annotation>>,
+) {
+    selection
+        .tiles
+        .retain(|e| !matches!(tiles.get(*e), Ok(p) if p.get() == layer_id));
+    if let Some(Ok(parent)) = state.active_object.map(|e| objects.get(e)) {
+        if parent.get() == layer_id {
+            state.active_object = None;
+        }
+    }
+    if let Some(Ok(parent)) = state.active_annotation.map(|e| annotations.get(e)) {
+        if parent.get() == layer_id {
+            state.active_annotation = None;
+        }
+    }
+    commands.entity(layer_id).despawn_recursive();
+
+    // the layer below becomes active, or the lowest layer if there is none
+    let order = layers.get(layer_id).map(|(_, l)| l.order).unwrap_or(0);
+    let remaining: Vec<(Entity, usize)> = layers
+        .iter()
+        .filter(|(entity, _)| *entity != layer_id)
+        .map(|(entity, l)| (entity, l.order))
+        .collect();
+    state.active_layer = remaining
+        .iter()
+        .filter(|(_, o)| *o < order)
+        .max_by_key(|(_, o)| *o)
+        .or_else(|| remaining.iter().min_by_key(|(_, o)| *o))
+        .map(|(entity, _)| *entity);
+    state.unsaved_changes = true;
+}
+
+impl StampBrush {
+    /// location & transform of each brush tile when the cursor is at
+    /// `location` with `rotation`
+    fn placed(
+        &self,
+        location: map::Location,
+        rotation: tileset::TileRotation,
+    ) -> impl Iterator<
+        Item = (
+            map::Location,
+            tileset::TileRef,
+            tileset::TileTransform,
+            tileset::TileOverrides,
+        ),
+    > + '_ {
+        self.tiles
+            .iter()
+            .map(move |(offset, tile_ref, tile_rotation, overrides)| {
+                let offset = offset.rotate(rotation);
+                (
+                    map::Location {
+                        x: location.x + offset.x,
+                        y: location.y + offset.y,
+                    },
+                    *tile_ref,
+                    tileset::TileTransform {
+                        rotation: tile_rotation.rotate_by(rotation),
+                    },
+                    overrides.rotate(rotation),
+                )
+            })
+    }
+}
+
+/// load the stamps in the library directory, and render their thumbnails
+fn load_stamp_library(
+    mut library: ResMut<StampLibrary>,
+    mut saved: EventReader<stamp::StampSaved>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut render_queue: ResMut<hex_sandbox::thumbnail_render::RenderQueue>,
+    mut egui_user_textures: ResMut<bevy_egui::EguiUserTextures>,
+) {
+    if saved.iter().count() > 0 {
+        library.dirty = true;
+    }
+    if !library.dirty {
+        return;
+    }
+    library.dirty = false;
+
+    for old in library.stamps.drain(..) {
+        egui_user_textures.remove_image(&old.image);
+    }
+
+    let paths = match stamp::list(&library.dir) {
+        Ok(paths) => paths,
+        Err(e) => {
+            warn!("failed to list stamp library: {:?}", e);
+            return;
+        }
+    };
+    info!(
+        "loading {} stamps from {}",
+        paths.len(),
+        library.dir.to_string_lossy()
+    );
+
+    for path in paths {
+        let stamp = match stamp::Stamp::load(&path) {
+            Ok(stamp) => stamp,
+            Err(e) => {
+                warn!("failed to load stamp {}: {:?}", path.to_string_lossy(), e);
+                continue;
+            }
+        };
+
+        let image = images.add(tileset::alloc_render_image(48 * 2, 48 * 2));
+        let texture = egui_user_textures.add_image(image.clone());
+        render_queue.push_scenes(image.clone(), stamp_thumbnail_scenes(&stamp, &asset_server));
+        library.stamps.push(LibraryStamp {
+            path,
+            stamp,
+            image,
+            texture,
+        });
+    }
+}
+
+/// tile scenes of a stamp, scaled to fit in a thumbnail
+fn stamp_thumbnail_scenes(
+    stamp: &stamp::Stamp,
+    asset_server: &AssetServer,
+) -> Vec<(Handle<Scene>, Transform)> {
+    let map = map::Map::default();
+    let mut scenes = Vec::new();
+    for tile in &stamp.tiles {
+        let Some(tileset) = stamp.tilesets.get(&tile.tileset) else { continue };
+        let Some(tileset_tile) = tileset.tiles.get(&tile.tile_id) else { continue };
+        let scene = asset_server.load(format!("{}#Scene0", tileset_tile.path.to_string_lossy()));
+        let transform = map.tile_transform(
+            tileset_tile,
+            tile.location,
+            &tileset::TileTransform {
+                rotation: tile.rotation,
+            },
+            &tile.overrides,
+        );
+        scenes.push((scene, transform));
+    }
+    if scenes.is_empty() {
+        return scenes;
+    }
+
+    // center the tiles on the origin, and scale them down to the size of a
+    // single tile
+    let sum: Vec3 = scenes.iter().map(|(_, t)| t.translation).sum();
+    let center = sum / scenes.len() as f32 * Vec3::new(1.0, 0.0, 1.0);
+    let extent = scenes
+        .iter()
+        .map(|(_, t)| (t.translation - center).length())
+        .fold(0.0, f32::max);
+    let scale = 0.5 / (extent + 0.5);
+    for (_, transform) in &mut scenes {
+        transform.translation = (transform.translation - center) * scale;
+        transform.scale *= scale;
+    }
+    scenes
+}
+
+fn select_stamp(world: &mut World, path: std::path::PathBuf) {
+    let library = world.resource::<StampLibrary>();
+    let Some(entry) = library.stamps.iter().find(|s| s.path == path) else {
+        warn!("stamp not in library: {}", path.to_string_lossy());
+        return;
+    };
+    let stamp = entry.stamp.clone();
+
+    let mut query = world.query_filtered::<Entity, With<map::Map>>();
+    let Ok(map) = query.get_single(world) else {
+        warn!("no map loaded");
+        return;
+    };
+
+    // add the stamp tilesets to the map so the brush can reference them
+    let mut query = world.query_filtered::<Entity, With<tileset::Tileset>>();
+    let existing: HashSet<Entity> = query.iter(world).collect();
+    let previewed = world.resource::<StampBrush>().tilesets.clone();
+    let tilesets = match persistence::merge_tilesets(world, map, &stamp.tilesets) {
+        Ok(tilesets) => tilesets,
+        Err(e) => {
+            warn!("failed to add stamp tilesets: {:?}", e);
+            return;
+        }
+    };
+    let added: HashSet<Entity> = tilesets
+        .values()
+        .filter(|entity| !existing.contains(entity) || previewed.contains(entity))
+        .copied()
+        .collect();
+    let mut tiles = Vec::new();
+    for tile in &stamp.tiles {
+        let Some(tileset) = tilesets.get(&tile.tileset) else {
+            warn!("unknown tileset in stamp {}: {:?}", stamp.name, tile);
+            continue;
+        };
+        let tile_ref = tileset::TileRef {
+            tileset: *tileset,
+            tile: tile.tile_id,
+        };
+        tiles.push((tile.location, tile_ref, tile.rotation, tile.overrides));
+    }
+
+    info!("select stamp {}", stamp.name);
+    *world.resource_mut::<StampBrush>() = StampBrush {
+        path: Some(path),
+        tiles,
+        tilesets: added.into_iter().collect(),
+    };
+}
+
+/// remove the tilesets added to preview a stamp once the stamp brush is no
+/// longer used, unless the stamp was placed, or the tiles were used otherwise
+fn remove_stamp_tilesets(
+    mut commands: Commands,
+    mut previewed: Local<Vec<Entity>>,
+    state: Res<EditorState>,
+    brush: Res<StampBrush>,
+    tile_selection: Res<TileSelection>,
+    tilesets: Query<(), With<tileset::Tileset>>,
+    tiles: Query<(&tileset::TileRef, &Parent)>,
+    layers: Query<(), With<map::Layer>>,
+) {
+    if !brush.is_changed() {
+        // placing the stamp keeps its tilesets
+        previewed.retain(|tileset| brush.tilesets.contains(tileset));
+        return;
+    }
+    for tileset in std::mem::replace(&mut *previewed, brush.tilesets.clone()) {
+        let used = brush.tilesets.contains(&tileset)
+            || state.active_tileset == Some(tileset)
+            || tile_selection.tiles.iter().any(|t| t.tileset == tileset)
+            || tiles
+                .iter()
+                .any(|(t, parent)| t.tileset == tileset && layers.contains(parent.get()));
+        if !used && tilesets.contains(tileset) {
+            debug!("removing tileset {:?} added for the stamp preview", tileset);
+            commands.entity(tileset).despawn_recursive();
+        }
+    }
+}
+
+fn deselect_stamp(
+    mut commands: Commands,
+    mut brush: ResMut<StampBrush>,
+    tile_selection: Res<TileSelection>,
+    cursor: Query<Entity, With<MapCursor>>,
+) {
+    *brush = StampBrush::default();
+
+    // restore the cursor model of the selected tile
+    let Some(tile_ref) = tile_selection.active_tile() else { return };
+    let Ok(cursor) = cursor.get_single() else { return };
+    commands.entity(cursor).insert(*tile_ref);
+}
+
+fn save_stamp(world: &mut World, name: String, tiles: Vec<Entity>) {
+    let stamp = match stamp::Stamp::try_new(world, &name, &tiles) {
+        Ok(stamp) => stamp,
+        Err(e) => {
+            warn!("failed to create stamp {}: {:?}", name, e);
+            return;
+        }
+    };
+    let path = stamp::library_path(&world.resource::<StampLibrary>().dir, &name);
+    info!("save stamp {} to {}", name, path.to_string_lossy());
+    world.spawn(stamp::StampExporter::new(path, stamp));
+}
+
+/// keep the stamp preview tiles under the cursor
+fn update_stamp_preview(
+    mut commands: Commands,
+    brush: Res<StampBrush>,
+    cursor: Query<
+        (
+            Entity,
+            Option<&map::Location>,
+            &tileset::TileTransform,
+            &Parent,
+        ),
+        With<MapCursor>,
+    >,
+    mut previews: Query<
+        (
+            Entity,
+            &StampPreview,
+            &mut map::Location,
+            &mut tileset::TileTransform,
+            &mut tileset::TileOverrides,
+        ),
+        Without<MapCursor>,
+    >,
+) {
+    let Ok((cursor, location, tile_transform, cursor_root)) = cursor.get_single() else { return };
+    let location = location.copied().unwrap_or_default();
+    let placed: Vec<_> = brush.placed(location, tile_transform.rotation).collect();
+
+    // move the existing preview tiles when the brush has not changed
+    if !brush.is_changed() {
+        for (_, preview, mut location, mut tile_transform, mut overrides) in &mut previews {
+            let Some((l, _, t, o)) = placed.get(preview.0) else { continue };
+            location.set_if_neq(*l);
+            tile_transform.set_if_neq(t.clone());
+            overrides.set_if_neq(*o);
+        }
+        return;
+    }
+
+    for (entity, ..) in &previews {
+        commands.entity(entity).despawn_recursive();
+    }
+    if brush.path.is_some() {
+        commands
+            .entity(cursor)
+            .remove::<(tileset::TileRef, Handle<Scene>)>()
+            .despawn_descendants();
+    }
+    for (index, (location, tile_ref, tile_transform, overrides)) in placed.into_iter().enumerate() {
+        commands
+            .spawn((
+                Name::new("stamp_preview"),
+                StampPreview(index),
+                location,
+                tile_ref,
+                tile_transform,
+                overrides,
+                SpatialBundle::default(),
+            ))
+            .set_parent(cursor_root.get());
+    }
+}
+
+/// place the stamp brush on the active layer
+fn place_stamp(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut brush: ResMut<StampBrush>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    cursor: Query<(&map::Location, &tileset::TileTransform), With<MapCursor>>,
+    tiles: Query<
+        (Entity, &map::Location, &Parent),
+        (
+            With<tileset::TileRef>,
+            Without<MapCursor>,
+            Without<StampPreview>,
+        ),
+    >,
+    (layers, tilesets): (Query<&map::Layer>, Query<&tileset::Tileset>),
+) {
+    if brush.tiles.is_empty()
+        || state.stamp_capture
+        || state.active_region.is_some()
+        || !buttons.just_pressed(MouseButton::Left)
+        || eyedropper_pressed(&keys)
+        || egui_contexts.ctx_mut().is_pointer_over_area()
+    {
+        return;
+    }
+    let Ok((location, tile_transform)) = cursor.get_single() else { return };
+    let Some(layer) = state.active_layer else {
+        warn!("no active layer");
+        return;
+    };
+    let Some(l) = layers.get(layer).ok().filter(|l| l.accepts_tiles()) else {
+        warn!("active layer is hidden, locked, or not a tile layer");
+        return;
+    };
+
+    // hexes covered by each tile of the stamp, including the footprints of
+    // multi-hex tiles
+    let placed: Vec<_> = brush
+        .placed(*location, tile_transform.rotation)
+        .map(|(location, tile_ref, transform, overrides)| {
+            let hexes = match tilesets.get(tile_ref.tileset) {
+                Ok(tileset) => tileset.footprint(tile_ref.tile, location, transform.rotation),
+                Err(_) => vec![location],
+            };
+            (location, tile_ref, transform, overrides, hexes)
+        })
+        .collect();
+    let stamp_hexes: Vec<map::Location> = placed
+        .iter()
+        .flat_map(|(.., hexes)| hexes.iter().copied())
+        .collect();
+
+    // replace any tiles covering a hex under the stamp
+    let mut covered = map::TileCoverage::new(l);
+    let replaced = covered.tiles_at(&stamp_hexes);
+    for (entity, tile_location, parent) in &tiles {
+        if parent.get() == layer
+            && (replaced.contains(&entity) || stamp_hexes.contains(tile_location))
+        {
+            commands.entity(entity).despawn_recursive();
+            covered.remove(entity);
+        }
+    }
+
+    // tiles of the stamp may not overlap each other
+    let mut overlaps = 0;
+    for (location, tile_ref, tile_transform, overrides, hexes) in placed {
+        if covered.overlaps(&hexes, |_| false) {
+            overlaps += 1;
+            continue;
+        }
+        let mut tile = commands.spawn((location, tile_ref, tile_transform));
+        tile.insert(SpatialBundle::default());
+        if !overrides.is_default() {
+            tile.insert(overrides);
+        }
+        tile.set_parent(layer);
+        covered.insert(tile.id(), tile_ref, hexes);
+    }
+    if overlaps > 0 {
+        warn!(
+            "{} stamp tiles not placed; they overlap other tiles",
+            overlaps
+        );
+    }
+    debug!("place stamp {:?} @ {:?}", brush.path, location);
+    // the tilesets added for the preview are now part of the map; the brush
+    // itself is unchanged, so the preview is kept
+    brush.bypass_change_detection().tilesets.clear();
+    state.unsaved_changes = true;
+}
+
+/// click on an object layer to select the object under the cursor, or place a
+/// new object if there is none
+fn place_object(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    cursor: Query<&map::Location, With<MapCursor>>,
+    map: Query<&map::Map>,
+    layers: Query<&map::Layer>,
+    objects: Query<(Entity, &object::MapObject, &Parent)>,
+) {
+    if state.stamp_capture
+        || state.active_region.is_some()
+        || !buttons.just_pressed(MouseButton::Left)
+        || eyedropper_pressed(&keys)
+        || egui_contexts.ctx_mut().is_pointer_over_area()
+    {
+        return;
+    }
+    let Some(layer) = state.active_layer else { return };
+    let Ok(l) = layers.get(layer) else { return };
+    if l.kind != map::LayerKind::Object {
+        return;
+    }
+    let (Ok(location), Ok(map)) = (cursor.get_single(), map.get_single()) else { return };
+
+    let existing = objects
+        .iter()
+        .find(|(_, o, p)| p.get() == layer && o.position.location(map) == *location);
+    if let Some((entity, _, _)) = existing {
+        state.active_object = Some(entity);
+        state.objects_window = true;
+        return;
+    }
+    if !l.editable() {
+        warn!("active layer is hidden or locked");
+        return;
+    }
+
+    let object = object::MapObject::new("marker", object::ObjectPosition::Hex(*location));
+    let entity = commands
+        .spawn((
+            Name::new(format!("object: {}", object.name)),
+            object,
+            SpatialBundle::default(),
+        ))
+        .set_parent(layer)
+        .id();
+    debug!("place object {:?} @ {:?}", entity, location);
+    state.active_object = Some(entity);
+    state.objects_window = true;
+    state.unsaved_changes = true;
+}
+
+/// click on an annotation layer to select the note under the cursor, or place
+/// a new note if there is none
+fn place_annotation(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    cursor: Query<&map::Location, With<MapCursor>>,
+    layers: Query<&map::Layer>,
+    annotations: Query<(Entity, &annotation::Annotation, &Parent)>,
+) {
+    if state.stamp_capture
+        || state.active_region.is_some()
+        || !buttons.just_pressed(MouseButton::Left)
+        || eyedropper_pressed(&keys)
+        || egui_contexts.ctx_mut().is_pointer_over_area()
+    {
+        return;
+    }
+    let Some(layer) = state.active_layer else { return };
+    let Ok(l) = layers.get(layer) else { return };
+    if l.kind != map::LayerKind::Annotation {
+        return;
+    }
+    let Ok(location) = cursor.get_single() else { return };
+
+    let existing = annotations
+        .iter()
+        .find(|(_, a, p)| p.get() == layer && a.location == *location);
+    if let Some((entity, _, _)) = existing {
+        state.active_annotation = Some(entity);
+        state.annotations_window = true;
+        return;
+    }
+    if !l.editable() {
+        warn!("active layer is hidden or locked");
+        return;
+    }
+
+    let entity = commands
+        .spawn((
+            Name::new("annotation"),
+            annotation::Annotation::new(*location, "note"),
+            SpatialBundle::default(),
+        ))
+        .set_parent(layer)
+        .id();
+    debug!("place annotation {:?} @ {:?}", entity, location);
+    state.active_annotation = Some(entity);
+    state.annotations_window = true;
+    state.unsaved_changes = true;
+}
+
+/// delete an object, along with any references to it from other objects &
+/// regions
+fn remove_object(
+    In(entity): In<Entity>,
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut objects: Query<&mut object::MapObject>,
+    mut regions: Query<&mut region::Region>,
+) {
+    use object::Property;
+    let references = |p: &Property| matches!(p, Property::Object(e) if *e == entity);
+    for mut object in &mut objects {
+        if object.properties.values().any(references) {
+            object.properties.retain(|_, p| !references(p));
+        }
+    }
+    for mut region in &mut regions {
+        if region.properties.values().any(references) {
+            region.properties.retain(|_, p| !references(p));
+        }
+    }
+    commands.entity(entity).despawn_recursive();
+    if state.active_object == Some(entity) {
+        state.active_object = None;
+    }
+    state.unsaved_changes = true;
+}
+
+fn remove_region(In(entity): In<Entity>, mut commands: Commands, mut state: ResMut<EditorState>) {
+    commands.entity(entity).despawn_recursive();
+    if state.active_region == Some(entity) {
+        state.active_region = None;
+    }
+    state.unsaved_changes = true;
+}
+
+/// select the tiles for a new stamp by dragging across the map
+fn capture_stamp(
+    mut state: ResMut<EditorState>,
+    mut start: Local<Option<map::Location>>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    cursor: Query<&map::Location, With<MapCursor>>,
+    tiles: Query<
+        (Entity, &map::Location, &Parent),
+        (
+            With<tileset::TileRef>,
+            Without<MapCursor>,
+            Without<StampPreview>,
+        ),
+    >,
+) {
+    if !state.stamp_capture {
+        *start = None;
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        state.stamp_capture = false;
+        return;
+    }
+    let Ok(location) = cursor.get_single() else { return };
+
+    if buttons.just_pressed(MouseButton::Left) && !egui_contexts.ctx_mut().is_pointer_over_area() {
+        *start = Some(*location);
+        return;
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(start) = start.take() else { return };
+    let Some(layer) = state.active_layer else {
+        warn!("no active layer");
+        return;
+    };
+
+    // select all tiles in the active layer between the two corners
+    let (min_x, max_x) = (start.x.min(location.x), start.x.max(location.x));
+    let (min_y, max_y) = (start.y.min(location.y), start.y.max(location.y));
+    let selected: Vec<Entity> = tiles
+        .iter()
+        .filter(|(_, l, parent)| {
+            parent.get() == layer
+                && (min_x..=max_x).contains(&l.x)
+                && (min_y..=max_y).contains(&l.y)
+        })
+        .map(|(entity, _, _)| entity)
+        .collect();
+
+    state.stamp_capture = false;
+    if selected.is_empty() {
+        warn!("no tiles selected for stamp");
+        return;
+    }
+    state.new_stamp = Some(selected);
+}
+
+fn redraw_map_tiles(
+    mut commands: Commands,
+    tile_selection: Res<TileSelection>,
+    tiles: Query<(
+        Entity,
+        &tileset::TileRef,
+        &tileset::TileTransform,
+        &map::Location,
+    )>,
+    tilesets: Query<&tileset::Tileset>,
+    map: Query<&map::Map>,
+) {
+    let Ok(map) = map.get_single() else { return; };
+    for (entity, tile_ref, tile_transform, location) in &tiles {
+        if !tile_selection.tiles.contains(tile_ref) {
+            continue;
+        }
+        let Ok(tileset) = tilesets.get(tile_ref.tileset) else {
+            warn!("unknown tileset {:?} in entity {:?}",
+                tile_ref.tileset, entity);
+            continue;
+        };
+
+        let bundle = tileset::TileBundle::new(
+            map,
+            *location,
+            tile_transform.clone(),
+            tileset,
+            tile_ref.tileset,
+            tile_ref.tile,
+        );
+        commands.entity(entity).insert(bundle);
+    }
+}
+
+fn handle_picker_events(
+    mut commands: Commands,
+    mut picker_events: EventReader<PickerEvent>,
+    mut state: ResMut<EditorState>,
+    mut tilesets: Query<&mut tileset::Tileset>,
+    mut editor_events: EventWriter<EditorUiEvent>,
+    map: Query<Entity, With<map::Map>>,
+) {
+    for event in picker_events.iter() {
+        match event {
+            PickerEvent::AddTiles { tileset_id, files } => {
+                let Ok(mut tileset) = tilesets.get_mut(*tileset_id) else { continue };
+                let Some(paths) = files else { continue };
+                for path in paths {
+                    tileset.add_tile(path.clone());
+                }
+                state.unsaved_changes = true;
+            }
+            PickerEvent::TilesetFolder(tileset_id, dir) => {
+                let Some(dir) = dir else { continue };
+                state.tileset_folder = Some((*tileset_id, dir.clone()));
+            }
+            PickerEvent::MapSave(path) => {
+                let Some(path) = path else { continue };
+                if state.map_path.is_none() {
+                    state.map_path = Some(path.clone());
+                }
+
+                editor_events.send(EditorUiEvent::MapSave(path.clone()));
+            }
+            PickerEvent::MapLoad(path) => {
+                let Some(path) = path else { continue };
+                if state.map_path.is_none() {
+                    state.map_path = Some(path.clone());
+                }
+
+                editor_events.send(EditorUiEvent::MapLoad(path.clone()));
+            }
+            PickerEvent::TmxImport(path) => {
+                let Some(path) = path else { continue };
+                commands.spawn(
+                    file_picker::Picker::new(PickerEvent::TmxMapping {
+                        tmx: path.clone(),
+                        mapping: None,
+                    })
+                    .add_filter("GID Mapping", &["ron"])
+                    .build(),
+                );
+            }
+            PickerEvent::TmxMapping { tmx, mapping } => {
+                let Some(mapping) = mapping else { continue };
+                editor_events.send(EditorUiEvent::MapImportTmx {
+                    tmx: tmx.clone(),
+                    mapping: mapping.clone(),
+                });
+            }
+            PickerEvent::ImportLayers(path) => {
+                let Some(path) = path else { continue };
+                state.import_layers = Some(path.clone());
+            }
+            PickerEvent::GltfExport(path) => {
+                let Some(path) = path else { continue };
+                editor_events.send(EditorUiEvent::MapExportGltf(path.clone()));
+            }
+            PickerEvent::SvgExport(path) => {
+                let Some(path) = path else { continue };
+                editor_events.send(EditorUiEvent::MapExportSvg(path.clone()));
+            }
+            PickerEvent::TilesetImport(paths) => {
+                let Some(paths) = paths else { continue };
+                let Ok(map) = map.get_single() else {
+                    error!("no map found; not loading tileset");
+                    continue;
+                };
+                commands.entity(map).with_children(|map| {
+                    for path in paths {
+                        let id = map.spawn(tileset::TilesetImporter::new(path.clone())).id();
+                        state.active_tileset = Some(id);
+                    }
+                });
+            }
+            PickerEvent::TilesetExport(tileset_id, path) => {
+                let Some(path) = path else { continue };
+                let Ok(tileset) = tilesets.get(*tileset_id) else {
+                    warn!("tileset not found: {:?}", event);
+                    continue;
+                };
+                commands.spawn(tileset::TilesetExporter::new(path.clone(), tileset.clone()));
+                commands
+                    .entity(*tileset_id)
+                    .insert(tileset::TilesetSource(path.clone()));
+            }
+        }
+    }
+    picker_events.clear();
+}
+
+fn handle_map_cursor_events(
+    mut commands: Commands,
+    mut events: EventReader<MapCursorMoveEvent>,
+    mut position: Local<Option<Vec3>>,
+    registry: Res<tool::ToolRegistry>,
+    map: Query<&map::Map>,
+    cursor: Query<Entity, With<MapCursor>>,
+) {
+    // the cursor also snaps again when the active tool changes
+    if let Some(event) = events.iter().last() {
+        *position = Some(event.0);
+    } else if !registry.is_changed() {
+        return;
+    }
+    let Some(pos) = *position else { return };
+    let Ok(map) = map.get_single() else { return };
+    let (_, location) = map.snap_to_grid(pos);
+
+    // update the cursor location; the active tool uses it on the next update
+    let Ok(cursor) = cursor.get_single() else { return };
+    let mut cursor = commands.entity(cursor);
+    cursor.insert(location);
+    match registry
+        .active()
+        .map(|tool| tool.snap())
+        .unwrap_or_default()
+    {
+        tool::ToolSnap::Hex => cursor.remove::<(map::Edge, map::Vertex)>(),
+        tool::ToolSnap::Edge => cursor.insert(map.nearest_edge(pos)).remove::<map::Vertex>(),
+        tool::ToolSnap::Vertex => cursor.insert(map.nearest_vertex(pos)).remove::<map::Edge>(),
+    };
+    trace!("move cursor: {:?}, {:?}", pos, location);
+}
+
+/// remove the tiles previewed by the scatter window once it is hidden
+fn remove_scatter_preview(
+    mut commands: Commands,
+    state: Res<EditorState>,
+    map: Query<(), With<map::Map>>,
+    previews: Query<Entity, With<ScatterPreview>>,
+) {
+    if state.scatter_window && !map.is_empty() {
+        return;
+    }
+    for entity in &previews {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// raise the cursor & picking plane to the height of the active layer, so the
+/// cursor snaps to the layer being edited
+fn follow_active_layer(
+    state: Res<EditorState>,
+    layers: Query<&map::Layer>,
+    changed: Query<(), Changed<map::Layer>>,
+    mut roots: Query<&mut Transform, Or<(With<CursorRoot>, With<GridSelectionPlane>)>>,
+) {
+    if !state.is_changed() && changed.is_empty() {
+        return;
+    }
+    let y = state
+        .active_layer
+        .and_then(|layer| layers.get(layer).ok())
+        .map(|layer| layer.y_offset)
+        .unwrap_or(0.0);
+    for mut transform in &mut roots {
+        if transform.translation.y != y {
+            transform.translation.y = y;
+        }
+    }
+}
+
+/// state of the active tool while a mouse button is held
+#[derive(Resource, Default, Debug)]
+struct ToolState {
+    drag: ToolDrag,
+    stroke: Stroke, // tiles changed since the button was pressed
+}
+
+/// mouse input of the active tool & the edits it previews; used by both the
+/// layer & region tools
+#[derive(Default, Debug)]
+struct ToolDrag {
+    start: Option<map::Location>,  // location the button was pressed at
+    erase: bool,                   // right button held; paint edits erase instead
+    path: Vec<map::Location>,      // locations the cursor passed through
+    input: Option<ToolStateInput>, // input used for the current edits
+    preview: Vec<tool::ToolEdit>,  // edits shown as the preview
+}
+
+impl ToolDrag {
+    /// start dragging when a mouse button is pressed over the map, and follow
+    /// the cursor while it is held; returns true if the drag just started
+    fn update(
+        &mut self,
+        location: map::Location,
+        buttons: &Input<MouseButton>,
+        over_map: bool,
+    ) -> bool {
+        let pressed = buttons.any_just_pressed([MouseButton::Left, MouseButton::Right]);
+        if self.start.is_none() && over_map && pressed {
+            self.start = Some(location);
+            self.erase = !buttons.just_pressed(MouseButton::Left);
+            self.path = vec![location];
+            return true;
+        }
+        if self.start.is_some() && self.path.last() != Some(&location) {
+            self.path.push(location);
+        }
+        false
+    }
+
+    /// check if the buttons were released, ending the drag
+    fn released(&self, buttons: &Input<MouseButton>) -> bool {
+        self.start.is_some() && !buttons.any_pressed([MouseButton::Left, MouseButton::Right])
+    }
+
+    /// keep the input the edits are calculated from; returns false if the
+    /// input didn't change
+    fn set_input(&mut self, input: ToolStateInput) -> bool {
+        if self.input.as_ref() == Some(&input) {
+            return false;
+        }
+        self.input = Some(input);
+        true
+    }
+
+    /// check if the edits are applied to the map; continuous tools apply
+    /// their edits while a button is held, drag tools once it is released
+    fn commits(&self, mode: tool::ToolMode, released: bool) -> bool {
+        match mode {
+            tool::ToolMode::Continuous => self.start.is_some(),
+            tool::ToolMode::Drag => released,
+        }
+    }
+
+    /// forget the drag once the buttons are released
+    fn finish(&mut self) {
+        self.start = None;
+        self.erase = false;
+        self.path.clear();
+    }
+}
+
+/// inputs that change the edits made by a tool
+#[derive(Debug, Clone, PartialEq)]
+struct ToolStateInput {
+    cursor: map::Location,
+    edge: Option<map::Edge>,
+    vertex: Option<map::Vertex>,
+    start: Option<map::Location>,
+    erase: bool,
+    tool: usize,
+    radius: u32,
+    brush: Option<tileset::TileRef>,
+    rotation: tileset::TileRotation,
+}
+
+/// where a tile recorded in the `EditHistory` is placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum HistoryKey {
+    Hex(map::Location),
+    Edge(map::Edge),
+    Vertex(map::Vertex),
+}
+
+/// a tile recorded in the `EditHistory`
+#[derive(Debug, Clone, PartialEq)]
+struct HistoryTile {
+    tile_ref: tileset::TileRef,
+    transform: tileset::TileTransform,
+    overrides: Option<tileset::TileOverrides>,
+}
+
+impl HistoryTile {
+    fn new(
+        tile_ref: &tileset::TileRef,
+        transform: &tileset::TileTransform,
+        overrides: Option<&tileset::TileOverrides>,
+    ) -> Self {
+        Self {
+            tile_ref: *tile_ref,
+            transform: transform.clone(),
+            overrides: overrides.copied(),
+        }
+    }
+}
+
+/// tiles changed by one stroke of a tool
+#[derive(Debug, Default)]
+struct Stroke {
+    layer: Option<Entity>,
+    seed: Option<u64>, // random brush seed used by the stroke
+    before: HashMap<HistoryKey, Option<HistoryTile>>,
+    after: HashMap<HistoryKey, Option<HistoryTile>>,
+}
+
+impl Stroke {
+    /// record a change; the stroke keeps the first tile before and the last
+    /// tile after each change at a location
+    fn record(&mut self, key: HistoryKey, before: Option<HistoryTile>, after: Option<HistoryTile>) {
+        self.before.entry(key).or_insert(before);
+        self.after.insert(key, after);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.before
+            .iter()
+            .all(|(key, tile)| self.after.get(key) == Some(tile))
+    }
+}
+
+/// strokes recorded by an edit of the selection, which may change tiles in
+/// several layers; each layer is undone separately
+#[derive(Debug, Default)]
+struct LayerStrokes(HashMap<Entity, Stroke>);
+
+impl LayerStrokes {
+    fn layer(&mut self, layer: Entity) -> &mut Stroke {
+        self.0.entry(layer).or_insert_with(|| Stroke {
+            layer: Some(layer),
+            ..default()
+        })
+    }
+}
+
+/// tool strokes that can be undone & redone
+#[derive(Resource, Debug, Default)]
+struct EditHistory {
+    undo: Vec<Stroke>,
+    redo: Vec<Stroke>,
+}
+
+impl EditHistory {
+    /// add the strokes of an edit to the undo history
+    fn push(&mut self, strokes: impl IntoIterator<Item = Stroke>) {
+        let len = self.undo.len();
+        self.undo
+            .extend(strokes.into_iter().filter(|stroke| !stroke.is_empty()));
+        if self.undo.len() > len {
+            self.redo.clear();
+        }
+    }
+
+    /// check if undoing or redoing a stroke would place a removed tile
+    fn uses_tiles(&self, removal: &TileRemoval) -> bool {
+        self.undo
+            .iter()
+            .chain(&self.redo)
+            .flat_map(|stroke| stroke.before.values().chain(stroke.after.values()))
+            .flatten()
+            .any(|tile| removal.removes(&tile.tile_ref))
+    }
+}
+
+/// meshes & materials used to preview tool edits and draw the selection
+#[derive(Resource)]
+struct ToolPreviewAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    select_material: Handle<StandardMaterial>,
+    selection_material: Handle<StandardMaterial>,
+}
+
+/// entity showing an edit the active tool will make
+#[derive(Component, Debug, Default)]
+struct ToolPreview;
+
+/// entity showing a tile the scatter window will place
+#[derive(Component, Debug)]
+struct ScatterPreview;
+
+/// entity showing the hexes the active tool will add to or remove from the
+/// active region
+#[derive(Component, Debug, Default)]
+struct RegionPreview;
+
+/// entities previewing the edits of a tool
+#[derive(Default)]
+struct ToolPreviews {
+    tiles: Vec<(map::Location, HistoryTile)>, // tiles placed by the edits
+    erased: Vec<(Vec3, f32)>,                 // translation & scale of erased tile markers
+    outline: HashSet<map::Location>,          // hexes selected or covered by placed tiles
+    erased_outline: HashSet<map::Location>,   // hexes removed by the edits
+}
+
+impl ToolPreviews {
+    /// spawn the previews below the cursor root
+    fn spawn<T: Component + Default>(
+        self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        assets: &ToolPreviewAssets,
+        map: &map::Map,
+        root: Entity,
+        name: &'static str,
+    ) {
+        for (location, tile) in self.tiles {
+            commands
+                .spawn((
+                    Name::new(name),
+                    T::default(),
+                    location,
+                    tile.tile_ref,
+                    tile.transform,
+                    SpatialBundle::default(),
+                ))
+                .set_parent(root);
+        }
+        for (translation, scale) in self.erased {
+            commands
+                .spawn((
+                    Name::new(name),
+                    T::default(),
+                    PbrBundle {
+                        mesh: assets.mesh.clone(),
+                        material: assets.material.clone(),
+                        transform: Transform::from_translation(translation + Vec3::Y * 0.05)
+                            .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2))
+                            .with_scale(Vec3::splat(scale)),
+                        ..default()
+                    },
+                ))
+                .set_parent(root);
+        }
+        for (hexes, material) in [
+            (self.outline, &assets.select_material),
+            (self.erased_outline, &assets.material),
+        ] {
+            if hexes.is_empty() {
+                continue;
+            }
+            commands
+                .spawn((
+                    Name::new(name),
+                    T::default(),
+                    PbrBundle {
+                        mesh: meshes.add(selection::outline_mesh(&map.layout, &hexes, 0.15)),
+                        material: material.clone(),
+                        transform: Transform::from_translation(Vec3::Y * 0.07),
+                        ..default()
+                    },
+                ))
+                .set_parent(root);
+        }
+    }
+}
+
+/// tiles of the active layer seen by the tools
+#[derive(Default)]
+struct LayerTiles {
+    hexes: HashMap<map::Location, (Entity, HistoryTile)>,
+    edges: HashMap<map::Edge, (Entity, HistoryTile)>,
+    vertices: HashMap<map::Vertex, (Entity, HistoryTile)>,
+    // every hex covered by a tile, including the footprints of multi-hex
+    // tiles; updated as tiles are placed & removed
+    covered: map::TileCoverage,
+}
+
+/// applies the edits of a tool to the active layer, recording the changed
+/// tiles in the stroke
+struct ToolCommit<'a, 'w, 's> {
+    commands: &'a mut Commands<'w, 's>,
+    layer: Entity,
+    tiles: &'a mut LayerTiles,
+    stroke: &'a mut Stroke,
+    changed: bool,
+    overlaps: usize, // tiles not placed as they would overlap other tiles
+}
+
+impl ToolCommit<'_, '_, '_> {
+    /// check if the tile placed somewhere is the tile being painted; the
+    /// overrides of the placed tile are kept
+    fn unchanged(current: Option<&(Entity, HistoryTile)>, tile: &HistoryTile) -> bool {
+        let Some((_, current)) = current else { return false };
+        current.tile_ref == tile.tile_ref && current.transform == tile.transform
+    }
+
+    /// place a tile at a hex, replacing the tile there; the tile isn't placed
+    /// if it would overlap any other tile
+    fn paint(&mut self, location: map::Location, tile: HistoryTile, hexes: Vec<map::Location>) {
+        let current = self.tiles.hexes.get(&location).cloned();
+        if Self::unchanged(current.as_ref(), &tile) {
+            return;
+        }
+        let replaced = current.as_ref().map(|(entity, _)| *entity);
+        if self
+            .tiles
+            .covered
+            .overlaps(&hexes, |entity| Some(entity) == replaced)
+        {
+            self.overlaps += 1;
+            return;
+        }
+        if let Some(entity) = replaced {
+            self.commands.entity(entity).despawn_recursive();
+            self.tiles.covered.remove(entity);
+        }
+        debug!("insert tile: {:?} @ {:?}", tile.tile_ref, location);
+        let entity = self
+            .commands
+            .spawn((
+                location,
+                tile.tile_ref,
+                tile.transform.clone(),
+                SpatialBundle::default(),
+            ))
+            .set_parent(self.layer)
+            .id();
+        self.tiles.covered.insert(entity, tile.tile_ref, hexes);
+        let before = current.map(|(_, tile)| tile);
+        self.stroke
+            .record(HistoryKey::Hex(location), before, Some(tile));
+        self.changed = true;
+    }
+
+    /// remove the tile covering a hex; erasing any hex of a multi-hex tile
+    /// removes the tile
+    fn erase(&mut self, location: map::Location) {
+        let Some((entity, _)) = self.tiles.covered.get(&location) else { return };
+        self.commands.entity(entity).despawn_recursive();
+        self.tiles.covered.remove(entity);
+        // the tile is recorded at its own location
+        let origin = self.tiles.hexes.iter().find(|(_, (e, _))| *e == entity);
+        if let Some((origin, (_, tile))) = origin {
+            let key = HistoryKey::Hex(*origin);
+            self.stroke.record(key, Some(tile.clone()), None);
+        }
+        self.changed = true;
+    }
+
+    /// move tiles to other hexes, replacing the tiles at their destinations;
+    /// nothing is moved if a moved multi-hex tile would overlap a tile that
+    /// stays in place.  Returns false if the tiles were not moved.
+    fn move_tiles(
+        &mut self,
+        moves: &[(map::Location, map::Location)],
+        footprint: impl Fn(&HistoryTile, map::Location) -> Vec<map::Location>,
+        selection: &mut HashSet<Entity>,
+    ) -> bool {
+        let hexes = &self.tiles.hexes;
+        let moved: HashSet<Entity> = moves
+            .iter()
+            .filter_map(|(from, _)| hexes.get(from))
+            .map(|(entity, _)| *entity)
+            .collect();
+        let replaced: HashSet<Entity> = moves
+            .iter()
+            .filter_map(|(_, to)| hexes.get(to))
+            .map(|(entity, _)| *entity)
+            .collect();
+        let overlaps = moves
+            .iter()
+            .filter(|(from, to)| {
+                let Some((_, tile)) = hexes.get(from) else { return false };
+                let ignored = |e: Entity| moved.contains(&e) || replaced.contains(&e);
+                self.tiles.covered.overlaps(&footprint(tile, *to), ignored)
+            })
+            .count();
+        if overlaps > 0 {
+            warn!(
+                "tiles not moved; {} moved tiles would overlap other tiles",
+                overlaps
+            );
+            return false;
+        }
+
+        // every tile is removed from its old location before the moved tiles
+        // are recorded at their destinations
+        for (from, to) in moves {
+            let Some((entity, tile)) = self.tiles.hexes.get(from) else { continue };
+            self.commands.entity(*entity).insert(*to);
+            self.stroke
+                .record(HistoryKey::Hex(*from), Some(tile.clone()), None);
+            self.changed = true;
+        }
+        for (from, to) in moves {
+            let current = self.tiles.hexes.get(to);
+            if let Some((_, tile)) = self.tiles.hexes.get(from) {
+                let before = current.map(|(_, tile)| tile.clone());
+                self.stroke
+                    .record(HistoryKey::Hex(*to), before, Some(tile.clone()));
+            }
+            let Some((entity, _)) = current else { continue };
+            if !moved.contains(entity) {
+                self.commands.entity(*entity).despawn_recursive();
+                selection.remove(entity);
+            }
+        }
+        true
+    }
+
+    fn paint_edge(&mut self, edge: map::Edge, tile: HistoryTile) {
+        let current = self.tiles.edges.get(&edge).cloned();
+        self.paint_part(HistoryKey::Edge(edge), edge, current, tile);
+    }
+
+    fn erase_edge(&mut self, edge: map::Edge) {
+        let current = self.tiles.edges.get(&edge).cloned();
+        self.erase_part(HistoryKey::Edge(edge), current);
+    }
+
+    fn paint_vertex(&mut self, vertex: map::Vertex, tile: HistoryTile) {
+        let current = self.tiles.vertices.get(&vertex).cloned();
+        self.paint_part(HistoryKey::Vertex(vertex), vertex, current, tile);
+    }
+
+    fn erase_vertex(&mut self, vertex: map::Vertex) {
+        let current = self.tiles.vertices.get(&vertex).cloned();
+        self.erase_part(HistoryKey::Vertex(vertex), current);
+    }
+
+    /// place a tile on an edge or vertex, replacing the tile there
+    fn paint_part(
+        &mut self,
+        key: HistoryKey,
+        part: impl Component + std::fmt::Debug,
+        current: Option<(Entity, HistoryTile)>,
+        tile: HistoryTile,
+    ) {
+        if Self::unchanged(current.as_ref(), &tile) {
+            return;
+        }
+        if let Some((entity, _)) = &current {
+            self.commands.entity(*entity).despawn_recursive();
+        }
+        debug!("insert tile: {:?} @ {:?}", tile.tile_ref, part);
+        self.commands
+            .spawn((
+                part,
+                tile.tile_ref,
+                tile.transform.clone(),
+                SpatialBundle::default(),
+            ))
+            .set_parent(self.layer);
+        self.stroke
+            .record(key, current.map(|(_, tile)| tile), Some(tile));
+        self.changed = true;
+    }
+
+    /// remove the tile on an edge or vertex
+    fn erase_part(&mut self, key: HistoryKey, current: Option<(Entity, HistoryTile)>) {
+        let Some((entity, tile)) = current else { return };
+        self.commands.entity(entity).despawn_recursive();
+        self.stroke.record(key, Some(tile), None);
+        self.changed = true;
+    }
+}
+
+/// apply the active tool to the active region
+///
+/// The tools see the tiles of the active layer, so the selection & fill tools
+/// pick hexes by the tiles in the layer.  Painted & selected hexes are added
+/// to the region; erased hexes, and any hexes edited with the right button,
+/// are removed.
+fn apply_region_tool(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut drag: Local<ToolDrag>,
+    registry: Res<tool::ToolRegistry>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    preview_assets: Res<ToolPreviewAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    map: Query<&map::Map>,
+    cursor: Query<(&map::Location, &Parent), With<MapCursor>>,
+    tiles: Query<(&map::Location, &tileset::TileRef, &Parent), Without<MapCursor>>,
+    mut regions: Query<&mut region::Region>,
+    previews: Query<Entity, With<RegionPreview>>,
+) {
+    use tool::{ToolEdit, ToolMode};
+
+    let Ok(map) = map.get_single() else { return };
+    let Ok((location, root)) = cursor.get_single() else { return };
+    let active = state
+        .active_region
+        .filter(|e| regions.contains(*e))
+        .zip(registry.active());
+    let Some((region_entity, tool)) = active else {
+        if !drag.preview.is_empty() {
+            for entity in &previews {
+                commands.entity(entity).despawn_recursive();
+            }
+            *drag = ToolDrag::default();
+        }
+        return;
+    };
+
+    let over_map = !eyedropper_pressed(&keys) && !egui_contexts.ctx_mut().is_pointer_over_area();
+    drag.update(*location, &buttons, over_map);
+    let released = drag.released(&buttons);
+
+    let input = ToolStateInput {
+        cursor: *location,
+        edge: None,
+        vertex: None,
+        start: drag.start,
+        erase: drag.erase,
+        tool: registry.active_index(),
+        radius: registry.radius,
+        brush: None,
+        rotation: tileset::TileRotation::None,
+    };
+    if !drag.set_input(input) && !released {
+        return;
+    }
+
+    let layer_tiles: HashMap<map::Location, tileset::TileRef> = tiles
+        .iter()
+        .filter(|(_, _, parent)| Some(parent.get()) == state.active_layer)
+        .map(|(location, tile_ref, _)| (*location, *tile_ref))
+        .collect();
+    let mut edits = tool.edits(&tool::ToolInput {
+        start: drag.start.unwrap_or(*location),
+        cursor: *location,
+        radius: registry.radius,
+        tiles: &layer_tiles,
+        brush: None,
+        path: &drag.path,
+        selection: &HashSet::new(),
+        edge: None,
+        vertex: None,
+    });
+    // regions are made of hexes; moves, edges & vertices don't apply
+    edits.retain(|edit| {
+        matches!(
+            edit,
+            ToolEdit::Paint(_) | ToolEdit::Erase(_) | ToolEdit::Select(_)
+        )
+    });
+
+    let commit = drag.commits(tool.mode(), released);
+    let erase = drag.erase;
+    if released {
+        drag.finish();
+    }
+
+    if commit {
+        let mut region = regions.get_mut(region_entity).unwrap();
+        let mut changed = false;
+        for edit in &edits {
+            let location = edit.location();
+            changed |= match edit {
+                ToolEdit::Erase(_) => region.locations.remove(&location),
+                _ if erase => region.locations.remove(&location),
+                _ => region.locations.insert(location),
+            };
+        }
+        if changed {
+            state.unsaved_changes = true;
+        }
+        if tool.mode() == ToolMode::Drag {
+            edits.clear();
+        }
+    }
+
+    if edits == drag.preview {
+        return;
+    }
+    for entity in &previews {
+        commands.entity(entity).despawn_recursive();
+    }
+    let mut preview = ToolPreviews::default();
+    for edit in &edits {
+        match edit {
+            ToolEdit::Erase(l) => preview.erased_outline.insert(*l),
+            _ if erase => preview.erased_outline.insert(edit.location()),
+            _ => preview.outline.insert(edit.location()),
+        };
+    }
+    preview.spawn::<RegionPreview>(
+        &mut commands,
+        &mut meshes,
+        &preview_assets,
+        map,
+        root.get(),
+        "region_preview",
+    );
+    drag.preview = edits;
+}
+
+/// apply the active tool to the active layer
+fn apply_tool(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut tool_state: ResMut<ToolState>,
+    (mut selection, mut history): (ResMut<MapSelection>, ResMut<EditHistory>),
+    registry: Res<tool::ToolRegistry>,
+    (brush, tile_selection, mut random_brush): (
+        Res<StampBrush>,
+        Res<TileSelection>,
+        ResMut<brush::RandomBrush>,
+    ),
+    (buttons, keys): (Res<Input<MouseButton>>, Res<Input<KeyCode>>),
+    mut egui_contexts: EguiContexts,
+    preview_assets: Res<ToolPreviewAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    map: Query<&map::Map>,
+    cursor: Query<
+        (
+            (&map::Location, Option<&map::Edge>, Option<&map::Vertex>),
+            Option<&tileset::TileRef>,
+            &tileset::TileTransform,
+            &Parent,
+        ),
+        With<MapCursor>,
+    >,
+    tiles: Query<
+        (
+            Entity,
+            &map::Location,
+            &tileset::TileRef,
+            &tileset::TileTransform,
+            &Parent,
+        ),
+        (
+            Without<MapCursor>,
+            Without<StampPreview>,
+            Without<ToolPreview>,
+        ),
+    >,
+    (edge_tiles, vertex_tiles): (
+        Query<
+            (
+                Entity,
+                &map::Edge,
+                &tileset::TileRef,
+                &tileset::TileTransform,
+                &Parent,
+            ),
+            (Without<MapCursor>, Without<ToolPreview>),
+        >,
+        Query<
+            (
+                Entity,
+                &map::Vertex,
+                &tileset::TileRef,
+                &tileset::TileTransform,
+                &Parent,
+            ),
+            (Without<MapCursor>, Without<ToolPreview>),
+        >,
+    ),
+    previews: Query<Entity, With<ToolPreview>>,
+    (layers, tilesets, overrides): (
+        Query<&map::Layer>,
+        Query<&tileset::Tileset>,
+        Query<&tileset::TileOverrides>,
+    ),
+) {
+    use tool::{ToolEdit, ToolMode};
+
+    let Ok(map) = map.get_single() else { return };
+    let Ok((position, brush_tile, tile_transform, root)) = cursor.get_single() else { return };
+    let (location, cursor_edge, cursor_vertex) = position;
+    let (Some(tool), Some(layer)) = (registry.active(), state.active_layer) else { return };
+    let ToolState { drag, stroke } = &mut *tool_state;
+
+    // stamps are placed & captured by their own systems, regions are edited by
+    // apply_region_tool, and tiles can only be placed on visible, unlocked tile
+    // layers
+    let editable = matches!(layers.get(layer), Ok(l) if l.accepts_tiles());
+    if state.stamp_capture || !brush.tiles.is_empty() || state.active_region.is_some() || !editable
+    {
+        drag.start = None;
+        drag.input = None;
+        if !drag.preview.is_empty() {
+            for entity in &previews {
+                commands.entity(entity).despawn_recursive();
+            }
+            drag.preview.clear();
+        }
+        return;
+    }
+
+    let over_map = !eyedropper_pressed(&keys) && !egui_contexts.ctx_mut().is_pointer_over_area();
+    if drag.update(*location, &buttons, over_map) {
+        *stroke = Stroke {
+            layer: Some(layer),
+            seed: random_brush.enabled.then_some(random_brush.seed),
+            ..default()
+        };
+    }
+    let released = drag.released(&buttons);
+
+    // only recalculate the edits when something has changed
+    let input = ToolStateInput {
+        cursor: *location,
+        edge: cursor_edge.copied(),
+        vertex: cursor_vertex.copied(),
+        start: drag.start,
+        erase: drag.erase,
+        tool: registry.active_index(),
+        radius: registry.radius,
+        brush: brush_tile.copied(),
+        rotation: tile_transform.rotation,
+    };
+    let random_changed = random_brush.is_changed() || tile_selection.is_changed();
+    if !drag.set_input(input) && !released && !selection.is_changed() && !random_changed {
+        return;
+    }
+    // the random brush may pick different tiles for the same edits
+    if random_changed {
+        drag.preview.clear();
+    }
+
+    let tile = |entity: Entity, tile_ref: &tileset::TileRef, transform: &tileset::TileTransform| {
+        let overrides = overrides.get(entity).ok();
+        (entity, HistoryTile::new(tile_ref, transform, overrides))
+    };
+    let mut existing = LayerTiles {
+        hexes: tiles
+            .iter()
+            .filter(|(_, _, _, _, parent)| parent.get() == layer)
+            .map(|(entity, location, tile_ref, transform, _)| {
+                (*location, tile(entity, tile_ref, transform))
+            })
+            .collect(),
+        edges: edge_tiles
+            .iter()
+            .filter(|(_, _, _, _, parent)| parent.get() == layer)
+            .map(|(entity, edge, tile_ref, transform, _)| {
+                (*edge, tile(entity, tile_ref, transform))
+            })
+            .collect(),
+        vertices: vertex_tiles
+            .iter()
+            .filter(|(_, _, _, _, parent)| parent.get() == layer)
+            .map(|(entity, vertex, tile_ref, transform, _)| {
+                (*vertex, tile(entity, tile_ref, transform))
+            })
+            .collect(),
+        covered: layer_coverage(layers.get(layer).ok(), |e| tiles.contains(e)),
+    };
+
+    // tools see every hex covered by a tile, including the footprints of
+    // multi-hex tiles
+    let layer_tiles: HashMap<map::Location, tileset::TileRef> = existing
+        .covered
+        .hexes
+        .iter()
+        .map(|(location, (_, tile_ref))| (*location, *tile_ref))
+        .collect();
+    let selected: HashSet<map::Location> = selection
+        .tiles
+        .iter()
+        .filter_map(|entity| tiles.get(*entity).ok())
+        .map(|(_, location, _, _, _)| *location)
+        .collect();
+    let mut edits = tool.edits(&tool::ToolInput {
+        start: drag.start.unwrap_or(*location),
+        cursor: *location,
+        radius: registry.radius,
+        tiles: &layer_tiles,
+        brush: brush_tile.copied(),
+        path: &drag.path,
+        selection: &selected,
+        edge: cursor_edge.copied(),
+        vertex: cursor_vertex.copied(),
+    });
+    if drag.erase && drag.start.is_some() {
+        for edit in edits.iter_mut() {
+            *edit = edit.to_erase();
+        }
+    }
+
+    let commit = drag.commits(tool.mode(), released);
+    let erase = drag.erase;
+    if released {
+        drag.finish();
+    }
+
+    // tile painted at a location; the random brush picks one of the selected
+    // tiles for each location
+    let picker = random_brush.enabled.then(|| random_brush.clone());
+    let random_tiles: Vec<tileset::TileRef> = tile_selection.tiles.iter().copied().collect();
+    let paint_tile = |location: map::Location| {
+        let tile_ref = brush_tile?;
+        let Some(picker) = &picker else {
+            return Some(HistoryTile::new(tile_ref, tile_transform, None));
+        };
+        let (tile_ref, rotation) = picker.pick(&random_tiles, location, tile_transform.rotation)?;
+        let transform = tileset::TileTransform { rotation };
+        Some(HistoryTile::new(&tile_ref, &transform, None))
+    };
+
+    // hexes covered by a tile placed at a location
+    let footprint = |tile: &HistoryTile, location: map::Location| {
+        let rotation = tile.transform.rotation;
+        match tilesets.get(tile.tile_ref.tileset) {
+            Ok(tileset) => tileset.footprint(tile.tile_ref.tile, location, rotation),
+            Err(_) => vec![location],
+        }
+    };
+
+    if commit {
+        let mut selected = HashSet::new();
+        let mut moves = Vec::new();
+        let mut changes = ToolCommit {
+            commands: &mut commands,
+            layer,
+            tiles: &mut existing,
+            stroke: &mut *stroke,
+            changed: false,
+            overlaps: 0,
+        };
+        let brush_tile =
+            brush_tile.map(|tile_ref| HistoryTile::new(tile_ref, tile_transform, None));
+        for edit in &edits {
+            match *edit {
+                ToolEdit::Paint(l) => {
+                    let Some(tile) = paint_tile(l) else { continue };
+                    let hexes = footprint(&tile, l);
+                    changes.paint(l, tile, hexes);
+                }
+                ToolEdit::Erase(l) => changes.erase(l),
+                ToolEdit::Select(l) => {
+                    selected.extend(changes.tiles.covered.get(&l).map(|(entity, _)| entity));
+                }
+                // moved tiles replace each other, so they are moved together
+                ToolEdit::Move { from, to } => moves.push((from, to)),
+                ToolEdit::PaintEdge(edge) => {
+                    let Some(tile) = brush_tile.clone() else { continue };
+                    changes.paint_edge(edge, tile);
+                }
+                ToolEdit::EraseEdge(edge) => changes.erase_edge(edge),
+                ToolEdit::PaintVertex(vertex) => {
+                    let Some(tile) = brush_tile.clone() else { continue };
+                    changes.paint_vertex(vertex, tile);
+                }
+                ToolEdit::EraseVertex(vertex) => changes.erase_vertex(vertex),
+            }
+        }
+        if !moves.is_empty() && !changes.move_tiles(&moves, footprint, &mut selection.tiles) {
+            edits.retain(|edit| !matches!(edit, ToolEdit::Move { .. }));
+        }
+        if changes.overlaps > 0 {
+            warn!(
+                "{} tiles not placed; they overlap other tiles",
+                changes.overlaps
+            );
+        }
+        if changes.changed {
+            state.unsaved_changes = true;
+        }
+
+        // shift adds to the selection, right button removes from it
+        if tool.selects() {
+            if erase {
+                selection.tiles.retain(|e| !selected.contains(e));
+            } else if keys.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+                selection.tiles.extend(selected);
+            } else {
+                selection.tiles = selected;
+            }
+        }
+
+        // the stroke can be undone once the button is released; the random
+        // brush gets a new seed so the next stroke picks different tiles
+        if released {
+            let stroke = std::mem::take(stroke);
+            if !stroke.is_empty() && stroke.seed.is_some() {
+                random_brush.reseed();
+            }
+            history.push([stroke]);
+        }
+
+        // the committed edits are now on the map
+        if tool.mode() == ToolMode::Drag {
+            edits.clear();
+        }
+    }
+
+    if edits == drag.preview {
+        return;
+    }
+    for entity in &previews {
+        commands.entity(entity).despawn_recursive();
+    }
+    let mut preview = ToolPreviews::default();
+    for edit in &edits {
+        match *edit {
+            ToolEdit::Paint(l) => {
+                let Some(tile) = paint_tile(l) else { continue };
+                // outline the hexes covered by multi-hex tiles
+                let hexes = footprint(&tile, l);
+                if hexes.len() > 1 {
+                    preview.outline.extend(hexes);
+                }
+                // the cursor already shows the tile at its location
+                if l != *location {
+                    preview.tiles.push((l, tile));
+                }
+            }
+            // edges & vertices get a smaller marker
+            ToolEdit::Erase(l) => preview.erased.push((map.translation(l), 1.0)),
+            ToolEdit::EraseEdge(edge) => preview.erased.push((map.edge_translation(edge), 0.3)),
+            ToolEdit::EraseVertex(vertex) => {
+                preview.erased.push((map.vertex_translation(vertex), 0.3));
+            }
+            // the cursor already shows the tile on the edge or vertex
+            ToolEdit::PaintEdge(_) | ToolEdit::PaintVertex(_) => (),
+            ToolEdit::Select(l) => {
+                preview.outline.insert(l);
+            }
+            ToolEdit::Move { from, to } => {
+                preview.outline.insert(to);
+                if let Some((_, tile)) = existing.hexes.get(&from) {
+                    preview.tiles.push((to, tile.clone()));
+                }
+            }
+        }
+    }
+    preview.spawn::<ToolPreview>(
+        &mut commands,
+        &mut meshes,
+        &preview_assets,
+        map,
+        root.get(),
+        "tool_preview",
+    );
+    drag.preview = edits;
+}
+
+/// redraw the outline of the selected tiles when they change
+fn update_selection_outline(
+    mut commands: Commands,
+    mut selection: ResMut<MapSelection>,
+    mut outlined: Local<HashSet<map::Location>>,
+    preview_assets: Res<ToolPreviewAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    map: Query<&map::Map>,
+    tiles: Query<&map::Location>,
+    outline: Query<Entity, With<SelectionOutline>>,
+    cursor_root: Query<Entity, With<CursorRoot>>,
+) {
+    // drop tiles that have been despawned
+    if selection.tiles.iter().any(|e| tiles.get(*e).is_err()) {
+        selection.tiles.retain(|e| tiles.get(*e).is_ok());
+    }
+
+    let locations: HashSet<map::Location> = selection
+        .tiles
+        .iter()
+        .filter_map(|e| tiles.get(*e).ok())
+        .copied()
+        .collect();
+    if locations == *outlined {
+        return;
+    }
+
+    for entity in &outline {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let (Ok(map), Ok(root), false) = (
+        map.get_single(),
+        cursor_root.get_single(),
+        locations.is_empty(),
+    ) {
+        let mesh = selection::outline_mesh(&map.layout, &locations, 0.1);
+        commands
+            .spawn((
+                Name::new("selection_outline"),
+                SelectionOutline,
+                PbrBundle {
+                    mesh: meshes.add(mesh),
+                    material: preview_assets.selection_material.clone(),
+                    transform: Transform::from_translation(Vec3::Y * 0.06),
+                    ..default()
+                },
+            ))
+            .set_parent(root);
+    }
+    *outlined = locations;
+}
+
+/// rotate the selected tiles around the tile nearest their center; the
+/// rotation is recorded in the undo history.  The selection is not rotated if
+/// a rotated tile would overlap a tile that isn't replaced.
+fn rotate_selection(
+    In(rotation): In<tileset::TileRotation>,
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut selection: ResMut<MapSelection>,
+    mut history: ResMut<EditHistory>,
+    mut tiles: Query<(
+        Entity,
+        &mut map::Location,
+        &tileset::TileRef,
+        &mut tileset::TileTransform,
+        &Parent,
+    )>,
+    mut overrides: Query<&mut tileset::TileOverrides>,
+    (layers, tilesets): (Query<&map::Layer>, Query<&tileset::Tileset>),
+) {
+    // tiles in hidden or locked layers are left in place
+    let editable = |layer: Entity| matches!(layers.get(layer), Ok(l) if l.editable());
+    let selected: Vec<(map::Location, Entity)> = selection
+        .tiles
+        .iter()
+        .filter_map(|e| tiles.get(*e).ok())
+        .filter(|(_, _, _, _, parent)| editable(parent.get()))
+        .map(|(entity, location, _, _, _)| (*location, entity))
+        .collect();
+    let Some(pivot) = selection::pivot(selected.iter().map(|(l, _)| l)) else { return };
+    let rotated: HashSet<Entity> = selected.iter().map(|(_, e)| *e).collect();
+
+    // tiles not in the selection are replaced by rotated tiles
+    let mut destinations: HashMap<Entity, HashSet<map::Location>> = HashMap::new();
+    for (entity, location, _, _, parent) in &tiles {
+        if rotated.contains(&entity) {
+            destinations
+                .entry(parent.get())
+                .or_default()
+                .insert(selection::rotate_about(*location, pivot, rotation));
+        }
+    }
+    let replaced: HashSet<Entity> = tiles
+        .iter()
+        .filter(|(entity, location, _, _, parent)| {
+            let destination = destinations.get(&parent.get());
+            let replaced = destination.map_or(false, |d| d.contains(*location));
+            replaced && !selection.tiles.contains(entity)
+        })
+        .map(|(entity, ..)| entity)
+        .collect();
+
+    // the footprints of rotated multi-hex tiles may only cover hexes of the
+    // rotated & replaced tiles
+    let mut coverage: HashMap<Entity, map::TileCoverage> = HashMap::new();
+    let mut overlaps = 0;
+    for (entity, location, tile_ref, transform, parent) in &tiles {
+        if !rotated.contains(&entity) {
+            continue;
+        }
+        let location = selection::rotate_about(*location, pivot, rotation);
+        let rotation = transform.rotation.rotate_by(rotation);
+        let hexes = match tilesets.get(tile_ref.tileset) {
+            Ok(tileset) => tileset.footprint(tile_ref.tile, location, rotation),
+            Err(_) => vec![location],
+        };
+        let covered = coverage.entry(parent.get()).or_insert_with(|| {
+            layer_coverage(layers.get(parent.get()).ok(), |e| tiles.contains(e))
+        });
+        if covered.overlaps(&hexes, |e| rotated.contains(&e) || replaced.contains(&e)) {
+            overlaps += 1;
+        }
+    }
+    if overlaps > 0 {
+        warn!(
+            "selection not rotated; {} rotated tiles would overlap other tiles",
+            overlaps
+        );
+        return;
+    }
+
+    // the rotated tiles are recorded once every tile has been removed from
+    // its old location
+    let mut strokes = LayerStrokes::default();
+    let mut placed = Vec::new();
+    for (entity, mut location, tile_ref, mut transform, parent) in &mut tiles {
+        if !rotated.contains(&entity) {
+            continue;
+        }
+        let before = HistoryTile::new(tile_ref, &transform, overrides.get(entity).ok());
+        strokes
+            .layer(parent.get())
+            .record(HistoryKey::Hex(*location), Some(before), None);
+
+        *location = selection::rotate_about(*location, pivot, rotation);
+        transform.rotation = transform.rotation.rotate_by(rotation);
+        if let Ok(mut overrides) = overrides.get_mut(entity) {
+            *overrides = overrides.rotate(rotation);
+        }
+        let after = HistoryTile::new(tile_ref, &transform, overrides.get(entity).ok());
+        placed.push((parent.get(), *location, after));
+    }
+    for (entity, location, tile_ref, transform, parent) in &tiles {
+        if replaced.contains(&entity) {
+            let before = HistoryTile::new(tile_ref, transform, overrides.get(entity).ok());
+            strokes
+                .layer(parent.get())
+                .record(HistoryKey::Hex(*location), Some(before), None);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for (layer, location, tile) in placed {
+        strokes
+            .layer(layer)
+            .record(HistoryKey::Hex(location), None, Some(tile));
+    }
+    history.push(strokes.0.into_values());
+    selection.set_changed();
+    state.unsaved_changes = true;
+}
+
+/// despawn the selected tiles; the removal is recorded in the undo history
+fn delete_selection(
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut selection: ResMut<MapSelection>,
+    mut history: ResMut<EditHistory>,
+    tiles: Query<(
+        &map::Location,
+        &tileset::TileRef,
+        &tileset::TileTransform,
+        Option<&tileset::TileOverrides>,
+        &Parent,
+    )>,
+    layers: Query<&map::Layer>,
+) {
+    // tiles in hidden or locked layers stay selected
+    let editable = |entity: &Entity| {
+        let Ok((_, _, _, _, parent)) = tiles.get(*entity) else { return false };
+        matches!(layers.get(parent.get()), Ok(l) if l.editable())
+    };
+    let deleted: Vec<Entity> = selection.tiles.iter().copied().filter(editable).collect();
+    let mut strokes = LayerStrokes::default();
+    for entity in deleted {
+        selection.tiles.remove(&entity);
+        let Ok((location, tile_ref, transform, overrides, parent)) = tiles.get(entity) else {
+            continue;
+        };
+        let before = HistoryTile::new(tile_ref, transform, overrides);
+        strokes
+            .layer(parent.get())
+            .record(HistoryKey::Hex(*location), Some(before), None);
+        commands.entity(entity).despawn_recursive();
+    }
+    history.push(strokes.0.into_values());
+    state.unsaved_changes = true;
+}
+
+/// undo the last tool stroke, or redo the last undone stroke
+///
+/// The tiles at the locations changed by the stroke are replaced with the
+/// tiles recorded before (or after) the stroke.  Undoing a random brush stroke
+/// restores its seed, so painting again reproduces the stroke.
+fn undo_stroke(
+    In(redo): In<bool>,
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut history: ResMut<EditHistory>,
+    mut selection: ResMut<MapSelection>,
+    mut random_brush: ResMut<brush::RandomBrush>,
+    tiles: Query<
+        (
+            Entity,
+            Option<&map::Location>,
+            Option<&map::Edge>,
+            Option<&map::Vertex>,
+            &Parent,
+        ),
+        (With<tileset::TileRef>, Without<MapCursor>),
+    >,
+    layers: Query<&map::Layer>,
+) {
+    let stroke = match redo {
+        false => history.undo.pop(),
+        true => history.redo.pop(),
+    };
+    let Some(stroke) = stroke else { return };
+    let Some(layer) = stroke.layer.filter(|layer| layers.contains(*layer)) else {
+        warn!("unable to undo stroke; layer has been removed");
+        return;
+    };
+    let recorded = match redo {
+        false => &stroke.before,
+        true => &stroke.after,
+    };
+
+    for (entity, location, edge, vertex, parent) in &tiles {
+        if parent.get() != layer {
+            continue;
+        }
+        let key = match (location, edge, vertex) {
+            (_, Some(edge), _) => HistoryKey::Edge(*edge),
+            (_, _, Some(vertex)) => HistoryKey::Vertex(*vertex),
+            (Some(location), _, _) => HistoryKey::Hex(*location),
+            _ => continue,
+        };
+        if recorded.contains_key(&key) {
+            commands.entity(entity).despawn_recursive();
+            selection.tiles.remove(&entity);
+        }
+    }
+
+    for (key, tile) in recorded {
+        let Some(tile) = tile else { continue };
+        let mut entity = commands.spawn((
+            tile.tile_ref,
+            tile.transform.clone(),
+            SpatialBundle::default(),
+        ));
+        match key {
+            HistoryKey::Hex(location) => entity.insert(*location),
+            HistoryKey::Edge(edge) => entity.insert(*edge),
+            HistoryKey::Vertex(vertex) => entity.insert(*vertex),
+        };
+        if let Some(overrides) = tile.overrides {
+            entity.insert(overrides);
+        }
+        entity.set_parent(layer);
+    }
+
+    if let (false, Some(seed)) = (redo, stroke.seed) {
+        random_brush.seed = seed;
+    }
+    match redo {
+        false => history.redo.push(stroke),
+        true => history.undo.push(stroke),
+    }
+    state.unsaved_changes = true;
+}
+
+/// move the selected tiles to another layer, replacing any tiles at the same
+/// locations in that layer; tiles that would overlap other tiles in that layer
+/// are left in place.  The move is recorded in the undo history of both layers.
+fn move_selection_to_layer(
+    In(layer): In<Entity>,
+    mut commands: Commands,
+    mut state: ResMut<EditorState>,
+    mut history: ResMut<EditHistory>,
+    selection: Res<MapSelection>,
     tiles: Query<
         (
             Entity,
             &map::Location,
             &tileset::TileRef,
             &tileset::TileTransform,
+            Option<&tileset::TileOverrides>,
             &Parent,
         ),
         Without<MapCursor>,
     >,
-) -> Result<()> {
-    let Some(event) = events.iter().last() else { return Ok(()) };
-    let Ok(map) = map.get_single() else { return Ok(()) };
-    let (_, location) = map.snap_to_grid(event.0);
-
-    // update the cursor location
-    let Ok((cursor, tile_ref, tile_transform)) = cursor.get_single() else { return Ok(()); };
-    commands.entity(cursor).insert(location);
-    trace!("move cursor: {:?}, {:?}", event, location);
-
-    // nothing more to be done if no mouse buttons have been pressed
-    if buttons.get_pressed().len() == 0 {
-        return Ok(());
+    mut layers: Query<&mut map::Layer>,
+    tilesets: Query<&tileset::Tileset>,
+) {
+    let editable = |layers: &Query<&mut map::Layer>, layer: Entity| -> bool {
+        let Ok(layer) = layers.get(layer) else { return false };
+        layer.editable()
+    };
+    if !matches!(layers.get(layer), Ok(l) if l.accepts_tiles()) {
+        warn!("cannot move tiles to a hidden, locked, or non-tile layer");
+        return;
     }
 
-    let layer = state.active_layer.context("no active layer")?;
-    // let start = std::time::Instant::now();
+    // tiles in hidden or locked layers are left in place
+    let moved: Vec<Entity> = selection
+        .tiles
+        .iter()
+        .filter_map(|e| tiles.get(*e).ok())
+        .filter(|(_, _, _, _, _, parent)| parent.get() != layer && editable(&layers, parent.get()))
+        .map(|(entity, ..)| entity)
+        .collect();
+    let replaceable: HashMap<map::Location, Entity> = tiles
+        .iter()
+        .filter(|(entity, _, _, _, _, parent)| {
+            parent.get() == layer && !selection.tiles.contains(entity)
+        })
+        .map(|(entity, location, ..)| (*location, entity))
+        .collect();
+    let mut covered = layer_coverage(layers.get(layer).ok(), |e| tiles.contains(e));
 
-    for (tile_entity, tile_location, tile_tile_ref, tile_tile_transform, tile_parent) in &tiles {
-        if tile_parent.get() != layer {
+    let mut strokes = LayerStrokes::default();
+    let mut overlaps = 0;
+    for entity in moved {
+        let Ok((_, location, tile_ref, transform, overrides, parent)) = tiles.get(entity) else {
             continue;
-        }
-        if *tile_location != location {
+        };
+        // the tile replaces the tile at its location, but may not overlap any
+        // other tile
+        let replaced = replaceable.get(location).copied();
+        let hexes = match tilesets.get(tile_ref.tileset) {
+            Ok(tileset) => tileset.footprint(tile_ref.tile, *location, transform.rotation),
+            Err(_) => vec![*location],
+        };
+        if covered.overlaps(&hexes, |e| Some(e) == replaced) {
+            overlaps += 1;
             continue;
         }
+        if let Some(replaced) = replaced {
+            if let Ok((_, _, tile_ref, transform, overrides, _)) = tiles.get(replaced) {
+                let before = HistoryTile::new(tile_ref, transform, overrides);
+                strokes
+                    .layer(layer)
+                    .record(HistoryKey::Hex(*location), Some(before), None);
+            }
+            commands.entity(replaced).despawn_recursive();
+            covered.remove(replaced);
+        }
+        covered.insert(entity, *tile_ref, hexes);
 
-        // if the tile matches, and they're adding a tile do nothing
-        if tile_tile_ref == tile_ref
-            && tile_tile_transform == tile_transform
-            && buttons.pressed(MouseButton::Left)
-        {
-            return Ok(());
+        let tile = HistoryTile::new(tile_ref, transform, overrides);
+        strokes
+            .layer(parent.get())
+            .record(HistoryKey::Hex(*location), Some(tile.clone()), None);
+        strokes
+            .layer(layer)
+            .record(HistoryKey::Hex(*location), None, Some(tile));
+        commands.entity(entity).set_parent(layer);
+    }
+    if overlaps > 0 {
+        warn!(
+            "{} tiles not moved; they would overlap other tiles",
+            overlaps
+        );
+    }
+    history.push(strokes.0.into_values());
+
+    // reapply the layer opacity to the moved tiles
+    if let Ok(mut layer) = layers.get_mut(layer) {
+        layer.set_changed();
+    }
+    state.active_layer = Some(layer);
+    state.unsaved_changes = true;
+}
+
+/// find the tiles in scope that will be replaced, along with their
+/// replacements
+fn replace_targets(
+    world: &mut World,
+    replace: &replace::TileReplace,
+    scope: ReplaceScope,
+) -> Vec<(Entity, tileset::TileRef, tileset::TileTransform)> {
+    let mut layers = world.query::<&map::Layer>();
+    let mut tiles = world
+        .query_filtered::<(Entity, &tileset::TileRef, &tileset::TileTransform, &Parent), (
+            Without<MapCursor>,
+            Without<StampPreview>,
+            Without<ToolPreview>,
+        )>();
+    let active_layer = world.resource::<EditorState>().active_layer;
+    let selection = &world.resource::<MapSelection>().tiles;
+
+    // tiles in hidden or locked layers are never replaced
+    tiles
+        .iter(world)
+        .filter(|(_, _, _, parent)| {
+            let Ok(layer) = layers.get(world, parent.get()) else { return false };
+            layer.editable()
+        })
+        .filter(|(entity, _, _, parent)| match scope {
+            ReplaceScope::ActiveLayer => Some(parent.get()) == active_layer,
+            ReplaceScope::AllLayers => true,
+            ReplaceScope::Selection => selection.contains(entity),
+        })
+        .filter_map(|(entity, tile_ref, transform, _)| {
+            let (tile_ref, transform) = replace.replacement(tile_ref, transform)?;
+            Some((entity, tile_ref, transform))
+        })
+        .collect()
+}
+
+/// replace the tiles in scope; tiles whose replacement would overlap other
+/// tiles are kept
+fn replace_tiles(world: &mut World, replace: &replace::TileReplace, scope: ReplaceScope) {
+    let targets = replace_targets(world, replace, scope);
+    info!("replacing {} tiles in {}", targets.len(), scope.label());
+    if targets.is_empty() {
+        return;
+    }
+    let count = targets.len();
+    let overlapping = change_tiles(world, targets);
+    if !overlapping.is_empty() {
+        warn!(
+            "{} tiles not replaced; they would overlap other tiles",
+            overlapping.len()
+        );
+    }
+    if overlapping.len() < count {
+        world.resource_mut::<EditorState>().unsaved_changes = true;
+    }
+}
+
+/// hexes covered by a tile placed at a location
+fn world_footprint(
+    world: &World,
+    tile_ref: &tileset::TileRef,
+    location: map::Location,
+    rotation: tileset::TileRotation,
+) -> Vec<map::Location> {
+    match world.get::<tileset::Tileset>(tile_ref.tileset) {
+        Some(tileset) => tileset.footprint(tile_ref.tile, location, rotation),
+        None => vec![location],
+    }
+}
+
+/// hexes covered by the tiles of a layer that still exist; the layer index is
+/// only rebuilt at the end of the frame, so it may still refer to despawned
+/// tiles
+fn layer_coverage(
+    layer: Option<&map::Layer>,
+    exists: impl Fn(Entity) -> bool,
+) -> map::TileCoverage {
+    let mut covered = layer.map(map::TileCoverage::new).unwrap_or_default();
+    covered.hexes.retain(|_, (entity, _)| exists(*entity));
+    covered
+}
+
+/// change placed tiles to other tiles; hex tiles are only changed if the new
+/// tile doesn't overlap another tile in the layer.  Returns the tiles that
+/// were not changed.
+fn change_tiles(
+    world: &mut World,
+    changes: Vec<(Entity, tileset::TileRef, tileset::TileTransform)>,
+) -> Vec<Entity> {
+    let mut coverage: HashMap<Entity, map::TileCoverage> = HashMap::new();
+    let mut overlapping = Vec::new();
+    for (entity, tile_ref, transform) in changes {
+        let location = world.get::<map::Location>(entity).copied();
+        let layer = world.get::<Parent>(entity).map(|parent| parent.get());
+        // edge & vertex tiles have no footprint
+        if let (Some(location), Some(layer)) = (location, layer) {
+            let hexes = world_footprint(world, &tile_ref, location, transform.rotation);
+            let covered = coverage.entry(layer).or_insert_with(|| {
+                layer_coverage(world.get(layer), |e| world.get_entity(e).is_some())
+            });
+            if covered.overlaps(&hexes, |e| e == entity) {
+                overlapping.push(entity);
+                continue;
+            }
+            covered.remove(entity);
+            covered.insert(entity, tile_ref, hexes);
+        }
+        world.entity_mut(entity).insert((tile_ref, transform));
+    }
+    overlapping
+}
+
+/// scatter decorations onto the target layer; the tiles placed are recorded as
+/// a stroke so they can be undone
+fn scatter_tiles(world: &mut World, scatter: &scatter::Scatter, source: Entity, target: Entity) {
+    let placements = match scatter.world_placements(world, source, target) {
+        Ok(placements) => placements,
+        Err(e) => {
+            error!("failed to scatter tiles: {:?}", e);
+            return;
         }
+    };
+    info!("scattering {} tiles", placements.len());
+    if placements.is_empty() {
+        return;
+    }
 
-        // we're either removing the tile, or replacing it; so despawn the tile
-        commands.entity(tile_entity).despawn_recursive();
+    let mut stroke = Stroke {
+        layer: Some(target),
+        ..default()
+    };
+    let mut covered = layer_coverage(world.get(target), |e| world.get_entity(e).is_some());
+    let mut overlaps = 0;
+    for (location, tile_ref, transform) in placements {
+        let hexes = world_footprint(world, &tile_ref, location, transform.rotation);
+        if covered.overlaps(&hexes, |_| false) {
+            overlaps += 1;
+            continue;
+        }
+        let tile = HistoryTile {
+            tile_ref,
+            transform: transform.clone(),
+            overrides: None,
+        };
+        stroke.record(HistoryKey::Hex(location), None, Some(tile));
+        let entity = world
+            .spawn((location, tile_ref, transform, SpatialBundle::default()))
+            .set_parent(target)
+            .id();
+        covered.insert(entity, tile_ref, hexes);
+    }
+    if overlaps > 0 {
+        warn!("{} tiles not scattered; they overlap other tiles", overlaps);
     }
-    // debug!("tiles {}, duration {:?}", tiles.iter().count(), start.elapsed());
+    if stroke.is_empty() {
+        return;
+    }
+    let mut history = world.resource_mut::<EditHistory>();
+    history.undo.push(stroke);
+    history.redo.clear();
+    world.resource_mut::<EditorState>().unsaved_changes = true;
+}
 
-    if buttons.pressed(MouseButton::Left) {
-        commands
-            .spawn((
-                location,
-                *tile_ref,
-                tile_transform.clone(),
-                SpatialBundle::default(),
-            ))
-            .set_parent(layer);
+/// check if the modifier for the eyedropper is held
+fn eyedropper_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.any_pressed([KeyCode::LAlt, KeyCode::RAlt])
+}
 
-        debug!("insert tile: {:?} @ {:?}", tile_ref, location);
+/// alt-click on a placed tile to select the same tile & rotation, along with
+/// the tileset & layer it belongs to
+fn eyedropper(
+    mut state: ResMut<EditorState>,
+    mut tile_selection: ResMut<TileSelection>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mut egui_contexts: EguiContexts,
+    layers: Query<(Entity, &map::Layer)>,
+    mut cursor: Query<(&map::Location, &mut tileset::TileTransform), With<MapCursor>>,
+    tiles: Query<
+        (
+            &map::Location,
+            &tileset::TileRef,
+            &tileset::TileTransform,
+            &Parent,
+        ),
+        (
+            Without<MapCursor>,
+            Without<StampPreview>,
+            Without<ToolPreview>,
+        ),
+    >,
+) {
+    if state.stamp_capture
+        || !buttons.just_pressed(MouseButton::Left)
+        || !eyedropper_pressed(&keys)
+        || egui_contexts.ctx_mut().is_pointer_over_area()
+    {
+        return;
     }
-    Ok(())
+    let Ok((location, mut cursor_transform)) = cursor.get_single_mut() else { return };
+
+    // prefer the tile in the active layer, then the top-most visible layer
+    let layer_order = |layer: Entity| -> Option<usize> {
+        let (_, l) = layers.get(layer).ok().filter(|(_, l)| l.visible)?;
+        if Some(layer) == state.active_layer {
+            Some(usize::MAX)
+        } else {
+            Some(l.order)
+        }
+    };
+    // the layer index includes the hexes covered by multi-hex tiles
+    let sampled = layers
+        .iter()
+        .filter_map(|(entity, layer)| {
+            let (tile, _) = layer.tiles.get(location)?;
+            Some((layer_order(entity)?, tiles.get(*tile).ok()?))
+        })
+        .max_by_key(|(order, _)| *order)
+        .map(|(_, tile)| tile);
+    let Some((_, tile_ref, tile_transform, layer)) = sampled else {
+        debug!("eyedropper: no tile at {:?}", location);
+        return;
+    };
+
+    debug!("eyedropper: {:?} @ {:?}", tile_ref, location);
+    tile_selection.tiles.clear();
+    tile_selection.tiles.insert(*tile_ref);
+    tile_selection.scroll_to = Some(*tile_ref);
+    cursor_transform.rotation = tile_transform.rotation;
+    state.active_tileset = Some(tile_ref.tileset);
+    state.active_layer = Some(layer.get());
 }
 
 /// update the cursor model when the TileSelection is changed
@@ -754,6 +3801,7 @@ fn handle_map_cursor_events(
 fn update_cursor_model(
     mut commands: Commands,
     tile_selection: Res<TileSelection>,
+    mut brush: ResMut<StampBrush>,
     cursor: Query<Entity, With<MapCursor>>,
 ) -> Result<()> {
     if !tile_selection.is_changed() {
@@ -761,6 +3809,11 @@ fn update_cursor_model(
     }
     let Some(tile_ref) = tile_selection.active_tile() else { return Ok(()); };
 
+    // selecting a tile replaces the stamp brush
+    if brush.path.is_some() {
+        *brush = StampBrush::default();
+    }
+
     let cursor = cursor.get_single().context("failed to get cursor entity")?;
     commands.entity(cursor).insert(*tile_ref);
     Ok(())
@@ -796,7 +3849,12 @@ fn update_cursor(
         );
     };
 
-    let transform = map.tile_transform(tile, cursor.grid_location, &cursor.tile_transform);
+    let transform = map.tile_transform(
+        tile,
+        cursor.grid_location,
+        &cursor.tile_transform,
+        &tileset::TileOverrides::default(),
+    );
 
     commands
         .entity(entity)
@@ -815,6 +3873,15 @@ pub fn draw_ui(world: &mut World) {
             basic_widget::<ui::EditorMenuBar>(world, ui, ui.id().with("menubar"));
         });
 
+        // toolbar
+        if world.get_map().is_ok() {
+            egui::SidePanel::left("toolbar")
+                .resizable(false)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::Toolbar>(world, ui, ui.id().with("toolbar"));
+                });
+        }
+
         // right panel
         if world.get_map().is_ok() {
             let state = world.resource_mut::<EditorState>();
@@ -831,6 +3898,24 @@ pub fn draw_ui(world: &mut World) {
         let mut egui_visuals_window = state.egui_visuals_window;
         let mut egui_debug = state.egui_debug;
         let new_tileset_window = state.new_tileset_window;
+        let import_layers_window = state.import_layers.is_some();
+        let save_stamp_window = state.new_stamp.is_some();
+        let mut find_replace_window = state.find_replace_window;
+        let mut objects_window = state.objects_window;
+        let mut annotations_window = state.annotations_window;
+        let mut placed_tiles_window = state.placed_tiles_window;
+        let mut random_brush_window = state.random_brush_window;
+        let scatter_window = state.scatter_window;
+        let mut property_schema_window = state.property_schema_window.is_some();
+        let tileset_folder_window = state.tileset_folder.is_some();
+        let folder_scan_window = state.folder_scan.is_some();
+        let map_changed_window = state.map_changed.is_some();
+        let remove_tiles_window = state.remove_tiles.is_some();
+        let import_warnings_window = world
+            .query::<&persistence::ImportWarnings>()
+            .iter(world)
+            .next()
+            .is_some();
 
         // properties window
         egui::Window::new("Properties")
@@ -860,6 +3945,137 @@ pub fn draw_ui(world: &mut World) {
                 });
         }
 
+        if tileset_folder_window {
+            egui::Window::new("Tileset from Folder")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .show(ctx, |ui| {
+                    basic_widget::<ui::TilesetFolderDialog>(
+                        world,
+                        ui,
+                        ui.id().with("tileset_folder"),
+                    );
+                });
+        }
+
+        if folder_scan_window {
+            egui::Window::new("Folder Scan")
+                .constrain(true)
+                .default_width(300.0)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::FolderScanResults>(world, ui, ui.id().with("folder_scan"));
+                });
+        }
+
+        if map_changed_window {
+            egui::Window::new("Map Changed on Disk")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::MapChangedDialog>(world, ui, ui.id().with("map_changed"));
+                });
+        }
+
+        if import_warnings_window {
+            egui::Window::new("Import Warnings")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::ImportWarningsDialog>(
+                        world,
+                        ui,
+                        ui.id().with("import_warnings"),
+                    );
+                });
+        }
+
+        if remove_tiles_window {
+            egui::Window::new("Remove Tiles")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::RemoveTilesDialog>(world, ui, ui.id().with("remove_tiles"));
+                });
+        }
+
+        if import_layers_window {
+            egui::Window::new("Import Map as Layers")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .show(ctx, |ui| {
+                    basic_widget::<ui::ImportLayers>(world, ui, ui.id().with("import_layers"));
+                });
+        }
+
+        if save_stamp_window {
+            egui::Window::new("Save Stamp")
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .show(ctx, |ui| {
+                    basic_widget::<ui::SaveStamp>(world, ui, ui.id().with("save_stamp"));
+                });
+        }
+
+        if find_replace_window {
+            egui::Window::new("Find & Replace")
+                .open(&mut find_replace_window)
+                .anchor(egui::Align2::CENTER_TOP, egui::Vec2::new(0.0, 200.0))
+                .show(ctx, |ui| {
+                    basic_widget::<ui::FindReplace>(world, ui, ui.id().with("find_replace"));
+                });
+        }
+
+        if world.get_map().is_ok() {
+            egui::Window::new("Objects")
+                .open(&mut objects_window)
+                .constrain(true)
+                .default_width(250.0)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::ObjectsWindow>(world, ui, ui.id().with("objects"));
+                });
+            egui::Window::new("Annotations")
+                .open(&mut annotations_window)
+                .constrain(true)
+                .default_width(250.0)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::AnnotationsWindow>(world, ui, ui.id().with("annotations"));
+                });
+            egui::Window::new("Placed Tiles")
+                .open(&mut placed_tiles_window)
+                .constrain(true)
+                .default_width(200.0)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::PlacedTiles>(world, ui, ui.id().with("placed_tiles"));
+                });
+        }
+
+        if scatter_window && world.get_map().is_ok() {
+            egui::Window::new("Scatter Decorations")
+                .constrain(true)
+                .vscroll(true)
+                .default_width(300.0)
+                .show(ctx, |ui| {
+                    basic_widget::<ui::ScatterDialog>(world, ui, ui.id().with("scatter"));
+                });
+        }
+
+        egui::Window::new("Tile Property Schema")
+            .open(&mut property_schema_window)
+            .constrain(true)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                basic_widget::<ui::PropertySchemaEditor>(
+                    world,
+                    ui,
+                    ui.id().with("property_schema"),
+                );
+            });
+
+        egui::Window::new("Random Brush")
+            .open(&mut random_brush_window)
+            .constrain(true)
+            .default_width(200.0)
+            .show(ctx, |ui| {
+                basic_widget::<ui::RandomBrushSettings>(world, ui, ui.id().with("random_brush"));
+            });
+
         egui::Window::new("egui pointer debug")
             .open(&mut egui_debug)
             .default_width(200.0)
@@ -871,5 +4087,13 @@ pub fn draw_ui(world: &mut World) {
         state.properties_window = property_window;
         state.egui_visuals_window = egui_visuals_window;
         state.egui_debug = egui_debug;
+        state.find_replace_window &= find_replace_window;
+        state.objects_window &= objects_window;
+        state.annotations_window &= annotations_window;
+        state.placed_tiles_window &= placed_tiles_window;
+        state.random_brush_window &= random_brush_window;
+        if !property_schema_window {
+            state.property_schema_window = None;
+        }
     });
 }
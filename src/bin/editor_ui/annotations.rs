@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{annotation::Annotation, map, ui::widget::*};
+
+use crate::EditorState;
+
+/// list of the notes in the active annotation layer, and the text & color of
+/// the selected note
+pub struct AnnotationsWindow;
+
+impl BasicWidget for AnnotationsWindow {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let state = world.resource::<EditorState>();
+        let (active_layer, active_annotation) = (state.active_layer, state.active_annotation);
+        let Some(layer) = active_layer else {
+            ui.label("Select an annotation layer, then click on the map to place notes");
+            return;
+        };
+        let Some(editable) = world
+            .get::<map::Layer>(layer)
+            .filter(|l| l.kind == map::LayerKind::Annotation)
+            .map(|l| l.editable())
+        else {
+            ui.label("Select an annotation layer, then click on the map to place notes");
+            return;
+        };
+
+        let mut query = world.query::<(Entity, &Annotation, &Parent)>();
+        let mut notes: Vec<(Entity, String)> = query
+            .iter(world)
+            .filter(|(_, _, parent)| parent.get() == layer)
+            .map(|(entity, annotation, _)| {
+                let l = annotation.location;
+                (entity, format!("({}, {}) {}", l.x, l.y, annotation.text))
+            })
+            .collect();
+        notes.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut selected = active_annotation;
+        egui::ScrollArea::vertical()
+            .id_source(id.with("list"))
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (entity, label) in &notes {
+                    ui.selectable_value(&mut selected, Some(*entity), label);
+                }
+            });
+        if selected != active_annotation {
+            world.resource_mut::<EditorState>().active_annotation = selected;
+        }
+        ui.separator();
+
+        let Some(entity) = selected else {
+            ui.label("Click on the map to place a note");
+            return;
+        };
+        let Some(original) = world.get::<Annotation>(entity).cloned() else { return };
+        let mut annotation = original.clone();
+
+        ui.add_enabled_ui(editable, |ui| {
+            egui::Grid::new(id.with("annotation"))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("location");
+                    ui.horizontal(|ui| {
+                        let location = &mut annotation.location;
+                        ui.add(egui::DragValue::new(&mut location.x).prefix("x: "));
+                        ui.add(egui::DragValue::new(&mut location.y).prefix("y: "));
+                    });
+                    ui.end_row();
+
+                    ui.label("color");
+                    let [r, g, b, _] = annotation
+                        .color
+                        .as_rgba_f32()
+                        .map(|c| (c * 255.0).round() as u8);
+                    let mut rgb = [r, g, b];
+                    if ui.color_edit_button_srgb(&mut rgb).changed() {
+                        annotation.color = Color::rgb_u8(rgb[0], rgb[1], rgb[2]);
+                    }
+                    ui.end_row();
+                });
+            ui.add(
+                egui::TextEdit::multiline(&mut annotation.text)
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY),
+            );
+
+            ui.separator();
+            if ui.button("Delete Note").clicked() {
+                world.entity_mut(entity).despawn_recursive();
+                let mut state = world.resource_mut::<EditorState>();
+                state.active_annotation = None;
+                state.unsaved_changes = true;
+            }
+        });
+
+        if annotation != original && world.get_entity(entity).is_some() {
+            world.entity_mut(entity).insert(annotation);
+            world.resource_mut::<EditorState>().unsaved_changes = true;
+        }
+    }
+}
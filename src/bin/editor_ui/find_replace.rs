@@ -0,0 +1,210 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{replace::TileReplace, tileset, ui::widget::*};
+
+use crate::{EditorState, EditorUiEvent, ReplaceScope, TileSelection};
+
+pub struct FindReplace {
+    by_tileset: bool,
+    from: Option<tileset::TileRef>,
+    to: Option<tileset::TileRef>,
+    from_tileset: Option<Entity>,
+    to_tileset: Option<Entity>,
+    scope: ReplaceScope,
+    rotation: Option<tileset::TileRotation>,
+    reset_rotation: bool,
+}
+
+impl BasicWidget for FindReplace {
+    fn new(world: &mut World, _ui: &egui::Ui) -> Self {
+        let from = world.resource::<TileSelection>().active_tile().copied();
+        Self {
+            by_tileset: false,
+            from,
+            to: None,
+            from_tileset: from.map(|t| t.tileset),
+            to_tileset: None,
+            scope: ReplaceScope::ActiveLayer,
+            rotation: None,
+            reset_rotation: false,
+        }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.by_tileset, false, "Tile");
+            ui.selectable_value(&mut self.by_tileset, true, "Tileset by name");
+        });
+        ui.separator();
+
+        egui::Grid::new(id.with("grid"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                if self.by_tileset {
+                    ui.label("find");
+                    tileset_combo(world, ui, id.with("from_tileset"), &mut self.from_tileset);
+                    ui.end_row();
+                    ui.label("replace with");
+                    tileset_combo(world, ui, id.with("to_tileset"), &mut self.to_tileset);
+                    ui.end_row();
+                } else {
+                    ui.label("find");
+                    tile_combo(world, ui, id.with("from"), &mut self.from);
+                    ui.end_row();
+                    ui.label("replace with");
+                    tile_combo(world, ui, id.with("to"), &mut self.to);
+                    ui.end_row();
+                }
+
+                ui.label("in");
+                egui::ComboBox::from_id_source(id.with("scope"))
+                    .selected_text(self.scope.label())
+                    .show_ui(ui, |ui| {
+                        for scope in [
+                            ReplaceScope::ActiveLayer,
+                            ReplaceScope::AllLayers,
+                            ReplaceScope::Selection,
+                        ] {
+                            ui.selectable_value(&mut self.scope, scope, scope.label());
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("rotation");
+                let text = match self.rotation {
+                    Some(rotation) => format!("{:?}", rotation),
+                    None => "Any".to_string(),
+                };
+                egui::ComboBox::from_id_source(id.with("rotation"))
+                    .selected_text(text)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.rotation, None, "Any");
+                        let mut rotation = tileset::TileRotation::None;
+                        for _ in 0..6 {
+                            ui.selectable_value(
+                                &mut self.rotation,
+                                Some(rotation),
+                                format!("{:?}", rotation),
+                            );
+                            rotation = rotation.clockwise();
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("");
+                ui.checkbox(&mut self.reset_rotation, "reset rotation");
+                ui.end_row();
+            });
+        ui.separator();
+
+        // show how many tiles will be replaced before applying
+        let replace = self.replace(world);
+        let count = match &replace {
+            Ok(replace) => {
+                let count = crate::replace_targets(world, replace, self.scope).len();
+                ui.label(format!("{} tiles will be replaced", count));
+                count
+            }
+            Err(e) => {
+                ui.colored_label(ui.visuals().warn_fg_color, e.to_string());
+                0
+            }
+        };
+
+        let (apply, close) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (
+                    ui.add_enabled(count > 0, egui::Button::new("Replace")),
+                    ui.button("Close"),
+                )
+            })
+            .inner;
+
+        if apply.clicked() {
+            if let Ok(replace) = replace {
+                let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+                events.send(EditorUiEvent::ReplaceTiles {
+                    replace,
+                    scope: self.scope,
+                });
+            }
+        }
+        if close.clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            world.resource_mut::<EditorState>().find_replace_window = false;
+            *self = Self::new(world, ui);
+        }
+    }
+}
+
+impl FindReplace {
+    fn replace(&self, world: &World) -> anyhow::Result<TileReplace> {
+        let mut replace = if self.by_tileset {
+            let (Some(from), Some(to)) = (self.from_tileset, self.to_tileset) else {
+                anyhow::bail!("select the tilesets");
+            };
+            TileReplace::tileset_by_name(world, from, to)?
+        } else {
+            let (Some(from), Some(to)) = (self.from, self.to) else {
+                anyhow::bail!("select the tiles");
+            };
+            TileReplace::tile(from, to)
+        };
+        replace.rotation = self.rotation;
+        replace.reset_rotation = self.reset_rotation;
+        Ok(replace)
+    }
+}
+
+/// combo box to select a tile from any tileset
+fn tile_combo(
+    world: &mut World,
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    value: &mut Option<tileset::TileRef>,
+) {
+    let mut tilesets = world.query::<(Entity, &tileset::Tileset)>();
+    let name = |tile_ref: &tileset::TileRef| -> Option<String> {
+        let (_, tileset) = tilesets.get(world, tile_ref.tileset).ok()?;
+        let tile = tileset.tiles.get(&tile_ref.tile)?;
+        Some(format!("{}: {}", tileset.name, tile.name))
+    };
+    let text = value.as_ref().and_then(name).unwrap_or_default();
+
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text)
+        .width(200.0)
+        .show_ui(ui, |ui| {
+            for (tileset_id, tileset) in tilesets.iter(world) {
+                for tile_id in &tileset.tile_order {
+                    let Some(tile) = tileset.tiles.get(tile_id) else { continue };
+                    let tile_ref = tileset::TileRef {
+                        tileset: tileset_id,
+                        tile: *tile_id,
+                    };
+                    ui.selectable_value(
+                        value,
+                        Some(tile_ref),
+                        format!("{}: {}", tileset.name, tile.name),
+                    );
+                }
+            }
+        });
+}
+
+/// combo box to select a tileset
+fn tileset_combo(world: &mut World, ui: &mut egui::Ui, id: egui::Id, value: &mut Option<Entity>) {
+    let mut tilesets = world.query::<(Entity, &tileset::Tileset)>();
+    let text = value
+        .and_then(|e| tilesets.get(world, e).ok())
+        .map(|(_, tileset)| tileset.name.clone())
+        .unwrap_or_default();
+
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text)
+        .width(200.0)
+        .show_ui(ui, |ui| {
+            for (tileset_id, tileset) in tilesets.iter(world) {
+                ui.selectable_value(value, Some(tileset_id), &tileset.name);
+            }
+        });
+}
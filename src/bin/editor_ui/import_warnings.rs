@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{persistence::ImportWarnings, ui::widget::*};
+
+/// problems found while importing the open map, such as Tiled GIDs without a
+/// tile; closing the dialog removes the warnings from the map
+#[derive(Default)]
+pub struct ImportWarningsDialog;
+
+impl BasicWidget for ImportWarningsDialog {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let mut query = world.query::<(Entity, &ImportWarnings)>();
+        let Some((map, warnings)) = query.iter(world).next() else { return };
+        let warnings = warnings.0.clone();
+
+        ui.label("The map was imported with warnings:");
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for warning in &warnings {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("• {}", warning));
+                }
+            });
+
+        let close = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                ui.button("Close")
+            })
+            .inner;
+        if close.clicked() {
+            world.entity_mut(map).remove::<ImportWarnings>();
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::ui::widget::*;
+
+use crate::{EditorState, EditorUiEvent};
+
+/// ask the user to reload the open map after it was changed by another
+/// program, showing what changed
+#[derive(Default)]
+pub struct MapChangedDialog;
+
+impl BasicWidget for MapChangedDialog {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let state = world.resource::<EditorState>();
+        let Some((path, summary)) = state.map_changed.clone() else { return };
+        let unsaved_changes = state.unsaved_changes;
+
+        ui.label(format!(
+            "{} was changed by another program:",
+            path.to_string_lossy()
+        ));
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for line in &summary {
+                    ui.label(format!("• {}", line));
+                }
+            });
+        if unsaved_changes {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "Reloading discards your unsaved changes",
+            );
+        }
+
+        let (reload, keep) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (ui.button("Reload"), ui.button("Keep current"))
+            })
+            .inner;
+
+        if reload.clicked() {
+            world
+                .resource_mut::<Events<EditorUiEvent>>()
+                .send(EditorUiEvent::MapReload(path));
+        } else if !keep.clicked() {
+            return;
+        }
+        world.resource_mut::<EditorState>().map_changed = None;
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 use bevy_egui::egui;
-use hex_sandbox::{file_picker, prelude::*, ui, ui::widget::*};
+use hex_sandbox::{file_picker, map, prelude::*, tileset, ui, ui::widget::*};
 
 use crate::EditorUiEvent;
 
@@ -18,9 +18,13 @@ impl BasicWidget for EditorMenuBar {
                 let id = ui.id().with("file");
                 basic_widget::<MapNew>(world, ui, id.with("map_new"));
                 basic_widget::<MapOpen>(world, ui, id.with("map_open"));
+                basic_widget::<MapImportTmx>(world, ui, id.with("map_import_tmx"));
+                basic_widget::<MapImportLayers>(world, ui, id.with("map_import_layers"));
                 ui.separator();
                 basic_widget::<MapSave>(world, ui, id.with("map_save"));
                 basic_widget::<MapSaveAs>(world, ui, id.with("map_save_as"));
+                basic_widget::<MapExportGltf>(world, ui, id.with("map_export_gltf"));
+                basic_widget::<MapExportSvg>(world, ui, id.with("map_export_svg"));
                 ui.separator();
                 basic_widget::<MapClose>(world, ui, id.with("map_close"));
                 basic_widget::<Quit>(world, ui, id.with("quit"));
@@ -33,6 +37,12 @@ impl BasicWidget for EditorMenuBar {
                 basic_widget::<Cut>(world, ui, id.with("cut"));
                 basic_widget::<MenuCopy>(world, ui, id.with("copy"));
                 basic_widget::<Paste>(world, ui, id.with("paste"));
+                ui.separator();
+                basic_widget::<FindReplace>(world, ui, id.with("find_replace"));
+                basic_widget::<ScatterTiles>(world, ui, id.with("scatter"));
+            });
+            egui::menu::menu_button(ui, "Selection", |ui| {
+                basic_widget::<SelectionMenu>(world, ui, ui.id().with("selection"));
             });
             egui::menu::menu_button(ui, "View", |ui| {
                 // don't need widgets here as all of these are simple checkboxes
@@ -46,6 +56,27 @@ impl BasicWidget for EditorMenuBar {
                 {
                     ui.close_menu();
                 }
+                if ui.checkbox(&mut state.objects_window, "Objects").clicked() {
+                    ui.close_menu();
+                }
+                if ui
+                    .checkbox(&mut state.annotations_window, "Annotations")
+                    .clicked()
+                {
+                    ui.close_menu();
+                }
+                if ui
+                    .checkbox(&mut state.placed_tiles_window, "Placed Tiles")
+                    .clicked()
+                {
+                    ui.close_menu();
+                }
+                if ui
+                    .checkbox(&mut state.random_brush_window, "Random Brush")
+                    .clicked()
+                {
+                    ui.close_menu();
+                }
                 ui.separator();
                 if ui
                     .checkbox(&mut state.inspector, "World Inspector")
@@ -118,6 +149,54 @@ impl BasicWidget for MapOpen {
     }
 }
 
+#[derive(Default, Clone)]
+pub struct MapImportTmx;
+
+impl BasicWidget for MapImportTmx {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if ui.button("Import Tiled Map...").clicked() {
+            world.spawn(
+                file_picker::Picker::new(crate::PickerEvent::TmxImport(None))
+                    .add_filter("Tiled Map", &["tmx"])
+                    .build(),
+            );
+            ui.close_menu();
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct MapImportLayers;
+
+impl BasicWidget for MapImportLayers {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if world.get_map().is_err() {
+            if ui
+                .add_enabled(false, egui::Button::new("Import Map as Layers..."))
+                .clicked()
+            {
+                unreachable!();
+            }
+            return;
+        };
+
+        if ui.button("Import Map as Layers...").clicked() {
+            world.spawn(
+                file_picker::Picker::new(crate::PickerEvent::ImportLayers(None))
+                    .add_filter("Map", &["ron", "json", "hexmap"])
+                    .build(),
+            );
+            ui.close_menu();
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct MapSave;
 
@@ -170,6 +249,65 @@ impl BasicWidget for MapSaveAs {
     }
 }
 
+#[derive(Default, Clone)]
+pub struct MapExportGltf;
+
+impl BasicWidget for MapExportGltf {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if world.get_map().is_err() {
+            if ui
+                .add_enabled(false, egui::Button::new("Export glTF..."))
+                .clicked()
+            {
+                unreachable!();
+            }
+            return;
+        };
+
+        if ui.button("Export glTF...").clicked() {
+            world.spawn(
+                file_picker::Picker::save_dialog(crate::PickerEvent::GltfExport(None))
+                    .add_filter("glTF Binary", &["glb"])
+                    .add_filter("glTF", &["gltf"])
+                    .build(),
+            );
+            ui.close_menu();
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct MapExportSvg;
+
+impl BasicWidget for MapExportSvg {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if world.get_map().is_err() {
+            if ui
+                .add_enabled(false, egui::Button::new("Export SVG..."))
+                .clicked()
+            {
+                unreachable!();
+            }
+            return;
+        };
+
+        if ui.button("Export SVG...").clicked() {
+            world.spawn(
+                file_picker::Picker::save_dialog(crate::PickerEvent::SvgExport(None))
+                    .add_filter("SVG", &["svg"])
+                    .build(),
+            );
+            ui.close_menu();
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct MapClose;
 
@@ -242,9 +380,12 @@ impl BasicWidget for Undo {
         Self::default()
     }
 
-    fn draw(&mut self, _world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
-        if ui.button("Undo").clicked() {
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let enabled = !world.resource::<crate::EditHistory>().undo.is_empty();
+        if ui.add_enabled(enabled, egui::Button::new("Undo")).clicked() {
             debug!("undo");
+            let mut events = world.resource_mut::<Events<crate::EditorUiEvent>>();
+            events.send(EditorUiEvent::Undo);
             ui.close_menu();
         }
     }
@@ -258,9 +399,12 @@ impl BasicWidget for Redo {
         Self::default()
     }
 
-    fn draw(&mut self, _world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
-        if ui.button("Redo").clicked() {
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let enabled = !world.resource::<crate::EditHistory>().redo.is_empty();
+        if ui.add_enabled(enabled, egui::Button::new("Redo")).clicked() {
             debug!("redo");
+            let mut events = world.resource_mut::<Events<crate::EditorUiEvent>>();
+            events.send(EditorUiEvent::Redo);
             ui.close_menu();
         }
     }
@@ -313,3 +457,124 @@ impl BasicWidget for Paste {
         }
     }
 }
+
+#[derive(Default, Clone)]
+pub struct ScatterTiles;
+
+impl BasicWidget for ScatterTiles {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if world.get_map().is_err() {
+            if ui
+                .add_enabled(false, egui::Button::new("Scatter Decorations..."))
+                .clicked()
+            {
+                unreachable!();
+            }
+            return;
+        }
+        if ui.button("Scatter Decorations...").clicked() {
+            let mut state = world.resource_mut::<crate::EditorState>();
+            state.scatter_window = true;
+            ui.close_menu();
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct FindReplace;
+
+impl BasicWidget for FindReplace {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        if world.get_map().is_err() {
+            if ui
+                .add_enabled(false, egui::Button::new("Find & Replace..."))
+                .clicked()
+            {
+                unreachable!();
+            }
+            return;
+        }
+        if ui.button("Find & Replace...").clicked() {
+            let mut state = world.resource_mut::<crate::EditorState>();
+            state.find_replace_window = true;
+            ui.close_menu();
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct SelectionMenu;
+
+impl BasicWidget for SelectionMenu {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let enabled = !world.resource::<crate::MapSelection>().tiles.is_empty();
+        let mut event = None;
+
+        if ui
+            .add_enabled(enabled, egui::Button::new("Rotate Clockwise"))
+            .clicked()
+        {
+            event = Some(EditorUiEvent::SelectionRotate(
+                tileset::TileRotation::Clockwise60,
+            ));
+        }
+        if ui
+            .add_enabled(enabled, egui::Button::new("Rotate Counter-Clockwise"))
+            .clicked()
+        {
+            event = Some(EditorUiEvent::SelectionRotate(
+                tileset::TileRotation::CounterClockwise60,
+            ));
+        }
+        if ui
+            .add_enabled(enabled, egui::Button::new("Delete"))
+            .clicked()
+        {
+            event = Some(EditorUiEvent::SelectionDelete);
+        }
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.menu_button("Move to Layer", |ui| {
+                let active = world.resource::<crate::EditorState>().active_layer;
+                let mut query = world.query::<(Entity, &map::Layer)>();
+                let mut layers: Vec<_> = query.iter(world).collect();
+                layers.sort_by_key(|(_, layer)| std::cmp::Reverse(layer.order));
+
+                // tiles can only be moved to visible, unlocked tile layers
+                for (layer_id, layer) in layers {
+                    let enabled = Some(layer_id) != active && layer.accepts_tiles();
+                    if ui
+                        .add_enabled(enabled, egui::Button::new(&layer.name))
+                        .clicked()
+                    {
+                        event = Some(EditorUiEvent::SelectionMoveToLayer(layer_id));
+                    }
+                }
+            });
+        });
+        ui.separator();
+        if ui
+            .add_enabled(enabled, egui::Button::new("Select None"))
+            .clicked()
+        {
+            event = Some(EditorUiEvent::SelectionClear);
+        }
+
+        let Some(event) = event else { return };
+        debug!("selection: {:?}", event);
+        ui.close_menu();
+        let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+        events.send(event);
+    }
+}
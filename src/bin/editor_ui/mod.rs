@@ -1,14 +1,41 @@
 use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
-use hex_sandbox::{tileset, ui::widget::*};
+use hex_sandbox::{map, tileset, ui::widget::*};
 
+mod annotations;
+mod find_replace;
+mod import_warnings;
+mod map_changed;
 mod menu;
+mod objects;
 mod panel;
+mod placed_tiles;
+mod property_schema;
+mod random_brush;
+mod regions;
+mod remove_tiles;
+mod scatter;
+mod stamps;
 mod tile_properties;
+mod tileset_folder;
+mod toolbar;
 
+pub use annotations::AnnotationsWindow;
+pub use find_replace::FindReplace;
+pub use import_warnings::ImportWarningsDialog;
+pub use map_changed::MapChangedDialog;
 pub use menu::EditorMenuBar;
+pub use objects::ObjectsWindow;
 pub use panel::EditorPanel;
+pub use placed_tiles::PlacedTiles;
+pub use property_schema::PropertySchemaEditor;
+pub use random_brush::RandomBrushSettings;
+pub use remove_tiles::RemoveTilesDialog;
+pub use scatter::ScatterDialog;
+pub use stamps::SaveStamp;
 pub use tile_properties::TileProperties;
+pub use tileset_folder::{FolderScanResults, TilesetFolderDialog};
+pub use toolbar::Toolbar;
 
 pub struct CreateTileset {
     just_opened: bool,
@@ -64,6 +91,74 @@ impl BasicWidget for CreateTileset {
     }
 }
 
+pub struct ImportLayers {
+    offset: map::Location,
+    rotation: tileset::TileRotation,
+}
+
+impl BasicWidget for ImportLayers {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self {
+            offset: map::Location::default(),
+            rotation: tileset::TileRotation::None,
+        }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        use crate::{EditorState, EditorUiEvent};
+
+        let Some(path) = world.resource::<EditorState>().import_layers.clone() else { return };
+
+        ui.label(path.to_string_lossy());
+        egui::Grid::new(id.with("grid"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("offset");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.offset.x).prefix("x: "));
+                    ui.add(egui::DragValue::new(&mut self.offset.y).prefix("y: "));
+                });
+                ui.end_row();
+
+                ui.label("rotation");
+                egui::ComboBox::from_id_source(id.with("rotation"))
+                    .selected_text(format!("{:?}", self.rotation))
+                    .show_ui(ui, |ui| {
+                        let mut rotation = tileset::TileRotation::None;
+                        for _ in 0..6 {
+                            ui.selectable_value(
+                                &mut self.rotation,
+                                rotation,
+                                format!("{:?}", rotation),
+                            );
+                            rotation = rotation.clockwise();
+                        }
+                    });
+                ui.end_row();
+            });
+
+        let (import, cancel) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (ui.button("Import"), ui.button("Cancel"))
+            })
+            .inner;
+
+        if import.clicked() {
+            let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+            events.send(EditorUiEvent::MapImportLayers {
+                path,
+                offset: self.offset,
+                rotation: self.rotation,
+            });
+        } else if !cancel.clicked() && !ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            return;
+        }
+
+        world.resource_mut::<EditorState>().import_layers = None;
+        *self = Self::new(world, ui);
+    }
+}
+
 pub struct EguiDebug<'w: 'static, 's: 'static> {
     system_state: SystemState<(
         Query<'w, 's, &'static leafwing_input_manager::prelude::ActionState<crate::InputActions>>,
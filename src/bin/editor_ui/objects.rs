@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{
+    map,
+    object::{MapObject, ObjectPosition, Property},
+    ui::widget::*,
+};
+use std::collections::BTreeMap;
+
+use crate::{EditorState, EditorUiEvent};
+
+/// property types in the order they're shown in the type dropdown
+const PROPERTY_TYPES: [&str; 5] = ["bool", "int", "float", "string", "object"];
+
+/// list of the objects in the active object layer, and the properties of the
+/// selected object
+#[derive(Default)]
+pub struct ObjectsWindow {
+    /// name of the property to add
+    new_property: String,
+}
+
+impl BasicWidget for ObjectsWindow {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let state = world.resource::<EditorState>();
+        let (active_layer, active_object) = (state.active_layer, state.active_object);
+        let is_object_layer = active_layer
+            .and_then(|layer| world.get::<map::Layer>(layer))
+            .map(|layer| layer.kind == map::LayerKind::Object)
+            .unwrap_or(false);
+        if !is_object_layer {
+            ui.label("Select an object layer, then click on the map to place objects");
+            return;
+        }
+
+        // every object in the map, for the list & for object references
+        let objects: Vec<(Entity, String, bool)> = object_labels(world)
+            .into_iter()
+            .map(|(entity, label, layer)| (entity, label, Some(layer) == active_layer))
+            .collect();
+
+        let mut selected = active_object;
+        egui::ScrollArea::vertical()
+            .id_source(id.with("list"))
+            .max_height(150.0)
+            .show(ui, |ui| {
+                for (entity, label, _) in objects.iter().filter(|(_, _, in_layer)| *in_layer) {
+                    ui.selectable_value(&mut selected, Some(*entity), label);
+                }
+            });
+        if selected != active_object {
+            world.resource_mut::<EditorState>().active_object = selected;
+        }
+        ui.separator();
+
+        let Some(entity) = selected else {
+            ui.label("Click on the map to place an object");
+            return;
+        };
+        let Some(original) = world.get::<MapObject>(entity).cloned() else { return };
+        let mut map = world.query::<&map::Map>();
+        let Ok(map) = map.get_single(world) else { return };
+        let mut object = original.clone();
+
+        egui::Grid::new(id.with("object"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("name");
+                ui.text_edit_singleline(&mut object.name);
+                ui.end_row();
+
+                ui.label("type");
+                ui.text_edit_singleline(&mut object.kind);
+                ui.end_row();
+
+                ui.label("position");
+                ui.horizontal(|ui| {
+                    let mut free = matches!(object.position, ObjectPosition::Free(_));
+                    if ui.checkbox(&mut free, "free").changed() {
+                        object.position = if free {
+                            ObjectPosition::Free(object.position.translation(map))
+                        } else {
+                            ObjectPosition::Hex(object.position.location(map))
+                        };
+                    }
+                    match &mut object.position {
+                        ObjectPosition::Hex(location) => {
+                            ui.add(egui::DragValue::new(&mut location.x).prefix("x: "));
+                            ui.add(egui::DragValue::new(&mut location.y).prefix("y: "));
+                        }
+                        ObjectPosition::Free(position) => {
+                            for (value, prefix) in [
+                                (&mut position.x, "x: "),
+                                (&mut position.y, "y: "),
+                                (&mut position.z, "z: "),
+                            ] {
+                                ui.add(egui::DragValue::new(value).speed(0.05).prefix(prefix));
+                            }
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("model");
+                let mut model = object
+                    .model
+                    .as_ref()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                if ui.text_edit_singleline(&mut model).changed() {
+                    object.model = (!model.is_empty()).then(|| model.into());
+                }
+                ui.end_row();
+
+                ui.label("icon");
+                let mut icon = object.icon.clone().unwrap_or_default();
+                if ui.text_edit_singleline(&mut icon).changed() {
+                    object.icon = (!icon.is_empty()).then_some(icon);
+                }
+                ui.end_row();
+            });
+
+        ui.separator();
+        ui.label("Properties");
+        let targets: Vec<(Entity, String)> = objects
+            .iter()
+            .filter(|(e, _, _)| *e != entity)
+            .map(|(e, label, _)| (*e, label.clone()))
+            .collect();
+        edit_properties(
+            ui,
+            id.with("properties"),
+            &mut object.properties,
+            &targets,
+            &mut self.new_property,
+        );
+
+        ui.separator();
+        if ui.button("Delete Object").clicked() {
+            world
+                .resource_mut::<Events<EditorUiEvent>>()
+                .send(EditorUiEvent::DeleteObject(entity));
+            return;
+        }
+
+        if object != original {
+            let layer_locked = world
+                .get::<Parent>(entity)
+                .and_then(|parent| world.get::<map::Layer>(parent.get()))
+                .map(|layer| !layer.editable())
+                .unwrap_or(false);
+            if layer_locked {
+                warn!("object layer is hidden or locked");
+                return;
+            }
+            if object.name != original.name {
+                world
+                    .entity_mut(entity)
+                    .insert(Name::new(format!("object: {}", object.name)));
+            }
+            world.entity_mut(entity).insert(object);
+            world.resource_mut::<EditorState>().unsaved_changes = true;
+        }
+    }
+}
+
+/// label & layer of every object in the map, sorted by label
+pub fn object_labels(world: &mut World) -> Vec<(Entity, String, Entity)> {
+    let mut query = world.query::<(Entity, &MapObject, &Parent)>();
+    let mut objects: Vec<(Entity, String, Entity)> = query
+        .iter(world)
+        .map(|(entity, object, parent)| {
+            let label = match &object.icon {
+                Some(icon) => format!("{} {} ({})", icon, object.name, object.kind),
+                None => format!("{} ({})", object.name, object.kind),
+            };
+            (entity, label, parent.get())
+        })
+        .collect();
+    objects.sort_by(|a, b| a.1.cmp(&b.1));
+    objects
+}
+
+/// edit a user-defined property bag; `targets` are the objects that may be
+/// referenced, and `new_property` holds the name of the property to add
+pub fn edit_properties(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    properties: &mut BTreeMap<String, Property>,
+    targets: &[(Entity, String)],
+    new_property: &mut String,
+) {
+    let mut remove = None;
+    egui::Grid::new(id).num_columns(4).show(ui, |ui| {
+        for (key, property) in properties.iter_mut() {
+            ui.label(key);
+
+            let mut kind = property.type_name();
+            egui::ComboBox::from_id_source(id.with(("type", key)))
+                .selected_text(kind)
+                .width(60.0)
+                .show_ui(ui, |ui| {
+                    for name in PROPERTY_TYPES {
+                        if name == "object" && targets.is_empty() {
+                            continue;
+                        }
+                        ui.selectable_value(&mut kind, name, name);
+                    }
+                });
+            if kind != property.type_name() {
+                *property = match kind {
+                    "bool" => Property::Bool(false),
+                    "int" => Property::Int(0),
+                    "float" => Property::Float(0.0),
+                    "object" => Property::Object(targets[0].0),
+                    _ => Property::String(String::new()),
+                };
+            }
+
+            match property {
+                Property::Bool(v) => {
+                    ui.checkbox(v, "");
+                }
+                Property::Int(v) => {
+                    ui.add(egui::DragValue::new(v));
+                }
+                Property::Float(v) => {
+                    ui.add(egui::DragValue::new(v).speed(0.1));
+                }
+                Property::String(v) => {
+                    ui.text_edit_singleline(v);
+                }
+                Property::Object(target) => {
+                    let selected = targets
+                        .iter()
+                        .find(|(e, _)| e == target)
+                        .map(|(_, label)| label.as_str())
+                        .unwrap_or("missing object");
+                    egui::ComboBox::from_id_source(id.with(("ref", key)))
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for (entity, label) in targets {
+                                ui.selectable_value(target, *entity, label);
+                            }
+                        });
+                }
+            }
+
+            if ui
+                .small_button("➖")
+                .on_hover_text("Remove property")
+                .clicked()
+            {
+                remove = Some(key.clone());
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(key) = remove {
+        properties.remove(&key);
+    }
+
+    ui.horizontal(|ui| {
+        ui.text_edit_singleline(new_property);
+        let valid = !new_property.is_empty() && !properties.contains_key(new_property);
+        if ui
+            .add_enabled(valid, egui::Button::new("➕"))
+            .on_hover_text("Add property")
+            .clicked()
+        {
+            properties.insert(
+                std::mem::take(new_property),
+                Property::String(String::new()),
+            );
+        }
+    });
+}
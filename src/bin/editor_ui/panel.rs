@@ -3,7 +3,7 @@ use bevy::prelude::*;
 use bevy_egui::egui;
 use hex_sandbox::{file_picker, map, tileset, ui, ui::widget::*};
 
-use crate::{EditorState, EditorUiEvent};
+use crate::{EditorState, EditorUiEvent, TileRemoval};
 
 #[derive(Default)]
 pub struct EditorPanel;
@@ -15,6 +15,8 @@ impl BasicWidget for EditorPanel {
 
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
         basic_widget::<TilesetPanel>(world, ui, id.with("tileset_panel"));
+        basic_widget::<super::stamps::StampsPanel>(world, ui, id.with("stamps_panel"));
+        basic_widget::<super::regions::RegionsPanel>(world, ui, id.with("regions_panel"));
         basic_widget::<LayersPanel>(world, ui, id.with("layers_panel"));
         ui.allocate_space(ui.available_size());
     }
@@ -96,7 +98,7 @@ impl BasicWidget for TilesetMenu {
             if ui.button("Import Tileset").clicked() {
                 world.spawn(
                     file_picker::Picker::for_many(crate::PickerEvent::TilesetImport(None))
-                        .add_filter("Tileset Files", &["ron"])
+                        .add_filter("Tileset Files", &["ron", "json"])
                         .build(),
                 );
                 ui.close_menu();
@@ -113,6 +115,21 @@ impl BasicWidget for TilesetMenu {
                     );
                     ui.close_menu();
                 }
+                if ui.button("Rescan Folder...").clicked() {
+                    world.spawn(
+                        file_picker::Picker::for_folder(crate::PickerEvent::TilesetFolder(
+                            Some(tileset_id),
+                            None,
+                        ))
+                        .build(),
+                    );
+                    ui.close_menu();
+                }
+                if ui.button("Edit Property Schema").clicked() {
+                    let mut state = world.resource_mut::<EditorState>();
+                    state.property_schema_window = Some(tileset_id);
+                    ui.close_menu();
+                }
             }
 
             ui.separator();
@@ -122,26 +139,27 @@ impl BasicWidget for TilesetMenu {
                 state.new_tileset_window = true;
                 ui.close_menu();
             }
+            if ui.button("New Tileset from Folder...").clicked() {
+                world.spawn(
+                    file_picker::Picker::for_folder(crate::PickerEvent::TilesetFolder(None, None))
+                        .build(),
+                );
+                ui.close_menu();
+            }
 
             let state = world.resource::<EditorState>();
 
             if let Some(tileset_id) = state.active_tileset {
-                if ui.button("Remove Tileset").clicked() {
-                    world.spawn(ui::ConfirmationDialog {
-                        title: "Delete Tileset",
-                        message: "Are you sure you want to delete this tileset",
-                        buttons: [
-                            Some((
-                                "Delete Tileset",
-                                Some(EditorUiEvent::DeleteTileset(tileset_id)),
-                            )),
-                            Some(("Cancel", None)),
-                            None,
-                        ],
+                if ui.button("Remove Tileset...").clicked() {
+                    let mut state = world.resource_mut::<EditorState>();
+                    state.remove_tiles = Some(TileRemoval {
+                        tileset: tileset_id,
+                        tiles: None,
                     });
+                    ui.close_menu();
                 }
             } else if ui
-                .add_enabled(false, egui::Button::new("Remove Tileset"))
+                .add_enabled(false, egui::Button::new("Remove Tileset..."))
                 .clicked()
             {
                 unreachable!();
@@ -169,19 +187,10 @@ impl BasicWidget for RemoveTilesetButton {
         };
 
         if ui.button("➖").clicked() {
-            // XXX expand dialog with details of the tileset
-
-            world.spawn(ui::ConfirmationDialog {
-                title: "Delete Tileset",
-                message: "Are you sure you want to delete this tileset",
-                buttons: [
-                    Some((
-                        "Delete Tileset",
-                        Some(EditorUiEvent::DeleteTileset(tileset_id)),
-                    )),
-                    Some(("Cancel", None)),
-                    None,
-                ],
+            let mut state = world.resource_mut::<EditorState>();
+            state.remove_tiles = Some(TileRemoval {
+                tileset: tileset_id,
+                tiles: None,
             });
         }
     }
@@ -189,22 +198,25 @@ impl BasicWidget for RemoveTilesetButton {
 #[derive(Default, Clone)]
 pub struct TilesetViewer {
     height: f32,
+    filter: tileset::TileFilter,
 }
 
 impl BasicWidget for TilesetViewer {
     fn new(_world: &mut World, ui: &egui::Ui) -> Self {
         Self {
             height: ui.available_height() * 0.60,
+            filter: default(),
         }
     }
 
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        self.draw_filter(world, ui, id);
         egui::ScrollArea::vertical()
             .max_height(self.height)
             .auto_shrink([false, false])
             .id_source(id.with("vscroll"))
             .show(ui, |ui| {
-                basic_widget::<TilePicker>(world, ui, id.with("tile_picker"));
+                fn_widget::<TilePicker>(world, ui, id.with("tile_picker"), self.filter.clone());
 
                 // fill in the scroll area so we don't shrink
                 ui.allocate_space(ui.available_size());
@@ -216,6 +228,71 @@ impl BasicWidget for TilesetViewer {
     }
 }
 
+impl TilesetViewer {
+    /// search box, grouping, & tag filter bar for the tile picker
+    fn draw_filter(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        use tileset::TileGrouping;
+
+        let tags = world
+            .resource::<EditorState>()
+            .active_tileset
+            .and_then(|entity| world.get::<tileset::Tileset>(entity))
+            .map(|tileset| tileset.tags())
+            .unwrap_or_default();
+        // forget tags that are no longer used, such as after changing tileset
+        self.filter.tags.retain(|tag| tags.contains(tag));
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.search)
+                    .hint_text("search")
+                    .desired_width(120.0),
+            );
+            let clear = ui
+                .add_enabled(!self.filter.is_empty(), egui::Button::new("×").small())
+                .on_hover_text("Clear the search & tag filter");
+            if clear.clicked() {
+                self.filter.search.clear();
+                self.filter.tags.clear();
+            }
+
+            let label = |grouping| match grouping {
+                TileGrouping::None => "no groups",
+                TileGrouping::Tag => "group by tag",
+                TileGrouping::NamePrefix => "group by name",
+            };
+            egui::ComboBox::from_id_source(id.with("grouping"))
+                .selected_text(label(self.filter.grouping))
+                .show_ui(ui, |ui| {
+                    for grouping in [
+                        TileGrouping::None,
+                        TileGrouping::Tag,
+                        TileGrouping::NamePrefix,
+                    ] {
+                        ui.selectable_value(&mut self.filter.grouping, grouping, label(grouping));
+                    }
+                });
+        });
+
+        if tags.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            for tag in tags {
+                let index = self.filter.tags.iter().position(|t| *t == tag);
+                if ui.selectable_label(index.is_some(), &tag).clicked() {
+                    match index {
+                        Some(i) => {
+                            self.filter.tags.remove(i);
+                        }
+                        None => self.filter.tags.push(tag),
+                    }
+                }
+            }
+        });
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct TilesetPanelFooter;
 
@@ -227,6 +304,7 @@ impl BasicWidget for TilesetPanelFooter {
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
         ui.horizontal(|ui| {
             basic_widget::<TilesetAddTiles>(world, ui, id.with("add_tiles"));
+            basic_widget::<TilesetRemoveTiles>(world, ui, id.with("remove_tiles"));
         });
     }
 }
@@ -262,6 +340,44 @@ impl BasicWidget for TilesetAddTiles {
     }
 }
 
+/// remove the selected tiles from the active tileset
+#[derive(Default, Clone)]
+pub struct TilesetRemoveTiles;
+
+impl BasicWidget for TilesetRemoveTiles {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let state = world.resource::<EditorState>();
+        let selection = world.resource::<crate::TileSelection>();
+        let Some(tileset_id) = state.active_tileset else { return };
+        let Some(tileset) = world.get::<tileset::Tileset>(tileset_id) else { return };
+        let tiles: Vec<tileset::TileId> = tileset
+            .tile_order
+            .iter()
+            .filter(|tile| {
+                selection.tiles.contains(&tileset::TileRef {
+                    tileset: tileset_id,
+                    tile: **tile,
+                })
+            })
+            .copied()
+            .collect();
+
+        let remove = ui
+            .add_enabled(!tiles.is_empty(), egui::Button::new("➖"))
+            .on_hover_text("Remove the selected tiles from the tileset");
+        if remove.clicked() {
+            world.resource_mut::<EditorState>().remove_tiles = Some(TileRemoval {
+                tileset: tileset_id,
+                tiles: Some(tiles),
+            });
+        }
+    }
+}
+
 pub struct TilePicker<'w: 'static, 's: 'static> {
     system_state: SystemState<(
         Res<'w, EditorState>,
@@ -269,50 +385,203 @@ pub struct TilePicker<'w: 'static, 's: 'static> {
         Query<'w, 's, &'static mut tileset::Tileset>,
     )>,
     tileset: Option<Entity>,
+    state: TilePickerState,
+}
+
+/// range selection & drag state of the tile picker; ranges are indexes into
+/// the `tile_order` of the tileset
+#[derive(Default)]
+struct TilePickerState {
     start_range: Option<usize>,
     last_range: Option<Vec<tileset::TileRef>>,
     drag_start: Option<egui::Pos2>,
 }
 
-impl<'w, 's> BasicWidget for TilePicker<'w, 's> {
+/// changes requested while drawing the tiles, applied once every tile is drawn
+#[derive(Default)]
+struct TilePickerInput {
+    deselect_range: Option<Vec<tileset::TileRef>>,
+    select_range: Option<std::ops::RangeInclusive<usize>>,
+    drop_index: Option<usize>,
+}
+
+impl<'w, 's> FnWidget for TilePicker<'w, 's> {
+    type Arg = tileset::TileFilter;
+    type Output = ();
+
     fn new(world: &mut World, _ui: &egui::Ui) -> Self {
         Self {
             system_state: SystemState::new(world),
             tileset: None,
-            start_range: None,
-            last_range: None,
-            drag_start: None,
+            state: default(),
         }
     }
 
-    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+    fn draw(
+        &mut self,
+        world: &mut World,
+        ui: &mut egui::Ui,
+        id: egui::Id,
+        filter: tileset::TileFilter,
+    ) {
         use tileset::TileRef;
 
         let (state, mut selection, mut tilesets) = self.system_state.get_mut(world);
 
         if self.tileset != state.active_tileset {
             self.tileset = state.active_tileset;
-            self.start_range = None;
-            self.last_range = None;
-            self.drag_start = None;
+            self.state = default();
         }
         let Some(tileset_id) = state.active_tileset else {
             ui.label("no active tileset");
             return;
         };
 
-        let modifiers = ui.input(|i| i.modifiers);
-        let mut deselect_range = None;
-        let mut select_range = None;
-        let mut drop_index = None;
+        let mut input = TilePickerInput::default();
         let Ok(tileset) = tilesets.get(tileset_id) else {
             ui.label(format!("invalid tileset {:?}", tileset_id));
             return;
         };
 
+        let groups = tileset.group_tiles(&filter);
+        if groups.is_empty() && !filter.is_empty() {
+            ui.label("no matching tiles");
+        }
+        let visible: std::collections::HashSet<usize> = groups
+            .iter()
+            .flat_map(|(_, indexes)| indexes)
+            .copied()
+            .collect();
+
+        // a tile hidden by the filter can't be scrolled to
+        let scroll_to = selection
+            .scroll_to
+            .filter(|tile_ref| tile_ref.tileset == tileset_id)
+            .and_then(|tile_ref| tileset.tile_order.iter().position(|t| *t == tile_ref.tile))
+            .filter(|index| visible.contains(index));
+        if scroll_to.is_none() {
+            selection.scroll_to = None;
+        }
+
+        let drag_layer = egui::LayerId::new(egui::Order::Tooltip, id.with("dragging"));
+        for (name, indexes) in &groups {
+            if filter.grouping == tileset::TileGrouping::None {
+                self.state.draw_tiles(
+                    ui,
+                    tileset,
+                    tileset_id,
+                    indexes,
+                    &mut selection,
+                    drag_layer,
+                    &mut input,
+                );
+                continue;
+            }
+
+            let mut header = egui::CollapsingHeader::new(format!("{} ({})", name, indexes.len()))
+                .id_source(id.with(("group", name)))
+                .default_open(true);
+            // open the group of the tile we're scrolling to
+            if matches!(scroll_to, Some(index) if indexes.contains(&index)) {
+                header = header.open(Some(true));
+            }
+            header.show(ui, |ui| {
+                self.state.draw_tiles(
+                    ui,
+                    tileset,
+                    tileset_id,
+                    indexes,
+                    &mut selection,
+                    drag_layer,
+                    &mut input,
+                );
+            });
+        }
+
+        // XXX need hover target to drop at the bottom
+        // XXX drag is sometimes resizing the panel; fix it
+
+        // handle range-based changes to the selection; we handle deselect
+        // before select because the deselect range will always overlap with
+        // the select range if both are present.
+        if let Some(range) = input.deselect_range {
+            for tile_ref in range {
+                selection.tiles.remove(&tile_ref);
+            }
+        }
+        if let Some(range) = input.select_range {
+            let mut added = Vec::new();
+            // skip the tiles hidden by the filter
+            for index in range.filter(|index| visible.contains(index)) {
+                let tile_id = tileset.tile_order.get(index).unwrap();
+                let tile_ref = TileRef {
+                    tileset: tileset_id,
+                    tile: *tile_id,
+                };
+                added.push(tile_ref.clone());
+                selection.tiles.insert(tile_ref);
+            }
+            self.state.last_range = Some(added);
+        }
+
+        // if we're dragging, show the drag cursor, and translate the drag layer
+        if let Some(drag_start) = self.state.drag_start {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            if let Some(pos) = ui.ctx().pointer_interact_pos() {
+                let delta = pos - drag_start;
+                ui.ctx().translate_layer(drag_layer, delta);
+            }
+        }
+
+        // if there was a drop, shuffle the tile order to move all selected
+        // tiles (in order) to the drop index.
+        if let Some(mut insert_index) = input.drop_index {
+            let mut tileset = tilesets.get_mut(tileset_id).unwrap();
+            let mut moved = Vec::new();
+
+            for (index, tile_id) in tileset.tile_order.iter().enumerate() {
+                let tile_ref = TileRef {
+                    tileset: tileset_id,
+                    tile: *tile_id,
+                };
+                if selection.tiles.contains(&tile_ref) {
+                    moved.push((*tile_id, index));
+                    if index < insert_index {
+                        insert_index -= 1;
+                    }
+                }
+            }
+            moved.reverse();
+
+            for (_, index) in moved.iter() {
+                tileset.tile_order.remove(*index);
+            }
+            for (tile_id, _) in moved.iter() {
+                tileset.tile_order.insert(insert_index, *tile_id);
+            }
+        }
+    }
+}
+
+impl TilePickerState {
+    /// draw the tiles at `indexes` in the tile order as a wrapped grid of
+    /// buttons
+    #[allow(clippy::too_many_arguments)]
+    fn draw_tiles(
+        &mut self,
+        ui: &mut egui::Ui,
+        tileset: &tileset::Tileset,
+        tileset_id: Entity,
+        indexes: &[usize],
+        selection: &mut crate::TileSelection,
+        drag_layer: egui::LayerId,
+        input: &mut TilePickerInput,
+    ) {
+        use tileset::TileRef;
+
+        let modifiers = ui.input(|i| i.modifiers);
         let tile_size = egui::Vec2::splat(48.0);
         let layout = egui::Layout::left_to_right(egui::Align::Min).with_main_wrap(true);
-        let drag_layer = egui::LayerId::new(egui::Order::Tooltip, id.with("dragging"));
         ui.with_layout(layout, |ui| {
             let mut spacing = ui.spacing_mut();
             spacing.item_spacing = egui::vec2(0.0, 0.0);
@@ -320,7 +589,8 @@ impl<'w, 's> BasicWidget for TilePicker<'w, 's> {
             let mut visuals = ui.visuals_mut();
             visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
 
-            for (index, tile_id) in tileset.tile_order.iter().enumerate() {
+            for &index in indexes {
+                let tile_id = &tileset.tile_order[index];
                 let Some(tile) = tileset.tiles.get(tile_id) else {
                     warn!("unknown tile id in tileset order; \
                         tileset \"{}\" ({:?}), tile id {}",
@@ -351,24 +621,28 @@ impl<'w, 's> BasicWidget for TilePicker<'w, 's> {
                     };
 
                     if res.hovered() && ui.input(|i| i.pointer.any_released()) {
-                        drop_index = Some(index);
+                        input.drop_index = Some(index);
                         self.drag_start = None;
                     }
                     continue;
                 }
 
                 // not dragging, just draw the button
-                let res = ui.add(button);
+                let res = ui.add(button).on_hover_text(&tile.name);
+                if selection.scroll_to == Some(tile_ref) {
+                    res.scroll_to_me(Some(egui::Align::Center));
+                    selection.scroll_to = None;
+                }
                 if res.clicked() {
                     if modifiers.shift_only() {
-                        deselect_range = self.last_range.take();
+                        input.deselect_range = self.last_range.take();
                         if let Some(start) = &self.start_range {
                             let range = if *start < index {
                                 *start..=index
                             } else {
                                 index..=*start
                             };
-                            select_range = Some(range);
+                            input.select_range = Some(range);
                         } else {
                             selection.tiles.insert(tile_ref);
                             self.start_range = Some(index);
@@ -399,68 +673,6 @@ impl<'w, 's> BasicWidget for TilePicker<'w, 's> {
                 }
             }
         });
-
-        // XXX need hover target to drop at the bottom
-        // XXX drag is sometimes resizing the panel; fix it
-
-        // handle range-based changes to the selection; we handle deselect
-        // before select because the deselect range will always overlap with
-        // the select range if both are present.
-        if let Some(range) = deselect_range {
-            for tile_ref in range {
-                selection.tiles.remove(&tile_ref);
-            }
-        }
-        if let Some(range) = select_range {
-            let mut added = Vec::new();
-            for index in range {
-                let tile_id = tileset.tile_order.get(index).unwrap();
-                let tile_ref = TileRef {
-                    tileset: tileset_id,
-                    tile: *tile_id,
-                };
-                added.push(tile_ref.clone());
-                selection.tiles.insert(tile_ref);
-            }
-            self.last_range = Some(added);
-        }
-
-        // if we're dragging, show the drag cursor, and translate the drag layer
-        if let Some(drag_start) = self.drag_start {
-            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
-            if let Some(pos) = ui.ctx().pointer_interact_pos() {
-                let delta = pos - drag_start;
-                ui.ctx().translate_layer(drag_layer, delta);
-            }
-        }
-
-        // if there was a drop, shuffle the tile order to move all selected
-        // tiles (in order) to the drop index.
-        if let Some(mut insert_index) = drop_index {
-            let mut tileset = tilesets.get_mut(tileset_id).unwrap();
-            let mut moved = Vec::new();
-
-            for (index, tile_id) in tileset.tile_order.iter().enumerate() {
-                let tile_ref = TileRef {
-                    tileset: tileset_id,
-                    tile: *tile_id,
-                };
-                if selection.tiles.contains(&tile_ref) {
-                    moved.push((*tile_id, index));
-                    if index < insert_index {
-                        insert_index -= 1;
-                    }
-                }
-            }
-            moved.reverse();
-
-            for (_, index) in moved.iter() {
-                tileset.tile_order.remove(*index);
-            }
-            for (tile_id, _) in moved.iter() {
-                tileset.tile_order.insert(insert_index, *tile_id);
-            }
-        }
     }
 }
 
@@ -475,7 +687,7 @@ impl BasicWidget for LayersPanel {
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
         fn_widget::<ui::widgets::PanelTitle>(world, ui, id.with("title"), "Layers");
         egui::ScrollArea::vertical()
-            .max_height(ui.available_height() - 25.0)
+            .max_height(ui.available_height() - 50.0)
             .auto_shrink([false, false])
             .show(ui, |ui| {
                 basic_widget::<LayersList>(world, ui, id.with("layer_list"));
@@ -486,7 +698,12 @@ impl BasicWidget for LayersPanel {
 }
 
 #[derive(Default)]
-pub struct LayersList;
+pub struct LayersList {
+    /// layer being renamed, and the name being edited
+    renaming: Option<(Entity, String)>,
+    /// layer being dragged to a new position in the list
+    dragging: Option<Entity>,
+}
 
 impl BasicWidget for LayersList {
     fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
@@ -496,17 +713,124 @@ impl BasicWidget for LayersList {
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
         let state = world.resource::<EditorState>();
         let mut active_layer = state.active_layer.unwrap_or(Entity::PLACEHOLDER);
-        let mut layers = world.query::<(Entity, &map::Layer)>();
         let mut changed = false;
+
+        // list the layers top-most first
+        let mut layers: Vec<(Entity, LayerRow)> = world
+            .query::<(Entity, &map::Layer)>()
+            .iter(world)
+            .map(|(entity, layer)| (entity, LayerRow::from(layer)))
+            .collect();
+        layers.sort_by_key(|(_, layer)| std::cmp::Reverse(layer.order));
+
+        let mut updates: Vec<(Entity, LayerRow)> = Vec::new();
+        let mut drop_index = None;
         let layout = egui::Layout::top_down(egui::Align::LEFT).with_cross_justify(true);
         ui.with_layout(layout, |ui| {
-            for (layer_id, layer) in layers.iter(world) {
-                changed |= ui
-                    .selectable_value(&mut active_layer, layer_id, &layer.name)
-                    .changed();
+            for (index, (layer_id, layer)) in layers.iter().enumerate() {
+                let mut layer_update = None;
+                let row = ui.horizontal(|ui| {
+                    let mut visible = layer.visible;
+                    if ui
+                        .selectable_label(visible, "👁")
+                        .on_hover_text("show layer")
+                        .clicked()
+                    {
+                        visible = !visible;
+                    }
+                    let mut locked = layer.locked;
+                    if ui
+                        .selectable_label(locked, "🔒")
+                        .on_hover_text("lock layer")
+                        .clicked()
+                    {
+                        locked = !locked;
+                    }
+                    ui.label(kind_icon(layer.kind))
+                        .on_hover_text(format!("{} layer", layer.kind.label()));
+                    let mut name = layer.name.clone();
+
+                    match &mut self.renaming {
+                        Some((id, text)) if id == layer_id => {
+                            let res = ui.text_edit_singleline(text);
+                            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                self.renaming = None;
+                            } else if res.lost_focus() {
+                                if !text.trim().is_empty() {
+                                    name = text.trim().to_string();
+                                }
+                                self.renaming = None;
+                            } else {
+                                res.request_focus();
+                            }
+                        }
+                        _ => {
+                            let res = ui
+                                .selectable_value(&mut active_layer, *layer_id, &layer.name)
+                                .interact(egui::Sense::drag())
+                                .on_hover_text("double-click to rename, drag to reorder");
+                            changed |= res.changed();
+                            if res.double_clicked() {
+                                self.renaming = Some((*layer_id, layer.name.clone()));
+                            } else if res.drag_started() {
+                                self.dragging = Some(*layer_id);
+                            }
+                        }
+                    }
+
+                    if visible != layer.visible || locked != layer.locked || name != layer.name {
+                        layer_update = Some(LayerRow {
+                            name,
+                            visible,
+                            locked,
+                            order: layer.order,
+                            kind: layer.kind,
+                        });
+                    }
+                });
+                if let Some(update) = layer_update {
+                    updates.push((*layer_id, update));
+                }
+
+                // show where the dragged layer will be dropped
+                if self.dragging.is_some() && ui.rect_contains_pointer(row.response.rect) {
+                    let rect = row.response.rect;
+                    let y = if self.dragging_below(&layers, index) {
+                        rect.bottom()
+                    } else {
+                        rect.top()
+                    };
+                    ui.painter()
+                        .hline(rect.x_range(), y, ui.visuals().selection.stroke);
+                    if ui.input(|i| i.pointer.any_released()) {
+                        drop_index = Some(index);
+                    }
+                }
             }
         });
 
+        if let Some(dragging) = self.dragging {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            if ui.input(|i| i.pointer.any_released()) {
+                self.dragging = None;
+                if let Some(index) = drop_index {
+                    reorder_layers(world, &layers, dragging, index);
+                }
+            }
+        }
+
+        for (layer_id, update) in updates {
+            let Some(mut layer) = world.get_mut::<map::Layer>(layer_id) else { continue };
+            layer.visible = update.visible;
+            layer.locked = update.locked;
+            if layer.name != update.name {
+                layer.name = update.name;
+                let name = Name::new(format!("layer: {}", layer.name));
+                world.entity_mut(layer_id).insert(name);
+            }
+            world.resource_mut::<EditorState>().unsaved_changes = true;
+        }
+
         if changed {
             let mut state = world.resource_mut::<EditorState>();
             state.active_layer = Some(active_layer);
@@ -514,6 +838,71 @@ impl BasicWidget for LayersList {
     }
 }
 
+/// the parts of a Layer shown in the LayersList
+struct LayerRow {
+    name: String,
+    order: usize,
+    visible: bool,
+    locked: bool,
+    kind: map::LayerKind,
+}
+
+impl From<&map::Layer> for LayerRow {
+    fn from(layer: &map::Layer) -> Self {
+        Self {
+            name: layer.name.clone(),
+            order: layer.order,
+            visible: layer.visible,
+            locked: layer.locked,
+            kind: layer.kind,
+        }
+    }
+}
+
+fn kind_icon(kind: map::LayerKind) -> &'static str {
+    match kind {
+        map::LayerKind::Tile => "⬣",
+        map::LayerKind::Object => "📍",
+        map::LayerKind::Annotation => "✏",
+    }
+}
+
+impl LayersList {
+    /// check if the layer being dragged is above the row at `index`; it will
+    /// be dropped below that row
+    fn dragging_below(&self, layers: &[(Entity, LayerRow)], index: usize) -> bool {
+        let dragged = layers.iter().position(|(e, _)| Some(*e) == self.dragging);
+        matches!(dragged, Some(d) if d < index)
+    }
+}
+
+/// move `layer_id` to `index` in the top-most first list of `layers`, and
+/// renumber the layer order to match
+fn reorder_layers(
+    world: &mut World,
+    layers: &[(Entity, LayerRow)],
+    layer_id: Entity,
+    index: usize,
+) {
+    let mut order: Vec<Entity> = layers.iter().map(|(e, _)| *e).collect();
+    let Some(from) = order.iter().position(|e| *e == layer_id) else { return };
+    if from == index {
+        return;
+    }
+    order.remove(from);
+    order.insert(index, layer_id);
+
+    let count = order.len();
+    for (index, entity) in order.into_iter().enumerate() {
+        let Some(mut layer) = world.get_mut::<map::Layer>(entity) else { continue };
+        let value = count - 1 - index;
+        if layer.order != value {
+            layer.order = value;
+        }
+    }
+    world.resource_mut::<EditorState>().unsaved_changes = true;
+}
+
 #[derive(Default)]
 pub struct LayersButtons {
     show_popup: bool,
@@ -525,27 +914,96 @@ impl BasicWidget for LayersButtons {
     }
 
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
-        let res = ui.button("➕");
-        if res.clicked() {
-            self.show_popup = true;
-        }
-        popup_widget::<CreateLayerPopup>(&mut self.show_popup, &res, world, ui, id.with("popup"));
+        ui.horizontal(|ui| {
+            let res = ui.button("➕").on_hover_text("new layer");
+            if res.clicked() {
+                self.show_popup = true;
+            }
+            popup_widget::<CreateLayerPopup>(
+                &mut self.show_popup,
+                &res,
+                world,
+                ui,
+                id.with("popup"),
+            );
+
+            let active_layer = world
+                .resource::<EditorState>()
+                .active_layer
+                .filter(|e| world.get::<map::Layer>(*e).is_some());
+            let Some(layer_id) = active_layer else {
+                ui.add_enabled(false, egui::Button::new("⎘"));
+                ui.add_enabled(false, egui::Button::new("➖"));
+                return;
+            };
+
+            if ui.button("⎘").on_hover_text("duplicate layer").clicked() {
+                world
+                    .resource_mut::<Events<EditorUiEvent>>()
+                    .send(EditorUiEvent::DuplicateLayer(layer_id));
+            }
+            if ui.button("➖").on_hover_text("delete layer").clicked() {
+                world.spawn(ui::ConfirmationDialog {
+                    title: "Delete Layer",
+                    message: "Are you sure you want to delete this layer and its tiles",
+                    buttons: [
+                        Some(("Delete Layer", Some(EditorUiEvent::DeleteLayer(layer_id)))),
+                        Some(("Cancel", None)),
+                        None,
+                    ],
+                });
+            }
+
+            let mut layer = world.get_mut::<map::Layer>(layer_id).unwrap();
+            let mut opacity = layer.opacity;
+            let res = ui
+                .add(egui::Slider::new(&mut opacity, 0.0..=1.0).show_value(false))
+                .on_hover_text("layer opacity");
+            if res.changed() {
+                layer.opacity = opacity;
+                world.resource_mut::<EditorState>().unsaved_changes = true;
+            }
+        });
+
+        // height of the active layer
+        ui.horizontal(|ui| {
+            let active_layer = world.resource::<EditorState>().active_layer;
+            let Some(mut layer) = active_layer.and_then(|e| world.get_mut::<map::Layer>(e)) else {
+                return;
+            };
+            ui.label("height");
+            let mut y_offset = layer.y_offset;
+            let res = ui
+                .add(egui::DragValue::new(&mut y_offset).speed(0.05))
+                .on_hover_text("layer height above the map");
+            if res.changed() {
+                layer.y_offset = y_offset;
+                world.resource_mut::<EditorState>().unsaved_changes = true;
+            }
+        });
     }
 }
 
 #[derive(Default, Clone)]
 pub struct CreateLayerPopup {
     name: String,
+    kind: map::LayerKind,
 }
 
 impl PopupWidget for CreateLayerPopup {
     fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
         Self {
             name: "New Layer".to_string(),
+            kind: map::LayerKind::Tile,
         }
     }
 
     fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) -> bool {
+        ui.horizontal(|ui| {
+            for kind in map::LayerKind::ALL {
+                ui.selectable_value(&mut self.kind, kind, kind.label());
+            }
+        });
         ui.horizontal(|ui| {
             ui.set_width(200.0);
             let res = ui.text_edit_singleline(&mut self.name);
@@ -553,13 +1011,23 @@ impl PopupWidget for CreateLayerPopup {
                 let mut query = world.query_filtered::<Entity, With<map::Map>>();
                 let map = query.single(world);
 
-                world
+                // new layers go above the existing layers
+                let mut layers = world.query::<&map::Layer>();
+                let order = layers.iter(world).map(|l| l.order + 1).max().unwrap_or(0);
+
+                let layer = world
                     .spawn((
                         Name::new(format!("layer: {}", self.name)),
-                        map::Layer::new(std::mem::take(&mut self.name)),
+                        map::Layer {
+                            order,
+                            kind: self.kind,
+                            ..map::Layer::new(std::mem::take(&mut self.name))
+                        },
                         SpatialBundle::default(),
                     ))
-                    .set_parent(map);
+                    .set_parent(map)
+                    .id();
+                world.resource_mut::<EditorState>().active_layer = Some(layer);
                 return false;
             }
             res.request_focus();
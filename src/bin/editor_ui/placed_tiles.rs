@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{map, tileset::TileOverrides, ui::widget::*};
+
+use crate::{EditorState, MapSelection};
+
+/// edits the overrides of the tiles selected on the map
+pub struct PlacedTiles {
+    /// largest change in scale made by the jitter button
+    jitter: f32,
+}
+
+impl BasicWidget for PlacedTiles {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self { jitter: 0.1 }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let mut tiles: Vec<Entity> = world
+            .resource::<MapSelection>()
+            .tiles
+            .iter()
+            .filter(|entity| world.get::<map::Location>(**entity).is_some())
+            .copied()
+            .collect();
+        if tiles.is_empty() {
+            ui.label("Select tiles on the map to edit them");
+            return;
+        }
+        // show the values of the same tile every frame
+        tiles.sort();
+        if tiles.len() > 1 {
+            ui.label(format!("{} tiles selected", tiles.len()));
+        }
+
+        let original = world
+            .get::<TileOverrides>(tiles[0])
+            .copied()
+            .unwrap_or_default();
+        let mut overrides = original;
+
+        egui::Grid::new(id.with("overrides"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("tint");
+                ui.horizontal(|ui| {
+                    let mut enabled = overrides.tint.is_some();
+                    ui.checkbox(&mut enabled, "");
+                    let mut color = overrides.tint.unwrap_or(Color::WHITE);
+                    if enabled {
+                        let mut rgba = color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+                        if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+                            color = Color::rgba_u8(rgba[0], rgba[1], rgba[2], rgba[3]);
+                        }
+                    }
+                    overrides.tint = enabled.then_some(color);
+                });
+                ui.end_row();
+
+                ui.label("scale");
+                ui.add(
+                    egui::DragValue::new(&mut overrides.scale)
+                        .speed(0.01)
+                        .clamp_range(0.05..=10.0)
+                        .fixed_decimals(2),
+                );
+                ui.end_row();
+
+                ui.label("offset");
+                ui.horizontal(|ui| {
+                    let offset = &mut overrides.offset;
+                    for (value, prefix) in [(&mut offset.x, "x: "), (&mut offset.y, "z: ")] {
+                        ui.add(egui::DragValue::new(value).speed(0.01).prefix(prefix));
+                    }
+                });
+                ui.end_row();
+
+                ui.label("y-offset");
+                ui.add(egui::DragValue::new(&mut overrides.y_offset).speed(0.01));
+                ui.end_row();
+
+                ui.label("flip");
+                ui.checkbox(&mut overrides.flip, "");
+                ui.end_row();
+            });
+
+        let (reset, jitter) = ui
+            .horizontal(|ui| {
+                let reset = ui.button("Reset").clicked();
+                let jitter = ui.button("Jitter scale").clicked();
+                ui.add(
+                    egui::DragValue::new(&mut self.jitter)
+                        .speed(0.01)
+                        .clamp_range(0.0..=0.9)
+                        .prefix("± "),
+                );
+                (reset, jitter)
+            })
+            .inner;
+
+        if overrides == original && !reset && !jitter {
+            return;
+        }
+
+        // only copy the values that were changed, so each selected tile keeps
+        // its other overrides
+        for entity in tiles {
+            let mut current = world
+                .get::<TileOverrides>(entity)
+                .copied()
+                .unwrap_or_default();
+            if reset {
+                current = TileOverrides::default();
+            }
+            if overrides.tint != original.tint {
+                current.tint = overrides.tint;
+            }
+            if overrides.scale != original.scale {
+                current.scale = overrides.scale;
+            }
+            if overrides.offset != original.offset {
+                current.offset = overrides.offset;
+            }
+            if overrides.y_offset != original.y_offset {
+                current.y_offset = overrides.y_offset;
+            }
+            if overrides.flip != original.flip {
+                current.flip = overrides.flip;
+            }
+            if jitter {
+                let location = world.get::<map::Location>(entity).copied().unwrap();
+                current.scale = 1.0 + self.jitter * noise(location);
+            }
+            world.entity_mut(entity).insert(current);
+        }
+        world.resource_mut::<EditorState>().unsaved_changes = true;
+    }
+}
+
+/// value in -1.0..1.0 that is stable for each location, so jittering the
+/// same tiles again gives the same result
+fn noise(location: map::Location) -> f32 {
+    let mut v = (location.x as u32).wrapping_mul(0x9e37_79b9) ^ (location.y as u32);
+    v = (v ^ (v >> 16)).wrapping_mul(0x85eb_ca6b);
+    v = (v ^ (v >> 13)).wrapping_mul(0xc2b2_ae35);
+    v ^= v >> 16;
+    (v as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
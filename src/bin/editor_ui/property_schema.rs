@@ -0,0 +1,256 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{
+    tileset::{TileProperty, TilePropertyField, TilePropertyKind, Tileset},
+    ui::widget::*,
+};
+
+use crate::EditorState;
+
+const PROPERTY_TYPES: [&str; 5] = ["int", "float", "bool", "string", "enum"];
+
+/// a field being edited in the property schema
+struct FieldRow {
+    /// name of the field in the tileset; None for new fields
+    original: Option<String>,
+    field: TilePropertyField,
+    /// comma separated options of an enum field
+    options: String,
+}
+
+impl FieldRow {
+    fn new(field: &TilePropertyField) -> Self {
+        let options = match &field.kind {
+            TilePropertyKind::Enum(options) => options.join(", "),
+            _ => String::new(),
+        };
+        Self {
+            original: Some(field.name.clone()),
+            field: field.clone(),
+            options,
+        }
+    }
+}
+
+/// edit the property schema of a tileset; changes are applied to the tileset,
+/// and the values of renamed & removed fields in its tiles, by "Apply"
+#[derive(Default)]
+pub struct PropertySchemaEditor {
+    tileset: Option<Entity>,
+    rows: Vec<FieldRow>,
+    new_field: String,
+}
+
+impl BasicWidget for PropertySchemaEditor {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let tileset_id = world.resource::<EditorState>().property_schema_window;
+        let Some(tileset) = tileset_id.and_then(|entity| world.get::<Tileset>(entity)) else {
+            ui.label("no tileset");
+            return;
+        };
+        if self.tileset != tileset_id {
+            self.tileset = tileset_id;
+            self.revert(tileset);
+        }
+        ui.label(format!("Properties of every tile in {}", tileset.name));
+
+        let mut remove = None;
+        egui::Grid::new(id.with("fields"))
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.label("name");
+                ui.label("type");
+                ui.label("default");
+                ui.end_row();
+
+                for (index, row) in self.rows.iter_mut().enumerate() {
+                    let row_id = id.with(("field", index));
+                    ui.add(egui::TextEdit::singleline(&mut row.field.name).desired_width(100.0));
+
+                    let mut kind = row.field.kind.type_name();
+                    egui::ComboBox::from_id_source(row_id.with("type"))
+                        .selected_text(kind)
+                        .width(60.0)
+                        .show_ui(ui, |ui| {
+                            for name in PROPERTY_TYPES {
+                                ui.selectable_value(&mut kind, name, name);
+                            }
+                        });
+                    if kind != row.field.kind.type_name() {
+                        row.field.kind = match kind {
+                            "int" => TilePropertyKind::Int,
+                            "float" => TilePropertyKind::Float,
+                            "bool" => TilePropertyKind::Bool,
+                            "enum" => TilePropertyKind::Enum(parse_options(&row.options)),
+                            _ => TilePropertyKind::String,
+                        };
+                        row.field.default = row.field.kind.zero();
+                    }
+
+                    ui.vertical(|ui| {
+                        if let TilePropertyKind::Enum(options) = &mut row.field.kind {
+                            let res = ui.add(
+                                egui::TextEdit::singleline(&mut row.options)
+                                    .hint_text("options, comma separated")
+                                    .desired_width(150.0),
+                            );
+                            if res.changed() {
+                                *options = parse_options(&row.options);
+                            }
+                        }
+                        if !row.field.kind.accepts(&row.field.default) {
+                            row.field.default = row.field.kind.zero();
+                        }
+                        edit_property(
+                            ui,
+                            row_id.with("default"),
+                            &row.field.kind,
+                            &mut row.field.default,
+                        );
+                    });
+
+                    if ui
+                        .small_button("➖")
+                        .on_hover_text("Remove property")
+                        .clicked()
+                    {
+                        remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = remove {
+            self.rows.remove(index);
+        }
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_field);
+            let valid = !self.new_field.is_empty()
+                && !self.rows.iter().any(|row| row.field.name == self.new_field);
+            if ui
+                .add_enabled(valid, egui::Button::new("➕"))
+                .on_hover_text("Add property")
+                .clicked()
+            {
+                let name = std::mem::take(&mut self.new_field);
+                self.rows.push(FieldRow {
+                    original: None,
+                    field: TilePropertyField::new(name, TilePropertyKind::Int),
+                    options: String::new(),
+                });
+            }
+        });
+        ui.separator();
+
+        let error = self.error();
+        if let Some(error) = &error {
+            ui.colored_label(ui.visuals().warn_fg_color, error);
+        }
+        let (apply, revert) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (
+                    ui.add_enabled(error.is_none(), egui::Button::new("Apply")),
+                    ui.button("Revert"),
+                )
+            })
+            .inner;
+
+        let Some(tileset_id) = tileset_id else { return };
+        if revert.clicked() {
+            let tileset = world.get::<Tileset>(tileset_id).unwrap();
+            self.revert(tileset);
+        } else if apply.clicked() {
+            let mut tileset = world.get_mut::<Tileset>(tileset_id).unwrap();
+            self.apply(&mut tileset);
+            self.revert(&tileset);
+            world.resource_mut::<EditorState>().unsaved_changes = true;
+        }
+    }
+}
+
+impl PropertySchemaEditor {
+    /// reload the rows from the tileset
+    fn revert(&mut self, tileset: &Tileset) {
+        self.rows = tileset.property_schema.iter().map(FieldRow::new).collect();
+    }
+
+    /// why the schema can't be applied
+    fn error(&self) -> Option<String> {
+        for (index, row) in self.rows.iter().enumerate() {
+            let name = &row.field.name;
+            if name.trim().is_empty() {
+                return Some(format!("property {} has no name", index + 1));
+            }
+            if self.rows[..index].iter().any(|r| r.field.name == *name) {
+                return Some(format!("more than one property named \"{}\"", name));
+            }
+            if matches!(&row.field.kind, TilePropertyKind::Enum(o) if o.is_empty()) {
+                return Some(format!("enum property \"{}\" has no options", name));
+            }
+        }
+        None
+    }
+
+    /// replace the schema of the tileset, moving the tile values of renamed
+    /// fields, and dropping the values that no longer fit the schema
+    fn apply(&self, tileset: &mut Tileset) {
+        for tile in tileset.tiles.values_mut() {
+            let mut values = std::mem::take(&mut tile.properties);
+            for row in &self.rows {
+                let Some(original) = &row.original else {
+                    continue;
+                };
+                if let Some(value) = values.remove(original) {
+                    tile.properties.insert(row.field.name.clone(), value);
+                }
+            }
+        }
+        tileset.property_schema = self.rows.iter().map(|row| row.field.clone()).collect();
+        tileset.clean_properties();
+    }
+}
+
+fn parse_options(text: &str) -> Vec<String> {
+    let mut options: Vec<String> = Vec::new();
+    for option in text.split(',').map(str::trim) {
+        if !option.is_empty() && !options.iter().any(|o| o == option) {
+            options.push(option.to_string());
+        }
+    }
+    options
+}
+
+/// widget for a tile property value of type `kind`; returns true if the value
+/// changed
+pub fn edit_property(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    kind: &TilePropertyKind,
+    value: &mut TileProperty,
+) -> bool {
+    if !kind.accepts(value) {
+        *value = kind.zero();
+    }
+    match (kind, value) {
+        (_, TileProperty::Int(v)) => ui.add(egui::DragValue::new(v)).changed(),
+        (_, TileProperty::Float(v)) => ui.add(egui::DragValue::new(v).speed(0.1)).changed(),
+        (_, TileProperty::Bool(v)) => ui.checkbox(v, "").changed(),
+        (_, TileProperty::String(v)) => ui.text_edit_singleline(v).changed(),
+        (TilePropertyKind::Enum(options), TileProperty::Enum(v)) => {
+            let mut changed = false;
+            egui::ComboBox::from_id_source(id)
+                .selected_text(v.as_str())
+                .show_ui(ui, |ui| {
+                    for option in options {
+                        changed |= ui.selectable_value(v, option.clone(), option).changed();
+                    }
+                });
+            changed
+        }
+        (_, TileProperty::Enum(_)) => false,
+    }
+}
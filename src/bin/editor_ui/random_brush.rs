@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{brush::RandomBrush, tileset, ui::widget::*};
+
+use crate::TileSelection;
+
+/// settings for painting with a random pick of the selected tiles
+#[derive(Default, Clone)]
+pub struct RandomBrushSettings;
+
+impl BasicWidget for RandomBrushSettings {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        // list the selected tiles in a stable order, with their names
+        let mut tiles: Vec<(tileset::TileRef, String)> = world
+            .resource::<TileSelection>()
+            .tiles
+            .iter()
+            .map(|tile_ref| {
+                let name = world
+                    .get::<tileset::Tileset>(tile_ref.tileset)
+                    .and_then(|tileset| tileset.tiles.get(&tile_ref.tile))
+                    .map(|tile| tile.name.clone())
+                    .unwrap_or_else(|| format!("{:?}", tile_ref));
+                (*tile_ref, name)
+            })
+            .collect();
+        tiles.sort_by(|a, b| a.1.cmp(&b.1));
+
+        // only mark the resource as changed when a value is edited, as that
+        // redraws the tool preview
+        let mut brush = world.resource::<RandomBrush>().clone();
+        let original = brush.clone();
+
+        ui.checkbox(&mut brush.enabled, "Paint with a random selected tile");
+        ui.add_enabled_ui(brush.enabled, |ui| {
+            ui.checkbox(&mut brush.random_rotation, "Random rotation");
+            ui.horizontal(|ui| {
+                ui.label("seed");
+                ui.add(egui::DragValue::new(&mut brush.seed));
+                if ui.button("🎲").on_hover_text("New seed").clicked() {
+                    brush.reseed();
+                }
+            });
+
+            ui.separator();
+            if tiles.len() < 2 {
+                ui.label("Select more tiles in the tile picker to mix them");
+            }
+            egui::Grid::new(id.with("weights"))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    for (tile_ref, name) in &tiles {
+                        let mut weight = brush.weight(tile_ref);
+                        ui.label(name);
+                        let res = ui.add(
+                            egui::DragValue::new(&mut weight)
+                                .speed(0.05)
+                                .clamp_range(0.0..=100.0)
+                                .fixed_decimals(2),
+                        );
+                        if res.changed() {
+                            brush.weights.insert(*tile_ref, weight);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if brush != original {
+            *world.resource_mut::<RandomBrush>() = brush;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{prelude::*, region::Region, ui, ui::widget::*};
+
+use crate::{EditorState, EditorUiEvent};
+
+/// colors given to new regions, in order
+const REGION_COLORS: [Color; 6] = [
+    Color::rgb(0.2, 0.7, 0.2),
+    Color::rgb(0.2, 0.4, 0.9),
+    Color::rgb(0.9, 0.3, 0.2),
+    Color::rgb(0.9, 0.8, 0.1),
+    Color::rgb(0.6, 0.3, 0.8),
+    Color::rgb(0.1, 0.8, 0.8),
+];
+
+#[derive(Default)]
+pub struct RegionsPanel {
+    /// name of the property to add to the active region
+    new_property: String,
+}
+
+impl BasicWidget for RegionsPanel {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        fn_widget::<ui::widgets::PanelTitle>(world, ui, id.with("title"), "Regions");
+
+        let active = world.resource::<EditorState>().active_region;
+        let mut query = world.query::<(Entity, &Region)>();
+        let mut regions: Vec<(Entity, String, Color)> = query
+            .iter(world)
+            .map(|(entity, region)| (entity, region.name.clone(), region.color))
+            .collect();
+        regions.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut clicked = None;
+        egui::ScrollArea::vertical()
+            .max_height(100.0)
+            .auto_shrink([false, true])
+            .id_source(id.with("vscroll"))
+            .show(ui, |ui| {
+                if regions.is_empty() {
+                    ui.label("no regions in map");
+                }
+                for (entity, name, color) in &regions {
+                    ui.horizontal(|ui| {
+                        let [r, g, b, _] = color.as_rgba_f32().map(|c| (c * 255.0) as u8);
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                        ui.painter()
+                            .rect_filled(rect, 2.0, egui::Color32::from_rgb(r, g, b));
+                        let selected = active == Some(*entity);
+                        if ui
+                            .selectable_label(selected, name)
+                            .on_hover_text("paint the region with the map tools")
+                            .clicked()
+                        {
+                            clicked = Some((*entity, selected));
+                        }
+                    });
+                }
+            });
+        if let Some((entity, selected)) = clicked {
+            world.resource_mut::<EditorState>().active_region = (!selected).then_some(entity);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("➕").on_hover_text("new region").clicked() {
+                let color = REGION_COLORS[regions.len() % REGION_COLORS.len()];
+                let name = format!("Region {}", regions.len() + 1);
+                create_region(world, name, color);
+            }
+            let Some(entity) = active else {
+                ui.add_enabled(false, egui::Button::new("➖"));
+                return;
+            };
+            if ui.button("➖").on_hover_text("delete region").clicked() {
+                world.spawn(ui::ConfirmationDialog {
+                    title: "Delete Region",
+                    message: "Are you sure you want to delete this region",
+                    buttons: [
+                        Some(("Delete Region", Some(EditorUiEvent::DeleteRegion(entity)))),
+                        Some(("Cancel", None)),
+                        None,
+                    ],
+                });
+            }
+        });
+
+        let Some(entity) = active else { return };
+        let Some(original) = world.get::<Region>(entity).cloned() else { return };
+        let mut region = original.clone();
+        let targets: Vec<(Entity, String)> = super::objects::object_labels(world)
+            .into_iter()
+            .map(|(entity, label, _)| (entity, label))
+            .collect();
+
+        ui.horizontal(|ui| {
+            let [r, g, b, _] = region
+                .color
+                .as_rgba_f32()
+                .map(|c| (c * 255.0).round() as u8);
+            let mut rgb = [r, g, b];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                region.color = Color::rgb_u8(rgb[0], rgb[1], rgb[2]);
+            }
+            ui.text_edit_singleline(&mut region.name);
+        });
+        ui.label(format!("{} hexes", region.locations.len()));
+        egui::CollapsingHeader::new("Properties")
+            .id_source(id.with("properties"))
+            .show(ui, |ui| {
+                super::objects::edit_properties(
+                    ui,
+                    id.with("property_grid"),
+                    &mut region.properties,
+                    &targets,
+                    &mut self.new_property,
+                );
+            });
+
+        if region != original {
+            let mut entity = world.entity_mut(entity);
+            if region.name != original.name {
+                entity.insert(Name::new(format!("region: {}", region.name)));
+            }
+            entity.insert(region);
+            world.resource_mut::<EditorState>().unsaved_changes = true;
+        }
+    }
+}
+
+/// add an empty region to the map, and make it the active region
+fn create_region(world: &mut World, name: String, color: Color) {
+    let mut query = world.query_filtered::<Entity, With<Map>>();
+    let Ok(map) = query.get_single(world) else { return };
+    let region = world
+        .spawn((
+            Name::new(format!("region: {}", name)),
+            Region::new(name, color),
+            SpatialBundle::default(),
+        ))
+        .id();
+    world.entity_mut(map).add_child(region);
+    let mut state = world.resource_mut::<EditorState>();
+    state.active_region = Some(region);
+    state.unsaved_changes = true;
+}
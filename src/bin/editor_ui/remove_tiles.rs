@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{replace, tileset, ui::widget::*};
+
+use crate::{EditorState, EditorUiEvent, TileRemoval};
+
+/// remove a tileset, or some of its tiles, choosing what happens to the tiles
+/// placed on the map that use them
+#[derive(Default)]
+pub struct RemoveTilesDialog {
+    removal: Option<TileRemoval>,
+    /// tileset to reassign the placed tiles to by name; None deletes them
+    reassign: Option<Entity>,
+}
+
+impl BasicWidget for RemoveTilesDialog {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self::default()
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let removal = world.resource::<EditorState>().remove_tiles.clone();
+        let Some(removal) = removal else { return };
+        if self.removal.as_ref() != Some(&removal) {
+            self.removal = Some(removal.clone());
+            self.reassign = None;
+        }
+        let Some(tileset) = world.get::<tileset::Tileset>(removal.tileset) else {
+            world.resource_mut::<EditorState>().remove_tiles = None;
+            return;
+        };
+        let name = tileset.name.clone();
+        let tiles = removal.tile_ids(tileset);
+
+        match &removal.tiles {
+            None => {
+                ui.label(format!(
+                    "Remove tileset \"{}\" and its {} tiles?",
+                    name,
+                    tiles.len()
+                ));
+            }
+            Some(_) => {
+                ui.label(format!("Remove {} tiles from \"{}\"?", tiles.len(), name));
+                egui::ScrollArea::vertical()
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for tile in tiles.iter().filter_map(|id| tileset.tiles.get(id)) {
+                            ui.label(format!("• {}", tile.name));
+                        }
+                    });
+            }
+        }
+        ui.separator();
+
+        let placed = crate::placed_tiles_using(world, &removal);
+        if placed.is_empty() {
+            ui.label("No placed tiles use them.");
+        } else {
+            ui.label(format!("{} placed tiles use them.", placed.len()));
+            self.draw_reassign(world, ui, id, &removal);
+            if let Some(to) = self.reassign {
+                let replace =
+                    replace::TileReplace::tiles_by_name(world, removal.tileset, &tiles, to);
+                let matched = match replace {
+                    Ok(replace) => placed
+                        .iter()
+                        .filter(|(_, tile_ref)| replace.mapping.contains_key(tile_ref))
+                        .count(),
+                    Err(_) => 0,
+                };
+                ui.label(format!(
+                    "{} reassigned; {} without a tile of the same name deleted",
+                    matched,
+                    placed.len() - matched
+                ));
+            }
+        }
+
+        let (remove, cancel) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (ui.button("Remove"), ui.button("Cancel"))
+            })
+            .inner;
+
+        if remove.clicked() {
+            world
+                .resource_mut::<Events<EditorUiEvent>>()
+                .send(EditorUiEvent::RemoveTiles {
+                    removal,
+                    reassign: self.reassign.filter(|_| !placed.is_empty()),
+                });
+        } else if !cancel.clicked() && !ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            return;
+        }
+        world.resource_mut::<EditorState>().remove_tiles = None;
+        self.removal = None;
+    }
+}
+
+impl RemoveTilesDialog {
+    /// choose between deleting the placed tiles, & reassigning them to
+    /// another tileset
+    fn draw_reassign(
+        &mut self,
+        world: &mut World,
+        ui: &mut egui::Ui,
+        id: egui::Id,
+        removal: &TileRemoval,
+    ) {
+        let mut tilesets = world.query::<(Entity, &tileset::Tileset)>();
+        let others: Vec<(Entity, String)> = tilesets
+            .iter(world)
+            .filter(|(entity, _)| *entity != removal.tileset)
+            .map(|(entity, tileset)| (entity, tileset.name.clone()))
+            .collect();
+
+        ui.radio_value(&mut self.reassign, None, "Delete the placed tiles");
+        ui.add_enabled_ui(!others.is_empty(), |ui| {
+            ui.horizontal(|ui| {
+                let reassign = ui.radio(
+                    self.reassign.is_some(),
+                    "Reassign to tiles with the same name in",
+                );
+                if reassign.clicked() && self.reassign.is_none() {
+                    self.reassign = others.first().map(|(entity, _)| *entity);
+                }
+                let selected = others
+                    .iter()
+                    .find(|(entity, _)| Some(*entity) == self.reassign)
+                    .map(|(_, name)| name.as_str())
+                    .unwrap_or("tileset");
+                egui::ComboBox::from_id_source(id.with("reassign"))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for (entity, name) in &others {
+                            ui.selectable_value(&mut self.reassign, Some(*entity), name);
+                        }
+                    });
+            });
+        });
+    }
+}
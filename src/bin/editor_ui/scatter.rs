@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{
+    map,
+    scatter::{self, Presets, Scatter, ScatterRule, TileName},
+    ui::widget::*,
+};
+
+use crate::{CursorRoot, EditorState, EditorUiEvent, ScatterPreview, TileSelection};
+
+/// scatter decoration tiles onto a layer, with a preview on the map
+pub struct ScatterDialog {
+    scatter: Scatter,
+    presets: Presets,
+    source: Option<Entity>,
+    target: Option<Entity>,
+    preview: bool,
+    /// inputs of the current preview: the rules, layers, the number of hexes
+    /// in each layer, & if the preview is shown
+    previewed: Option<(Scatter, Entity, Entity, usize, usize, bool)>,
+    /// number of tiles that will be placed, or why none can be
+    count: Result<usize, String>,
+}
+
+impl BasicWidget for ScatterDialog {
+    fn new(world: &mut World, _ui: &egui::Ui) -> Self {
+        let presets = Presets::load(&scatter::presets_path()).unwrap_or_else(|e| {
+            warn!("failed to load scatter presets: {:?}", e);
+            Presets::default()
+        });
+        let active_layer = world.resource::<EditorState>().active_layer;
+        Self {
+            scatter: Scatter {
+                rules: vec![ScatterRule::default()],
+                ..default()
+            },
+            presets,
+            source: None,
+            target: active_layer,
+            preview: true,
+            previewed: None,
+            count: Ok(0),
+        }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        self.draw_presets(ui, id);
+        ui.separator();
+
+        egui::Grid::new(id.with("grid"))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("source layer");
+                layer_combo(world, ui, id.with("source"), &mut self.source, false);
+                ui.end_row();
+
+                ui.label("target layer");
+                layer_combo(world, ui, id.with("target"), &mut self.target, true);
+                ui.end_row();
+
+                ui.label("min spacing");
+                ui.add(egui::DragValue::new(&mut self.scatter.min_spacing).clamp_range(0..=10));
+                ui.end_row();
+
+                ui.label("seed");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut self.scatter.seed));
+                    if ui.button("🎲").on_hover_text("New seed").clicked() {
+                        self.scatter.seed = scatter_seed(self.scatter.seed);
+                    }
+                });
+                ui.end_row();
+            });
+        ui.separator();
+
+        // names of the tiles selected in the tile picker, to add to the rules
+        let mut selected: Vec<TileName> = world
+            .resource::<TileSelection>()
+            .tiles
+            .iter()
+            .filter_map(|tile_ref| TileName::from_ref(world, tile_ref))
+            .collect();
+        selected.sort_by(|a, b| (&a.tileset, &a.tile).cmp(&(&b.tileset, &b.tile)));
+
+        let mut removed = None;
+        for (index, rule) in self.scatter.rules.iter_mut().enumerate() {
+            let rule_id = id.with(("rule", index));
+            egui::CollapsingHeader::new(format!("rule {}", index + 1))
+                .id_source(rule_id)
+                .default_open(true)
+                .show(ui, |ui| {
+                    egui::Grid::new(rule_id.with("grid"))
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            ui.label("density");
+                            ui.add(egui::Slider::new(&mut rule.density, 0.0..=1.0));
+                            ui.end_row();
+
+                            ui.label("rotation");
+                            ui.checkbox(&mut rule.random_rotation, "random");
+                            ui.end_row();
+
+                            ui.label("place");
+                            tile_list(ui, &mut rule.place, &selected);
+                            ui.end_row();
+
+                            ui.label("on");
+                            tile_list(ui, &mut rule.on, &selected);
+                            ui.end_row();
+
+                            ui.label("not next to");
+                            tile_list(ui, &mut rule.not_adjacent, &selected);
+                            ui.end_row();
+                        });
+                    if ui.button("Remove rule").clicked() {
+                        removed = Some(index);
+                    }
+                });
+        }
+        if let Some(index) = removed {
+            self.scatter.rules.remove(index);
+        }
+        if ui.button("Add rule").clicked() {
+            self.scatter.rules.push(ScatterRule::default());
+        }
+        ui.separator();
+
+        ui.checkbox(&mut self.preview, "Preview");
+        self.update_preview(world);
+        match &self.count {
+            Ok(count) => ui.label(format!("{} tiles will be placed", count)),
+            Err(e) => ui.colored_label(ui.visuals().warn_fg_color, e),
+        };
+
+        let (apply, close) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (
+                    ui.add_enabled(
+                        matches!(self.count, Ok(c) if c > 0),
+                        egui::Button::new("Scatter"),
+                    ),
+                    ui.button("Close"),
+                )
+            })
+            .inner;
+
+        if apply.clicked() {
+            if let (Some(source), Some(target)) = (self.source, self.target) {
+                let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+                events.send(EditorUiEvent::ScatterTiles {
+                    scatter: self.scatter.clone(),
+                    source,
+                    target,
+                });
+                self.scatter.seed = scatter_seed(self.scatter.seed);
+            }
+        }
+        if close.clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            world.resource_mut::<EditorState>().scatter_window = false;
+        }
+    }
+}
+
+impl ScatterDialog {
+    /// select, save, & delete presets
+    fn draw_presets(&mut self, ui: &mut egui::Ui, id: egui::Id) {
+        let mut save = false;
+        let mut delete = false;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source(id.with("preset"))
+                .selected_text("Presets")
+                .show_ui(ui, |ui| {
+                    for preset in &self.presets.presets {
+                        if ui.selectable_label(false, &preset.name).clicked() {
+                            self.scatter = preset.clone();
+                        }
+                    }
+                });
+            ui.add(egui::TextEdit::singleline(&mut self.scatter.name).desired_width(100.0));
+            let named = !self.scatter.name.is_empty();
+            save = ui.add_enabled(named, egui::Button::new("Save")).clicked();
+            let exists = self.presets.get(&self.scatter.name).is_some();
+            delete = ui
+                .add_enabled(exists, egui::Button::new("Delete"))
+                .clicked();
+        });
+
+        if save {
+            self.presets.insert(self.scatter.clone());
+        } else if delete {
+            self.presets.remove(&self.scatter.name);
+        } else {
+            return;
+        }
+        if let Err(e) = self.presets.save(&scatter::presets_path()) {
+            error!("failed to save scatter presets: {:?}", e);
+        }
+    }
+
+    /// recalculate the placements when the rules or layers change, and show
+    /// them on the map if the preview is enabled
+    fn update_preview(&mut self, world: &mut World) {
+        let (Some(source), Some(target)) = (self.source, self.target) else {
+            self.count = Err("select the source & target layers".into());
+            self.previewed = None;
+            despawn_preview(world);
+            return;
+        };
+        let hexes = |layer| world.get::<map::Layer>(layer).map(|l| l.tiles.len());
+        let (Some(source_hexes), Some(target_hexes)) = (hexes(source), hexes(target)) else {
+            self.count = Err("select the source & target layers".into());
+            return;
+        };
+        // the preview is removed while the window is hidden
+        let shown = world
+            .query_filtered::<(), With<ScatterPreview>>()
+            .iter(world)
+            .next()
+            .is_some();
+        if self.preview && !shown && matches!(self.count, Ok(c) if c > 0) {
+            self.previewed = None;
+        }
+
+        let inputs = (
+            self.scatter.clone(),
+            source,
+            target,
+            source_hexes,
+            target_hexes,
+            self.preview,
+        );
+        if self.previewed.as_ref() == Some(&inputs) {
+            return;
+        }
+        self.previewed = Some(inputs);
+
+        despawn_preview(world);
+        let placements = match self.scatter.world_placements(world, source, target) {
+            Ok(placements) => placements,
+            Err(e) => {
+                self.count = Err(e.to_string());
+                return;
+            }
+        };
+        self.count = Ok(placements.len());
+        if !self.preview {
+            return;
+        }
+        let mut roots = world.query_filtered::<Entity, With<CursorRoot>>();
+        let Ok(root) = roots.get_single(world) else { return };
+        for (location, tile_ref, transform) in placements {
+            world
+                .spawn((
+                    Name::new("scatter_preview"),
+                    ScatterPreview,
+                    location,
+                    tile_ref,
+                    transform,
+                    SpatialBundle::default(),
+                ))
+                .set_parent(root);
+        }
+    }
+}
+
+fn despawn_preview(world: &mut World) {
+    let previews: Vec<Entity> = world
+        .query_filtered::<Entity, With<ScatterPreview>>()
+        .iter(world)
+        .collect();
+    for entity in previews {
+        world.entity_mut(entity).despawn_recursive();
+    }
+}
+
+/// seed for the next scatter
+fn scatter_seed(seed: u64) -> u64 {
+    hex_sandbox::brush::Rng::new(seed).next_u64()
+}
+
+/// tiles of a rule, with buttons to remove each tile & add the selected tiles
+fn tile_list(ui: &mut egui::Ui, tiles: &mut Vec<TileName>, selected: &[TileName]) {
+    ui.vertical(|ui| {
+        let mut removed = None;
+        for (index, tile) in tiles.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("×").clicked() {
+                    removed = Some(index);
+                }
+                ui.label(tile.to_string());
+            });
+        }
+        if let Some(index) = removed {
+            tiles.remove(index);
+        }
+        let add = ui
+            .add_enabled(!selected.is_empty(), egui::Button::new("+ selected tiles"))
+            .on_hover_text("add the tiles selected in the tile picker");
+        if add.clicked() {
+            for tile in selected {
+                if !tiles.contains(tile) {
+                    tiles.push(tile.clone());
+                }
+            }
+        }
+    });
+}
+
+/// combo box to select a tile layer of the map
+fn layer_combo(
+    world: &mut World,
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    value: &mut Option<Entity>,
+    editable: bool,
+) {
+    let mut layers: Vec<(Entity, &map::Layer)> = world
+        .query::<(Entity, &map::Layer)>()
+        .iter(world)
+        .filter(|(_, layer)| layer.kind == map::LayerKind::Tile)
+        .filter(|(_, layer)| !editable || layer.editable())
+        .collect();
+    layers.sort_by_key(|(_, layer)| std::cmp::Reverse(layer.order));
+    if matches!(value, Some(entity) if !layers.iter().any(|(e, _)| e == entity)) {
+        *value = None;
+    }
+    let text = value
+        .and_then(|entity| layers.iter().find(|(e, _)| *e == entity))
+        .map(|(_, layer)| layer.name.clone())
+        .unwrap_or_default();
+
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text)
+        .width(150.0)
+        .show_ui(ui, |ui| {
+            for (entity, layer) in &layers {
+                ui.selectable_value(value, Some(*entity), &layer.name);
+            }
+        });
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{prelude::*, ui, ui::widget::*};
+
+use crate::{EditorState, EditorUiEvent, StampBrush, StampLibrary};
+
+#[derive(Default, Clone)]
+pub struct StampsPanel;
+
+impl BasicWidget for StampsPanel {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        fn_widget::<ui::widgets::PanelTitle>(world, ui, id.with("panel_title"), "Stamps");
+        egui::ScrollArea::vertical()
+            .max_height(120.0)
+            .auto_shrink([false, false])
+            .id_source(id.with("vscroll"))
+            .show(ui, |ui| {
+                basic_widget::<StampPicker>(world, ui, id.with("stamp_picker"));
+                ui.allocate_space(ui.available_size());
+            });
+        ui.separator();
+        basic_widget::<StampsPanelFooter>(world, ui, id.with("stamps_footer"));
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct StampPicker;
+
+impl BasicWidget for StampPicker {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let library = world.resource::<StampLibrary>();
+        if library.stamps.is_empty() {
+            ui.label("no stamps in library");
+            return;
+        }
+
+        let active = world.resource::<StampBrush>().path.clone();
+        let mut clicked = None;
+        let tile_size = egui::Vec2::splat(48.0);
+        let layout = egui::Layout::left_to_right(egui::Align::Min).with_main_wrap(true);
+        ui.with_layout(layout, |ui| {
+            let spacing = ui.spacing_mut();
+            spacing.item_spacing = egui::vec2(0.0, 0.0);
+            spacing.button_padding = egui::vec2(0.0, 0.0);
+            let visuals = ui.visuals_mut();
+            visuals.widgets.inactive.weak_bg_fill = egui::Color32::TRANSPARENT;
+
+            for stamp in &library.stamps {
+                let selected = active.as_ref() == Some(&stamp.path);
+                let res =
+                    ui.add(egui::ImageButton::new(stamp.texture, tile_size).selected(selected));
+                if res.on_hover_text(&stamp.stamp.name).clicked() {
+                    clicked = Some((stamp.path.clone(), selected));
+                }
+            }
+        });
+
+        let Some((path, selected)) = clicked else { return };
+        let event = if selected {
+            EditorUiEvent::StampDeselect
+        } else {
+            EditorUiEvent::StampSelect(path)
+        };
+        let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+        events.send(event);
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct StampsPanelFooter;
+
+impl BasicWidget for StampsPanelFooter {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, mut world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let has_map = world.get_map().is_ok();
+        ui.horizontal(|ui| {
+            let mut state = world.resource_mut::<EditorState>();
+            let capture = ui
+                .add_enabled(
+                    has_map,
+                    egui::SelectableLabel::new(state.stamp_capture, "➕"),
+                )
+                .on_hover_text("drag on the map to select tiles for a new stamp");
+            if capture.clicked() {
+                state.stamp_capture = !state.stamp_capture;
+            }
+
+            if ui
+                .button("⟳")
+                .on_hover_text("reload the stamp library")
+                .clicked()
+            {
+                world.resource_mut::<StampLibrary>().dirty = true;
+            }
+        });
+    }
+}
+
+pub struct SaveStamp {
+    just_opened: bool,
+    name: String,
+}
+
+impl BasicWidget for SaveStamp {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self {
+            name: "New Stamp".to_string(),
+            just_opened: true,
+        }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let state = world.resource::<EditorState>();
+        let Some(tiles) = state.new_stamp.clone() else { return };
+
+        ui.set_width(200.0);
+        ui.label(format!("{} tiles selected", tiles.len()));
+        let text_box = ui.text_edit_singleline(&mut self.name);
+
+        let (save, cancel) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (ui.button("Save"), ui.button("Cancel"))
+            })
+            .inner;
+
+        if self.just_opened {
+            self.just_opened = false;
+            return;
+        }
+
+        if save.clicked() || text_box.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+        {
+            let mut events = world.resource_mut::<Events<EditorUiEvent>>();
+            events.send(EditorUiEvent::StampSave {
+                name: std::mem::take(&mut self.name),
+                tiles,
+            });
+            world.resource_mut::<EditorState>().new_stamp = None;
+            *self = Self::new(world, ui);
+            return;
+        }
+        if cancel.clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            world.resource_mut::<EditorState>().new_stamp = None;
+            *self = Self::new(world, ui);
+            return;
+        }
+
+        text_box.request_focus();
+    }
+}
@@ -1,8 +1,9 @@
 use bevy::ecs::system::SystemState;
 use bevy::prelude::*;
 use bevy_egui::egui;
-use hex_sandbox::{tileset, ui::widget::*};
+use hex_sandbox::{map, tileset, ui::widget::*};
 
+use super::property_schema::edit_property;
 use crate::{EditorUiEvent, TileSelection};
 
 #[derive(Reflect, Clone, Copy, Debug)]
@@ -88,6 +89,11 @@ pub struct TileProperties<'w: 'static, 's: 'static> {
         EventWriter<'w, EditorUiEvent>,
     )>,
     transform: TileTransform,
+    color: Option<Color>,
+    footprint: Vec<map::Location>,
+    tags: Vec<String>,
+    /// tag being typed in to add to the tile
+    new_tag: String,
 }
 
 impl<'w, 's> BasicWidget for TileProperties<'w, 's> {
@@ -95,6 +101,10 @@ impl<'w, 's> BasicWidget for TileProperties<'w, 's> {
         Self {
             system_state: SystemState::new(world),
             transform: TileTransform::default(),
+            color: None,
+            footprint: Vec::new(),
+            tags: Vec::new(),
+            new_tag: String::new(),
         }
     }
 
@@ -117,6 +127,9 @@ impl<'w, 's> BasicWidget for TileProperties<'w, 's> {
                 return;
             };
             self.transform = tile.transform.into();
+            self.color = tile.color;
+            self.footprint = tile.footprint.clone();
+            self.tags = tile.tags.clone();
         }
 
         let mut full = false;
@@ -163,7 +176,58 @@ impl<'w, 's> BasicWidget for TileProperties<'w, 's> {
             }
         }
 
-        if !changed {
+        let color_changed = ui
+            .horizontal(|ui| {
+                let mut enabled = self.color.is_some();
+                let mut changed = ui.checkbox(&mut enabled, "color").changed();
+                let mut color = self.color.unwrap_or(Color::GRAY);
+                if enabled {
+                    let mut rgba = color.as_rgba_f32().map(|c| (c * 255.0).round() as u8);
+                    if ui.color_edit_button_srgba_unmultiplied(&mut rgba).changed() {
+                        color = Color::rgba_u8(rgba[0], rgba[1], rgba[2], rgba[3]);
+                        changed = true;
+                    }
+                }
+                self.color = enabled.then_some(color);
+                changed
+            })
+            .inner;
+
+        let footprint_changed = ui
+            .collapsing("footprint", |ui| footprint_grid(ui, &mut self.footprint))
+            .body_returned
+            .unwrap_or(false);
+
+        let tags_changed = tag_editor(ui, &mut self.tags, &mut self.new_tag);
+
+        // values of the tileset property schema; read from the tileset every
+        // frame so changes to the schema are shown right away
+        let mut property_changes = Vec::new();
+        if !tileset.property_schema.is_empty() {
+            ui.collapsing("properties", |ui| {
+                egui::Grid::new(id.with("properties"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for field in &tileset.property_schema {
+                            let value = tileset.tile_property(tile_ref.tile, &field.name);
+                            let Some(mut value) = value.cloned() else { continue };
+                            ui.label(&field.name);
+                            let id = id.with(("property", &field.name));
+                            if edit_property(ui, id, &field.kind, &mut value) {
+                                property_changes.push((field.name.clone(), value));
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        }
+
+        if !changed
+            && !color_changed
+            && !footprint_changed
+            && !tags_changed
+            && property_changes.is_empty()
+        {
             return;
         }
 
@@ -179,9 +243,117 @@ impl<'w, 's> BasicWidget for TileProperties<'w, 's> {
                 continue;
             };
 
-            tile.transform = self.transform.into();
+            if changed {
+                tile.transform = self.transform.into();
+            }
+            if color_changed {
+                tile.color = self.color;
+            }
+            if footprint_changed {
+                tile.footprint = self.footprint.clone();
+            }
+            if tags_changed {
+                tile.tags = self.tags.clone();
+            }
+        }
+
+        // property values are only set on selected tiles with the same field
+        // in their tileset; values equal to the default are not stored
+        for tile_ref in &selection.tiles {
+            let Ok(mut tileset) = tilesets.get_mut(tile_ref.tileset) else { continue };
+            for (name, value) in &property_changes {
+                let Some(field) = tileset.property_field(name) else { continue };
+                if !field.kind.accepts(value) {
+                    continue;
+                }
+                let default = field.default == *value;
+                let Some(tile) = tileset.tiles.get_mut(&tile_ref.tile) else { continue };
+                if default {
+                    tile.properties.remove(name);
+                } else {
+                    tile.properties.insert(name.clone(), value.clone());
+                }
+            }
         }
         events.send(EditorUiEvent::RedrawMapTiles);
         self.system_state.apply(world);
     }
 }
+
+/// hexes around the tile origin that can be toggled in the footprint grid
+const FOOTPRINT_RADIUS: i32 = 2;
+
+/// grid of the hexes around the tile origin, clicking a hex toggles it in the
+/// footprint; returns true if the footprint changed
+fn footprint_grid(ui: &mut egui::Ui, footprint: &mut Vec<map::Location>) -> bool {
+    let size = egui::vec2(18.0, 18.0);
+    let mut changed = false;
+    for y in -FOOTPRINT_RADIUS..=FOOTPRINT_RADIUS {
+        ui.horizontal(|ui| {
+            // offset each row by half a hex to line up with the map
+            let width = size.x + ui.spacing().item_spacing.x;
+            ui.add_space(y.abs() as f32 * width / 2.0);
+            let first = (-FOOTPRINT_RADIUS).max(-FOOTPRINT_RADIUS - y);
+            let last = FOOTPRINT_RADIUS.min(FOOTPRINT_RADIUS - y);
+            for x in first..=last {
+                let location = map::Location { x, y };
+                let origin = x == 0 && y == 0;
+                let index = footprint.iter().position(|l| *l == location);
+                let fill = if origin || index.is_some() {
+                    ui.visuals().selection.bg_fill
+                } else {
+                    ui.visuals().widgets.inactive.bg_fill
+                };
+                let button = egui::Button::new(if origin { "●" } else { "" })
+                    .min_size(size)
+                    .fill(fill);
+                let response = ui.add_enabled(!origin, button);
+                if !response.clicked() {
+                    continue;
+                }
+                match index {
+                    Some(i) => {
+                        footprint.remove(i);
+                    }
+                    None => footprint.push(location),
+                }
+                changed = true;
+            }
+        });
+    }
+    changed
+}
+
+/// tags of the tile, with a button to remove each tag, & a text field to add
+/// one; returns true if the tags changed
+fn tag_editor(ui: &mut egui::Ui, tags: &mut Vec<String>, new_tag: &mut String) -> bool {
+    let mut changed = false;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("tags");
+        let mut removed = None;
+        for (index, tag) in tags.iter().enumerate() {
+            if ui.small_button(format!("{} ×", tag)).clicked() {
+                removed = Some(index);
+            }
+        }
+        if let Some(index) = removed {
+            tags.remove(index);
+            changed = true;
+        }
+
+        let res = ui.add(
+            egui::TextEdit::singleline(new_tag)
+                .hint_text("add tag")
+                .desired_width(80.0),
+        );
+        if res.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            let tag = new_tag.trim();
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+                changed = true;
+            }
+            new_tag.clear();
+        }
+    });
+    changed
+}
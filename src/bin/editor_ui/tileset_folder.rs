@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{export, map, persistence, tileset, ui::widget::*};
+
+use crate::{EditorState, TileSelection};
+
+/// create a tileset from the models in a folder, or rescan the folder of an
+/// existing tileset for new & removed models
+pub struct TilesetFolderDialog {
+    /// folder the dialog was opened for
+    dir: Option<std::path::PathBuf>,
+    name: String,
+    /// apply the transform below to the new tiles
+    use_transform: bool,
+    y_translation: f32,
+    y_rotation: f32,
+    scale: f32,
+    error: Option<String>,
+}
+
+impl BasicWidget for TilesetFolderDialog {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self {
+            dir: None,
+            name: String::new(),
+            use_transform: false,
+            y_translation: 0.0,
+            y_rotation: 0.0,
+            scale: 1.0,
+            error: None,
+        }
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, id: egui::Id) {
+        let folder = world.resource::<EditorState>().tileset_folder.clone();
+        let Some((tileset_id, dir)) = folder else { return };
+        if self.dir.as_ref() != Some(&dir) {
+            self.dir = Some(dir.clone());
+            self.name = dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "New Tileset".into());
+            self.error = None;
+        }
+
+        ui.label(format!("folder: {}", dir.to_string_lossy()));
+        match tileset_id.and_then(|entity| world.get::<tileset::Tileset>(entity)) {
+            Some(tileset) => {
+                ui.label(format!("rescan into tileset: {}", tileset.name));
+            }
+            None => {
+                ui.horizontal(|ui| {
+                    ui.label("name");
+                    ui.text_edit_singleline(&mut self.name);
+                });
+            }
+        }
+
+        ui.checkbox(&mut self.use_transform, "transform for new tiles");
+        ui.add_enabled_ui(self.use_transform, |ui| {
+            egui::Grid::new(id.with("transform"))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("y-translation");
+                    ui.add(egui::DragValue::new(&mut self.y_translation).speed(0.1));
+                    ui.end_row();
+
+                    ui.label("y-rotation");
+                    ui.drag_angle_tau(&mut self.y_rotation);
+                    ui.end_row();
+
+                    ui.label("scale");
+                    ui.add(
+                        egui::DragValue::new(&mut self.scale)
+                            .speed(0.05)
+                            .fixed_decimals(2),
+                    );
+                    ui.end_row();
+                });
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        let label = if tileset_id.is_some() {
+            "Rescan"
+        } else {
+            "Create"
+        };
+        let (scan, cancel) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (ui.button(label), ui.button("Cancel"))
+            })
+            .inner;
+
+        if scan.clicked() {
+            let transform = if self.use_transform {
+                Transform {
+                    translation: Vec3::new(0.0, self.y_translation, 0.0),
+                    rotation: Quat::from_rotation_y(self.y_rotation),
+                    scale: Vec3::splat(self.scale),
+                }
+            } else {
+                Transform::IDENTITY
+            };
+            if let Err(e) = scan_folder(world, tileset_id, &self.name, &dir, transform) {
+                self.error = Some(format!("{:?}", e));
+                return;
+            }
+        } else if !cancel.clicked() && !ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            return;
+        }
+        world.resource_mut::<EditorState>().tileset_folder = None;
+        self.dir = None;
+    }
+}
+
+/// scan `dir` into the tileset, or a new tileset named `name`, and show the
+/// results
+fn scan_folder(
+    world: &mut World,
+    tileset_id: Option<Entity>,
+    name: &str,
+    dir: &std::path::Path,
+    transform: Transform,
+) -> anyhow::Result<()> {
+    let asset_dir = export::asset_dir();
+    let (entity, scan) = match tileset_id {
+        Some(entity) => {
+            let mut tileset = world
+                .get_mut::<tileset::Tileset>(entity)
+                .ok_or_else(|| anyhow::anyhow!("tileset not found: {:?}", entity))?;
+            let scan = tileset.scan_folder(dir, &asset_dir, transform)?;
+            (entity, scan)
+        }
+        None => {
+            let mut query = world.query_filtered::<Entity, With<map::Map>>();
+            let map = query
+                .get_single(world)
+                .map_err(|_| anyhow::anyhow!("no map loaded"))?;
+            let mut tileset = tileset::Tileset::new(name);
+            let scan = tileset.scan_folder(dir, &asset_dir, transform)?;
+            (persistence::spawn_tileset(world, map, tileset), scan)
+        }
+    };
+    info!(
+        "scanned {:?} into tileset {:?}; {} added, {} missing",
+        dir,
+        entity,
+        scan.added.len(),
+        scan.missing.len()
+    );
+
+    let mut state = world.resource_mut::<EditorState>();
+    state.active_tileset = Some(entity);
+    state.unsaved_changes = true;
+    state.folder_scan = Some((entity, scan));
+    Ok(())
+}
+
+/// results of the last folder scan; the tiles added & the tiles whose model
+/// was removed from the folder
+#[derive(Default)]
+pub struct FolderScanResults;
+
+impl BasicWidget for FolderScanResults {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let scan = world.resource::<EditorState>().folder_scan.clone();
+        let Some((tileset_id, scan)) = scan else { return };
+        let Some(tileset) = world.get::<tileset::Tileset>(tileset_id) else {
+            world.resource_mut::<EditorState>().folder_scan = None;
+            return;
+        };
+
+        let added = scan.added.len();
+        ui.label(format!("{} tiles added to {}", added, tileset.name));
+        if scan.missing.is_empty() {
+            ui.label("no models were removed from the folder");
+        } else {
+            ui.label(format!(
+                "{} tiles no longer have a model in the folder:",
+                scan.missing.len()
+            ));
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for tile_id in &scan.missing {
+                        let Some(tile) = tileset.tiles.get(tile_id) else { continue };
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!("{} ({})", tile.name, tile.path.to_string_lossy()),
+                        );
+                    }
+                });
+        }
+
+        let (select, close) = ui
+            .with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                (
+                    ui.add_enabled(
+                        !scan.missing.is_empty(),
+                        egui::Button::new("Select missing tiles"),
+                    ),
+                    ui.button("Close"),
+                )
+            })
+            .inner;
+
+        if select.clicked() {
+            let mut selection = world.resource_mut::<TileSelection>();
+            selection.tiles.clear();
+            for tile_id in &scan.missing {
+                selection.tiles.insert(tileset::TileRef {
+                    tileset: tileset_id,
+                    tile: *tile_id,
+                });
+            }
+        }
+        if close.clicked() {
+            world.resource_mut::<EditorState>().folder_scan = None;
+        }
+    }
+}
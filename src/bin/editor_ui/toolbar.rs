@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use bevy_egui::egui;
+use hex_sandbox::{tool, ui::widget::*};
+
+#[derive(Default, Clone)]
+pub struct Toolbar;
+
+impl BasicWidget for Toolbar {
+    fn new(_world: &mut World, _ui: &egui::Ui) -> Self {
+        Self
+    }
+
+    fn draw(&mut self, world: &mut World, ui: &mut egui::Ui, _id: egui::Id) {
+        let mut registry = world.resource_mut::<tool::ToolRegistry>();
+        let active = registry.active_index();
+        let mut selected = None;
+
+        ui.vertical_centered(|ui| {
+            for (index, tool) in registry.tools().enumerate() {
+                let res = ui
+                    .add(egui::SelectableLabel::new(index == active, tool.icon()))
+                    .on_hover_text(tool.name());
+                if res.clicked() {
+                    selected = Some(index);
+                }
+            }
+        });
+        if let Some(index) = selected {
+            registry.set_active(index);
+        }
+
+        if registry.active().map(|t| t.uses_radius()).unwrap_or(false) {
+            ui.separator();
+            ui.label("radius");
+            ui.add(egui::DragValue::new(&mut registry.radius).clamp_range(0..=20));
+        }
+    }
+}
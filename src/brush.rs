@@ -0,0 +1,198 @@
+//! Random brush for painting with a mix of tiles.
+//!
+//! The brush picks one of several tiles for each location it paints, with an
+//! optional weight per tile, and can also pick a random rotation.  Picks are
+//! made from the brush seed and the location, so painting the same locations
+//! with the same seed always gives the same tiles.
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::{map, tileset};
+
+/// small, deterministic pseudo-random number generator (splitmix64)
+///
+/// Not suitable for anything but picking tiles; it is used so results are
+/// reproducible from a seed on every platform.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// generator for a location, so each location gets its own sequence
+    pub fn for_location(seed: u64, location: map::Location) -> Self {
+        let hash = (location.x as u32 as u64) << 32 | location.y as u32 as u64;
+        let mut rng = Self(seed ^ hash.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// value in 0.0..1.0
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// value in 0..n; n must be greater than zero
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// brush that paints a random pick of the selected tiles
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct RandomBrush {
+    /// paint with a random pick of the selected tiles instead of the active
+    /// tile
+    pub enabled: bool,
+    /// relative chance of each tile being picked; tiles without a weight have
+    /// a weight of 1.0
+    pub weights: HashMap<tileset::TileRef, f32>,
+    /// also pick a random rotation for each tile
+    pub random_rotation: bool,
+    /// seed of the current stroke
+    pub seed: u64,
+}
+
+impl Default for RandomBrush {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            weights: HashMap::new(),
+            random_rotation: false,
+            seed: 1,
+        }
+    }
+}
+
+impl RandomBrush {
+    pub fn weight(&self, tile_ref: &tileset::TileRef) -> f32 {
+        self.weights.get(tile_ref).copied().unwrap_or(1.0).max(0.0)
+    }
+
+    /// pick the tile & rotation to paint at a location
+    ///
+    /// Tiles are sorted before picking, so the order of `tiles` doesn't change
+    /// the result.  `rotation` is used when the rotation isn't randomized.
+    /// Returns None if there are no tiles, or all weights are zero.
+    pub fn pick(
+        &self,
+        tiles: &[tileset::TileRef],
+        location: map::Location,
+        rotation: tileset::TileRotation,
+    ) -> Option<(tileset::TileRef, tileset::TileRotation)> {
+        let mut tiles: Vec<(tileset::TileRef, f32)> = tiles
+            .iter()
+            .map(|tile_ref| (*tile_ref, self.weight(tile_ref)))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        tiles.sort_by_key(|(tile_ref, _)| (tile_ref.tileset, tile_ref.tile));
+        tiles.dedup_by_key(|(tile_ref, _)| *tile_ref);
+
+        let total: f32 = tiles.iter().map(|(_, weight)| weight).sum();
+        if tiles.is_empty() || total <= 0.0 {
+            return None;
+        }
+
+        let mut rng = Rng::for_location(self.seed, location);
+        let mut remaining = rng.next_f32() * total;
+        let mut picked = tiles[tiles.len() - 1].0;
+        for (tile_ref, weight) in &tiles {
+            if remaining < *weight {
+                picked = *tile_ref;
+                break;
+            }
+            remaining -= weight;
+        }
+
+        let rotation = if self.random_rotation {
+            (0..rng.below(6)).fold(tileset::TileRotation::None, |r, _| r.clockwise())
+        } else {
+            rotation
+        };
+        Some((picked, rotation))
+    }
+
+    /// change the seed so the next stroke picks different tiles
+    pub fn reseed(&mut self) {
+        self.seed = Rng::new(self.seed).next_u64();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    fn tiles() -> Vec<tileset::TileRef> {
+        let tileset = Entity::from_raw(1);
+        (0..3)
+            .map(|tile| tileset::TileRef { tileset, tile })
+            .collect()
+    }
+
+    fn locations() -> impl Iterator<Item = map::Location> {
+        (-5..5).flat_map(|x| (-5..5).map(move |y| map::Location { x, y }))
+    }
+
+    #[test]
+    fn pick_is_reproducible() {
+        let brush = RandomBrush {
+            enabled: true,
+            random_rotation: true,
+            seed: 42,
+            ..default()
+        };
+        let mut reversed = tiles();
+        reversed.reverse();
+
+        let mut picked = std::collections::HashSet::new();
+        for location in locations() {
+            let rotation = tileset::TileRotation::None;
+            let pick = brush.pick(&tiles(), location, rotation);
+            assert_eq!(pick, brush.pick(&reversed, location, rotation));
+            picked.insert(pick.unwrap().0);
+        }
+        assert_eq!(picked.len(), 3, "every tile should be picked");
+
+        let mut reseeded = brush.clone();
+        reseeded.reseed();
+        assert!(locations().any(|l| {
+            let rotation = tileset::TileRotation::None;
+            brush.pick(&tiles(), l, rotation) != reseeded.pick(&tiles(), l, rotation)
+        }));
+    }
+
+    #[test]
+    fn pick_weights() {
+        let tiles = tiles();
+        let mut brush = RandomBrush::default();
+        brush.weights.insert(tiles[0], 0.0);
+        brush.weights.insert(tiles[2], 10.0);
+
+        let mut counts = HashMap::new();
+        for location in locations() {
+            let (tile_ref, rotation) = brush
+                .pick(&tiles, location, tileset::TileRotation::Clockwise60)
+                .unwrap();
+            assert_eq!(rotation, tileset::TileRotation::Clockwise60);
+            *counts.entry(tile_ref).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(&tiles[0]), None);
+        assert!(counts[&tiles[2]] > counts[&tiles[1]]);
+
+        brush.weights.insert(tiles[1], 0.0);
+        brush.weights.insert(tiles[2], 0.0);
+        let pick = brush.pick(&tiles, map::Location::default(), default());
+        assert_eq!(pick, None);
+    }
+}
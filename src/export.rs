@@ -0,0 +1,33 @@
+//! Export maps to formats that can be used without this crate
+use std::path::{Path, PathBuf};
+
+pub mod gltf;
+pub mod svg;
+
+/// get the directory bevy's `AssetServer` loads assets from
+///
+/// Tile paths in a tileset are relative to this directory, so exporters need
+/// it to find the tile models when running outside of bevy.  This mirrors the
+/// lookup done by bevy's `FileAssetIo`.
+pub fn asset_dir() -> PathBuf {
+    let base = if let Ok(dir) = std::env::var("BEVY_ASSET_ROOT") {
+        PathBuf::from(dir)
+    } else if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        PathBuf::from(dir)
+    } else {
+        std::env::current_exe()
+            .ok()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default()
+    };
+    base.join("assets")
+}
+
+/// resolve the path of a tile model relative to the asset directory
+pub fn asset_path(asset_dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        asset_dir.join(path)
+    }
+}
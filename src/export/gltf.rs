@@ -0,0 +1,823 @@
+//! Export a map as a single glTF scene
+//!
+//! Each tile model is read from disk once, and its meshes, materials, and
+//! textures are merged into the output document.  Every tile placed on the map
+//! becomes a node referencing those shared meshes, so the geometry for a model
+//! is only stored once no matter how many times it appears on the map.
+//!
+//! The scene has one node per map layer, raised by the layer `y_offset`, with
+//! each tile node a child of its layer node; annotation layers are skipped.
+//! Tile nodes are named after the tile, and the node extras hold the tile &
+//! tileset names along with the tile location.  Tiles placed on an edge or
+//! vertex also hold the `side` of their canonical location.
+//!
+//! Tinted tiles instance a copy of each mesh whose materials have the base
+//! color multiplied by the tint, as the editor draws them; every tint gets one
+//! copy per mesh.  Models requiring glTF extensions are skipped with a warning,
+//! leaving empty tile nodes in their place.
+//!
+//! Everything here runs on the CPU; no render device or `App` is needed.
+use ::gltf::{binary, json, json::Index};
+use anyhow::{bail, Context, Result};
+use bevy::prelude::{warn, Color};
+use serde::Serialize;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    map,
+    persistence::{MapFormat, MAP_FORMAT_VERSION},
+    tileset,
+};
+
+/// a tile model that has been merged into the output document
+struct Model {
+    /// every node from the model; child indices are relative to this Vec, all
+    /// other indices have been remapped into the output document
+    nodes: Vec<json::Node>,
+    /// root nodes of the model's default scene
+    roots: Vec<usize>,
+}
+
+/// extras stored on every tile node
+#[derive(Serialize)]
+struct TileExtras<'a> {
+    tileset: &'a str,
+    tile: &'a str,
+    location: [i32; 2],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge: Option<map::EdgeSide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vertex: Option<map::VertexSide>,
+}
+
+/// where a tile is placed in a layer
+#[derive(Debug, Clone, Copy)]
+enum Placement {
+    /// the overrides of the placed tile apply to tiles on hexes only
+    Hex(map::Location, tileset::TileOverrides),
+    Edge(map::Edge),
+    Vertex(map::Vertex),
+}
+
+/// Builds a single glTF document from one or more maps
+pub struct GltfExporter {
+    /// directory relative tile paths are resolved against
+    asset_dir: PathBuf,
+    /// output document
+    root: json::Root,
+    /// contents of the only buffer in the output document
+    buffer: Vec<u8>,
+    /// models already merged into the document, by path
+    models: HashMap<PathBuf, Model>,
+    /// tinted copies of meshes & materials, by the original index and the
+    /// bits of the tint
+    tinted_meshes: HashMap<(usize, [u32; 4]), Index<json::Mesh>>,
+    tinted_materials: HashMap<(Option<usize>, [u32; 4]), Index<json::Material>>,
+}
+
+impl GltfExporter {
+    pub fn new(asset_dir: PathBuf) -> Self {
+        let mut root = json::Root::default();
+        root.asset.generator = Some(format!("hex_sandbox {}", env!("CARGO_PKG_VERSION")));
+        Self {
+            asset_dir,
+            root,
+            buffer: Vec::new(),
+            models: HashMap::new(),
+            tinted_meshes: HashMap::new(),
+            tinted_materials: HashMap::new(),
+        }
+    }
+
+    /// add a scene containing every tile in the map
+    pub fn add_map(&mut self, map: &MapFormat) -> Result<&mut Self> {
+        if map.version != MAP_FORMAT_VERSION {
+            bail!(
+                "unsupported map version: {} != {}",
+                map.version,
+                MAP_FORMAT_VERSION
+            );
+        }
+        let layout = map::Map {
+            layout: map.layout.clone(),
+        };
+
+        let mut layers = Vec::new();
+        for layer in map.ordered_layers() {
+            // annotations are editor-only
+            if layer.kind == map::LayerKind::Annotation {
+                continue;
+            }
+            let mut tiles = Vec::new();
+            for t in &layer.tiles {
+                let placement = Placement::Hex(t.location, t.overrides);
+                tiles.push((placement, t.tileset, t.tile_id, t.rotation));
+            }
+            for t in &layer.edges {
+                tiles.push((Placement::Edge(t.edge), t.tileset, t.tile_id, t.rotation));
+            }
+            for t in &layer.vertices {
+                tiles.push((
+                    Placement::Vertex(t.vertex),
+                    t.tileset,
+                    t.tile_id,
+                    t.rotation,
+                ));
+            }
+
+            let mut children = Vec::new();
+            for (placement, tileset_id, tile_id, rotation) in tiles {
+                let tileset = map
+                    .tilesets
+                    .get(&tileset_id)
+                    .context(format!("unknown tileset for tile at {:?}", placement))?;
+                let tileset_tile = tileset.tiles.get(&tile_id).context(format!(
+                    "unknown tile for tile at {:?} in tileset {}",
+                    placement, tileset.name
+                ))?;
+                let tile_transform = tileset::TileTransform { rotation };
+                let tint = match placement {
+                    Placement::Hex(_, overrides) => overrides.tint,
+                    _ => None,
+                };
+                let (transform, location, edge, vertex) = match placement {
+                    Placement::Hex(l, overrides) => {
+                        let transform =
+                            layout.tile_transform(tileset_tile, l, &tile_transform, &overrides);
+                        (transform, l, None, None)
+                    }
+                    Placement::Edge(e) => {
+                        let transform = layout.edge_transform(tileset_tile, e, &tile_transform);
+                        (transform, e.location, Some(e.side), None)
+                    }
+                    Placement::Vertex(v) => {
+                        let transform = layout.vertex_transform(tileset_tile, v, &tile_transform);
+                        (transform, v.location, None, Some(v.side))
+                    }
+                };
+
+                let path = super::asset_path(&self.asset_dir, &tileset_tile.path);
+                let model_roots = self
+                    .instance_model(&path, tint)
+                    .context(format!("failed to add tile {}", tileset_tile.name))?;
+
+                let extras = TileExtras {
+                    tileset: &tileset.name,
+                    tile: &tileset_tile.name,
+                    location: [location.x, location.y],
+                    edge,
+                    vertex,
+                };
+                let mut node = new_node(&tileset_tile.name);
+                node.extras = Some(serde_json::value::to_raw_value(&extras)?);
+                node.translation = Some(transform.translation.to_array());
+                node.rotation = Some(json::scene::UnitQuaternion(transform.rotation.to_array()));
+                node.scale = Some(transform.scale.to_array());
+                // glTF does not allow empty children, as left by skipped models
+                node.children = (!model_roots.is_empty()).then_some(model_roots);
+                children.push(self.push_node(node));
+            }
+
+            let mut node = new_node(&layer.name);
+            if layer.y_offset != 0.0 {
+                node.translation = Some([0.0, layer.y_offset, 0.0]);
+            }
+            node.children = Some(children);
+            layers.push(self.push_node(node));
+        }
+
+        self.root.scenes.push(json::Scene {
+            extensions: None,
+            extras: Default::default(),
+            name: None,
+            nodes: layers,
+        });
+        if self.root.scene.is_none() {
+            self.root.scene = Some(Index::new(0));
+        }
+
+        Ok(self)
+    }
+
+    /// write the document to `path`; binary glTF is written when the
+    /// extension is `.glb`, otherwise JSON with the buffer in a `.bin` file
+    /// alongside it.
+    pub fn write(mut self, path: &Path) -> Result<()> {
+        let binary = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("glb"))
+            .unwrap_or(false);
+        let bin_path = path.with_extension("bin");
+
+        if !self.buffer.is_empty() {
+            pad_to_four(&mut self.buffer);
+            let uri = match binary {
+                true => None,
+                false => Some(
+                    bin_path
+                        .file_name()
+                        .context("invalid output path")?
+                        .to_string_lossy()
+                        .into(),
+                ),
+            };
+            self.root.buffers.push(json::Buffer {
+                byte_length: self.buffer.len() as u32,
+                name: None,
+                uri,
+                extensions: None,
+                extras: Default::default(),
+            });
+        }
+
+        let f = File::create(path).context(format!("create {:?}", path))?;
+        if binary {
+            let json = self.root.to_vec().context("serialize glTF")?;
+            let glb = binary::Glb {
+                // length is calculated by Glb::to_writer()
+                header: binary::Header {
+                    magic: *b"glTF",
+                    version: 2,
+                    length: 0,
+                },
+                json: Cow::Owned(json),
+                bin: match self.buffer.is_empty() {
+                    true => None,
+                    false => Some(Cow::Owned(self.buffer)),
+                },
+            };
+            glb.to_writer(BufWriter::new(f))
+                .context(format!("write {:?}", path))?;
+        } else {
+            if !self.buffer.is_empty() {
+                std::fs::write(&bin_path, &self.buffer).context(format!("write {:?}", bin_path))?;
+            }
+            self.root
+                .to_writer_pretty(BufWriter::new(f))
+                .context(format!("write {:?}", path))?;
+        }
+        Ok(())
+    }
+
+    fn push_node(&mut self, node: json::Node) -> Index<json::Node> {
+        self.root.nodes.push(node);
+        Index::new(self.root.nodes.len() as u32 - 1)
+    }
+
+    /// append bytes to the output buffer and return a view of them
+    fn push_view(&mut self, bytes: &[u8]) -> Index<json::buffer::View> {
+        pad_to_four(&mut self.buffer);
+        let offset = self.buffer.len() as u32;
+        self.buffer.extend_from_slice(bytes);
+        self.root.buffer_views.push(json::buffer::View {
+            buffer: Index::new(0),
+            byte_length: bytes.len() as u32,
+            byte_offset: Some(offset),
+            byte_stride: None,
+            name: None,
+            target: None,
+            extensions: None,
+            extras: Default::default(),
+        });
+        Index::new(self.root.buffer_views.len() as u32 - 1)
+    }
+
+    /// add a copy of the model's nodes to the document, loading the model if
+    /// necessary; returns the root nodes of the copy
+    fn instance_model(
+        &mut self,
+        path: &Path,
+        tint: Option<Color>,
+    ) -> Result<Vec<Index<json::Node>>> {
+        if !self.models.contains_key(path) {
+            let model = self.load_model(path)?;
+            self.models.insert(path.to_path_buf(), model);
+        }
+        let model = &self.models[path];
+
+        let base = self.root.nodes.len() as u32;
+        let roots = model
+            .roots
+            .iter()
+            .map(|i| Index::new(base + *i as u32))
+            .collect();
+        for mut node in model.nodes.clone() {
+            node.children = node
+                .children
+                .map(|children| children.iter().map(|c| offset(*c, base)).collect());
+            if let Some(tint) = tint {
+                node.mesh = node.mesh.map(|mesh| self.tinted_mesh(mesh, tint));
+            }
+            self.root.nodes.push(node);
+        }
+        Ok(roots)
+    }
+
+    /// copy of a mesh with every material tinted
+    fn tinted_mesh(&mut self, mesh: Index<json::Mesh>, tint: Color) -> Index<json::Mesh> {
+        let key = (mesh.value(), tint.as_rgba_f32().map(f32::to_bits));
+        if let Some(index) = self.tinted_meshes.get(&key) {
+            return *index;
+        }
+        let mut copy = self.root.meshes[mesh.value()].clone();
+        for primitive in &mut copy.primitives {
+            primitive.material = Some(self.tinted_material(primitive.material, tint));
+        }
+        self.root.meshes.push(copy);
+        let index = Index::new(self.root.meshes.len() as u32 - 1);
+        self.tinted_meshes.insert(key, index);
+        index
+    }
+
+    /// copy of a material with the base color multiplied by the tint; the
+    /// glTF default material is tinted for primitives without a material
+    fn tinted_material(
+        &mut self,
+        material: Option<Index<json::Material>>,
+        tint: Color,
+    ) -> Index<json::Material> {
+        let key = (
+            material.map(|m| m.value()),
+            tint.as_rgba_f32().map(f32::to_bits),
+        );
+        if let Some(index) = self.tinted_materials.get(&key) {
+            return *index;
+        }
+        let mut copy = match material {
+            Some(material) => self.root.materials[material.value()].clone(),
+            None => json::Material::default(),
+        };
+        let factor = &mut copy.pbr_metallic_roughness.base_color_factor.0;
+        for (c, t) in factor.iter_mut().zip(tint.as_linear_rgba_f32()) {
+            *c *= t;
+        }
+        self.root.materials.push(copy);
+        let index = Index::new(self.root.materials.len() as u32 - 1);
+        self.tinted_materials.insert(key, index);
+        index
+    }
+
+    /// merge the buffers, meshes, materials, & textures from a glTF file into
+    /// the output document; models requiring extensions are left empty
+    fn load_model(&mut self, path: &Path) -> Result<Model> {
+        let bytes = std::fs::read(path).context(format!("read model {:?}", path))?;
+
+        // the required extensions are checked before validating the model,
+        // which rejects extensions the gltf crate does not support
+        let (src, mut blob) = match bytes.starts_with(b"glTF") {
+            true => {
+                let glb =
+                    binary::Glb::from_slice(&bytes).context(format!("parse model {:?}", path))?;
+                let src =
+                    json::Root::from_slice(&glb.json).context(format!("parse model {:?}", path))?;
+                (src, glb.bin.map(|bin| bin.into_owned()))
+            }
+            false => {
+                let src =
+                    json::Root::from_slice(&bytes).context(format!("parse model {:?}", path))?;
+                (src, None)
+            }
+        };
+        if let Some(ext) = src.extensions_required.first() {
+            warn!(
+                "skipping model {:?}; it requires unsupported extension {}",
+                path, ext
+            );
+            return Ok(Model {
+                nodes: Vec::new(),
+                roots: Vec::new(),
+            });
+        }
+        let src = ::gltf::Document::from_json(src)
+            .context(format!("parse model {:?}", path))?
+            .into_json();
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for ext in &src.extensions_used {
+            if !self.root.extensions_used.contains(ext) {
+                self.root.extensions_used.push(ext.clone());
+            }
+        }
+
+        // load the contents of every buffer in the model
+        let mut buffers = Vec::new();
+        for buffer in &src.buffers {
+            let data = match &buffer.uri {
+                None => blob.take().context("glb missing binary chunk")?,
+                Some(uri) if uri.starts_with("data:") => {
+                    bail!("model {:?}: data uri buffers not supported", path)
+                }
+                Some(uri) => std::fs::read(dir.join(uri))
+                    .context(format!("read buffer {:?} for {:?}", uri, path))?,
+            };
+            buffers.push(data);
+        }
+
+        // copy every buffer view into our buffer
+        let views = self.root.buffer_views.len() as u32;
+        for view in &src.buffer_views {
+            let data = buffers
+                .get(view.buffer.value())
+                .context(format!("invalid buffer in view {:?}", view))?;
+            let start = view.byte_offset.unwrap_or(0) as usize;
+            let bytes = data
+                .get(start..start + view.byte_length as usize)
+                .context(format!("buffer view out of range: {:?}", view))?;
+            let index = self.push_view(bytes);
+            let new = &mut self.root.buffer_views[index.value()];
+            new.byte_stride = view.byte_stride;
+            new.target = view.target;
+            new.name = view.name.clone();
+        }
+
+        let accessors = self.root.accessors.len() as u32;
+        for accessor in &src.accessors {
+            let mut accessor = accessor.clone();
+            accessor.buffer_view = accessor.buffer_view.map(|v| offset(v, views));
+            if let Some(sparse) = accessor.sparse.as_mut() {
+                sparse.indices.buffer_view = offset(sparse.indices.buffer_view, views);
+                sparse.values.buffer_view = offset(sparse.values.buffer_view, views);
+            }
+            self.root.accessors.push(accessor);
+        }
+
+        // images referencing external files are embedded so the export is a
+        // single self-contained document
+        let images = self.root.images.len() as u32;
+        for image in &src.images {
+            let mut image = image.clone();
+            image.buffer_view = image.buffer_view.map(|v| offset(v, views));
+            if let Some(uri) = image.uri.take() {
+                if uri.starts_with("data:") {
+                    image.uri = Some(uri);
+                } else {
+                    let image_path = dir.join(&uri);
+                    let bytes = std::fs::read(&image_path)
+                        .context(format!("read image {:?} for {:?}", uri, path))?;
+                    image.mime_type = Some(json::image::MimeType(mime_type(&image_path)?.into()));
+                    image.buffer_view = Some(self.push_view(&bytes));
+                }
+            }
+            self.root.images.push(image);
+        }
+
+        let samplers = self.root.samplers.len() as u32;
+        self.root.samplers.extend(src.samplers.iter().cloned());
+
+        let textures = self.root.textures.len() as u32;
+        for texture in &src.textures {
+            let mut texture = texture.clone();
+            texture.source = offset(texture.source, images);
+            texture.sampler = texture.sampler.map(|s| offset(s, samplers));
+            self.root.textures.push(texture);
+        }
+
+        let materials = self.root.materials.len() as u32;
+        for material in &src.materials {
+            let mut material = material.clone();
+            let pbr = &mut material.pbr_metallic_roughness;
+            for info in [
+                pbr.base_color_texture.as_mut(),
+                pbr.metallic_roughness_texture.as_mut(),
+                material.emissive_texture.as_mut(),
+            ]
+            .into_iter()
+            .flatten()
+            {
+                info.index = offset(info.index, textures);
+            }
+            if let Some(info) = material.normal_texture.as_mut() {
+                info.index = offset(info.index, textures);
+            }
+            if let Some(info) = material.occlusion_texture.as_mut() {
+                info.index = offset(info.index, textures);
+            }
+            if let Some(extensions) = material.extensions.take() {
+                let mut value = serde_json::to_value(extensions)?;
+                offset_extension_textures(&mut value, textures);
+                material.extensions = Some(serde_json::from_value(value)?);
+            }
+            self.root.materials.push(material);
+        }
+
+        let meshes = self.root.meshes.len() as u32;
+        for mesh in &src.meshes {
+            let mut mesh = mesh.clone();
+            for primitive in &mut mesh.primitives {
+                for accessor in primitive.attributes.values_mut() {
+                    *accessor = offset(*accessor, accessors);
+                }
+                primitive.indices = primitive.indices.map(|i| offset(i, accessors));
+                primitive.material = primitive.material.map(|m| offset(m, materials));
+                for target in primitive.targets.iter_mut().flatten() {
+                    for accessor in [
+                        target.positions.as_mut(),
+                        target.normals.as_mut(),
+                        target.tangents.as_mut(),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    {
+                        *accessor = offset(*accessor, accessors);
+                    }
+                }
+            }
+            self.root.meshes.push(mesh);
+        }
+
+        // cameras & skins are not exported; the nodes remain for their
+        // transforms and children
+        let mut nodes = Vec::new();
+        for node in &src.nodes {
+            let mut node = node.clone();
+            node.mesh = node.mesh.map(|m| offset(m, meshes));
+            node.camera = None;
+            node.skin = None;
+            nodes.push(node);
+        }
+
+        let scene = src.scene.map(|s| s.value()).unwrap_or(0);
+        let roots = match src.scenes.get(scene) {
+            Some(scene) => scene.nodes.iter().map(|n| n.value()).collect(),
+            None => (0..nodes.len()).collect(),
+        };
+
+        Ok(Model { nodes, roots })
+    }
+}
+
+/// export a map to a glTF file
+pub fn export(map: &MapFormat, asset_dir: &Path, path: &Path) -> Result<()> {
+    let mut exporter = GltfExporter::new(asset_dir.to_path_buf());
+    exporter.add_map(map)?;
+    exporter.write(path)
+}
+
+fn offset<T>(index: Index<T>, by: u32) -> Index<T> {
+    Index::new(index.value() as u32 + by)
+}
+
+/// offset the textures referenced by material extensions such as
+/// `KHR_materials_transmission`; texture infos are the objects with an `index`
+/// under a key ending in "Texture", like `transmissionTexture`
+fn offset_extension_textures(value: &mut serde_json::Value, by: u32) {
+    let serde_json::Value::Object(object) = value else { return };
+    for (key, value) in object.iter_mut() {
+        match value.get_mut("index") {
+            Some(index) if key.ends_with("Texture") => {
+                if let Some(i) = index.as_u64() {
+                    *index = (i + by as u64).into();
+                }
+            }
+            _ => offset_extension_textures(value, by),
+        }
+    }
+}
+
+fn pad_to_four(buf: &mut Vec<u8>) {
+    buf.resize((buf.len() + 3) & !3, 0);
+}
+
+fn new_node(name: &str) -> json::Node {
+    json::Node {
+        camera: None,
+        children: None,
+        extensions: None,
+        extras: Default::default(),
+        matrix: None,
+        mesh: None,
+        name: Some(name.into()),
+        rotation: None,
+        scale: None,
+        translation: None,
+        skin: None,
+        weights: None,
+    }
+}
+
+fn mime_type(path: &Path) -> Result<&'static str> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => Ok("image/png"),
+        "jpg" | "jpeg" => Ok("image/jpeg"),
+        _ => bail!("unsupported image type: {:?}", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{EdgeTile, Layer, SaveId, Tile};
+    use test_log::test;
+
+    /// write a glTF model containing a single triangle into `dir`
+    fn write_triangle(dir: &Path) -> PathBuf {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let bytes: Vec<u8> = positions.iter().flat_map(|f| f.to_le_bytes()).collect();
+        std::fs::write(dir.join("triangle.bin"), bytes).unwrap();
+
+        let path = dir.join("triangle.gltf");
+        std::fs::write(
+            &path,
+            r#"{
+                "asset": { "version": "2.0" },
+                "scene": 0,
+                "scenes": [ { "nodes": [0] } ],
+                "nodes": [ { "mesh": 0 } ],
+                "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 } } ] } ],
+                "accessors": [ {
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 0.0, 1.0]
+                } ],
+                "bufferViews": [ { "buffer": 0, "byteLength": 36 } ],
+                "buffers": [ { "uri": "triangle.bin", "byteLength": 36 } ]
+            }"#,
+        )
+        .unwrap();
+        path
+    }
+
+    fn map_format(model: &Path) -> MapFormat {
+        let mut tileset = tileset::Tileset::new("test tileset");
+        tileset.add_tile(model.to_path_buf());
+
+        let mut map = MapFormat::new(hexx::HexLayout::default());
+        map.tilesets.insert(SaveId::default(), tileset);
+        for (name, locations) in [("ground", vec![(0, 0), (1, 0)]), ("top", vec![(0, 1)])] {
+            map.layers.push(Layer {
+                name: name.into(),
+                tiles: locations
+                    .into_iter()
+                    .map(|location| Tile {
+                        location: location.into(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            });
+        }
+        map.layers[1].edges.push(EdgeTile {
+            edge: map::Edge::new((0, 1).into(), (1, 1).into()).unwrap(),
+            ..Default::default()
+        });
+        map
+    }
+
+    #[test]
+    fn export_glb() {
+        let dir = std::env::temp_dir().join(format!("hex_sandbox_gltf_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = write_triangle(&dir);
+        let map = map_format(&model);
+
+        let out = dir.join("map.glb");
+        export(&map, &dir, &out).unwrap();
+
+        let bytes = std::fs::read(&out).unwrap();
+        let gltf = ::gltf::Gltf::from_slice(&bytes).unwrap();
+        assert_eq!(gltf.blob.as_ref().map(|b| b.len()), Some(36));
+        let root = gltf.document.into_json();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the triangle mesh is only present once
+        assert_eq!(root.meshes.len(), 1);
+        assert_eq!(root.buffers.len(), 1);
+
+        // one node per layer
+        let layers: Vec<&json::Node> = root.scenes[0]
+            .nodes
+            .iter()
+            .map(|n| &root.nodes[n.value()])
+            .collect();
+        let names: Vec<&str> = layers.iter().filter_map(|n| n.name.as_deref()).collect();
+        assert_eq!(names, ["ground", "top"]);
+
+        // tile nodes are named, have extras, and instance the shared mesh
+        let tiles: Vec<&json::Node> = layers
+            .iter()
+            .flat_map(|layer| layer.children.iter().flatten())
+            .map(|n| &root.nodes[n.value()])
+            .collect();
+        assert_eq!(tiles.len(), 4);
+        let mut edges = 0;
+        for tile in tiles {
+            assert_eq!(tile.name.as_deref(), Some("triangle"));
+            let extras: serde_json::Value =
+                serde_json::from_str(tile.extras.as_ref().unwrap().get()).unwrap();
+            assert_eq!(extras["tile"], "triangle");
+            assert_eq!(extras["tileset"], "test tileset");
+            if extras["edge"] == "PosX" {
+                assert_eq!(extras["location"], serde_json::json!([0, 1]));
+                edges += 1;
+            }
+
+            let children = tile.children.as_ref().unwrap();
+            assert_eq!(children.len(), 1);
+            assert_eq!(root.nodes[children[0].value()].mesh, Some(Index::new(0)));
+        }
+        assert_eq!(edges, 1);
+    }
+
+    #[test]
+    fn export_tinted_tiles() {
+        let dir = std::env::temp_dir().join(format!("hex_sandbox_tint_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = write_triangle(&dir);
+        let mut map = map_format(&model);
+        for tile in &mut map.layers[0].tiles {
+            tile.overrides.tint = Some(Color::RED);
+        }
+
+        let out = dir.join("map.gltf");
+        export(&map, &dir, &out).unwrap();
+        let root = json::Root::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // both tinted tiles share one tinted copy of the mesh
+        assert_eq!(root.meshes.len(), 2);
+        assert_eq!(root.materials.len(), 1);
+        let material = root.meshes[1].primitives[0].material;
+        assert_eq!(material, Some(Index::new(0)));
+        assert_eq!(
+            root.materials[0].pbr_metallic_roughness.base_color_factor.0,
+            [1.0, 0.0, 0.0, 1.0]
+        );
+        let meshes: Vec<Option<Index<json::Mesh>>> = root
+            .nodes
+            .iter()
+            .filter(|node| node.mesh.is_some())
+            .map(|node| node.mesh)
+            .collect();
+        assert_eq!(
+            meshes.iter().filter(|m| **m == Some(Index::new(1))).count(),
+            2
+        );
+        assert_eq!(
+            meshes.iter().filter(|m| **m == Some(Index::new(0))).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn skip_required_extensions() {
+        let dir = std::env::temp_dir().join(format!("hex_sandbox_ext_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let model = dir.join("compressed.gltf");
+        std::fs::write(
+            &model,
+            r#"{
+                "asset": { "version": "2.0" },
+                "extensionsUsed": [ "KHR_draco_mesh_compression" ],
+                "extensionsRequired": [ "KHR_draco_mesh_compression" ],
+                "scenes": [ { "nodes": [0] } ],
+                "nodes": [ {} ]
+            }"#,
+        )
+        .unwrap();
+        let map = map_format(&model);
+
+        // the tiles are exported without geometry instead of failing the map
+        let out = dir.join("map.gltf");
+        export(&map, &dir, &out).unwrap();
+        let root = json::Root::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(root.extensions_required.is_empty());
+        assert!(root.meshes.is_empty());
+        let tiles: Vec<&json::Node> = root
+            .nodes
+            .iter()
+            .filter(|node| node.name.as_deref() == Some("compressed"))
+            .collect();
+        assert_eq!(tiles.len(), 4);
+        assert!(tiles.iter().all(|node| node.children.is_none()));
+    }
+
+    #[test]
+    fn extension_textures() {
+        let mut extensions = serde_json::json!({
+            "KHR_materials_transmission": {
+                "transmissionFactor": 0.5,
+                "transmissionTexture": { "index": 1, "texCoord": 0 }
+            },
+            "KHR_materials_volume": {
+                "thicknessTexture": { "index": 0 },
+                "attenuationColor": [1.0, 1.0, 1.0]
+            },
+            "KHR_materials_unlit": {}
+        });
+        offset_extension_textures(&mut extensions, 3);
+        let transmission = &extensions["KHR_materials_transmission"];
+        assert_eq!(transmission["transmissionTexture"]["index"], 4);
+        assert_eq!(transmission["transmissionFactor"], 0.5);
+        let volume = &extensions["KHR_materials_volume"];
+        assert_eq!(volume["thicknessTexture"]["index"], 3);
+    }
+}
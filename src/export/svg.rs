@@ -0,0 +1,399 @@
+//! Export a top-down schematic of a map as an SVG
+//!
+//! Every placed tile is drawn as a hex polygon filled with the tile's `color`,
+//! with one SVG group per map layer; annotation layers are skipped.  Multi-hex
+//! tiles fill every hex of their footprint, edge tiles are drawn as a line
+//! along the edge, and vertex tiles as a dot on the corner.  Tile name labels,
+//! `Location` labels, and a grid outline around the tiles are optional.
+//!
+//! Only the `MapFormat` is needed; the tile models are never loaded.
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use hexx::Hex;
+use std::{fmt::Write, path::Path};
+
+use crate::{
+    map,
+    persistence::{Layer, MapFormat, SaveId, Tile, MAP_FORMAT_VERSION},
+    tileset,
+};
+
+/// color used for tiles that do not have a color set
+const DEFAULT_FILL: &str = "#c8c8c8";
+
+/// margin around the map, in hex sizes
+const MARGIN: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// label each tile with the tile name
+    pub tile_names: bool,
+    /// label each tile with its `Location`
+    pub coordinates: bool,
+    /// draw the outline of every hex within the bounds of the map
+    pub grid: bool,
+    /// size of the SVG document in pixels per world unit
+    pub scale: f32,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            tile_names: true,
+            coordinates: false,
+            grid: true,
+            scale: 32.0,
+        }
+    }
+}
+
+/// render the map as an SVG document
+pub fn render(map: &MapFormat, options: &SvgOptions) -> Result<String> {
+    if map.version != MAP_FORMAT_VERSION {
+        bail!(
+            "unsupported map version: {} != {}",
+            map.version,
+            MAP_FORMAT_VERSION
+        );
+    }
+    let layout = &map.layout;
+    let hex_size = layout.hex_size.x.abs().min(layout.hex_size.y.abs());
+
+    // hexes covered by a tile, including the footprint of multi-hex tiles
+    let footprint = |tile: &Tile| match map.tilesets.get(&tile.tileset) {
+        Some(tileset) => tileset.footprint(tile.tile_id, tile.location, tile.rotation),
+        None => vec![tile.location],
+    };
+
+    // find the bounds of the map; hexes are all the same size so only the
+    // centers need to be checked
+    let hexes = map.layers.iter().flat_map(|layer| {
+        let tiles = layer.tiles.iter().flat_map(footprint);
+        let edges = layer.edges.iter().flat_map(|tile| tile.edge.hexes());
+        let vertices = layer.vertices.iter().flat_map(|tile| tile.vertex.hexes());
+        tiles
+            .chain(edges)
+            .chain(vertices)
+            .map(|location| location.hex())
+    });
+    let (min, max) = hexes.fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), hex| {
+            let pos = layout.hex_to_world_pos(hex);
+            (min.min(pos), max.max(pos))
+        },
+    );
+    let (min, max) = match min.x <= max.x {
+        true => (
+            min - layout.hex_size.abs() * (1.0 + MARGIN),
+            max + layout.hex_size.abs() * (1.0 + MARGIN),
+        ),
+        false => (-layout.hex_size.abs(), layout.hex_size.abs()),
+    };
+    let size = max - min;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        size.x * options.scale,
+        size.y * options.scale,
+        min.x,
+        min.y,
+        size.x,
+        size.y,
+    )?;
+    writeln!(
+        svg,
+        r#"<g font-family="sans-serif" font-size="{}" text-anchor="middle" stroke-width="{}">"#,
+        hex_size * 0.3,
+        hex_size * 0.03,
+    )?;
+
+    for (index, layer) in map.ordered_layers().enumerate() {
+        // annotations are editor-only
+        if layer.kind == map::LayerKind::Annotation {
+            continue;
+        }
+        writeln!(svg, r#"<g id="layer-{}">"#, index)?;
+        writeln!(svg, "<title>{}</title>", escape(&layer.name))?;
+        for tile in &layer.tiles {
+            let tileset_tile = tileset_tile(map, layer, tile.tileset, tile.tile_id, tile)?;
+            let fill = paint("fill", tileset_tile.color);
+            for hex in footprint(tile) {
+                writeln!(
+                    svg,
+                    r#"<polygon points="{}" {} stroke="black"/>"#,
+                    points(layout, hex.hex()),
+                    fill
+                )?;
+            }
+
+            let center = layout.hex_to_world_pos(tile.location.hex());
+            if options.tile_names {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}">{}</text>"#,
+                    center.x,
+                    center.y,
+                    escape(&tileset_tile.name)
+                )?;
+            }
+            if options.coordinates {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}">{},{}</text>"#,
+                    center.x,
+                    center.y + hex_size * 0.4,
+                    tile.location.x,
+                    tile.location.y
+                )?;
+            }
+        }
+        for tile in &layer.edges {
+            let tileset_tile = tileset_tile(map, layer, tile.tileset, tile.tile_id, tile)?;
+            let [a, b] = edge_points(layout, tile.edge);
+            writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="{}" stroke-linecap="round"/>"#,
+                a.x,
+                a.y,
+                b.x,
+                b.y,
+                paint("stroke", tileset_tile.color),
+                hex_size * 0.15,
+            )?;
+        }
+        for tile in &layer.vertices {
+            let tileset_tile = tileset_tile(map, layer, tile.tileset, tile.tile_id, tile)?;
+            let [a, b, c] = tile
+                .vertex
+                .hexes()
+                .map(|location| layout.hex_to_world_pos(location.hex()));
+            let center = (a + b + c) / 3.0;
+            writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" {} stroke="black"/>"#,
+                center.x,
+                center.y,
+                hex_size * 0.15,
+                paint("fill", tileset_tile.color),
+            )?;
+        }
+        writeln!(svg, "</g>")?;
+    }
+
+    if options.grid {
+        writeln!(svg, r#"<g id="grid" fill="none" stroke="gray">"#)?;
+        let center = layout.world_pos_to_hex((min + max) / 2.0);
+        let radius = (size.max_element() / hex_size).ceil() as u32;
+        for hex in center.range(radius) {
+            let pos = layout.hex_to_world_pos(hex);
+            if pos.cmplt(min).any() || pos.cmpgt(max).any() {
+                continue;
+            }
+            writeln!(svg, r#"<polygon points="{}"/>"#, points(layout, hex))?;
+        }
+        writeln!(svg, "</g>")?;
+    }
+
+    writeln!(svg, "</g>")?;
+    writeln!(svg, "</svg>")?;
+    Ok(svg)
+}
+
+/// export the map to an SVG file
+pub fn export(map: &MapFormat, options: &SvgOptions, path: &Path) -> Result<()> {
+    let svg = render(map, options)?;
+    std::fs::write(path, svg).context(format!("write {:?}", path))?;
+    Ok(())
+}
+
+fn points(layout: &hexx::HexLayout, hex: Hex) -> String {
+    layout
+        .hex_corners(hex)
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// end points of the side shared by the two hexes of an edge; the two corners
+/// of the first hex nearest the second hex
+fn edge_points(layout: &hexx::HexLayout, edge: map::Edge) -> [Vec2; 2] {
+    let [a, b] = edge.hexes();
+    let center = layout.hex_to_world_pos(b.hex());
+    let mut corners = layout.hex_corners(a.hex());
+    corners.sort_by(|p, q| {
+        p.distance_squared(center)
+            .total_cmp(&q.distance_squared(center))
+    });
+    [corners[0], corners[1]]
+}
+
+/// the tileset tile placed by a hex, edge, or vertex tile
+fn tileset_tile<'a>(
+    map: &'a MapFormat,
+    layer: &Layer,
+    tileset: SaveId,
+    tile_id: tileset::TileId,
+    tile: &impl std::fmt::Debug,
+) -> Result<&'a tileset::Tile> {
+    map.tilesets
+        .get(&tileset)
+        .and_then(|tileset| tileset.tiles.get(&tile_id))
+        .context(format!("unknown tile in layer {}: {:?}", layer.name, tile))
+}
+
+/// `fill` or `stroke` attributes for a tile color
+fn paint(attribute: &str, color: Option<Color>) -> String {
+    let Some(color) = color else {
+        return format!(r#"{}="{}""#, attribute, DEFAULT_FILL);
+    };
+    let [r, g, b, a] = color
+        .as_rgba_f32()
+        .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    let mut paint = format!(r##"{}="#{:02x}{:02x}{:02x}""##, attribute, r, g, b);
+    if a != 255 {
+        paint += &format!(r#" {}-opacity="{:.3}""#, attribute, a as f32 / 255.0);
+    }
+    paint
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{EdgeTile, VertexTile};
+    use test_log::test;
+
+    #[test]
+    fn render_layers() {
+        let mut tileset = tileset::Tileset::new("test");
+        tileset.add_tile("grass.glb".into());
+        tileset.add_tile("water & sand.glb".into());
+        tileset.tiles.get_mut(&0).unwrap().color = Some(Color::rgb_u8(0, 255, 0));
+
+        let mut map = MapFormat::new(hexx::HexLayout::default());
+        map.tilesets.insert(SaveId::default(), tileset);
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles: vec![
+                Tile::default(),
+                Tile {
+                    location: (1, 0).into(),
+                    tile_id: 1,
+                    ..default()
+                },
+            ],
+            ..default()
+        });
+        map.layers.push(Layer {
+            name: "top".into(),
+            tiles: vec![Tile {
+                location: (-2, 3).into(),
+                ..default()
+            }],
+            ..default()
+        });
+        map.layers.push(Layer {
+            name: "notes".into(),
+            kind: map::LayerKind::Annotation,
+            annotations: vec![crate::annotation::Annotation::new(
+                (4, 4).into(),
+                "bridge goes here",
+            )],
+            ..default()
+        });
+
+        let options = SvgOptions {
+            coordinates: true,
+            grid: false,
+            ..default()
+        };
+        let svg = render(&map, &options).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<g id=\"layer-").count(), 2);
+        assert!(svg.contains("<title>ground</title>"));
+        assert!(svg.contains("<title>top</title>"));
+        assert!(!svg.contains("notes"));
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert_eq!(svg.matches("fill=\"#00ff00\"").count(), 2);
+        assert_eq!(
+            svg.matches(&format!("fill=\"{}\"", DEFAULT_FILL)).count(),
+            1
+        );
+        assert!(svg.contains(">water &amp; sand</text>"));
+        assert!(svg.contains(">-2,3</text>"));
+        assert!(!svg.contains("id=\"grid\""));
+
+        let svg = render(&map, &SvgOptions::default()).unwrap();
+        assert!(svg.contains("id=\"grid\""));
+        assert!(svg.matches("<polygon").count() > 3);
+    }
+
+    #[test]
+    fn render_footprints_edges_vertices() {
+        let mut tileset = tileset::Tileset::new("test");
+        tileset.add_tile("castle.glb".into());
+        tileset.add_tile("wall.glb".into());
+        let castle = tileset.tiles.get_mut(&0).unwrap();
+        castle.color = Some(Color::rgb_u8(0, 255, 0));
+        castle.footprint = vec![(1, 0).into(), (0, 1).into()];
+
+        let mut map = MapFormat::new(hexx::HexLayout::default());
+        map.tilesets.insert(SaveId::default(), tileset);
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles: vec![Tile::default()],
+            edges: vec![EdgeTile {
+                edge: map::Edge::new((3, 0).into(), (4, 0).into()).unwrap(),
+                tile_id: 1,
+                ..default()
+            }],
+            vertices: vec![VertexTile {
+                vertex: map::Vertex::new((3, 3).into(), (4, 3).into(), (3, 4).into()).unwrap(),
+                ..default()
+            }],
+            ..default()
+        });
+
+        let options = SvgOptions {
+            grid: false,
+            ..default()
+        };
+        let svg = render(&map, &options).unwrap();
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert_eq!(svg.matches("fill=\"#00ff00\"").count(), 4);
+        assert_eq!(svg.matches("<text").count(), 1);
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains(&format!("stroke=\"{}\"", DEFAULT_FILL)));
+        assert_eq!(svg.matches("<circle").count(), 1);
+
+        // the edge runs along the side shared by the two hexes
+        let [a, b] = edge_points(&map.layout, map.layers[0].edges[0].edge);
+        let side = map.layout.hex_corners(Hex::new(3, 0));
+        assert!(side.iter().any(|p| p.distance(a) < 1e-4));
+        assert!(side.iter().any(|p| p.distance(b) < 1e-4));
+        let neighbor = map.layout.hex_corners(Hex::new(4, 0));
+        assert!(neighbor.iter().any(|p| p.distance(a) < 1e-4));
+        assert!(neighbor.iter().any(|p| p.distance(b) < 1e-4));
+    }
+
+    #[test]
+    fn render_unknown_tile() {
+        let mut map = MapFormat::new(hexx::HexLayout::default());
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles: vec![Tile::default()],
+            ..default()
+        });
+        assert!(render(&map, &SvgOptions::default()).is_err());
+    }
+}
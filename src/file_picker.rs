@@ -34,6 +34,7 @@ enum PickerMode {
     Save,
     OpenOne,
     OpenMany,
+    Folder,
 }
 
 #[derive(Debug)]
@@ -58,6 +59,13 @@ impl<E: PickerEvent> Picker<E> {
             event,
         }
     }
+    pub fn for_folder(event: E) -> Self {
+        Self {
+            mode: PickerMode::Folder,
+            dialog: FileDialog::new(),
+            event,
+        }
+    }
     pub fn save_dialog(event: E) -> Self {
         Self {
             mode: PickerMode::Save,
@@ -81,6 +89,9 @@ impl<E: PickerEvent> Picker<E> {
                 task_pool.spawn(async move { self.dialog.save_file().map(|p| vec![p]) })
             }
             PickerMode::OpenMany => task_pool.spawn(async move { self.dialog.pick_files() }),
+            PickerMode::Folder => {
+                task_pool.spawn(async move { self.dialog.pick_folder().map(|p| vec![p]) })
+            }
         };
         PickerDialog {
             task,
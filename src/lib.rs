@@ -1,14 +1,26 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::too_many_arguments)]
 
+pub mod annotation;
+pub mod brush;
 pub mod constants;
+pub mod export;
 pub mod file_picker;
 pub mod map;
+pub mod object;
 pub mod persistence;
+pub mod region;
+pub mod replace;
+pub mod scatter;
+pub mod selection;
+pub mod stamp;
 pub mod thumbnail_render;
+pub mod tiled;
 pub mod tileset;
+pub mod tool;
 pub mod ui;
 pub mod util;
+pub mod watch;
 
 pub mod prelude {
     pub use super::map::{Map, WorldMapExt};
@@ -3,7 +3,7 @@ use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
 use hexx::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::tileset;
 
@@ -15,8 +15,18 @@ impl bevy::app::Plugin for Plugin {
             .register_type::<HashMap<Location, (Entity, tileset::TileRef)>>()
             .register_type::<(Entity, tileset::TileRef)>()
             .register_type::<Location>()
+            .register_type::<Edge>()
+            .register_type::<Vertex>()
             .register_type::<Layer>()
-            .add_systems((update_location,).in_base_set(CoreSet::First));
+            .register_type::<LayerKind>()
+            .add_systems((update_location,).in_base_set(CoreSet::First))
+            .add_system(update_layer_index.in_base_set(CoreSet::PostUpdate))
+            .add_systems((
+                update_layer_visibility,
+                update_tile_tint.before(update_layer_opacity),
+                update_layer_opacity,
+                update_layer_transform,
+            ));
     }
 }
 
@@ -44,6 +54,15 @@ impl Location {
     pub fn hex(&self) -> Hex {
         Hex::from(*self)
     }
+
+    /// rotate the location around the origin; the rotation matches that of a
+    /// tile with the same `TileRotation`
+    pub fn rotate(&self, rotation: tileset::TileRotation) -> Location {
+        (0..rotation.clockwise_steps()).fold(*self, |l, _| {
+            let z = -l.x - l.y;
+            Location { x: -z, y: -l.x }
+        })
+    }
 }
 
 impl From<Hex> for Location {
@@ -64,6 +83,167 @@ impl From<Location> for Hex {
     }
 }
 
+/// an edge between two neighboring hexes, such as a wall or a river
+///
+/// Every edge is shared by two hexes, so edges are kept in a canonical form:
+/// the location of one of the hexes, and which of three sides of that hex the
+/// edge is on.  The other three sides of a hex are the canonical edges of its
+/// neighbors.  Use `Edge::new()` to get the edge between two hexes.
+#[derive(
+    Component,
+    Default,
+    Debug,
+    PartialEq,
+    Reflect,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    FromReflect,
+    Serialize,
+    Deserialize,
+)]
+#[reflect_value(Component, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    pub location: Location,
+    pub side: EdgeSide,
+}
+
+/// side of a hex an `Edge` is on, named by the axial offset of the neighbor
+/// sharing the edge
+#[derive(Default, Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub enum EdgeSide {
+    /// shared with the hex at (x + 1, y)
+    #[default]
+    PosX,
+    /// shared with the hex at (x + 1, y - 1)
+    PosXNegY,
+    /// shared with the hex at (x, y - 1)
+    NegY,
+}
+
+impl EdgeSide {
+    /// offset of the neighbor sharing the edge
+    fn offset(self) -> (i32, i32) {
+        match self {
+            EdgeSide::PosX => (1, 0),
+            EdgeSide::PosXNegY => (1, -1),
+            EdgeSide::NegY => (0, -1),
+        }
+    }
+}
+
+impl Edge {
+    /// the edge between two hexes; None if the hexes are not neighbors
+    pub fn new(a: Location, b: Location) -> Option<Edge> {
+        let delta = (b.x - a.x, b.y - a.y);
+        for side in [EdgeSide::PosX, EdgeSide::PosXNegY, EdgeSide::NegY] {
+            let (x, y) = side.offset();
+            if delta == (x, y) {
+                return Some(Edge { location: a, side });
+            }
+            if delta == (-x, -y) {
+                return Some(Edge { location: b, side });
+            }
+        }
+        None
+    }
+
+    /// the two hexes sharing the edge
+    pub fn hexes(&self) -> [Location; 2] {
+        let (x, y) = self.side.offset();
+        let l = self.location;
+        [l, (l.x + x, l.y + y).into()]
+    }
+
+    /// move the edge by transforming both hexes with `f`, which must keep
+    /// neighboring hexes neighbors, such as a rotation or an offset
+    pub fn map_hexes(&self, f: impl Fn(Location) -> Location) -> Edge {
+        let [a, b] = self.hexes();
+        Edge::new(f(a), f(b)).expect("transformed hexes are neighbors")
+    }
+}
+
+/// a corner shared by three hexes, such as a tower or a tree
+///
+/// Like `Edge`, vertices are kept in a canonical form: the location of one of
+/// the hexes, and which of two corners of that hex the vertex is.  Use
+/// `Vertex::new()` to get the vertex shared by three hexes.
+#[derive(
+    Component,
+    Default,
+    Debug,
+    PartialEq,
+    Reflect,
+    Eq,
+    Hash,
+    Copy,
+    Clone,
+    FromReflect,
+    Serialize,
+    Deserialize,
+)]
+#[reflect_value(Component, Hash, Serialize, Deserialize)]
+pub struct Vertex {
+    pub location: Location,
+    pub side: VertexSide,
+}
+
+/// corner of a hex a `Vertex` is at, named by the axial offsets of the other
+/// two hexes sharing the corner
+#[derive(Default, Debug, PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub enum VertexSide {
+    /// shared with the hexes at (x + 1, y) & (x, y + 1)
+    #[default]
+    PosXPosY,
+    /// shared with the hexes at (x + 1, y - 1) & (x + 1, y)
+    PosXNegY,
+}
+
+impl VertexSide {
+    /// offsets of the other hexes sharing the corner
+    fn offsets(self) -> [(i32, i32); 2] {
+        match self {
+            VertexSide::PosXPosY => [(1, 0), (0, 1)],
+            VertexSide::PosXNegY => [(1, -1), (1, 0)],
+        }
+    }
+}
+
+impl Vertex {
+    /// the vertex shared by three hexes; None if the hexes are not all
+    /// neighbors of each other
+    pub fn new(a: Location, b: Location, c: Location) -> Option<Vertex> {
+        let mut hexes = [a, b, c];
+        hexes.sort_by_key(|l| (l.x, l.y));
+        for location in [a, b, c] {
+            for side in [VertexSide::PosXPosY, VertexSide::PosXNegY] {
+                let vertex = Vertex { location, side };
+                let mut shared = vertex.hexes();
+                shared.sort_by_key(|l| (l.x, l.y));
+                if shared == hexes {
+                    return Some(vertex);
+                }
+            }
+        }
+        None
+    }
+
+    /// the three hexes sharing the vertex
+    pub fn hexes(&self) -> [Location; 3] {
+        let l = self.location;
+        let [(ax, ay), (bx, by)] = self.side.offsets();
+        [l, (l.x + ax, l.y + ay).into(), (l.x + bx, l.y + by).into()]
+    }
+
+    /// move the vertex by transforming the hexes with `f`, which must keep
+    /// neighboring hexes neighbors, such as a rotation or an offset
+    pub fn map_hexes(&self, f: impl Fn(Location) -> Location) -> Vertex {
+        let [a, b, c] = self.hexes();
+        Vertex::new(f(a), f(b), f(c)).expect("transformed hexes are neighbors")
+    }
+}
+
 #[derive(Component, Default)]
 pub struct Map {
     pub layout: HexLayout,
@@ -82,20 +262,130 @@ impl WorldMapExt for &mut World {
     }
 }
 
-#[derive(Component, Default, Reflect, Debug)]
+#[derive(Component, Reflect, Debug)]
 #[reflect(Component)]
 pub struct Layer {
     pub name: String,
+    /// spatial index of the tiles in the layer; every hex covered by a tile,
+    /// including the footprint of multi-hex tiles, maps to the tile entity.
+    /// Maintained by the map plugin at the end of each frame.
     pub tiles: HashMap<Location, (Entity, tileset::TileRef)>,
+    /// position of the layer in the map; higher layers are above lower ones
+    pub order: usize,
+    /// hidden layers are not drawn, and their tiles cannot be picked
+    pub visible: bool,
+    /// locked layers cannot be edited
+    pub locked: bool,
+    /// opacity of the tiles in the layer, 0.0 to 1.0
+    pub opacity: f32,
+    /// height of the layer above the map
+    pub y_offset: f32,
+    /// what can be placed on the layer
+    pub kind: LayerKind,
+}
+
+/// type of a map layer; determines what can be placed on the layer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum LayerKind {
+    /// map tiles
+    #[default]
+    Tile,
+    /// objects & markers placed on the map
+    Object,
+    /// editor-only notes; not included in exports
+    Annotation,
+}
+
+impl LayerKind {
+    pub const ALL: [LayerKind; 3] = [LayerKind::Tile, LayerKind::Object, LayerKind::Annotation];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayerKind::Tile => "Tile",
+            LayerKind::Object => "Object",
+            LayerKind::Annotation => "Annotation",
+        }
+    }
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            tiles: HashMap::new(),
+            order: 0,
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            y_offset: 0.0,
+            kind: LayerKind::Tile,
+        }
+    }
 }
 
 impl Layer {
     pub fn new(name: String) -> Self {
+        Self { name, ..default() }
+    }
+
+    /// check if the tiles in the layer can be changed
+    pub fn editable(&self) -> bool {
+        self.visible && !self.locked
+    }
+
+    /// check if tiles can be placed on the layer
+    pub fn accepts_tiles(&self) -> bool {
+        self.editable() && self.kind == LayerKind::Tile
+    }
+}
+
+/// hexes covered by the tiles of a layer, starting from the layer index and
+/// updated as tiles are placed & removed; used to keep multi-hex tiles from
+/// overlapping other tiles
+#[derive(Debug, Default, Clone)]
+pub struct TileCoverage {
+    pub hexes: HashMap<Location, (Entity, tileset::TileRef)>,
+}
+
+impl TileCoverage {
+    pub fn new(layer: &Layer) -> Self {
         Self {
-            name,
-            tiles: HashMap::new(),
+            hexes: layer.tiles.clone(),
         }
     }
+
+    /// tile covering a hex
+    pub fn get(&self, hex: &Location) -> Option<(Entity, tileset::TileRef)> {
+        self.hexes.get(hex).copied()
+    }
+
+    /// the tiles covering any of the hexes
+    pub fn tiles_at(&self, hexes: &[Location]) -> HashSet<Entity> {
+        hexes
+            .iter()
+            .filter_map(|hex| self.hexes.get(hex))
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+
+    /// check if any of the hexes is covered by a tile other than the tiles
+    /// `ignored`, such as the tile being replaced
+    pub fn overlaps(&self, hexes: &[Location], ignored: impl Fn(Entity) -> bool) -> bool {
+        hexes
+            .iter()
+            .any(|hex| matches!(self.hexes.get(hex), Some((entity, _)) if !ignored(*entity)))
+    }
+
+    /// record a tile covering the hexes
+    pub fn insert(&mut self, entity: Entity, tile_ref: tileset::TileRef, hexes: Vec<Location>) {
+        self.hexes
+            .extend(hexes.into_iter().map(|hex| (hex, (entity, tile_ref))));
+    }
+
+    /// forget every hex covered by a tile
+    pub fn remove(&mut self, entity: Entity) {
+        self.hexes.retain(|_, (e, _)| *e != entity);
+    }
 }
 
 /// Add this component to anything with a Location that should be updated based
@@ -124,21 +414,115 @@ impl Map {
         Vec3::new(pos.x, tile.transform.translation.y, pos.y)
     }
 
+    /// transform of a tile placed on a hex, including the overrides of the
+    /// placed tile
     pub fn tile_transform(
         &self,
         tile: &tileset::Tile,
         location: Location,
         tile_transform: &tileset::TileTransform,
+        overrides: &tileset::TileOverrides,
     ) -> Transform {
-        let pos = self.layout.hex_to_world_pos(location.into());
+        let pos = self.layout.hex_to_world_pos(location.into()) + overrides.offset;
+        let mut scale = tile.transform.scale * overrides.scale;
+        if overrides.flip {
+            scale.x = -scale.x;
+        }
         Transform {
-            translation: Vec3::new(pos.x, tile.transform.translation.y, pos.y),
+            translation: Vec3::new(
+                pos.x,
+                tile.transform.translation.y + overrides.y_offset,
+                pos.y,
+            ),
             rotation: tile.transform.rotation
                 * Quat::from_euler(EulerRot::XYZ, 0.0, tile_transform.rotation.into(), 0.0),
+            scale,
+        }
+    }
+
+    /// center of an edge
+    pub fn edge_translation(&self, edge: Edge) -> Vec3 {
+        let [a, b] = edge.hexes();
+        (self.translation(a) + self.translation(b)) / 2.0
+    }
+
+    /// transform of a tile placed on an edge
+    ///
+    /// The tile is rotated so its X axis runs along the edge; the tile
+    /// rotation then turns it around the center of the edge.
+    pub fn edge_transform(
+        &self,
+        tile: &tileset::Tile,
+        edge: Edge,
+        tile_transform: &tileset::TileTransform,
+    ) -> Transform {
+        let [a, b] = edge.hexes();
+        let across = self.translation(b) - self.translation(a);
+        let angle = across.x.atan2(across.z);
+        let translation = self.edge_translation(edge);
+        Transform {
+            translation: Vec3::new(translation.x, tile.transform.translation.y, translation.z),
+            rotation: Quat::from_rotation_y(angle)
+                * tile.transform.rotation
+                * Quat::from_euler(EulerRot::XYZ, 0.0, tile_transform.rotation.into(), 0.0),
             scale: tile.transform.scale,
         }
     }
 
+    /// position of a vertex
+    pub fn vertex_translation(&self, vertex: Vertex) -> Vec3 {
+        let [a, b, c] = vertex.hexes();
+        (self.translation(a) + self.translation(b) + self.translation(c)) / 3.0
+    }
+
+    /// transform of a tile placed on a vertex
+    pub fn vertex_transform(
+        &self,
+        tile: &tileset::Tile,
+        vertex: Vertex,
+        tile_transform: &tileset::TileTransform,
+    ) -> Transform {
+        let translation = self.vertex_translation(vertex);
+        Transform {
+            translation: Vec3::new(translation.x, tile.transform.translation.y, translation.z),
+            rotation: tile.transform.rotation
+                * Quat::from_euler(EulerRot::XYZ, 0.0, tile_transform.rotation.into(), 0.0),
+            scale: tile.transform.scale,
+        }
+    }
+
+    /// the edge nearest to a world position
+    pub fn nearest_edge(&self, pos: Vec3) -> Edge {
+        let hex = self.layout.world_pos_to_hex(pos.xz());
+        let neighbor = hex
+            .all_neighbors()
+            .into_iter()
+            .min_by(|a, b| {
+                let a = self.hex_to_world_pos(*a, pos.y).distance_squared(pos);
+                let b = self.hex_to_world_pos(*b, pos.y).distance_squared(pos);
+                a.total_cmp(&b)
+            })
+            .unwrap();
+        Edge::new(hex.into(), neighbor.into()).unwrap()
+    }
+
+    /// the vertex nearest to a world position
+    pub fn nearest_vertex(&self, pos: Vec3) -> Vertex {
+        let hex = self.layout.world_pos_to_hex(pos.xz());
+        let neighbors = hex.all_neighbors();
+        (0..6)
+            .filter_map(|i| {
+                let (a, b) = (neighbors[i], neighbors[(i + 1) % 6]);
+                Vertex::new(hex.into(), a.into(), b.into())
+            })
+            .min_by(|a, b| {
+                let a = self.vertex_translation(*a).xz().distance_squared(pos.xz());
+                let b = self.vertex_translation(*b).xz().distance_squared(pos.xz());
+                a.total_cmp(&b)
+            })
+            .unwrap()
+    }
+
     pub fn hex_to_world_pos(&self, hex: Hex, y: f32) -> Vec3 {
         let hex = self.layout.hex_to_world_pos(hex);
         Vec3::new(hex.x, y, hex.y)
@@ -158,3 +542,573 @@ fn update_location(
         loc.set_if_neq(hex.into());
     }
 }
+
+/// rebuild the spatial index of the layers whose tiles changed
+///
+/// The index is derived from the tiles, so rebuilding it doesn't mark the
+/// layer as changed.  When tiles overlap, the hex is indexed to one of them.
+fn update_layer_index(
+    mut layers: Query<(Entity, &mut Layer, Option<&Children>)>,
+    changed_layers: Query<Entity, (With<Layer>, Changed<Children>)>,
+    mut emptied_layers: RemovedComponents<Children>,
+    changed_tiles: Query<
+        &Parent,
+        (
+            With<Location>,
+            Or<(
+                Changed<Location>,
+                Changed<tileset::TileRef>,
+                Changed<tileset::TileTransform>,
+            )>,
+        ),
+    >,
+    changed_tilesets: Query<(), Changed<tileset::Tileset>>,
+    tiles: Query<(&Location, &tileset::TileRef, &tileset::TileTransform)>,
+    tilesets: Query<&tileset::Tileset>,
+) {
+    // a footprint may have changed when a tileset changes, so rebuild all
+    let all = !changed_tilesets.is_empty();
+    let mut dirty: HashSet<Entity> = changed_layers.iter().collect();
+    dirty.extend(emptied_layers.iter());
+    dirty.extend(changed_tiles.iter().map(|parent| parent.get()));
+
+    for (entity, mut layer, children) in &mut layers {
+        if !all && !dirty.contains(&entity) {
+            continue;
+        }
+        let index = &mut layer.bypass_change_detection().tiles;
+        index.clear();
+        for child in children.into_iter().flatten() {
+            let Ok((location, tile_ref, transform)) = tiles.get(*child) else { continue };
+            let footprint = match tilesets.get(tile_ref.tileset) {
+                Ok(tileset) => tileset.footprint(tile_ref.tile, *location, transform.rotation),
+                Err(_) => vec![*location],
+            };
+            for hex in footprint {
+                index.insert(hex, (*child, *tile_ref));
+            }
+        }
+    }
+}
+
+/// hide the entities of layers that are not visible
+fn update_layer_visibility(mut layers: Query<(&Layer, &mut Visibility), Changed<Layer>>) {
+    for (layer, mut visibility) in &mut layers {
+        let value = if layer.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(value);
+    }
+}
+
+/// raise the layer entities to the layer height
+fn update_layer_transform(mut layers: Query<(&Layer, &mut Transform), Changed<Layer>>) {
+    for (layer, mut transform) in &mut layers {
+        if transform.translation.y != layer.y_offset {
+            transform.translation.y = layer.y_offset;
+        }
+    }
+}
+
+/// original material of a mesh in a tinted tile, replaced with a copy whose
+/// color is multiplied by the tint
+#[derive(Component, Debug)]
+pub struct TintMaterial {
+    tint: Color,
+    original: Handle<StandardMaterial>,
+}
+
+/// key for a tinted copy of a material; `Color` cannot be hashed, so tints
+/// are compared by their bits
+type TintKey = (Handle<StandardMaterial>, [u32; 4]);
+
+fn tint_key(original: &Handle<StandardMaterial>, tint: Color) -> TintKey {
+    (original.clone(), tint.as_rgba_f32().map(f32::to_bits))
+}
+
+/// apply the tile tints to the materials of the tile meshes
+///
+/// Each tint gets its own copy of the materials it is applied to, shared by
+/// every tile using the same tint.  Copies no tile uses any more are dropped,
+/// and meshes of tiles whose tint was removed go back to the original
+/// material.
+fn update_tile_tint(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut copies: Local<HashMap<TintKey, Handle<StandardMaterial>>>,
+    tinted: Query<&TintMaterial>,
+    overrides: Query<&tileset::TileOverrides>,
+    changed_overrides: Query<Entity, Changed<tileset::TileOverrides>>,
+    mut removed_overrides: RemovedComponents<tileset::TileOverrides>,
+    mut meshes: ParamSet<(
+        Query<Entity, Added<Handle<StandardMaterial>>>,
+        Query<(
+            &mut Handle<StandardMaterial>,
+            Option<&TintMaterial>,
+            Option<&LayerMaterial>,
+        )>,
+    )>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+) {
+    let in_use: HashSet<TintKey> = tinted
+        .iter()
+        .map(|m| tint_key(&m.original, m.tint))
+        .collect();
+    copies.retain(|key, _| in_use.contains(key));
+
+    // new meshes, and the meshes below tiles whose overrides changed
+    let retinted = changed_overrides
+        .iter()
+        .chain(removed_overrides.iter())
+        .flat_map(|tile| children.iter_descendants(tile));
+    let visit: HashSet<Entity> = meshes.p0().iter().chain(retinted).collect();
+    let mut meshes = meshes.p1();
+    for entity in visit {
+        let Ok((mut handle, tint_material, layer_material)) = meshes.get_mut(entity) else {
+            continue;
+        };
+        let tint = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| overrides.get(ancestor).ok())
+            .and_then(|overrides| overrides.tint);
+        if tint_material.map(|m| m.tint) == tint {
+            continue;
+        }
+
+        // the material shared by every tile using the model, from before
+        // the tint & layer opacity were applied
+        let original = match (tint_material, layer_material) {
+            (Some(m), _) => m.original.clone(),
+            (None, Some(m)) => m.original.clone(),
+            (None, None) => handle.clone(),
+        };
+        let Some(tint) = tint else {
+            *handle = original;
+            commands.entity(entity).remove::<TintMaterial>();
+            continue;
+        };
+
+        let key = tint_key(&original, tint);
+        let copy = match copies.get(&key) {
+            Some(copy) => copy.clone(),
+            None => {
+                let Some(material) = materials.get(&original) else { continue };
+                let mut material = material.clone();
+                material.base_color =
+                    material.base_color.as_rgba_linear() * tint.as_linear_rgba_f32();
+                let copy = materials.add(material);
+                copies.insert(key, copy.clone());
+                copy
+            }
+        };
+        *handle = copy;
+        commands
+            .entity(entity)
+            .insert(TintMaterial { tint, original });
+    }
+}
+
+/// original material of a mesh in a layer, replaced with a copy whose alpha
+/// is scaled by the layer opacity
+#[derive(Component, Debug)]
+pub struct LayerMaterial {
+    layer: Entity,
+    original: Handle<StandardMaterial>,
+}
+
+/// apply the layer opacity to the materials of the tile meshes in the layer
+///
+/// Materials are shared between every tile using the same model, so each
+/// layer gets its own copy of a material the first time the layer opacity is
+/// below 1.0.  The copies are updated in place when the opacity changes, and
+/// dropped once no mesh uses them.  Meshes whose material was replaced by
+/// `update_tile_tint` get a copy of the new material.
+fn update_layer_opacity(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut copies: Local<
+        HashMap<(Entity, Handle<StandardMaterial>), (Handle<StandardMaterial>, Color, AlphaMode)>,
+    >,
+    layers: Query<&Layer>,
+    changed_layers: Query<Entity, Changed<Layer>>,
+    copied: Query<&LayerMaterial>,
+    mut meshes: ParamSet<(
+        Query<Entity, Changed<Handle<StandardMaterial>>>,
+        Query<(
+            Entity,
+            &mut Handle<StandardMaterial>,
+            Option<&LayerMaterial>,
+        )>,
+    )>,
+    parents: Query<&Parent>,
+) {
+    let in_use: HashSet<(Entity, Handle<StandardMaterial>)> = copied
+        .iter()
+        .map(|m| (m.layer, m.original.clone()))
+        .collect();
+    copies.retain(|key, _| in_use.contains(key) && layers.contains(key.0));
+
+    // update the existing copies for layers that changed
+    let changed: Vec<Entity> = changed_layers.iter().collect();
+    for ((layer, _), (handle, color, alpha_mode)) in copies.iter() {
+        if !changed.contains(layer) {
+            continue;
+        }
+        let (Ok(layer), Some(material)) = (layers.get(*layer), materials.get_mut(handle)) else {
+            continue;
+        };
+        set_opacity(material, *color, *alpha_mode, layer.opacity);
+    }
+
+    // copy the materials of new meshes, meshes whose material was replaced,
+    // and meshes in layers that changed
+    let replaced: HashSet<Entity> = meshes.p0().iter().collect();
+    let visit: HashSet<Entity> = if changed.is_empty() {
+        replaced.clone()
+    } else {
+        meshes.p1().iter().map(|(entity, _, _)| entity).collect()
+    };
+    let mut meshes = meshes.p1();
+    for entity in visit {
+        let Ok((_, mut handle, layer_material)) = meshes.get_mut(entity) else { continue };
+
+        // find the layer the mesh belongs to
+        let layer = parents
+            .iter_ancestors(entity)
+            .find_map(|ancestor| Some((ancestor, layers.get(ancestor).ok()?)));
+        let Some((layer_entity, layer)) = layer else { continue };
+
+        // meshes already using a copy only change if they moved layers
+        let original = match layer_material {
+            _ if replaced.contains(&entity) => handle.clone(),
+            Some(m) if m.layer == layer_entity => continue,
+            Some(m) => m.original.clone(),
+            None => handle.clone(),
+        };
+        if layer.opacity >= 1.0 {
+            if layer_material.is_some() {
+                *handle = original;
+                commands.entity(entity).remove::<LayerMaterial>();
+            }
+            continue;
+        }
+
+        let key = (layer_entity, original.clone());
+        let copy = match copies.get(&key) {
+            Some((copy, _, _)) => copy.clone(),
+            None => {
+                let Some(original) = materials.get(&original) else { continue };
+                let (color, alpha_mode) = (original.base_color, original.alpha_mode);
+                let mut material = original.clone();
+                set_opacity(&mut material, color, alpha_mode, layer.opacity);
+                let copy = materials.add(material);
+                copies.insert(key, (copy.clone(), color, alpha_mode));
+                copy
+            }
+        };
+        *handle = copy;
+        commands.entity(entity).insert(LayerMaterial {
+            layer: layer_entity,
+            original,
+        });
+    }
+}
+
+/// scale the alpha of a material copy; `color` & `alpha_mode` are the values
+/// from the original material
+fn set_opacity(material: &mut StandardMaterial, color: Color, alpha_mode: AlphaMode, opacity: f32) {
+    material.base_color = color.with_a(color.a() * opacity);
+    material.alpha_mode = if opacity < 1.0 {
+        AlphaMode::Blend
+    } else {
+        alpha_mode
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use tileset::TileRotation;
+
+    #[test]
+    fn location_rotate_matches_tile_rotation() {
+        let map = Map::new();
+        let tile = tileset::Tile::default();
+        let location = Location { x: 2, y: -1 };
+        let mut rotation = TileRotation::None;
+        for _ in 0..6 {
+            let transform = map.tile_transform(
+                &tile,
+                Location::default(),
+                &tileset::TileTransform { rotation },
+                &tileset::TileOverrides::default(),
+            );
+            let expected = transform.rotation * map.translation(location);
+            let rotated = map.translation(location.rotate(rotation));
+            assert!(
+                rotated.distance(expected) < 0.001,
+                "{:?}: {} != {}",
+                rotation,
+                rotated,
+                expected
+            );
+            rotation = rotation.clockwise();
+        }
+        assert_eq!(location.rotate(TileRotation::Clockwise180), (-2, 1).into());
+    }
+
+    #[test]
+    fn tile_transform_overrides() {
+        let map = Map::new();
+        let tile = tileset::Tile {
+            transform: Transform::from_xyz(0.0, 0.2, 0.0).with_scale(Vec3::splat(0.5)),
+            ..default()
+        };
+        let location = Location { x: 1, y: 1 };
+        let rotation = tileset::TileTransform::default();
+        let overrides = tileset::TileOverrides {
+            scale: 2.0,
+            offset: Vec2::new(0.25, -0.5),
+            y_offset: 0.3,
+            flip: true,
+            ..Default::default()
+        };
+        let transform = map.tile_transform(&tile, location, &rotation, &overrides);
+        let center = map.translation(location);
+        assert_eq!(transform.scale, Vec3::new(-1.0, 1.0, 1.0));
+        assert!((transform.translation.y - 0.5).abs() < 0.001);
+        assert!((transform.translation.x - center.x - 0.25).abs() < 0.001);
+        assert!((transform.translation.z - center.z + 0.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn edge_canonical() {
+        let center = Location { x: 2, y: -1 };
+        let mut edges = std::collections::HashSet::new();
+        for neighbor in center.hex().all_neighbors() {
+            let edge = Edge::new(center, neighbor.into()).unwrap();
+            assert_eq!(Edge::new(neighbor.into(), center), Some(edge));
+            assert!(edge.hexes().contains(&center));
+            assert!(edge.hexes().contains(&neighbor.into()));
+            edges.insert(edge);
+        }
+        assert_eq!(edges.len(), 6);
+        assert_eq!(Edge::new(center, center), None);
+        assert_eq!(Edge::new(center, (4, -1).into()), None);
+    }
+
+    #[test]
+    fn vertex_canonical() {
+        let center = Location { x: 2, y: -1 };
+        let neighbors: Vec<Location> = center
+            .hex()
+            .all_neighbors()
+            .into_iter()
+            .map(Location::from)
+            .collect();
+        let mut vertices = std::collections::HashSet::new();
+        for i in 0..6 {
+            let (a, b) = (neighbors[i], neighbors[(i + 1) % 6]);
+            let vertex = Vertex::new(center, a, b).unwrap();
+            assert_eq!(Vertex::new(b, center, a), Some(vertex));
+            assert_eq!(Vertex::new(a, b, center), Some(vertex));
+            vertices.insert(vertex);
+        }
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(Vertex::new(center, neighbors[0], neighbors[3]), None);
+    }
+
+    #[test]
+    fn nearest_edge_and_vertex() {
+        let map = Map::new();
+        let center = Location { x: -1, y: 3 };
+        let center_pos = map.translation(center);
+        for neighbor in center.hex().all_neighbors() {
+            let edge = Edge::new(center, neighbor.into()).unwrap();
+            let pos = map.edge_translation(edge).lerp(center_pos, 0.1);
+            assert_eq!(map.nearest_edge(pos), edge);
+
+            let rotate = |l: Location| l.rotate(TileRotation::Clockwise60);
+            assert_eq!(
+                edge.map_hexes(rotate),
+                Edge::new(rotate(center), rotate(neighbor.into())).unwrap()
+            );
+        }
+        let neighbors = center.hex().all_neighbors();
+        for i in 0..6 {
+            let (a, b) = (neighbors[i].into(), neighbors[(i + 1) % 6].into());
+            let vertex = Vertex::new(center, a, b).unwrap();
+            let pos = map.vertex_translation(vertex).lerp(center_pos, 0.1);
+            assert_eq!(map.nearest_vertex(pos), vertex);
+        }
+    }
+
+    #[test]
+    fn layer_index_covers_footprint() {
+        let mut tileset = tileset::Tileset::new("test");
+        tileset.add_tile("castle.glb".into());
+        tileset.add_tile("grass.glb".into());
+        let castle = tileset.tiles.get_mut(&0).unwrap();
+        castle.footprint = vec![(1, 0).into(), (0, 1).into()];
+        assert_eq!(
+            castle.footprint_at((2, 2).into(), TileRotation::Clockwise180),
+            vec![(2, 2).into(), (1, 2).into(), (2, 1).into()]
+        );
+
+        let mut app = App::new();
+        app.add_system(update_layer_index);
+        let tileset = app.world.spawn(tileset).id();
+        let tile_ref = |tile| tileset::TileRef { tileset, tile };
+        let layer = app.world.spawn(Layer::new("ground".into())).id();
+        let castle = app
+            .world
+            .spawn((
+                Location { x: 0, y: 0 },
+                tile_ref(0),
+                tileset::TileTransform::default(),
+            ))
+            .set_parent(layer)
+            .id();
+        let grass = app
+            .world
+            .spawn((
+                Location { x: 3, y: 0 },
+                tile_ref(1),
+                tileset::TileTransform::default(),
+            ))
+            .set_parent(layer)
+            .id();
+        app.update();
+
+        // hexes covered by each tile in the layer index
+        let covered = |app: &App, tile: Entity| {
+            let layer = app.world.get::<Layer>(layer).unwrap();
+            let mut hexes: Vec<_> = layer
+                .tiles
+                .iter()
+                .filter(|(_, (entity, _))| *entity == tile)
+                .map(|(location, _)| (location.x, location.y))
+                .collect();
+            hexes.sort();
+            hexes
+        };
+        assert_eq!(covered(&app, castle), vec![(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(covered(&app, grass), vec![(3, 0)]);
+
+        // rotating the tile turns the footprint
+        let mut transform = app.world.get_mut::<tileset::TileTransform>(castle).unwrap();
+        transform.rotation = TileRotation::Clockwise180;
+        app.update();
+        assert_eq!(covered(&app, castle), vec![(-1, 0), (0, -1), (0, 0)]);
+
+        app.world.entity_mut(castle).despawn_recursive();
+        app.update();
+        assert_eq!(covered(&app, castle), vec![]);
+        assert_eq!(covered(&app, grass), vec![(3, 0)]);
+    }
+
+    #[test]
+    fn tile_coverage_overlaps() {
+        let tile_ref = tileset::TileRef {
+            tileset: Entity::from_raw(0),
+            tile: 0,
+        };
+        let castle = Entity::from_raw(1);
+        let grass = Entity::from_raw(2);
+        let mut coverage = TileCoverage::default();
+        coverage.insert(castle, tile_ref, vec![(0, 0).into(), (1, 0).into()]);
+        coverage.insert(grass, tile_ref, vec![(3, 0).into()]);
+
+        let hexes: Vec<Location> = vec![(1, 0).into(), (2, 0).into()];
+        assert!(coverage.overlaps(&hexes, |_| false));
+        assert!(!coverage.overlaps(&hexes, |entity| entity == castle));
+        assert_eq!(coverage.tiles_at(&hexes), HashSet::from([castle]));
+
+        coverage.remove(castle);
+        assert!(!coverage.overlaps(&hexes, |_| false));
+        assert_eq!(coverage.get(&(0, 0).into()), None);
+        assert_eq!(coverage.get(&(3, 0).into()), Some((grass, tile_ref)));
+    }
+
+    #[test]
+    fn tile_tint() {
+        let mut app = App::new();
+        app.add_plugin(AssetPlugin::default())
+            .add_asset::<StandardMaterial>()
+            .add_systems((update_tile_tint, update_layer_opacity).chain());
+        let original = app
+            .world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(Color::WHITE.into());
+        let spawn_tile = |world: &mut World, layer: Entity| {
+            let mesh = world.spawn(original.clone()).id();
+            world
+                .spawn(tileset::TileOverrides::default())
+                .set_parent(layer)
+                .push_children(&[mesh])
+                .id()
+        };
+        let layer = app.world.spawn(Layer::new("ground".into())).id();
+        let red = spawn_tile(&mut app.world, layer);
+        let plain = spawn_tile(&mut app.world, layer);
+        app.update();
+
+        let material = |app: &App, tile: Entity| {
+            let mesh = app.world.get::<Children>(tile).unwrap()[0];
+            let handle = app.world.get::<Handle<StandardMaterial>>(mesh).unwrap();
+            let materials = app.world.resource::<Assets<StandardMaterial>>();
+            let color = materials.get(handle).unwrap().base_color;
+            (handle.clone_weak(), color)
+        };
+        let set_tint = |app: &mut App, tint: Option<Color>| {
+            let mut overrides = app.world.get_mut::<tileset::TileOverrides>(red).unwrap();
+            overrides.tint = tint;
+        };
+        assert_eq!(material(&app, red), (original.clone_weak(), Color::WHITE));
+
+        // only the mesh of the tinted tile gets a copy of the material
+        set_tint(&mut app, Some(Color::RED));
+        app.update();
+        let (handle, color) = material(&app, red);
+        assert_ne!(handle, original);
+        assert!(Vec4::from(color).abs_diff_eq(Color::RED.into(), 0.001));
+        assert_eq!(material(&app, plain), (original.clone_weak(), Color::WHITE));
+
+        // changing the scale keeps the copy
+        app.world
+            .get_mut::<tileset::TileOverrides>(red)
+            .unwrap()
+            .scale = 2.0;
+        app.update();
+        assert_eq!(material(&app, red).0, handle);
+
+        // the layer opacity applies on top of the tint
+        app.world.get_mut::<Layer>(layer).unwrap().opacity = 0.5;
+        app.update();
+        let (_, color) = material(&app, red);
+        assert!(Vec4::from(color).abs_diff_eq(Vec4::new(1.0, 0.0, 0.0, 0.5), 0.001));
+        app.world.get_mut::<Layer>(layer).unwrap().opacity = 1.0;
+        app.update();
+
+        // copies of tints no tile uses any more are freed
+        set_tint(&mut app, Some(Color::BLUE));
+        for _ in 0..5 {
+            app.update();
+        }
+        let materials = app.world.resource::<Assets<StandardMaterial>>();
+        assert!(!materials.contains(&handle));
+        let (_, color) = material(&app, red);
+        assert!(Vec4::from(color).abs_diff_eq(Color::BLUE.into(), 0.001));
+
+        // removing the overrides restores the original material
+        app.world.entity_mut(red).remove::<tileset::TileOverrides>();
+        app.update();
+        assert_eq!(material(&app, red), (original.clone_weak(), Color::WHITE));
+        let mesh = app.world.get::<Children>(red).unwrap()[0];
+        assert!(app.world.get::<TintMaterial>(mesh).is_none());
+    }
+}
@@ -0,0 +1,253 @@
+//! Objects & markers placed on object layers
+//!
+//! Objects are the non-tile things on a map: spawn points, NPCs, chests, and
+//! triggers.  Each has a type name used by the game, an optional model or
+//! icon, and a bag of user-defined properties.  Properties may reference other
+//! objects; in the World the reference is the object `Entity`, and in the save
+//! file it's the `SaveId` of the object, so references survive save & load.
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::map::{self, Location};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup)
+            .add_system(update_object_transform)
+            .add_system(update_object_model);
+    }
+}
+
+/// where an object is placed within its layer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ObjectPosition {
+    /// centered on a hex
+    Hex(Location),
+    /// free-form position relative to the layer
+    Free(Vec3),
+}
+
+impl Default for ObjectPosition {
+    fn default() -> Self {
+        ObjectPosition::Hex(Location::default())
+    }
+}
+
+impl ObjectPosition {
+    /// translation of the object relative to its layer
+    pub fn translation(&self, map: &map::Map) -> Vec3 {
+        match self {
+            ObjectPosition::Hex(location) => map.translation(*location),
+            ObjectPosition::Free(position) => *position,
+        }
+    }
+
+    /// the hex the object is in
+    pub fn location(&self, map: &map::Map) -> Location {
+        match self {
+            ObjectPosition::Hex(location) => *location,
+            ObjectPosition::Free(position) => map.layout.world_pos_to_hex(position.xz()).into(),
+        }
+    }
+}
+
+/// value of an object property
+///
+/// `R` is the type used to reference other objects; `Entity` in the World, and
+/// `SaveId` in the save file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Property<R = Entity> {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Object(R),
+}
+
+impl<R> Property<R> {
+    /// name of the property type, for display
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Property::Bool(_) => "bool",
+            Property::Int(_) => "int",
+            Property::Float(_) => "float",
+            Property::String(_) => "string",
+            Property::Object(_) => "object",
+        }
+    }
+
+    /// convert an object reference to another type; returns None if `f`
+    /// returns None for the reference
+    pub fn map_ref<T>(&self, f: impl FnOnce(&R) -> Option<T>) -> Option<Property<T>> {
+        Some(match self {
+            Property::Bool(v) => Property::Bool(*v),
+            Property::Int(v) => Property::Int(*v),
+            Property::Float(v) => Property::Float(*v),
+            Property::String(v) => Property::String(v.clone()),
+            Property::Object(r) => Property::Object(f(r)?),
+        })
+    }
+}
+
+/// an object placed on an object layer
+#[derive(Component, Debug, Clone, PartialEq, Default)]
+pub struct MapObject {
+    pub name: String,
+    /// type of the object, for example "spawn" or "chest"; used by the game
+    pub kind: String,
+    pub position: ObjectPosition,
+    /// gltf model drawn for the object
+    pub model: Option<PathBuf>,
+    /// short text or emoji shown for the object in the editor
+    pub icon: Option<String>,
+    pub properties: BTreeMap<String, Property>,
+}
+
+impl MapObject {
+    pub fn new(kind: impl Into<String>, position: ObjectPosition) -> Self {
+        let kind = kind.into();
+        Self {
+            name: kind.clone(),
+            kind,
+            position,
+            ..default()
+        }
+    }
+
+    /// the objects this object references in its properties
+    pub fn references(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.properties.values().filter_map(|p| match p {
+            Property::Object(entity) => Some(*entity),
+            _ => None,
+        })
+    }
+}
+
+/// mesh & material drawn for objects without a model
+#[derive(Resource)]
+struct MarkerAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(MarkerAssets {
+        mesh: meshes.add(Mesh::from(shape::Capsule {
+            radius: 0.15,
+            depth: 0.5,
+            ..default()
+        })),
+        material: materials.add(Color::rgb(0.9, 0.3, 0.6).into()),
+    });
+}
+
+/// move objects when their position changes
+fn update_object_transform(
+    map: Query<&map::Map>,
+    mut objects: Query<(&MapObject, &mut Transform), Changed<MapObject>>,
+) {
+    let Ok(map) = map.get_single() else { return };
+    for (object, mut transform) in &mut objects {
+        let translation = object.position.translation(map);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
+
+/// model currently drawn for an object
+#[derive(Component, Debug, PartialEq)]
+struct ObjectModel(Option<PathBuf>);
+
+/// draw the object model, or the marker for objects without a model
+fn update_object_model(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    marker: Res<MarkerAssets>,
+    objects: Query<(Entity, &MapObject, Option<&ObjectModel>), Changed<MapObject>>,
+) {
+    for (entity, object, model) in &objects {
+        if model.map(|m| &m.0) == Some(&object.model) {
+            continue;
+        }
+        let mut entity = commands.entity(entity);
+        entity.despawn_descendants();
+        entity.insert(ObjectModel(object.model.clone()));
+        match &object.model {
+            Some(path) => {
+                let scene = asset_server.load(format!("{}#Scene0", path.to_string_lossy()));
+                entity.with_children(|object| {
+                    object.spawn(SceneBundle { scene, ..default() });
+                });
+            }
+            None => {
+                entity.with_children(|object| {
+                    object.spawn(PbrBundle {
+                        mesh: marker.mesh.clone(),
+                        material: marker.material.clone(),
+                        transform: Transform::from_xyz(0.0, 0.4, 0.0),
+                        ..default()
+                    });
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn property_map_ref() {
+        let value: Property<usize> = Property::Object(3);
+        assert_eq!(
+            value.map_ref(|r| Some(*r as i32 * 2)),
+            Some(Property::Object(6))
+        );
+        assert_eq!(value.map_ref(|_| None::<i32>), None);
+
+        let value: Property<usize> = Property::String("key".into());
+        assert_eq!(
+            value.map_ref(|_| None::<i32>),
+            Some(Property::String("key".into()))
+        );
+    }
+
+    #[test]
+    fn position() {
+        let map = map::Map::default();
+        let hex = ObjectPosition::Hex((2, -1).into());
+        assert_eq!(hex.location(&map), (2, -1).into());
+
+        let free = ObjectPosition::Free(hex.translation(&map) + Vec3::new(0.1, 1.0, 0.0));
+        assert_eq!(free.location(&map), (2, -1).into());
+        assert_eq!(free.translation(&map).y, 1.0);
+    }
+
+    #[test]
+    fn references() {
+        let mut object = MapObject::new("door", ObjectPosition::default());
+        assert_eq!(object.name, "door");
+        assert_eq!(object.references().count(), 0);
+        object
+            .properties
+            .insert("key".into(), Property::Object(Entity::PLACEHOLDER));
+        object
+            .properties
+            .insert("locked".into(), Property::Bool(true));
+        assert_eq!(
+            object.references().collect::<Vec<_>>(),
+            vec![Entity::PLACEHOLDER]
+        );
+    }
+}
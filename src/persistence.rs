@@ -1,7 +1,8 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fs::File,
-    path::PathBuf,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
 };
 
 use anyhow::{bail, Context, Result};
@@ -12,17 +13,21 @@ use bevy::{
 };
 use futures_lite::future;
 use hexx::HexLayout;
-use ron::ser::{to_writer_pretty, PrettyConfig};
-use serde::{de::Visitor, Deserialize, Serialize};
+use serde::{de::DeserializeOwned, de::Visitor, Deserialize, Serialize};
 
-use crate::{map, tileset};
+use crate::{annotation, map, object, region, tileset};
+
+pub mod binary;
+pub mod diff;
+pub mod schema;
 
 pub struct Plugin;
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.register_type::<SaveId>()
             .add_system(map_writers)
-            .add_system(map_importer);
+            .add_system(map_importer)
+            .add_system(layer_importer);
     }
 }
 
@@ -43,7 +48,10 @@ impl bevy::app::Plugin for Plugin {
 /// a SaveId, it will be assigned in the World and reused from that point
 /// forward.
 ///
-/// NOTE: Right now there's no support for resolving SaveId collisions
+/// When layers from another map are imported using `ImportLayersCommand`, the
+/// tilesets from that map are remapped to the tilesets in the World, and any
+/// new tilesets are assigned unused `SaveId`s.  `SaveId` collisions are not
+/// resolved anywhere else.
 #[derive(Clone, Component, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Reflect)]
 pub struct SaveId(usize);
 
@@ -148,27 +156,176 @@ impl WorldSaveIdExt for &mut World {
     }
 }
 
+/// file formats that maps & tilesets can be saved as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Ron,
+    Json,
+    /// compact binary format for maps; see `binary`
+    Binary,
+}
+
+/// file extension used for maps in the binary format
+pub const BINARY_EXTENSION: &str = "hexmap";
+
+impl FileFormat {
+    /// determine the file format from the extension of `path`; files without
+    /// a `.json` or `.hexmap` extension are RON
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => FileFormat::Json,
+            Some(ext) if ext.eq_ignore_ascii_case(BINARY_EXTENSION) => FileFormat::Binary,
+            _ => FileFormat::Ron,
+        }
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(self, buf: &str) -> Result<T> {
+        let value = match self {
+            FileFormat::Ron => ron::from_str(buf)?,
+            FileFormat::Json => serde_json::from_str(buf)?,
+            FileFormat::Binary => bail!("binary format is only supported for maps"),
+        };
+        Ok(value)
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String> {
+        let buf = match self {
+            FileFormat::Ron => {
+                ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())?
+            }
+            FileFormat::Json => serde_json::to_string_pretty(value)?,
+            FileFormat::Binary => bail!("binary format is only supported for maps"),
+        };
+        Ok(buf)
+    }
+}
+
 /// save file representation of a map tile
-#[derive(Default, Debug, Serialize, Deserialize)]
-struct Tile {
-    location: map::Location,
-    tileset: SaveId,
-    tile_id: tileset::TileId,
-    rotation: tileset::TileRotation,
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Tile {
+    pub location: map::Location,
+    pub tileset: SaveId,
+    pub tile_id: tileset::TileId,
+    pub rotation: tileset::TileRotation,
+    #[serde(default, skip_serializing_if = "tileset::TileOverrides::is_default")]
+    pub overrides: tileset::TileOverrides,
+}
+
+/// save file representation of a tile placed on the edge between two hexes
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct EdgeTile {
+    pub edge: map::Edge,
+    pub tileset: SaveId,
+    pub tile_id: tileset::TileId,
+    pub rotation: tileset::TileRotation,
+}
+
+/// save file representation of a tile placed on the corner of three hexes
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct VertexTile {
+    pub vertex: map::Vertex,
+    pub tileset: SaveId,
+    pub tile_id: tileset::TileId,
+    pub rotation: tileset::TileRotation,
 }
 
 /// save file representation of a tilemap layer
-#[derive(Default, Debug, Serialize, Deserialize)]
-struct Layer {
-    name: String,
-    tiles: Vec<Tile>,
+///
+/// Layers are saved in order, lowest first; `order` is loaded from older maps
+/// as 0, so ties keep the order of the layers in the file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Layer {
+    pub name: String,
+    #[serde(default)]
+    pub order: usize,
+    #[serde(default = "default_visible", skip_serializing_if = "is_visible")]
+    pub visible: bool,
+    #[serde(default, skip_serializing_if = "is_unlocked")]
+    pub locked: bool,
+    #[serde(default = "default_opacity", skip_serializing_if = "is_opaque")]
+    pub opacity: f32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub y_offset: f32,
+    #[serde(default, skip_serializing_if = "is_tile_layer")]
+    pub kind: map::LayerKind,
+    /// only tile layers have tiles
+    pub tiles: Vec<Tile>,
+    /// tiles placed on the edges between hexes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<EdgeTile>,
+    /// tiles placed on the corners of hexes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vertices: Vec<VertexTile>,
+    /// only object layers have objects
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objects: Vec<Object>,
+    /// only annotation layers have annotations
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<annotation::Annotation>,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn is_visible(visible: &bool) -> bool {
+    *visible
+}
+
+fn is_unlocked(locked: &bool) -> bool {
+    !*locked
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+fn is_opaque(opacity: &f32) -> bool {
+    *opacity >= 1.0
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+fn is_tile_layer(kind: &map::LayerKind) -> bool {
+    *kind == map::LayerKind::Tile
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            order: 0,
+            visible: true,
+            locked: false,
+            opacity: 1.0,
+            y_offset: 0.0,
+            kind: map::LayerKind::Tile,
+            tiles: Vec::new(),
+            edges: Vec::new(),
+            vertices: Vec::new(),
+            objects: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
 }
 
 impl From<&map::Layer> for Layer {
     fn from(value: &map::Layer) -> Self {
         Self {
             name: value.name.clone(),
+            order: value.order,
+            visible: value.visible,
+            locked: value.locked,
+            opacity: value.opacity,
+            y_offset: value.y_offset,
+            kind: value.kind,
             tiles: Vec::new(),
+            edges: Vec::new(),
+            vertices: Vec::new(),
+            objects: Vec::new(),
+            annotations: Vec::new(),
         }
     }
 }
@@ -178,26 +335,185 @@ impl From<&Layer> for map::Layer {
         Self {
             name: value.name.clone(),
             tiles: HashMap::new(),
+            order: value.order,
+            visible: value.visible,
+            locked: value.locked,
+            opacity: value.opacity.clamp(0.0, 1.0),
+            y_offset: value.y_offset,
+            kind: value.kind,
+        }
+    }
+}
+
+/// save file representation of an `object::MapObject`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Object {
+    /// referenced by the `Property::Object` values of other objects
+    pub id: SaveId,
+    pub name: String,
+    pub kind: String,
+    pub position: object::ObjectPosition,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, object::Property<SaveId>>,
+}
+
+impl Object {
+    /// convert a MapObject; references to objects missing from `ids` are
+    /// dropped
+    fn new(id: SaveId, value: &object::MapObject, ids: &HashMap<Entity, SaveId>) -> Self {
+        Self {
+            id,
+            name: value.name.clone(),
+            kind: value.kind.clone(),
+            position: value.position,
+            model: value.model.clone(),
+            icon: value.icon.clone(),
+            properties: map_properties(&value.name, &value.properties, |entity| {
+                ids.get(entity).copied()
+            }),
+        }
+    }
+
+    /// create the MapObject for this object, using `entities` to resolve the
+    /// references to other objects
+    fn to_component(&self, entities: &HashMap<SaveId, Entity>) -> object::MapObject {
+        object::MapObject {
+            name: self.name.clone(),
+            kind: self.kind.clone(),
+            position: self.position,
+            model: self.model.clone(),
+            icon: self.icon.clone(),
+            properties: map_properties(&self.name, &self.properties, |id| {
+                entities.get(id).copied()
+            }),
         }
     }
 }
 
-const MAP_FORMAT_VERSION: usize = 1;
+/// save file representation of a `region::Region`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub color: Color,
+    /// sorted to keep the save file stable
+    pub locations: Vec<map::Location>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, object::Property<SaveId>>,
+}
+
+impl Region {
+    /// convert a Region; references to objects missing from `ids` are dropped
+    fn new(value: &region::Region, ids: &HashMap<Entity, SaveId>) -> Self {
+        let mut locations: Vec<map::Location> = value.locations.iter().copied().collect();
+        locations.sort_by_key(|l| (l.y, l.x));
+        Self {
+            name: value.name.clone(),
+            color: value.color,
+            locations,
+            properties: map_properties(&value.name, &value.properties, |entity| {
+                ids.get(entity).copied()
+            }),
+        }
+    }
+
+    /// create the Region component, using `entities` to resolve the
+    /// references to objects
+    fn to_component(&self, entities: &HashMap<SaveId, Entity>) -> region::Region {
+        region::Region {
+            name: self.name.clone(),
+            color: self.color,
+            locations: self.locations.iter().copied().collect(),
+            properties: map_properties(&self.name, &self.properties, |id| {
+                entities.get(id).copied()
+            }),
+        }
+    }
+}
+
+/// convert the object references in `properties`; properties referencing
+/// objects that `f` cannot convert are dropped
+fn map_properties<R, T>(
+    owner: &str,
+    properties: &BTreeMap<String, object::Property<R>>,
+    f: impl Fn(&R) -> Option<T>,
+) -> BTreeMap<String, object::Property<T>> {
+    properties
+        .iter()
+        .filter_map(|(key, property)| {
+            let property = property.map_ref(&f);
+            if property.is_none() {
+                warn!("{}: dropping reference to unknown object in {}", owner, key);
+            }
+            Some((key.clone(), property?))
+        })
+        .collect()
+}
+
+pub const MAP_FORMAT_VERSION: usize = 1;
+
+/// save file representation of a `map::Map`
 #[derive(Default, Debug, Serialize, Deserialize)]
-struct MapFormat {
-    version: usize,
-    layout: HexLayout,
-    tilesets: BTreeMap<SaveId, tileset::Tileset>, // btree map for enforced order
-    layers: Vec<Layer>,
+pub struct MapFormat {
+    pub version: usize,
+    pub layout: HexLayout,
+    pub tilesets: BTreeMap<SaveId, tileset::Tileset>, // btree map for enforced order
+    pub layers: Vec<Layer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<Region>,
 
     // just used during construction, do not save
     #[serde(skip)]
     entity_map: HashMap<Entity, SaveId>,
+    #[serde(skip)]
+    object_map: HashMap<Entity, SaveId>,
 }
 
 impl MapFormat {
+    /// create an empty MapFormat using the provided layout
+    pub fn new(layout: HexLayout) -> Self {
+        Self {
+            version: MAP_FORMAT_VERSION,
+            layout,
+            ..default()
+        }
+    }
+
+    /// load a MapFormat from a RON, JSON, or binary file
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = FileFormat::from_path(path);
+        if format == FileFormat::Binary {
+            let f = File::open(path).context("failed to open file")?;
+            return binary::read(BufReader::new(f)).context("failed to parse map");
+        }
+
+        let buf = std::fs::read_to_string(path).context("failed to read file")?;
+        let map = format.deserialize(&buf).context("failed to parse map")?;
+        Ok(map)
+    }
+
+    /// save the MapFormat to a file; the format is determined by the extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = FileFormat::from_path(path);
+        if format == FileFormat::Binary {
+            let f = File::create(path).context(format!("open map {:?}", path))?;
+            let mut writer = BufWriter::new(f);
+            binary::write(self, &mut writer, binary::Compression::default())
+                .context(format!("writing map to {:?}", path))?;
+            writer.flush()?;
+            return Ok(());
+        }
+
+        let buf = format.serialize(self)?;
+        std::fs::write(path, buf).context(format!("writing map to {:?}", path))?;
+        Ok(())
+    }
+
     /// build a MapFormat struct from the World and the root Map entity
-    fn try_new(world: &mut World, root: Entity) -> Result<Self> {
+    pub fn try_new(world: &mut World, root: Entity) -> Result<Self> {
         let mut map = Self {
             version: MAP_FORMAT_VERSION,
             ..default()
@@ -212,7 +528,9 @@ impl MapFormat {
             .layout
             .clone();
 
-        map.add_tilesets(world, root)?.add_layers(world, root)?;
+        map.add_tilesets(world, root)?
+            .add_layers(world, root)?
+            .add_regions(world, root);
         Ok(map)
     }
 
@@ -243,18 +561,68 @@ impl MapFormat {
         Ok(self)
     }
 
-    fn add_layers(&mut self, world: &mut World, root: Entity) -> Result<&mut Self> {
-        let mut query = world.query::<(&map::Layer, &Parent, &Children)>();
-        let mut tiles =
-            world.query::<(&map::Location, &tileset::TileRef, &tileset::TileTransform)>();
+    fn add_layers(&mut self, mut world: &mut World, root: Entity) -> Result<&mut Self> {
+        // objects may reference objects in any layer, so assign every object a
+        // SaveId before saving any of them
+        let mut objects = world.query::<(Entity, &object::MapObject, &Parent)>();
+        let mut parents = world.query::<&Parent>();
+        let object_entities: Vec<Entity> = objects
+            .iter(world)
+            .filter(|(_, _, layer)| {
+                matches!(parents.get(world, layer.get()), Ok(map) if map.get() == root)
+            })
+            .map(|(entity, _, _)| entity)
+            .collect();
+        self.object_map = world.assign_save_ids(object_entities.into_iter())?;
+        let object_ids = &self.object_map;
+
+        // empty layers have no Children
+        let mut query = world.query::<(&map::Layer, &Parent, Option<&Children>)>();
+        let mut tiles = world.query::<(
+            &map::Location,
+            &tileset::TileRef,
+            &tileset::TileTransform,
+            Option<&tileset::TileOverrides>,
+        )>();
+        let mut edges = world.query::<(&map::Edge, &tileset::TileRef, &tileset::TileTransform)>();
+        let mut vertices =
+            world.query::<(&map::Vertex, &tileset::TileRef, &tileset::TileTransform)>();
+        let mut annotations = world.query::<&annotation::Annotation>();
         for (layer, parent, children) in query.iter(world) {
             if parent.get() != root {
                 continue;
             }
             let mut layer: Layer = layer.into();
+            if layer.kind == map::LayerKind::Object {
+                for child in children.into_iter().flatten() {
+                    let Ok((entity, object, _)) = objects.get(world, *child) else { continue };
+                    let id = object_ids[&entity];
+                    layer.objects.push(Object::new(id, object, object_ids));
+                }
+                layer.objects.sort_by_key(|object| object.id);
+            }
+            if layer.kind == map::LayerKind::Annotation {
+                for child in children.into_iter().flatten() {
+                    let Ok(annotation) = annotations.get(world, *child) else { continue };
+                    layer.annotations.push(annotation.clone());
+                }
+                // annotations have no other order, so sort them for a stable
+                // save file
+                layer.annotations.sort_by(|a, b| {
+                    let key = |a: &annotation::Annotation| (a.location.y, a.location.x);
+                    key(a).cmp(&key(b)).then_with(|| a.text.cmp(&b.text))
+                });
+            }
+            if layer.kind != map::LayerKind::Tile {
+                self.layers.push(layer);
+                continue;
+            }
 
-            for child in children {
-                let Ok((location, tile_ref, tile_transform)) = tiles.get(world, *child) else { continue; };
+            for child in children.into_iter().flatten() {
+                let Ok((location, tile_ref, tile_transform, overrides)) = tiles.get(world, *child)
+                else {
+                    continue;
+                };
                 let tileset = self
                     .entity_map
                     .get(&tile_ref.tileset)
@@ -266,14 +634,122 @@ impl MapFormat {
                     tileset: *tileset,
                     tile_id: tile_ref.tile,
                     rotation: tile_transform.rotation,
+                    overrides: overrides.copied().unwrap_or_default(),
                 };
                 layer.tiles.push(tile);
             }
+            for child in children.into_iter().flatten() {
+                let Ok((edge, tile_ref, tile_transform)) = edges.get(world, *child) else {
+                    continue;
+                };
+                let tileset = self
+                    .entity_map
+                    .get(&tile_ref.tileset)
+                    .context(format!("tileset SaveId not found: {:?}", tile_ref))?;
+                layer.edges.push(EdgeTile {
+                    edge: *edge,
+                    tileset: *tileset,
+                    tile_id: tile_ref.tile,
+                    rotation: tile_transform.rotation,
+                });
+            }
+            for child in children.into_iter().flatten() {
+                let Ok((vertex, tile_ref, tile_transform)) = vertices.get(world, *child) else {
+                    continue;
+                };
+                let tileset = self
+                    .entity_map
+                    .get(&tile_ref.tileset)
+                    .context(format!("tileset SaveId not found: {:?}", tile_ref))?;
+                layer.vertices.push(VertexTile {
+                    vertex: *vertex,
+                    tileset: *tileset,
+                    tile_id: tile_ref.tile,
+                    rotation: tile_transform.rotation,
+                });
+            }
             self.layers.push(layer);
         }
+        self.layers.sort_by_key(|layer| layer.order);
         Ok(self)
     }
 
+    fn add_regions(&mut self, world: &mut World, root: Entity) -> &mut Self {
+        let mut query = world.query::<(&region::Region, &Parent)>();
+        for (region, parent) in query.iter(world) {
+            if parent.get() == root {
+                self.regions.push(Region::new(region, &self.object_map));
+            }
+        }
+        // regions have no other order, so sort them for a stable save file
+        self.regions.sort_by(|a, b| a.name.cmp(&b.name));
+        self
+    }
+
+    /// verify every tile references a tileset in the map
+    fn check_tilesets(&self) -> Result<()> {
+        for layer in &self.layers {
+            for tile in &layer.tiles {
+                if !self.tilesets.contains_key(&tile.tileset) {
+                    bail!(
+                        "unknown tileset for tile {:?} in layer {}",
+                        tile,
+                        layer.name
+                    );
+                }
+            }
+            let edges = layer.edges.iter().map(|t| t.tileset);
+            let vertices = layer.vertices.iter().map(|t| t.tileset);
+            for tileset in edges.chain(vertices) {
+                if !self.tilesets.contains_key(&tileset) {
+                    bail!(
+                        "unknown tileset {:?} for edge or vertex tile in layer {}",
+                        tileset,
+                        layer.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// verify every object has a unique id
+    fn check_object_ids(&self) -> Result<()> {
+        let mut ids = std::collections::HashSet::new();
+        for object in self.layers.iter().flat_map(|layer| &layer.objects) {
+            if !ids.insert(object.id) {
+                bail!("duplicate object id: {:?}", object.id);
+            }
+        }
+        Ok(())
+    }
+
+    /// number of tiles in `layer` covering a hex already covered by an earlier
+    /// tile in the layer, including the footprints of multi-hex tiles
+    pub fn overlapping_tiles(&self, layer: &Layer) -> usize {
+        let mut covered = std::collections::HashSet::new();
+        layer
+            .tiles
+            .iter()
+            .filter(|tile| {
+                let footprint = match self.tilesets.get(&tile.tileset) {
+                    Some(tileset) => tileset.footprint(tile.tile_id, tile.location, tile.rotation),
+                    None => vec![tile.location],
+                };
+                // insert every hex of the footprint before checking
+                let added: Vec<bool> = footprint.into_iter().map(|h| covered.insert(h)).collect();
+                added.contains(&false)
+            })
+            .count()
+    }
+
+    /// layers sorted by `Layer::order`, keeping the file order for ties
+    pub fn ordered_layers(&self) -> impl Iterator<Item = &Layer> {
+        let mut layers: Vec<&Layer> = self.layers.iter().collect();
+        layers.sort_by_key(|layer| layer.order);
+        layers.into_iter()
+    }
+
     pub fn try_spawn(&self, root: &mut EntityCommands) -> Result<()> {
         if self.version != MAP_FORMAT_VERSION {
             bail!(
@@ -282,6 +758,8 @@ impl MapFormat {
                 MAP_FORMAT_VERSION
             );
         }
+        self.check_tilesets()?;
+        self.check_object_ids()?;
         debug!("loading map into {:?}", root.id());
 
         let map = map::Map {
@@ -293,15 +771,23 @@ impl MapFormat {
         for (id, tileset) in &self.tilesets {
             let entity = root
                 .commands()
-                .spawn((Name::new("tileset"), tileset.clone()))
+                .spawn((Name::new("tileset"), tileset.clone(), *id))
                 .id();
             root.add_child(entity);
             entity_map.insert(id, entity);
         }
 
-        // restore layers
-        for layer in &self.layers {
-            let layer_component: map::Layer = layer.into();
+        // objects may reference objects in any layer, so reserve the entities
+        // for every object first
+        let mut objects = HashMap::new();
+        for object in self.layers.iter().flat_map(|layer| &layer.objects) {
+            objects.insert(object.id, root.commands().spawn_empty().id());
+        }
+
+        // restore layers in order; the order is renumbered to remove any ties
+        for (order, layer) in self.ordered_layers().enumerate() {
+            let mut layer_component: map::Layer = layer.into();
+            layer_component.order = order;
             let layer_entity = root
                 .commands()
                 .spawn((
@@ -312,17 +798,61 @@ impl MapFormat {
                 .id();
             root.add_child(layer_entity);
 
+            let overlaps = self.overlapping_tiles(layer);
+            if overlaps > 0 {
+                warn!(
+                    "layer {:?}: {} tiles overlap other tiles",
+                    layer.name, overlaps
+                );
+            }
+
             let mut tiles = Vec::new();
 
             for tile in &layer.tiles {
                 let tile_ref = tileset::TileRef {
-                    tileset: *entity_map.get(&tile.tileset).unwrap(),
+                    tileset: entity_map[&tile.tileset],
+                    tile: tile.tile_id,
+                };
+                let mut tile_entity = root.commands().spawn((
+                    tile.location,
+                    tile_ref,
+                    tileset::TileTransform {
+                        rotation: tile.rotation,
+                    },
+                    SpatialBundle::default(),
+                ));
+                if !tile.overrides.is_default() {
+                    tile_entity.insert(tile.overrides);
+                }
+                tiles.push(tile_entity.id());
+            }
+            for tile in &layer.edges {
+                let tile_ref = tileset::TileRef {
+                    tileset: entity_map[&tile.tileset],
+                    tile: tile.tile_id,
+                };
+                let tile_entity = root
+                    .commands()
+                    .spawn((
+                        tile.edge,
+                        tile_ref,
+                        tileset::TileTransform {
+                            rotation: tile.rotation,
+                        },
+                        SpatialBundle::default(),
+                    ))
+                    .id();
+                tiles.push(tile_entity);
+            }
+            for tile in &layer.vertices {
+                let tile_ref = tileset::TileRef {
+                    tileset: entity_map[&tile.tileset],
                     tile: tile.tile_id,
                 };
                 let tile_entity = root
                     .commands()
                     .spawn((
-                        tile.location,
+                        tile.vertex,
                         tile_ref,
                         tileset::TileTransform {
                             rotation: tile.rotation,
@@ -332,9 +862,42 @@ impl MapFormat {
                     .id();
                 tiles.push(tile_entity);
             }
+            for object in &layer.objects {
+                let entity = objects[&object.id];
+                root.commands().entity(entity).insert((
+                    Name::new(format!("object: {}", object.name)),
+                    object.to_component(&objects),
+                    object.id,
+                    SpatialBundle::default(),
+                ));
+                tiles.push(entity);
+            }
+            for annotation in &layer.annotations {
+                let entity = root
+                    .commands()
+                    .spawn((
+                        Name::new("annotation"),
+                        annotation.clone(),
+                        SpatialBundle::default(),
+                    ))
+                    .id();
+                tiles.push(entity);
+            }
             root.commands().entity(layer_entity).push_children(&tiles);
         }
 
+        for region in &self.regions {
+            let entity = root
+                .commands()
+                .spawn((
+                    Name::new(format!("region: {}", region.name)),
+                    region.to_component(&objects),
+                    SpatialBundle::default(),
+                ))
+                .id();
+            root.add_child(entity);
+        }
+
         root.insert((SpatialBundle::default(), map));
         Ok(())
     }
@@ -349,11 +912,26 @@ pub struct SaveMapCommand {
     path: std::path::PathBuf,
     /// root entity of map; has `map::Map` component
     map: Entity,
+    /// function used to write the MapFormat to path within the IoTaskPool
+    writer: Box<dyn FnOnce(&Path, &MapFormat) -> Result<()> + Send>,
 }
 
 impl SaveMapCommand {
     pub fn new(path: std::path::PathBuf, map: Entity) -> Self {
-        Self { path, map }
+        Self::with_writer(path, map, |path, map| map.save(path))
+    }
+
+    /// create a command that uses `writer` to write the MapFormat for `map`
+    /// to `path`.  Used by exporters that write the map in other formats.
+    pub fn with_writer<F>(path: std::path::PathBuf, map: Entity, writer: F) -> Self
+    where
+        F: FnOnce(&Path, &MapFormat) -> Result<()> + Send + 'static,
+    {
+        Self {
+            path,
+            map,
+            writer: Box::new(writer),
+        }
     }
 }
 
@@ -368,26 +946,34 @@ impl Command for SaveMapCommand {
         };
 
         let task_pool = IoTaskPool::get();
-        let task = task_pool.spawn(async move {
-            let f = File::create(self.path.clone()).context(format!("open map {:?}", self.path))?;
-            to_writer_pretty(f, &map, PrettyConfig::default())
-                .context(format!("writing map to {:?}", self.path))?;
-            Ok::<(), anyhow::Error>(())
-        });
-        world.spawn(MapWriterTask(task));
+        let writer = self.writer;
+        let path = self.path;
+        let path_copy = path.clone();
+        let task = task_pool.spawn(async move { writer(&path, &map) });
+        world.spawn(MapWriterTask(path_copy, task));
     }
 }
 
 /// This component is used to track the IoTask that is writing the map to the
 /// disk.
 #[derive(Component)]
-struct MapWriterTask(Task<Result<()>>);
+struct MapWriterTask(PathBuf, Task<Result<()>>);
 
-fn map_writers(mut commands: Commands, mut map_writers: Query<(Entity, &mut MapWriterTask)>) {
+fn map_writers(
+    mut commands: Commands,
+    mut map_writers: Query<(Entity, &mut MapWriterTask)>,
+    mut watcher: Option<ResMut<crate::watch::FileWatcher>>,
+) {
     for (entity, mut writer) in &mut map_writers {
-        let Some(result) = future::block_on(future::poll_once(&mut writer.0)) else { continue };
-        if let Err(e) = result {
-            warn!("{:#?}", e);
+        let Some(result) = future::block_on(future::poll_once(&mut writer.1)) else { continue };
+        match result {
+            Err(e) => warn!("{:#?}", e),
+            // our own changes to the file aren't reported by the watcher
+            Ok(()) => {
+                if let Some(watcher) = &mut watcher {
+                    watcher.touch(&writer.0);
+                }
+            }
         }
         commands.entity(entity).despawn();
     }
@@ -396,18 +982,25 @@ fn map_writers(mut commands: Commands, mut map_writers: Query<(Entity, &mut MapW
 #[derive(Component)]
 pub struct MapImporter {
     path: PathBuf,
-    task: Task<Result<MapFormat>>,
+    task: Task<Result<(MapFormat, Vec<String>)>>,
 }
 
 impl MapImporter {
     pub fn new(path: PathBuf) -> Self {
+        Self::with_loader(path, |path| Ok((MapFormat::load(&path)?, Vec::new())))
+    }
+
+    /// create an importer that uses `loader` to build the MapFormat from
+    /// `path` within the IoTaskPool.  The resulting map is spawned the same
+    /// way as a map loaded by `MapImporter::new()`, and any warnings returned
+    /// by the loader are added to the map root as `ImportWarnings`.
+    pub fn with_loader<F>(path: PathBuf, loader: F) -> Self
+    where
+        F: FnOnce(PathBuf) -> Result<(MapFormat, Vec<String>)> + Send + 'static,
+    {
         let path_copy = path.clone();
         let task_pool = IoTaskPool::get();
-        let task = task_pool.spawn(async move {
-            let buf = std::fs::read_to_string(path).context("failed to read file")?;
-            let map = ron::from_str(&buf).context("failed to parse map")?;
-            Ok(map)
-        });
+        let task = task_pool.spawn(async move { loader(path) });
 
         Self {
             path: path_copy,
@@ -416,6 +1009,11 @@ impl MapImporter {
     }
 }
 
+/// problems found while importing a map that did not stop the import, such as
+/// cells of a Tiled map without a tile; added to the map root
+#[derive(Component, Debug, Default, Clone)]
+pub struct ImportWarnings(pub Vec<String>);
+
 fn map_importer(mut commands: Commands, mut map_importers: Query<(Entity, &mut MapImporter)>) {
     for (entity, mut importer) in &mut map_importers {
         let Some(result) = future::block_on(future::poll_once(&mut importer.task)) else { continue };
@@ -428,7 +1026,7 @@ fn map_importer(mut commands: Commands, mut map_importers: Query<(Entity, &mut M
                 );
                 commands.entity(entity).despawn();
             }
-            Ok(map) => {
+            Ok((map, warnings)) => {
                 let name = importer.path.file_stem().unwrap().to_string_lossy();
                 let mut entity_ref = commands.entity(entity);
 
@@ -448,15 +1046,355 @@ fn map_importer(mut commands: Commands, mut map_importers: Query<(Entity, &mut M
                 entity_ref
                     .remove::<MapImporter>()
                     .insert(Name::new(format!("map: {}", name)));
+                if !warnings.is_empty() {
+                    entity_ref.insert(ImportWarnings(warnings));
+                }
+            }
+        };
+    }
+}
+
+/// spawn `tileset` as a child of the map root; only the tilesets of the map
+/// root are saved with the map
+pub fn spawn_tileset(world: &mut World, map: Entity, tileset: tileset::Tileset) -> Entity {
+    let entity = world.spawn((Name::new("tileset"), tileset)).id();
+    world.entity_mut(map).add_child(entity);
+    entity
+}
+
+/// add tilesets from a save file to a map, returning the entity each `SaveId`
+/// now refers to
+///
+/// Tilesets identical to a tileset already in the map are reused; all others
+/// are spawned as children of the map with new `SaveId`s.
+pub fn merge_tilesets(
+    mut world: &mut World,
+    map: Entity,
+    tilesets: &BTreeMap<SaveId, tileset::Tileset>,
+) -> Result<HashMap<SaveId, Entity>> {
+    // serialized tilesets already in the map; used to find duplicates
+    let mut query = world.query::<(Entity, &Parent, &tileset::Tileset)>();
+    let mut existing = Vec::new();
+    for (entity, parent, tileset) in query.iter(world) {
+        if parent.get() == map {
+            existing.push((entity, ron::to_string(tileset)?));
+        }
+    }
+
+    let mut next_id = world.save_id_next();
+    let mut entity_map = HashMap::new();
+    for (id, tileset) in tilesets {
+        let serialized = ron::to_string(tileset)?;
+        let entity = match existing.iter().find(|(_, s)| *s == serialized) {
+            Some((entity, _)) => *entity,
+            None => {
+                let entity = spawn_tileset(world, map, tileset.clone());
+                world.entity_mut(entity).insert(next_id);
+                debug!("added tileset {} as {:?}", tileset.name, next_id);
+                next_id += 1;
+                existing.push((entity, serialized));
+                entity
             }
         };
+        entity_map.insert(*id, entity);
+    }
+    Ok(entity_map)
+}
+
+/// Command used to add the layers from a `MapFormat` to an existing map
+///
+/// Tilesets in the source map that are identical to a tileset already in the
+/// map are reused; all others are added to the map with new `SaveId`s.  The
+/// imported tiles can be rotated around the origin & offset.
+pub struct ImportLayersCommand {
+    /// root entity of the map to add the layers to
+    map: Entity,
+    /// map containing the layers to import
+    source: MapFormat,
+    /// offset applied to each tile location after rotating it
+    offset: map::Location,
+    /// rotation applied to the tile locations & tiles
+    rotation: tileset::TileRotation,
+}
+
+impl ImportLayersCommand {
+    pub fn new(
+        map: Entity,
+        source: MapFormat,
+        offset: map::Location,
+        rotation: tileset::TileRotation,
+    ) -> Self {
+        Self {
+            map,
+            source,
+            offset,
+            rotation,
+        }
+    }
+
+    fn try_write(self, world: &mut World) -> Result<()> {
+        if self.source.version != MAP_FORMAT_VERSION {
+            bail!(
+                "unsupported map version: {} != {}",
+                self.source.version,
+                MAP_FORMAT_VERSION
+            );
+        }
+        let Some(map) = world.get::<map::Map>(self.map) else {
+            bail!("map entity not found: {:?}", self.map);
+        };
+        // tiles are imported by location, so both maps need the same hex size
+        // & orientation to put them in the same place
+        if ron::to_string(&self.source.layout)? != ron::to_string(&map.layout)? {
+            bail!(
+                "hex layout of the imported map {:?} differs from the map {:?}",
+                self.source.layout,
+                map.layout
+            );
+        }
+
+        // verify every tile references a tileset before modifying the World
+        self.source.check_tilesets()?;
+        self.source.check_object_ids()?;
+
+        let tilesets = merge_tilesets(world, self.map, &self.source.tilesets)?;
+
+        // imported objects are assigned new SaveIds when the map is saved, so
+        // reserve their entities to resolve the references between them
+        let layout = map::Map {
+            layout: world.get::<map::Map>(self.map).unwrap().layout.clone(),
+        };
+        let objects: HashMap<SaveId, Entity> = self
+            .source
+            .layers
+            .iter()
+            .flat_map(|layer| &layer.objects)
+            .map(|object| (object.id, world.spawn_empty().id()))
+            .collect();
+
+        // imported layers go above the existing layers
+        let mut query = world.query::<(&map::Layer, &Parent)>();
+        let base_order = query
+            .iter(world)
+            .filter(|(_, parent)| parent.get() == self.map)
+            .map(|(layer, _)| layer.order + 1)
+            .max()
+            .unwrap_or(0);
+
+        // tiles, regions, & the hexes edges & vertices are between are rotated
+        // then offset
+        let transform_location = |location: map::Location| {
+            let location = location.rotate(self.rotation);
+            map::Location {
+                x: location.x + self.offset.x,
+                y: location.y + self.offset.y,
+            }
+        };
+
+        for (index, layer) in self.source.ordered_layers().enumerate() {
+            let overlaps = self.source.overlapping_tiles(layer);
+            if overlaps > 0 {
+                warn!(
+                    "layer {:?}: {} tiles overlap other tiles",
+                    layer.name, overlaps
+                );
+            }
+
+            let mut tiles: Vec<Entity> = layer
+                .tiles
+                .iter()
+                .map(|tile| {
+                    let mut entity = world.spawn((
+                        transform_location(tile.location),
+                        tileset::TileRef {
+                            tileset: tilesets[&tile.tileset],
+                            tile: tile.tile_id,
+                        },
+                        tileset::TileTransform {
+                            rotation: tile.rotation.rotate_by(self.rotation),
+                        },
+                        SpatialBundle::default(),
+                    ));
+                    if !tile.overrides.is_default() {
+                        entity.insert(tile.overrides.rotate(self.rotation));
+                    }
+                    entity.id()
+                })
+                .collect();
+
+            // edges & vertices are moved by moving the hexes they are between
+            for tile in &layer.edges {
+                // edge tiles are oriented along the edge, so they only turn
+                // when the canonical direction of the edge flips
+                let edge = tile.edge.map_hexes(transform_location);
+                let rotation = if edge.location == transform_location(tile.edge.location) {
+                    tile.rotation
+                } else {
+                    tile.rotation.rotate_by(tileset::TileRotation::Clockwise180)
+                };
+                let entity = world
+                    .spawn((
+                        edge,
+                        tileset::TileRef {
+                            tileset: tilesets[&tile.tileset],
+                            tile: tile.tile_id,
+                        },
+                        tileset::TileTransform { rotation },
+                        SpatialBundle::default(),
+                    ))
+                    .id();
+                tiles.push(entity);
+            }
+            for tile in &layer.vertices {
+                let entity = world
+                    .spawn((
+                        tile.vertex.map_hexes(transform_location),
+                        tileset::TileRef {
+                            tileset: tilesets[&tile.tileset],
+                            tile: tile.tile_id,
+                        },
+                        tileset::TileTransform {
+                            rotation: tile.rotation.rotate_by(self.rotation),
+                        },
+                        SpatialBundle::default(),
+                    ))
+                    .id();
+                tiles.push(entity);
+            }
+
+            for object in &layer.objects {
+                let mut component = object.to_component(&objects);
+                component.position = match component.position {
+                    object::ObjectPosition::Hex(location) => {
+                        object::ObjectPosition::Hex(transform_location(location))
+                    }
+                    object::ObjectPosition::Free(position) => {
+                        let angle: f32 = self.rotation.into();
+                        let position = Quat::from_rotation_y(angle) * position;
+                        object::ObjectPosition::Free(position + layout.translation(self.offset))
+                    }
+                };
+                let entity = objects[&object.id];
+                world.entity_mut(entity).insert((
+                    Name::new(format!("object: {}", object.name)),
+                    component,
+                    SpatialBundle::default(),
+                ));
+                tiles.push(entity);
+            }
+            for annotation in &layer.annotations {
+                let annotation = annotation::Annotation {
+                    location: transform_location(annotation.location),
+                    ..annotation.clone()
+                };
+                let entity = world
+                    .spawn((
+                        Name::new("annotation"),
+                        annotation,
+                        SpatialBundle::default(),
+                    ))
+                    .id();
+                tiles.push(entity);
+            }
+
+            let mut layer_component: map::Layer = layer.into();
+            layer_component.order = base_order + index;
+            let layer_entity = world
+                .spawn((
+                    Name::new("layer"),
+                    layer_component,
+                    SpatialBundle::default(),
+                ))
+                .push_children(&tiles)
+                .id();
+            world.entity_mut(self.map).add_child(layer_entity);
+        }
+
+        for region in &self.source.regions {
+            let mut component = region.to_component(&objects);
+            component.locations = component
+                .locations
+                .into_iter()
+                .map(transform_location)
+                .collect();
+            let entity = world
+                .spawn((
+                    Name::new(format!("region: {}", region.name)),
+                    component,
+                    SpatialBundle::default(),
+                ))
+                .id();
+            world.entity_mut(self.map).add_child(entity);
+        }
+
+        Ok(())
+    }
+}
+
+impl Command for ImportLayersCommand {
+    fn write(self, world: &mut World) {
+        if let Err(e) = self.try_write(world) {
+            warn!("failed to import layers: {:?}", e);
+        }
+    }
+}
+
+/// Component used to load a map in the IoTaskPool, and import its layers into
+/// an existing map using `ImportLayersCommand`.
+#[derive(Component)]
+pub struct LayerImporter {
+    path: PathBuf,
+    map: Entity,
+    offset: map::Location,
+    rotation: tileset::TileRotation,
+    task: Task<Result<MapFormat>>,
+}
+
+impl LayerImporter {
+    pub fn new(
+        path: PathBuf,
+        map: Entity,
+        offset: map::Location,
+        rotation: tileset::TileRotation,
+    ) -> Self {
+        let path_copy = path.clone();
+        let task_pool = IoTaskPool::get();
+        let task = task_pool.spawn(async move { MapFormat::load(&path) });
+
+        Self {
+            path: path_copy,
+            map,
+            offset,
+            rotation,
+            task,
+        }
+    }
+}
+
+fn layer_importer(mut commands: Commands, mut importers: Query<(Entity, &mut LayerImporter)>) {
+    for (entity, mut importer) in &mut importers {
+        let Some(result) = future::block_on(future::poll_once(&mut importer.task)) else { continue };
+        match result {
+            Err(e) => warn!(
+                "failed to load map {}: {:?}",
+                importer.path.to_string_lossy(),
+                e
+            ),
+            Ok(source) => commands.add(ImportLayersCommand::new(
+                importer.map,
+                source,
+                importer.offset,
+                importer.rotation,
+            )),
+        }
+        commands.entity(entity).despawn();
     }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use map::*;
+    use super::*;
     use test_log::test;
 
     fn spawn_map(world: &mut World) -> Entity {
@@ -537,6 +1475,35 @@ mod tests {
         assert_eq!(map_format.layers.len(), 0);
     }
 
+    #[test]
+    fn map_format_folder_tileset() {
+        let assets =
+            std::env::temp_dir().join(format!("hex_sandbox_folder_map_{}", std::process::id()));
+        let dir = assets.join("models");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("rock.glb"), "").unwrap();
+
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut tileset = tileset::Tileset::new("models");
+        let scan = tileset.scan_folder(&dir, &assets, default()).unwrap();
+        let tileset = spawn_tileset(&mut world, root, tileset);
+        let mut query = world.query_filtered::<Entity, With<map::Layer>>();
+        let layer = query.single(&world);
+        world.entity_mut(layer).with_children(|layer| {
+            spawn_tile(layer, tileset, scan.added[0], 5, 5, default());
+        });
+
+        // the tileset is saved with the map, so the tile can reference it
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(map.tilesets.len(), 3);
+        let id = world.get::<SaveId>(tileset).unwrap();
+        assert_eq!(map.tilesets[id].name, "models");
+        assert!(map.layers[0].tiles.iter().any(|t| t.tileset == *id));
+
+        std::fs::remove_dir_all(&assets).unwrap();
+    }
+
     #[test]
     fn save_id_serde() {
         let id = SaveId(231);
@@ -545,4 +1512,634 @@ mod tests {
         let value = ron::from_str::<SaveId>(&str).expect("deserialize successfully");
         assert_eq!(value, id);
     }
+
+    fn test_tileset() -> tileset::Tileset {
+        let mut tileset = tileset::Tileset::new("tileset \"a\"");
+        tileset.add_tile("grass.glb".into());
+        tileset.add_tile("water.glb".into());
+        let tile = tileset.tiles.get_mut(&1).unwrap();
+        tile.transform = Transform::from_xyz(0.0, 0.25, 0.0).with_scale(Vec3::splat(0.5));
+        tile.color = Some(Color::rgb(0.0, 0.2, 0.8));
+        tileset
+    }
+
+    fn test_map_format() -> MapFormat {
+        let mut map = MapFormat::new(HexLayout::default());
+        map.tilesets.insert(SaveId(0), test_tileset());
+        map.tilesets
+            .insert(SaveId(7), tileset::Tileset::new("empty"));
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles: vec![
+                Tile::default(),
+                Tile {
+                    location: (-3, 2).into(),
+                    tileset: SaveId(0),
+                    tile_id: 1,
+                    rotation: tileset::TileRotation::CounterClockwise60,
+                    overrides: tileset::TileOverrides {
+                        offset: Vec2::new(0.25, -0.1),
+                        y_offset: 0.5,
+                        ..Default::default()
+                    },
+                },
+            ],
+            ..Default::default()
+        });
+        map.layers.push(Layer {
+            name: "empty".into(),
+            tiles: Vec::new(),
+            ..Default::default()
+        });
+        map
+    }
+
+    #[test]
+    fn file_format_from_path() {
+        assert_eq!(FileFormat::from_path(Path::new("map.ron")), FileFormat::Ron);
+        assert_eq!(FileFormat::from_path(Path::new("map")), FileFormat::Ron);
+        assert_eq!(
+            FileFormat::from_path(Path::new("a.tileset.json")),
+            FileFormat::Json
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("MAP.JSON")),
+            FileFormat::Json
+        );
+        assert_eq!(
+            FileFormat::from_path(Path::new("big.hexmap")),
+            FileFormat::Binary
+        );
+    }
+
+    #[test]
+    fn map_format_ron_json_round_trip() {
+        let ron = FileFormat::Ron.serialize(&test_map_format()).unwrap();
+        let map: MapFormat = FileFormat::Ron.deserialize(&ron).unwrap();
+        let json = FileFormat::Json.serialize(&map).unwrap();
+        debug!("{}", json);
+        let map: MapFormat = FileFormat::Json.deserialize(&json).unwrap();
+        assert_eq!(FileFormat::Ron.serialize(&map).unwrap(), ron);
+        assert_eq!(FileFormat::Json.serialize(&map).unwrap(), json);
+    }
+
+    #[test]
+    fn layer_properties() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let top = world
+            .spawn(crate::map::Layer {
+                name: "top".into(),
+                order: 3,
+                visible: false,
+                locked: true,
+                opacity: 0.5,
+                y_offset: 2.0,
+                kind: map::LayerKind::Object,
+                ..default()
+            })
+            .id();
+        world.entity_mut(root).add_child(top);
+
+        // layers are saved lowest order first, regardless of Children order
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        let names: Vec<&str> = map.layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["layer 0", "top"]);
+
+        let ron = FileFormat::Ron.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Ron.deserialize(&ron).unwrap();
+        let top = &map.layers[1];
+        assert_eq!(top.order, 3);
+        assert!(!top.visible);
+        assert!(top.locked);
+        assert_eq!(top.opacity, 0.5);
+        assert_eq!(top.y_offset, 2.0);
+        assert_eq!(top.kind, map::LayerKind::Object);
+
+        // default properties are not saved, and older maps load with defaults
+        let ground = ron::to_string(&map.layers[0]).unwrap();
+        assert!(!ground.contains("visible"));
+        assert!(!ground.contains("opacity"));
+        assert!(!ground.contains("y_offset"));
+        assert!(!ground.contains("kind"));
+        let layer: Layer = ron::from_str(r#"(name: "old", tiles: [])"#).unwrap();
+        assert_eq!(layer.order, 0);
+        assert!(layer.visible);
+        assert!(!layer.locked);
+        assert_eq!(layer.opacity, 1.0);
+        assert_eq!(layer.y_offset, 0.0);
+        assert_eq!(layer.kind, map::LayerKind::Tile);
+    }
+
+    #[test]
+    fn object_references() {
+        use object::{MapObject, ObjectPosition, Property};
+
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let key = world
+            .spawn((
+                MapObject::new("key", ObjectPosition::Free(Vec3::new(0.5, 0.0, 1.0))),
+                SaveId(7),
+            ))
+            .id();
+        let mut door = MapObject::new("door", ObjectPosition::Hex((1, 2).into()));
+        door.properties.insert("key".into(), Property::Object(key));
+        door.properties
+            .insert("locked".into(), Property::Bool(true));
+        let door = world.spawn(door).id();
+        let layer = world
+            .spawn(map::Layer {
+                name: "objects".into(),
+                order: 1,
+                kind: map::LayerKind::Object,
+                ..default()
+            })
+            .push_children(&[door, key])
+            .id();
+        world.entity_mut(root).add_child(layer);
+
+        // existing ids are kept, and references use the SaveId of the object
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        let objects = &map.layers[1].objects;
+        assert_eq!(objects.len(), 2);
+        let door = objects.iter().find(|o| o.kind == "door").unwrap();
+        let key = objects.iter().find(|o| o.kind == "key").unwrap();
+        assert_eq!(key.id, SaveId(7));
+        assert_ne!(door.id, key.id);
+        assert_eq!(door.properties["key"], Property::Object(SaveId(7)));
+
+        // references are restored to the entities of the loaded objects
+        let ron = FileFormat::Ron.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Ron.deserialize(&ron).unwrap();
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let root = {
+            let mut commands = Commands::new(&mut queue, &world);
+            let mut root = commands.spawn_empty();
+            map.try_spawn(&mut root).unwrap();
+            root.id()
+        };
+        queue.apply(&mut world);
+
+        let mut query = world.query::<(Entity, &MapObject, &SaveId)>();
+        let objects: Vec<_> = query.iter(&world).collect();
+        let (door, _, _) = objects.iter().find(|(_, o, _)| o.kind == "door").unwrap();
+        let (key, _, _) = objects.iter().find(|(_, o, _)| o.kind == "key").unwrap();
+        let (door, key) = (*door, *key);
+        let door_object = world.get::<MapObject>(door).unwrap();
+        assert_eq!(door_object.properties["key"], Property::Object(key));
+        assert_eq!(door_object.position, ObjectPosition::Hex((1, 2).into()));
+
+        // saving again produces the same map
+        let saved = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(FileFormat::Ron.serialize(&saved).unwrap(), ron);
+
+        // duplicate ids are rejected
+        let mut map = saved;
+        map.layers[1].objects[1].id = map.layers[1].objects[0].id;
+        let world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        assert!(map.try_spawn(&mut commands.spawn_empty()).is_err());
+    }
+
+    #[test]
+    fn regions() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let chest = world
+            .spawn(object::MapObject::new(
+                "chest",
+                object::ObjectPosition::default(),
+            ))
+            .id();
+        let layer = world
+            .spawn(map::Layer {
+                kind: map::LayerKind::Object,
+                ..default()
+            })
+            .add_child(chest)
+            .id();
+        let mut region = region::Region::new("forest", Color::GREEN);
+        region.locations = [(2, 1).into(), (0, 0).into(), (1, 0).into()].into();
+        region
+            .properties
+            .insert("treasure".into(), object::Property::Object(chest));
+        let region = world.spawn(region).id();
+        world.entity_mut(root).push_children(&[layer, region]);
+
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(map.regions.len(), 1);
+        let saved = &map.regions[0];
+        assert_eq!(
+            saved.locations,
+            vec![(0, 0).into(), (1, 0).into(), (2, 1).into()]
+        );
+        let chest_id = map.layers[1].objects[0].id;
+        assert_eq!(
+            saved.properties["treasure"],
+            object::Property::Object(chest_id)
+        );
+
+        // regions are restored as children of the map root
+        let json = FileFormat::Json.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Json.deserialize(&json).unwrap();
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let root = {
+            let mut commands = Commands::new(&mut queue, &world);
+            let mut root = commands.spawn_empty();
+            map.try_spawn(&mut root).unwrap();
+            root.id()
+        };
+        queue.apply(&mut world);
+
+        let mut query = world.query::<(&region::Region, &Parent)>();
+        let (region, parent) = query.single(&world);
+        assert_eq!(parent.get(), root);
+        assert!(region.contains((2, 1).into()));
+        let object::Property::Object(chest) = region.properties["treasure"] else {
+            panic!("treasure is not an object reference");
+        };
+        assert_eq!(world.get::<object::MapObject>(chest).unwrap().kind, "chest");
+    }
+
+    #[test]
+    fn annotations() {
+        use annotation::Annotation;
+
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let notes = [
+            Annotation::new((3, -1).into(), "bridge goes here"),
+            Annotation {
+                color: Color::RED,
+                ..Annotation::new((0, 2).into(), "needs more trees")
+            },
+        ];
+        let layer = world
+            .spawn(map::Layer {
+                name: "notes".into(),
+                order: 1,
+                kind: map::LayerKind::Annotation,
+                ..default()
+            })
+            .with_children(|layer| {
+                for note in &notes {
+                    layer.spawn(note.clone());
+                }
+            })
+            .id();
+        world.entity_mut(root).add_child(layer);
+
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(map.layers[1].kind, map::LayerKind::Annotation);
+        assert_eq!(map.layers[1].annotations, notes);
+        assert!(map.layers[1].tiles.is_empty());
+
+        let ron = FileFormat::Ron.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Ron.deserialize(&ron).unwrap();
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let root = {
+            let mut commands = Commands::new(&mut queue, &world);
+            let mut root = commands.spawn_empty();
+            map.try_spawn(&mut root).unwrap();
+            root.id()
+        };
+        queue.apply(&mut world);
+
+        let mut query = world.query::<(&Annotation, &Parent)>();
+        let loaded: Vec<_> = query.iter(&world).collect();
+        assert_eq!(loaded.len(), 2);
+        for (annotation, parent) in loaded {
+            assert!(notes.contains(annotation));
+            let layer = world.get::<map::Layer>(parent.get()).unwrap();
+            assert_eq!(layer.kind, map::LayerKind::Annotation);
+        }
+
+        // saving again produces the same map
+        let saved = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(FileFormat::Ron.serialize(&saved).unwrap(), ron);
+    }
+
+    #[test]
+    fn footprint_overlaps() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut map = MapFormat::try_new(&mut world, root).unwrap();
+        assert_eq!(map.overlapping_tiles(&map.layers[0]), 0);
+
+        // the tile at (0, 0) grows to cover the tile at (2, 3)
+        let origin = map.layers[0].tiles.iter().find(|t| t.location.x == 0);
+        let id = origin.unwrap().tileset;
+        let tileset = map.tilesets.get_mut(&id).unwrap();
+        tileset.add_tile("castle.glb".into());
+        tileset.tiles.get_mut(&0).unwrap().footprint = vec![(1, 1).into(), (2, 3).into()];
+
+        let json = FileFormat::Json.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Json.deserialize(&json).unwrap();
+        assert_eq!(map.tilesets[&id].tiles[&0].footprint.len(), 2);
+        assert_eq!(map.overlapping_tiles(&map.layers[0]), 1);
+    }
+
+    #[test]
+    fn edge_and_vertex_tiles() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut query = world.query::<(Entity, &map::Layer)>();
+        let (layer, _) = query.single(&world);
+        let mut query = world.query::<(Entity, &tileset::Tileset)>();
+        let tileset = query.iter(&world).next().unwrap().0;
+        let edge = map::Edge::new((1, 0).into(), (0, 0).into()).unwrap();
+        let vertex = map::Vertex::new((0, 0).into(), (1, 0).into(), (0, 1).into()).unwrap();
+        let tile_ref = tileset::TileRef { tileset, tile: 0 };
+        let rotation = tileset::TileTransform {
+            rotation: tileset::TileRotation::Clockwise60,
+        };
+        let edge_tile = world.spawn((edge, tile_ref, rotation.clone())).id();
+        let vertex_tile = world.spawn((vertex, tile_ref, rotation)).id();
+        world
+            .entity_mut(layer)
+            .push_children(&[edge_tile, vertex_tile]);
+
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        let layer = &map.layers[0];
+        assert_eq!(layer.tiles.len(), 2);
+        assert_eq!(layer.edges.len(), 1);
+        assert_eq!(layer.edges[0].edge, edge);
+        assert_eq!(layer.vertices.len(), 1);
+        assert_eq!(layer.vertices[0].vertex, vertex);
+
+        let json = FileFormat::Json.serialize(&map).unwrap();
+        let map: MapFormat = FileFormat::Json.deserialize(&json).unwrap();
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        {
+            let mut commands = Commands::new(&mut queue, &world);
+            map.try_spawn(&mut commands.spawn_empty()).unwrap();
+        }
+        queue.apply(&mut world);
+        let mut query = world.query::<(&map::Edge, &tileset::TileTransform)>();
+        let (loaded, transform) = query.single(&world);
+        assert_eq!(*loaded, edge);
+        assert_eq!(transform.rotation, tileset::TileRotation::Clockwise60);
+        let mut query = world.query::<&map::Vertex>();
+        assert_eq!(*query.single(&world), vertex);
+
+        // turning the edge around flips its canonical direction, so the tile
+        // is turned around to keep the same orientation along the edge
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        ImportLayersCommand::new(
+            root,
+            map,
+            (0, 0).into(),
+            tileset::TileRotation::Clockwise180,
+        )
+        .write(&mut world);
+        let mut query = world.query::<(&map::Edge, &tileset::TileTransform)>();
+        let (imported, transform) = query.single(&world);
+        assert_eq!(
+            *imported,
+            map::Edge::new((-1, 0).into(), (0, 0).into()).unwrap()
+        );
+        assert_eq!(
+            transform.rotation,
+            tileset::TileRotation::CounterClockwise120
+        );
+        let mut query = world.query::<&map::Vertex>();
+        assert_eq!(
+            query.single(&world).hexes().map(|l| (l.x, l.y)),
+            [(-1, 0), (0, -1), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn unknown_tileset() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut query = world.query::<(Entity, &map::Layer)>();
+        let (layer, _) = query.single(&world);
+        let mut query = world.query::<(Entity, &tileset::Tileset)>();
+        let tileset = query.iter(&world).next().unwrap().0;
+        let edge = map::Edge::new((1, 0).into(), (0, 0).into()).unwrap();
+        let tile_ref = tileset::TileRef { tileset, tile: 0 };
+        let edge_tile = world
+            .spawn((edge, tile_ref, tileset::TileTransform::default()))
+            .id();
+        world.entity_mut(layer).add_child(edge_tile);
+
+        // every kind of tile is checked before anything is spawned
+        for kind in 0..2 {
+            let mut map = MapFormat::try_new(&mut world, root).unwrap();
+            match kind {
+                0 => map.layers[0].tiles[0].tileset = SaveId(99),
+                _ => map.layers[0].edges[0].tileset = SaveId(99),
+            }
+            let mut loaded = World::new();
+            let mut queue = bevy::ecs::system::CommandQueue::default();
+            {
+                let mut commands = Commands::new(&mut queue, &loaded);
+                assert!(map.try_spawn(&mut commands.spawn_empty()).is_err());
+            }
+            queue.apply(&mut loaded);
+            assert_eq!(loaded.query::<&map::Layer>().iter(&loaded).count(), 0);
+        }
+    }
+
+    #[test]
+    fn tileset_ron_json_round_trip() {
+        let ron = FileFormat::Ron.serialize(&test_tileset()).unwrap();
+        let tileset: tileset::Tileset = FileFormat::Ron.deserialize(&ron).unwrap();
+        let json = FileFormat::Json.serialize(&tileset).unwrap();
+        debug!("{}", json);
+        let tileset: tileset::Tileset = FileFormat::Json.deserialize(&json).unwrap();
+        assert_eq!(FileFormat::Ron.serialize(&tileset).unwrap(), ron);
+        assert_eq!(FileFormat::Json.serialize(&tileset).unwrap(), json);
+    }
+
+    #[test]
+    fn tileset_json_key_order() {
+        let json = r#"{ "name": "test", "version": 1, "tiles": [] }"#;
+        assert!(FileFormat::Json
+            .deserialize::<tileset::Tileset>(json)
+            .is_err());
+        let json = r#"{ "version": 1, "name": "test", "tiles": [] }"#;
+        let tileset: tileset::Tileset = FileFormat::Json.deserialize(json).unwrap();
+        assert_eq!(tileset.name, "test");
+    }
+
+    #[test]
+    fn import_layers() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        world.spawn(SaveId(4));
+
+        let mut source = MapFormat::new(HexLayout::default());
+        source
+            .tilesets
+            .insert(SaveId(0), tileset::Tileset::new("tileset a"));
+        source.tilesets.insert(SaveId(1), test_tileset());
+        source.layers.push(Layer {
+            name: "imported".into(),
+            tiles: vec![
+                Tile {
+                    location: (1, 0).into(),
+                    tileset: SaveId(0),
+                    tile_id: 0,
+                    rotation: tileset::TileRotation::None,
+                    ..Default::default()
+                },
+                Tile {
+                    location: (0, 0).into(),
+                    tileset: SaveId(1),
+                    tile_id: 1,
+                    rotation: tileset::TileRotation::Clockwise60,
+                    overrides: tileset::TileOverrides {
+                        offset: Vec2::new(0.5, 0.0),
+                        ..Default::default()
+                    },
+                },
+            ],
+            ..Default::default()
+        });
+        source.regions.push(Region {
+            name: "town".into(),
+            color: Color::RED,
+            locations: vec![(0, 0).into(), (1, 0).into()],
+            properties: BTreeMap::new(),
+        });
+
+        ImportLayersCommand::new(
+            root,
+            source,
+            (10, -5).into(),
+            tileset::TileRotation::Clockwise60,
+        )
+        .write(&mut world);
+
+        // regions move with the tiles
+        let mut query = world.query::<(&region::Region, &Parent)>();
+        let (region, parent) = query.single(&world);
+        assert_eq!(parent.get(), root);
+        assert_eq!(region.name, "town");
+        let mut locations: Vec<map::Location> = region.locations.iter().copied().collect();
+        locations.sort_by_key(|l| (l.y, l.x));
+        let expected: Vec<map::Location> = vec![(11, -6).into(), (10, -5).into()];
+        assert_eq!(locations, expected);
+
+        // "tileset a" is reused, the other tileset is added with a new SaveId
+        let mut query = world.query::<(Entity, &tileset::Tileset, Option<&SaveId>)>();
+        let tilesets: Vec<_> = query.iter(&world).collect();
+        assert_eq!(tilesets.len(), 3);
+        let (tileset_a, _, _) = tilesets
+            .iter()
+            .find(|(_, t, _)| t.name == "tileset a")
+            .unwrap();
+        let (imported, _, id) = tilesets
+            .iter()
+            .find(|(_, t, _)| t.name == test_tileset().name)
+            .unwrap();
+        assert_eq!(*id, Some(&SaveId(5)));
+        let (tileset_a, imported) = (*tileset_a, *imported);
+
+        let mut query = world.query::<(&map::Layer, &Children)>();
+        let (_, children) = query
+            .iter(&world)
+            .find(|(layer, _)| layer.name == "imported")
+            .expect("imported layer");
+        let tiles: Vec<Entity> = children.iter().cloned().collect();
+        assert_eq!(world.query::<&map::Layer>().iter(&world).count(), 2);
+
+        let tile = world.entity(tiles[0]);
+        assert_eq!(tile.get::<map::Location>(), Some(&(11, -6).into()));
+        assert_eq!(
+            tile.get::<tileset::TileRef>(),
+            Some(&tileset::TileRef {
+                tileset: tileset_a,
+                tile: 0
+            })
+        );
+        assert_eq!(
+            tile.get::<tileset::TileTransform>().unwrap().rotation,
+            tileset::TileRotation::Clockwise60
+        );
+
+        let tile = world.entity(tiles[1]);
+        assert_eq!(tile.get::<map::Location>(), Some(&(10, -5).into()));
+        assert_eq!(
+            tile.get::<tileset::TileRef>(),
+            Some(&tileset::TileRef {
+                tileset: imported,
+                tile: 1
+            })
+        );
+        assert_eq!(
+            tile.get::<tileset::TileTransform>().unwrap().rotation,
+            tileset::TileRotation::Clockwise120
+        );
+        // the offset turns with the imported tiles
+        let offset = tile.get::<tileset::TileOverrides>().unwrap().offset;
+        let expected = Quat::from_rotation_y(tileset::TileRotation::Clockwise60.into())
+            * Vec3::new(0.5, 0.0, 0.0);
+        assert!(offset.distance(Vec2::new(expected.x, expected.z)) < 0.001);
+        let tile = world.entity(tiles[0]);
+        assert!(tile.get::<tileset::TileOverrides>().is_none());
+    }
+
+    #[test]
+    fn import_layers_layout_mismatch() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut source = MapFormat::new(HexLayout {
+            hex_size: Vec2::splat(2.0),
+            ..default()
+        });
+        source.layers.push(Layer {
+            name: "imported".into(),
+            ..Default::default()
+        });
+
+        let command = ImportLayersCommand::new(root, source, default(), default());
+        assert!(command.try_write(&mut world).is_err());
+        assert_eq!(world.query::<&map::Layer>().iter(&world).count(), 1);
+    }
+
+    #[test]
+    fn tile_overrides() {
+        let mut world = World::new();
+        let root = spawn_map(&mut world);
+        let mut query = world.query_filtered::<Entity, With<map::Location>>();
+        let tiles: Vec<Entity> = query.iter(&world).collect();
+        let overrides = tileset::TileOverrides {
+            tint: Some(Color::rgb(0.5, 1.0, 0.5)),
+            scale: 0.8,
+            ..Default::default()
+        };
+        world.entity_mut(tiles[0]).insert(overrides);
+        world
+            .entity_mut(tiles[1])
+            .insert(tileset::TileOverrides::default());
+
+        // only values that differ from the defaults are saved
+        let map = MapFormat::try_new(&mut world, root).unwrap();
+        let ron = FileFormat::Ron.serialize(&map).unwrap();
+        assert_eq!(ron.matches("overrides").count(), 1);
+        assert!(ron.contains("scale: 0.8"));
+        assert!(!ron.contains("y_offset"));
+        assert!(!ron.contains("flip"));
+
+        let map: MapFormat = FileFormat::Ron.deserialize(&ron).unwrap();
+        let mut world = World::new();
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        {
+            let mut commands = Commands::new(&mut queue, &world);
+            map.try_spawn(&mut commands.spawn_empty()).unwrap();
+        }
+        queue.apply(&mut world);
+        let mut query = world.query::<&tileset::TileOverrides>();
+        assert_eq!(query.iter(&world).collect::<Vec<_>>(), [&overrides]);
+    }
 }
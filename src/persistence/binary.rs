@@ -0,0 +1,799 @@
+//! Compact binary encoding of `MapFormat` for large maps
+//!
+//! File layout:
+//! ```text
+//! magic       4 bytes, "HXMB"
+//! version     u8, BINARY_VERSION
+//! compression u8, see `Compression`
+//! body        compressed according to `compression`
+//! ```
+//!
+//! The body starts with a length-prefixed RON document holding the map
+//! version, layout, & tilesets, as these are small and benefit from staying
+//! human-readable in a hex dump.  It's followed by the layers:
+//! ```text
+//! layer count                 varint
+//! per layer:
+//!     name                    varint length, utf-8 bytes
+//!     order                   varint
+//!     flags                   u8, bit 0 hidden, bit 1 locked
+//!     opacity                 f32, little endian
+//!     y offset                f32, little endian
+//!     kind                    u8, see `kind_to_u8()`
+//!     palette length          varint
+//!     per palette entry:
+//!         tileset SaveId      varint
+//!         tile id             varint
+//!         rotation            u8
+//!     tile count              varint
+//!     per tile:
+//!         x delta             zigzag varint
+//!         y delta             zigzag varint
+//!         palette index       varint
+//!     tile overrides          varint length, RON list of (tile index,
+//!                             `TileOverrides`)
+//!     edge tile count         varint
+//!     per edge tile:
+//!         x delta             zigzag varint
+//!         y delta             zigzag varint
+//!         side                u8, see `edge_side_to_u8()`
+//!         palette index       varint
+//!     vertex tile count       varint
+//!     per vertex tile:
+//!         x delta             zigzag varint
+//!         y delta             zigzag varint
+//!         side                u8, see `vertex_side_to_u8()`
+//!         palette index       varint
+//!     objects                 varint length, RON list of `Object`
+//!     annotations             varint length, RON list of `Annotation`
+//! region count                varint
+//! per region:
+//!     region                  varint length, RON `Region` without locations
+//!     location count          varint
+//!     per location:
+//!         x delta             zigzag varint
+//!         y delta             zigzag varint
+//! ```
+//! Tiles are written sorted by location, and each location is stored as the
+//! difference from the previous tile in the layer, so the deltas for densely
+//! packed layers are almost all a single byte.  Edge & vertex tiles, and
+//! region locations are stored the same way, using the canonical location of
+//! each edge & vertex.  Objects, annotations, and tile overrides are few and
+//! carry free-form values, so they're stored as RON like the metadata; tile
+//! overrides refer to tiles by their index in the sorted tile list.
+//!
+//! Reads & writes are streamed, so a map never needs to be held in memory in
+//! its encoded form.
+use anyhow::{bail, Context, Result};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder};
+use hexx::HexLayout;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Read, Write},
+};
+
+use super::{EdgeTile, Layer, MapFormat, Region, SaveId, Tile, VertexTile};
+use crate::{
+    map::{Edge, EdgeSide, LayerKind, Location, Vertex, VertexSide},
+    tileset::{self, TileRotation},
+};
+
+const MAGIC: &[u8; 4] = b"HXMB";
+
+/// version of the binary container; independent of `MAP_FORMAT_VERSION`
+pub const BINARY_VERSION: u8 = 1;
+
+/// layer flag bits
+const LAYER_HIDDEN: u8 = 1 << 0;
+const LAYER_LOCKED: u8 = 1 << 1;
+
+/// upper bound for preallocating Vecs from counts read from the file
+const MAX_PREALLOC: usize = 4096;
+
+/// compression applied to the body of the file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    #[default]
+    Deflate,
+}
+
+impl Compression {
+    fn from_u8(v: u8) -> Result<Self> {
+        match v {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Deflate),
+            v => bail!("unsupported compression: {}", v),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Deflate => 1,
+        }
+    }
+}
+
+/// the parts of a MapFormat stored as RON in the body
+#[derive(Serialize, Deserialize)]
+struct Metadata<'a> {
+    version: usize,
+    layout: HexLayout,
+    tilesets: std::borrow::Cow<'a, BTreeMap<SaveId, tileset::Tileset>>,
+}
+
+/// write `map` to `writer` using the binary encoding
+pub fn write<W: Write>(map: &MapFormat, mut writer: W, compression: Compression) -> Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[BINARY_VERSION, compression.as_u8()])?;
+
+    match compression {
+        Compression::None => write_body(map, &mut writer)?,
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(writer, flate2::Compression::default());
+            write_body(map, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// read a MapFormat written by `write()` from `reader`
+pub fn read<R: Read>(mut reader: R) -> Result<MapFormat> {
+    let mut header = [0; 6];
+    reader
+        .read_exact(&mut header)
+        .context("failed to read header")?;
+    if &header[0..4] != MAGIC {
+        bail!("not a binary map file");
+    }
+    let version = header[4];
+    if version != BINARY_VERSION {
+        bail!(
+            "unsupported binary map version: {} != {}",
+            version,
+            BINARY_VERSION
+        );
+    }
+
+    match Compression::from_u8(header[5])? {
+        Compression::None => read_body(&mut reader),
+        Compression::Deflate => read_body(&mut DeflateDecoder::new(reader)),
+    }
+}
+
+/// check if `buf` starts with the binary map header
+pub fn is_binary(buf: &[u8]) -> bool {
+    buf.starts_with(MAGIC)
+}
+
+fn write_body<W: Write>(map: &MapFormat, w: &mut W) -> Result<()> {
+    let metadata = Metadata {
+        version: map.version,
+        layout: map.layout.clone(),
+        tilesets: std::borrow::Cow::Borrowed(&map.tilesets),
+    };
+    let metadata = ron::to_string(&metadata).context("failed to serialize metadata")?;
+    write_bytes(w, metadata.as_bytes())?;
+
+    write_varint(w, map.layers.len() as u64)?;
+    for layer in &map.layers {
+        write_bytes(w, layer.name.as_bytes())?;
+        write_varint(w, layer.order as u64)?;
+        let mut flags = 0;
+        if !layer.visible {
+            flags |= LAYER_HIDDEN;
+        }
+        if layer.locked {
+            flags |= LAYER_LOCKED;
+        }
+        w.write_all(&[flags])?;
+        w.write_all(&layer.opacity.to_le_bytes())?;
+        w.write_all(&layer.y_offset.to_le_bytes())?;
+        w.write_all(&[kind_to_u8(layer.kind)])?;
+
+        // build the palette in order of first appearance
+        let mut palette: Vec<(SaveId, tileset::TileId, TileRotation)> = Vec::new();
+        let mut palette_index = HashMap::new();
+        let mut index_of = |tileset, tile_id, rotation| {
+            let key = (tileset, tile_id, rotation);
+            *palette_index.entry(key).or_insert_with(|| {
+                palette.push(key);
+                palette.len() - 1
+            })
+        };
+        let mut tiles = Vec::with_capacity(layer.tiles.len());
+        for tile in &layer.tiles {
+            let index = index_of(tile.tileset, tile.tile_id, tile.rotation);
+            tiles.push((tile.location, index, tile.overrides));
+        }
+        tiles.sort_by_key(|(location, ..)| (location.y, location.x));
+        let overrides: Vec<(usize, tileset::TileOverrides)> = tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, (.., overrides))| !overrides.is_default())
+            .map(|(i, (.., overrides))| (i, *overrides))
+            .collect();
+        let mut edges = Vec::with_capacity(layer.edges.len());
+        for tile in &layer.edges {
+            let index = index_of(tile.tileset, tile.tile_id, tile.rotation);
+            let side = edge_side_to_u8(tile.edge.side);
+            edges.push((tile.edge.location, side, index));
+        }
+        edges.sort_by_key(|(location, side, _)| (location.y, location.x, *side));
+        let mut vertices = Vec::with_capacity(layer.vertices.len());
+        for tile in &layer.vertices {
+            let index = index_of(tile.tileset, tile.tile_id, tile.rotation);
+            let side = vertex_side_to_u8(tile.vertex.side);
+            vertices.push((tile.vertex.location, side, index));
+        }
+        vertices.sort_by_key(|(location, side, _)| (location.y, location.x, *side));
+
+        write_varint(w, palette.len() as u64)?;
+        for (tileset, tile_id, rotation) in palette {
+            write_varint(w, tileset.0 as u64)?;
+            write_varint(w, tile_id as u64)?;
+            w.write_all(&[rotation_to_u8(rotation)])?;
+        }
+
+        write_varint(w, tiles.len() as u64)?;
+        let (mut x, mut y) = (0, 0);
+        for (location, index, _) in tiles {
+            write_varint(w, zigzag(location.x.wrapping_sub(x)))?;
+            write_varint(w, zigzag(location.y.wrapping_sub(y)))?;
+            write_varint(w, index as u64)?;
+            (x, y) = (location.x, location.y);
+        }
+
+        let overrides = ron::to_string(&overrides).context(format!(
+            "failed to serialize tile overrides in layer {}",
+            layer.name
+        ))?;
+        write_bytes(w, overrides.as_bytes())?;
+
+        write_sided(w, &edges)?;
+        write_sided(w, &vertices)?;
+
+        let objects = ron::to_string(&layer.objects).context(format!(
+            "failed to serialize objects in layer {}",
+            layer.name
+        ))?;
+        write_bytes(w, objects.as_bytes())?;
+
+        let annotations = ron::to_string(&layer.annotations).context(format!(
+            "failed to serialize annotations in layer {}",
+            layer.name
+        ))?;
+        write_bytes(w, annotations.as_bytes())?;
+    }
+
+    write_varint(w, map.regions.len() as u64)?;
+    for region in &map.regions {
+        let metadata = Region {
+            locations: Vec::new(),
+            ..region.clone()
+        };
+        let metadata = ron::to_string(&metadata)
+            .context(format!("failed to serialize region {}", region.name))?;
+        write_bytes(w, metadata.as_bytes())?;
+
+        let mut locations = region.locations.clone();
+        locations.sort_by_key(|l| (l.y, l.x));
+        write_varint(w, locations.len() as u64)?;
+        let (mut x, mut y) = (0, 0);
+        for location in locations {
+            write_varint(w, zigzag(location.x.wrapping_sub(x)))?;
+            write_varint(w, zigzag(location.y.wrapping_sub(y)))?;
+            (x, y) = (location.x, location.y);
+        }
+    }
+    Ok(())
+}
+
+fn read_body<R: Read>(r: &mut R) -> Result<MapFormat> {
+    let metadata = read_bytes(r).context("failed to read metadata")?;
+    let metadata: Metadata = ron::de::from_bytes(&metadata).context("failed to parse metadata")?;
+    let mut map = MapFormat::new(metadata.layout);
+    map.version = metadata.version;
+    map.tilesets = metadata.tilesets.into_owned();
+
+    let layer_count = read_varint(r)? as usize;
+    for _ in 0..layer_count {
+        let name = String::from_utf8(read_bytes(r)?).context("invalid layer name")?;
+        let order = read_varint(r)? as usize;
+        let flags = read_u8(r)?;
+        let mut layer = Layer {
+            name,
+            order,
+            visible: flags & LAYER_HIDDEN == 0,
+            locked: flags & LAYER_LOCKED != 0,
+            opacity: read_f32(r)?.clamp(0.0, 1.0),
+            y_offset: read_f32(r)?,
+            kind: kind_from_u8(read_u8(r)?)?,
+            ..Default::default()
+        };
+
+        let palette_len = read_varint(r)? as usize;
+        let mut palette = Vec::with_capacity(palette_len.min(MAX_PREALLOC));
+        for _ in 0..palette_len {
+            let tileset = SaveId(read_varint(r)? as usize);
+            let tile_id = read_varint(r)? as tileset::TileId;
+            let rotation = rotation_from_u8(read_u8(r)?)?;
+            palette.push((tileset, tile_id, rotation));
+        }
+
+        let tile_count = read_varint(r)? as usize;
+        layer.tiles.reserve(tile_count.min(MAX_PREALLOC));
+        let (mut x, mut y) = (0i32, 0i32);
+        for _ in 0..tile_count {
+            x = x.wrapping_add(unzigzag(read_varint(r)?)?);
+            y = y.wrapping_add(unzigzag(read_varint(r)?)?);
+            let index = read_varint(r)? as usize;
+            let (tileset, tile_id, rotation) = *palette.get(index).context(format!(
+                "invalid palette index {} in layer {}",
+                index, layer.name
+            ))?;
+            layer.tiles.push(Tile {
+                location: (x, y).into(),
+                tileset,
+                tile_id,
+                rotation,
+                overrides: Default::default(),
+            });
+        }
+
+        let buf = read_bytes(r)?;
+        let overrides: Vec<(usize, tileset::TileOverrides)> = ron::de::from_bytes(&buf)
+            .context(format!("invalid tile overrides in layer {}", layer.name))?;
+        for (index, overrides) in overrides {
+            let tile = layer.tiles.get_mut(index).context(format!(
+                "invalid tile index {} for overrides in layer {}",
+                index, layer.name
+            ))?;
+            tile.overrides = overrides;
+        }
+
+        for (location, side, index) in read_sided(r)? {
+            let (tileset, tile_id, rotation) = *palette.get(index).context(format!(
+                "invalid palette index {} in layer {}",
+                index, layer.name
+            ))?;
+            let side = edge_side_from_u8(side)?;
+            layer.edges.push(EdgeTile {
+                edge: Edge { location, side },
+                tileset,
+                tile_id,
+                rotation,
+            });
+        }
+        for (location, side, index) in read_sided(r)? {
+            let (tileset, tile_id, rotation) = *palette.get(index).context(format!(
+                "invalid palette index {} in layer {}",
+                index, layer.name
+            ))?;
+            let side = vertex_side_from_u8(side)?;
+            layer.vertices.push(VertexTile {
+                vertex: Vertex { location, side },
+                tileset,
+                tile_id,
+                rotation,
+            });
+        }
+
+        let objects = read_bytes(r)?;
+        layer.objects = ron::de::from_bytes(&objects)
+            .context(format!("failed to parse objects in layer {}", layer.name))?;
+        let annotations = read_bytes(r)?;
+        layer.annotations = ron::de::from_bytes(&annotations).context(format!(
+            "failed to parse annotations in layer {}",
+            layer.name
+        ))?;
+        map.layers.push(layer);
+    }
+
+    let region_count = read_varint(r)? as usize;
+    for _ in 0..region_count {
+        let metadata = read_bytes(r)?;
+        let mut region: Region =
+            ron::de::from_bytes(&metadata).context("failed to parse region")?;
+        let location_count = read_varint(r)? as usize;
+        region.locations.reserve(location_count.min(MAX_PREALLOC));
+        let (mut x, mut y) = (0i32, 0i32);
+        for _ in 0..location_count {
+            x = x.wrapping_add(unzigzag(read_varint(r)?)?);
+            y = y.wrapping_add(unzigzag(read_varint(r)?)?);
+            region.locations.push((x, y).into());
+        }
+        map.regions.push(region);
+    }
+    map.check_tilesets()?;
+    Ok(map)
+}
+
+fn rotation_to_u8(rotation: TileRotation) -> u8 {
+    match rotation {
+        TileRotation::None => 0,
+        TileRotation::Clockwise60 => 1,
+        TileRotation::Clockwise120 => 2,
+        TileRotation::Clockwise180 => 3,
+        TileRotation::CounterClockwise120 => 4,
+        TileRotation::CounterClockwise60 => 5,
+    }
+}
+
+fn rotation_from_u8(v: u8) -> Result<TileRotation> {
+    Ok(match v {
+        0 => TileRotation::None,
+        1 => TileRotation::Clockwise60,
+        2 => TileRotation::Clockwise120,
+        3 => TileRotation::Clockwise180,
+        4 => TileRotation::CounterClockwise120,
+        5 => TileRotation::CounterClockwise60,
+        v => bail!("invalid tile rotation: {}", v),
+    })
+}
+
+/// write edge or vertex tiles, sorted by location
+fn write_sided<W: Write>(w: &mut W, tiles: &[(Location, u8, usize)]) -> Result<()> {
+    write_varint(w, tiles.len() as u64)?;
+    let (mut x, mut y) = (0, 0);
+    for (location, side, index) in tiles {
+        write_varint(w, zigzag(location.x.wrapping_sub(x)))?;
+        write_varint(w, zigzag(location.y.wrapping_sub(y)))?;
+        w.write_all(&[*side])?;
+        write_varint(w, *index as u64)?;
+        (x, y) = (location.x, location.y);
+    }
+    Ok(())
+}
+
+/// read edge or vertex tiles written by `write_sided()`
+fn read_sided<R: Read>(r: &mut R) -> Result<Vec<(Location, u8, usize)>> {
+    let count = read_varint(r)? as usize;
+    let mut tiles = Vec::with_capacity(count.min(MAX_PREALLOC));
+    let (mut x, mut y) = (0i32, 0i32);
+    for _ in 0..count {
+        x = x.wrapping_add(unzigzag(read_varint(r)?)?);
+        y = y.wrapping_add(unzigzag(read_varint(r)?)?);
+        let side = read_u8(r)?;
+        let index = read_varint(r)? as usize;
+        tiles.push(((x, y).into(), side, index));
+    }
+    Ok(tiles)
+}
+
+fn edge_side_to_u8(side: EdgeSide) -> u8 {
+    match side {
+        EdgeSide::PosX => 0,
+        EdgeSide::PosXNegY => 1,
+        EdgeSide::NegY => 2,
+    }
+}
+
+fn edge_side_from_u8(v: u8) -> Result<EdgeSide> {
+    Ok(match v {
+        0 => EdgeSide::PosX,
+        1 => EdgeSide::PosXNegY,
+        2 => EdgeSide::NegY,
+        v => bail!("invalid edge side: {}", v),
+    })
+}
+
+fn vertex_side_to_u8(side: VertexSide) -> u8 {
+    match side {
+        VertexSide::PosXPosY => 0,
+        VertexSide::PosXNegY => 1,
+    }
+}
+
+fn vertex_side_from_u8(v: u8) -> Result<VertexSide> {
+    Ok(match v {
+        0 => VertexSide::PosXPosY,
+        1 => VertexSide::PosXNegY,
+        v => bail!("invalid vertex side: {}", v),
+    })
+}
+
+fn kind_to_u8(kind: LayerKind) -> u8 {
+    match kind {
+        LayerKind::Tile => 0,
+        LayerKind::Object => 1,
+        LayerKind::Annotation => 2,
+    }
+}
+
+fn kind_from_u8(v: u8) -> Result<LayerKind> {
+    Ok(match v {
+        0 => LayerKind::Tile,
+        1 => LayerKind::Object,
+        2 => LayerKind::Annotation,
+        v => bail!("invalid layer kind: {}", v),
+    })
+}
+
+fn zigzag(v: i32) -> u64 {
+    ((v << 1) ^ (v >> 31)) as u32 as u64
+}
+
+fn unzigzag(v: u64) -> Result<i32> {
+    let v = u32::try_from(v).context("location delta out of range")?;
+    Ok((v >> 1) as i32 ^ -((v & 1) as i32))
+}
+
+fn write_varint<W: Write>(w: &mut W, mut v: u64) -> Result<()> {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            w.write_all(&[byte])?;
+            return Ok(());
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(r: &mut R) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(r)?;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("varint too long")
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8> {
+    let mut buf = [0];
+    r.read_exact(&mut buf).context("unexpected end of file")?;
+    Ok(buf[0])
+}
+
+fn read_f32<R: Read>(r: &mut R) -> Result<f32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf).context("unexpected end of file")?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> Result<()> {
+    write_varint(w, bytes.len() as u64)?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>> {
+    let len = read_varint(r)? as usize;
+    let mut buf = Vec::with_capacity(len.min(MAX_PREALLOC));
+    r.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len {
+        bail!("unexpected end of file");
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotation::Annotation;
+    use crate::object::{ObjectPosition, Property};
+    use crate::persistence::Object;
+    use bevy::prelude::{debug, Color, Vec3};
+    use test_log::test;
+
+    fn test_map() -> MapFormat {
+        let mut tileset = tileset::Tileset::new("test");
+        tileset.add_tile("grass.glb".into());
+        tileset.add_tile("water.glb".into());
+
+        let mut map = MapFormat::new(HexLayout::default());
+        map.tilesets.insert(SaveId(4), tileset);
+        let mut tiles = Vec::new();
+        for y in -20..20 {
+            for x in -20..20 {
+                tiles.push(Tile {
+                    location: (x, y).into(),
+                    tileset: SaveId(4),
+                    tile_id: ((x + y) & 1) as usize,
+                    rotation: match x % 3 {
+                        0 => TileRotation::None,
+                        _ => TileRotation::Clockwise120,
+                    },
+                    ..Default::default()
+                });
+            }
+        }
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles,
+            order: 2,
+            ..Default::default()
+        });
+        map.layers.push(Layer {
+            name: "walls".into(),
+            order: 4,
+            edges: vec![
+                EdgeTile {
+                    edge: Edge::new((0, 0).into(), (0, -1).into()).unwrap(),
+                    tileset: SaveId(4),
+                    tile_id: 1,
+                    rotation: TileRotation::Clockwise180,
+                },
+                EdgeTile {
+                    edge: Edge::new((5, -3).into(), (4, -3).into()).unwrap(),
+                    tileset: SaveId(4),
+                    ..Default::default()
+                },
+            ],
+            vertices: vec![VertexTile {
+                vertex: Vertex::new((0, 0).into(), (1, -1).into(), (1, 0).into()).unwrap(),
+                tileset: SaveId(4),
+                tile_id: 1,
+                rotation: TileRotation::Clockwise60,
+            }],
+            ..Default::default()
+        });
+        map.layers.push(Layer {
+            name: "sparse".into(),
+            tiles: vec![
+                Tile {
+                    location: (i32::MAX, i32::MIN).into(),
+                    ..Default::default()
+                },
+                Tile {
+                    location: (-1000, 3).into(),
+                    tile_id: 1,
+                    overrides: tileset::TileOverrides {
+                        tint: Some(Color::rgb(1.0, 0.5, 0.0)),
+                        scale: 1.25,
+                        flip: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+            order: 0,
+            locked: true,
+            opacity: 0.25,
+            ..Default::default()
+        });
+        map.layers.push(Layer {
+            name: "notes".into(),
+            tiles: Vec::new(),
+            order: 1,
+            visible: false,
+            y_offset: 1.5,
+            kind: LayerKind::Annotation,
+            annotations: vec![
+                Annotation::new((2, -1).into(), "bridge goes here"),
+                Annotation {
+                    color: Color::RED,
+                    ..Annotation::new((-4, 0).into(), "needs more trees")
+                },
+            ],
+            ..Default::default()
+        });
+        map.layers.push(Layer {
+            name: "objects".into(),
+            order: 3,
+            kind: LayerKind::Object,
+            objects: vec![
+                Object {
+                    id: SaveId(0),
+                    name: "door".into(),
+                    kind: "door".into(),
+                    position: ObjectPosition::Hex((1, 2).into()),
+                    model: None,
+                    icon: Some("🚪".into()),
+                    properties: [("key".to_string(), Property::Object(SaveId(1)))].into(),
+                },
+                Object {
+                    id: SaveId(1),
+                    name: "key".into(),
+                    kind: "item".into(),
+                    position: ObjectPosition::Free(Vec3::new(0.5, 0.0, -1.25)),
+                    model: Some("key.glb".into()),
+                    icon: None,
+                    properties: [("weight".to_string(), Property::Float(0.1))].into(),
+                },
+            ],
+            ..Default::default()
+        });
+        map.regions.push(Region {
+            name: "forest".into(),
+            color: Color::GREEN,
+            locations: vec![(3, -2).into(), (4, -2).into(), (-7, 9).into()],
+            properties: [("encounter".to_string(), Property::Int(3))].into(),
+        });
+        map
+    }
+
+    /// binary encoding sorts tiles, so sort them for comparison
+    fn sorted_ron(map: &MapFormat) -> String {
+        let mut map = MapFormat {
+            version: map.version,
+            layout: map.layout.clone(),
+            tilesets: map.tilesets.clone(),
+            layers: map.layers.clone(),
+            regions: map.regions.clone(),
+            ..Default::default()
+        };
+        for layer in &mut map.layers {
+            layer.tiles.sort_by_key(|t| (t.location.y, t.location.x));
+            layer
+                .edges
+                .sort_by_key(|t| (t.edge.location.y, t.edge.location.x));
+            layer
+                .vertices
+                .sort_by_key(|t| (t.vertex.location.y, t.vertex.location.x));
+        }
+        for region in &mut map.regions {
+            region.locations.sort_by_key(|l| (l.y, l.x));
+        }
+        ron::to_string(&map).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let map = test_map();
+        for compression in [Compression::None, Compression::Deflate] {
+            let mut buf = Vec::new();
+            write(&map, &mut buf, compression).unwrap();
+            assert!(is_binary(&buf));
+            let read_map = read(buf.as_slice()).unwrap();
+            assert_eq!(sorted_ron(&read_map), sorted_ron(&map));
+        }
+    }
+
+    #[test]
+    fn smaller_than_ron() {
+        let map = test_map();
+        let ron = ron::to_string(&map).unwrap();
+        let mut buf = Vec::new();
+        write(&map, &mut buf, Compression::None).unwrap();
+        debug!("ron {} bytes, binary {} bytes", ron.len(), buf.len());
+        assert!(buf.len() * 10 < ron.len());
+    }
+
+    #[test]
+    fn bad_header() {
+        assert!(read(&b"HXMA\x01\x00"[..]).is_err());
+        assert!(read(&b"HXMB\x00\x00"[..]).is_err());
+        assert!(read(&b"HXMB\x02\x00"[..]).is_err());
+        assert!(read(&b"HXMB\x01\x09"[..]).is_err());
+        assert!(read(&b"HXMB"[..]).is_err());
+    }
+
+    #[test]
+    fn unknown_tileset() {
+        let mut map = test_map();
+        map.layers[0].tiles[0].tileset = SaveId(5);
+        let mut buf = Vec::new();
+        write(&map, &mut buf, Compression::None).unwrap();
+        assert!(read(buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut buf = Vec::new();
+        write(&test_map(), &mut buf, Compression::None).unwrap();
+        buf.truncate(buf.len() - 3);
+        assert!(read(buf.as_slice()).is_err());
+    }
+
+    #[test]
+    fn varint() {
+        for v in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, v).unwrap();
+            assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), v);
+        }
+        for v in [0, 1, -1, 63, -64, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(v)).unwrap(), v);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+}
@@ -0,0 +1,370 @@
+//! Differences between two versions of a map, such as the map open in the
+//! editor & the same map changed on disk by another program
+//!
+//! Tiles are compared by their position, objects by their `SaveId`,
+//! annotations by their contents, and layers, tilesets & regions by their
+//! name, so the order things are saved in does not matter.  Duplicate layer
+//! names are matched in the order they appear.
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use super::{Layer, MapFormat};
+
+/// differences between the tiles & objects of a layer present in both maps
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LayerDiff {
+    pub name: String,
+    /// tiles placed on hexes, edges, or vertices that were empty
+    pub tiles_added: usize,
+    pub tiles_removed: usize,
+    /// tiles replaced by another tile, or rotated, or with other overrides
+    pub tiles_changed: usize,
+    /// objects added, removed, or changed
+    pub objects: usize,
+    /// annotations added or removed; a changed note is one of each
+    pub annotations: usize,
+    /// order, visibility, opacity, or other layer settings changed
+    pub settings: bool,
+}
+
+impl LayerDiff {
+    fn new(name: &str, old: &Layer, new: &Layer) -> Self {
+        let (tiles_added, tiles_removed, tiles_changed) = diff(&tiles(old), &tiles(new));
+        let (objects_added, objects_removed, objects_changed) = diff(&objects(old), &objects(new));
+        Self {
+            name: name.to_string(),
+            tiles_added,
+            tiles_removed,
+            tiles_changed,
+            objects: objects_added + objects_removed + objects_changed,
+            annotations: annotations_changed(old, new),
+            settings: serialize(&settings(old)) != serialize(&settings(new)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self
+            == Self {
+                name: self.name.clone(),
+                ..Self::default()
+            }
+    }
+
+    /// short description of the changes, such as "tiles: 3 added, 1 removed"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        let tiles: Vec<String> = [
+            (self.tiles_added, "added"),
+            (self.tiles_removed, "removed"),
+            (self.tiles_changed, "changed"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{} {}", count, what))
+        .collect();
+        if !tiles.is_empty() {
+            parts.push(format!("tiles: {}", tiles.join(", ")));
+        }
+        if self.objects > 0 {
+            parts.push(format!("{} changed", count_of(self.objects, "objects")));
+        }
+        if self.annotations > 0 {
+            parts.push(format!(
+                "{} changed",
+                count_of(self.annotations, "annotations")
+            ));
+        }
+        if self.settings {
+            parts.push("settings changed".into());
+        }
+        parts.join("; ")
+    }
+}
+
+/// differences between two maps; see the module documentation
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MapDiff {
+    /// the hex layout of the map changed
+    pub layout: bool,
+    pub tilesets_added: Vec<String>,
+    pub tilesets_removed: Vec<String>,
+    /// tilesets whose tiles or settings changed
+    pub tilesets_changed: Vec<String>,
+    pub layers_added: Vec<String>,
+    pub layers_removed: Vec<String>,
+    /// only the layers in both maps that changed
+    pub layers: Vec<LayerDiff>,
+    /// regions added, removed, or changed
+    pub regions: usize,
+}
+
+impl MapDiff {
+    /// find what changed to get from `old` to `new`
+    pub fn new(old: &MapFormat, new: &MapFormat) -> Self {
+        let mut map_diff = Self {
+            layout: serialize(&old.layout) != serialize(&new.layout),
+            ..Self::default()
+        };
+
+        let old_tilesets = tilesets(old);
+        let new_tilesets = tilesets(new);
+        for (name, tileset) in &new_tilesets {
+            match old_tilesets.get(name) {
+                None => map_diff.tilesets_added.push(name.clone()),
+                Some(old) if old != tileset => map_diff.tilesets_changed.push(name.clone()),
+                Some(_) => (),
+            }
+        }
+        for name in old_tilesets.keys() {
+            if !new_tilesets.contains_key(name) {
+                map_diff.tilesets_removed.push(name.clone());
+            }
+        }
+
+        let old_layers = layers(old);
+        let new_layers = layers(new);
+        for key @ (name, _) in new_layers.keys() {
+            if !old_layers.contains_key(key) {
+                map_diff.layers_added.push(name.clone());
+            }
+        }
+        for (key @ (name, _), old_layer) in &old_layers {
+            let Some(new_layer) = new_layers.get(key) else {
+                map_diff.layers_removed.push(name.clone());
+                continue;
+            };
+            let layer = LayerDiff::new(name, old_layer, new_layer);
+            if !layer.is_empty() {
+                map_diff.layers.push(layer);
+            }
+        }
+
+        let regions = |map: &MapFormat| -> HashMap<String, String> {
+            map.regions
+                .iter()
+                .map(|region| (region.name.clone(), serialize(region)))
+                .collect()
+        };
+        let (added, removed, changed) = diff(&regions(old), &regions(new));
+        map_diff.regions = added + removed + changed;
+
+        map_diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// one line per change, for showing to the user
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.layout {
+            lines.push("hex layout changed".to_string());
+        }
+        for (names, what) in [
+            (&self.tilesets_added, "tileset added"),
+            (&self.tilesets_removed, "tileset removed"),
+            (&self.tilesets_changed, "tileset changed"),
+            (&self.layers_added, "layer added"),
+            (&self.layers_removed, "layer removed"),
+        ] {
+            for name in names {
+                lines.push(format!("{}: {}", what, name));
+            }
+        }
+        for layer in &self.layers {
+            lines.push(format!("layer {}: {}", layer.name, layer.summary()));
+        }
+        if self.regions > 0 {
+            lines.push(format!("{} changed", count_of(self.regions, "regions")));
+        }
+        lines
+    }
+}
+
+/// count the keys only in `new`, only in `old`, and in both with different
+/// values
+fn diff(old: &HashMap<String, String>, new: &HashMap<String, String>) -> (usize, usize, usize) {
+    let added = new.keys().filter(|key| !old.contains_key(*key)).count();
+    let removed = old.keys().filter(|key| !new.contains_key(*key)).count();
+    let changed = new
+        .iter()
+        .filter(|(key, value)| matches!(old.get(*key), Some(old) if old != *value))
+        .count();
+    (added, removed, changed)
+}
+
+/// "1 region" or "3 regions"
+fn count_of(count: usize, plural: &str) -> String {
+    match count {
+        1 => format!("1 {}", plural.trim_end_matches('s')),
+        _ => format!("{} {}", count, plural),
+    }
+}
+
+fn serialize<T: Serialize>(value: &T) -> String {
+    ron::to_string(value).unwrap_or_default()
+}
+
+/// serialized tilesets by name
+fn tilesets(map: &MapFormat) -> BTreeMap<String, String> {
+    map.tilesets
+        .values()
+        .map(|tileset| (tileset.name.clone(), serialize(tileset)))
+        .collect()
+}
+
+/// layers by name & the number of earlier layers with the same name
+fn layers(map: &MapFormat) -> BTreeMap<(String, usize), &Layer> {
+    let mut layers = BTreeMap::new();
+    for layer in &map.layers {
+        let count = layers
+            .keys()
+            .filter(|(name, _)| *name == layer.name)
+            .count();
+        layers.insert((layer.name.clone(), count), layer);
+    }
+    layers
+}
+
+/// serialized hex, edge, & vertex tiles by position
+fn tiles(layer: &Layer) -> HashMap<String, String> {
+    let hexes = layer
+        .tiles
+        .iter()
+        .map(|tile| (serialize(&("hex", tile.location)), serialize(tile)));
+    let edges = layer
+        .edges
+        .iter()
+        .map(|tile| (serialize(&("edge", tile.edge)), serialize(tile)));
+    let vertices = layer
+        .vertices
+        .iter()
+        .map(|tile| (serialize(&("vertex", tile.vertex)), serialize(tile)));
+    hexes.chain(edges).chain(vertices).collect()
+}
+
+/// serialized objects by SaveId
+fn objects(layer: &Layer) -> HashMap<String, String> {
+    layer
+        .objects
+        .iter()
+        .map(|object| (serialize(&object.id), serialize(object)))
+        .collect()
+}
+
+/// number of annotations in only one of the layers
+fn annotations_changed(old: &Layer, new: &Layer) -> usize {
+    let count = |layer: &Layer| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for annotation in &layer.annotations {
+            *counts.entry(serialize(annotation)).or_default() += 1;
+        }
+        counts
+    };
+    let (old, new) = (count(old), count(new));
+    let missing = |a: &HashMap<String, usize>, b: &HashMap<String, usize>| -> usize {
+        a.iter()
+            .map(|(key, n)| n.saturating_sub(b.get(key).copied().unwrap_or(0)))
+            .sum()
+    };
+    missing(&old, &new) + missing(&new, &old)
+}
+
+/// the layer without its tiles, objects, & annotations
+fn settings(layer: &Layer) -> Layer {
+    Layer {
+        tiles: Vec::new(),
+        edges: Vec::new(),
+        vertices: Vec::new(),
+        objects: Vec::new(),
+        annotations: Vec::new(),
+        ..layer.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        map,
+        persistence::{SaveId, Tile},
+        tileset,
+    };
+    use test_log::test;
+
+    fn tile(x: i32, tile_id: tileset::TileId) -> Tile {
+        Tile {
+            location: map::Location { x, y: 0 },
+            tile_id,
+            ..Tile::default()
+        }
+    }
+
+    fn test_map() -> MapFormat {
+        let mut map = MapFormat::new(hexx::HexLayout::default());
+        map.tilesets
+            .insert(SaveId::default(), tileset::Tileset::new("tiles"));
+        map.layers.push(Layer {
+            name: "ground".into(),
+            tiles: vec![tile(0, 0), tile(1, 0), tile(2, 1)],
+            ..Layer::default()
+        });
+        map.layers.push(Layer {
+            name: "decor".into(),
+            ..Layer::default()
+        });
+        map
+    }
+
+    #[test]
+    fn same_map() {
+        let map = test_map();
+        let mut reordered = test_map();
+        reordered.layers[0].tiles.reverse();
+        let diff = MapDiff::new(&map, &reordered);
+        assert!(diff.is_empty(), "{:?}", diff);
+        assert!(diff.summary().is_empty());
+    }
+
+    #[test]
+    fn changed_map() {
+        let old = test_map();
+        let mut new = test_map();
+        new.tilesets
+            .values_mut()
+            .next()
+            .unwrap()
+            .add_tile("rock.glb".into());
+        let ground = &mut new.layers[0];
+        ground.tiles.remove(0);
+        ground.tiles[0].tile_id = 2;
+        ground.tiles.push(tile(5, 0));
+        ground.tiles.push(tile(6, 0));
+        new.layers[1].visible = false;
+        new.layers.push(Layer {
+            name: "roads".into(),
+            ..Layer::default()
+        });
+
+        let diff = MapDiff::new(&old, &new);
+        assert_eq!(diff.tilesets_changed, vec!["tiles"]);
+        assert_eq!(diff.layers_added, vec!["roads"]);
+        assert!(diff.layers_removed.is_empty());
+        assert_eq!(
+            diff.summary(),
+            vec![
+                "tileset changed: tiles",
+                "layer added: roads",
+                "layer decor: settings changed",
+                "layer ground: tiles: 2 added, 1 removed, 1 changed",
+            ]
+        );
+
+        let diff = MapDiff::new(&new, &old);
+        assert_eq!(diff.layers_removed, vec!["roads"]);
+        assert_eq!(diff.layers[1].tiles_added, 1);
+        assert_eq!(diff.layers[1].tiles_removed, 2);
+    }
+}
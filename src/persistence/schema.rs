@@ -0,0 +1,542 @@
+//! JSON Schema for the JSON representation of maps & tilesets
+//!
+//! The schemas are published in the `schema/` directory of the repository,
+//! and can be regenerated with `cargo run --bin cli -- schema schema/`.
+//! `published_schemas_up_to_date` fails when the published files do not match
+//! the schemas generated here, so any change to the save formats must update
+//! both.
+use serde_json::{json, Map, Value};
+
+use super::MAP_FORMAT_VERSION;
+use crate::tileset::TILESET_VERSION;
+
+const SCHEMA_DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// file name & schema pairs for every published schema
+pub fn schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        ("map.schema.json", map_schema()),
+        ("tileset.schema.json", tileset_schema()),
+    ]
+}
+
+/// schema for a `MapFormat` saved as JSON
+pub fn map_schema() -> Value {
+    let mut defs = common_defs();
+    defs.insert("Tileset".into(), tileset_def());
+    defs.insert(
+        "HexLayout".into(),
+        json!({
+            "description": "hexx::HexLayout used to convert map locations to world positions",
+            "type": "object",
+            "required": ["orientation", "origin", "hex_size"],
+            "properties": {
+                "orientation": {
+                    "description": "hexx::HexOrientation",
+                    "type": "object",
+                    "required": ["forward_matrix", "inverse_matrix"],
+                    "properties": {
+                        "forward_matrix": { "$ref": "#/$defs/Vec4" },
+                        "inverse_matrix": { "$ref": "#/$defs/Vec4" }
+                    }
+                },
+                "origin": { "$ref": "#/$defs/Vec2" },
+                "hex_size": { "$ref": "#/$defs/Vec2" }
+            }
+        }),
+    );
+    defs.insert(
+        "Layer".into(),
+        json!({
+            "type": "object",
+            "required": ["name", "tiles"],
+            "properties": {
+                "name": { "type": "string" },
+                "order": {
+                    "description": "layers are drawn lowest order first",
+                    "type": "integer",
+                    "minimum": 0,
+                    "default": 0
+                },
+                "visible": { "type": "boolean", "default": true },
+                "locked": { "type": "boolean", "default": false },
+                "opacity": { "type": "number", "minimum": 0, "maximum": 1, "default": 1 },
+                "y_offset": {
+                    "description": "height of the layer above the map",
+                    "type": "number",
+                    "default": 0
+                },
+                "kind": {
+                    "description": "what can be placed on the layer; only tile layers have tiles",
+                    "enum": ["Tile", "Object", "Annotation"],
+                    "default": "Tile"
+                },
+                "tiles": { "type": "array", "items": { "$ref": "#/$defs/MapTile" } },
+                "edges": {
+                    "description": "tiles placed on the edges between hexes",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/EdgeTile" }
+                },
+                "vertices": {
+                    "description": "tiles placed on the corners of hexes",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/VertexTile" }
+                },
+                "objects": {
+                    "description": "objects placed on an object layer",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/Object" }
+                },
+                "annotations": {
+                    "description": "editor-only notes placed on an annotation layer",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/Annotation" }
+                }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "MapTile".into(),
+        json!({
+            "description": "a tile placed on the map",
+            "type": "object",
+            "required": ["location", "tileset", "tile_id", "rotation"],
+            "properties": {
+                "location": { "$ref": "#/$defs/Location" },
+                "tileset": {
+                    "description": "SaveId of the tileset; key in the map `tilesets` object",
+                    "$ref": "#/$defs/SaveId"
+                },
+                "tile_id": {
+                    "description": "`id` of a tile within the tileset",
+                    "type": "integer",
+                    "minimum": 0
+                },
+                "rotation": { "$ref": "#/$defs/TileRotation" },
+                "overrides": { "$ref": "#/$defs/TileOverrides" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "TileOverrides".into(),
+        json!({
+            "description": "per-instance changes to a placed tile; omitted values are defaults",
+            "type": "object",
+            "properties": {
+                "tint": {
+                    "description": "color multiplied with the tile materials",
+                    "$ref": "#/$defs/Color"
+                },
+                "scale": {
+                    "description": "uniform scale applied on top of the tile scale; default 1",
+                    "type": "number"
+                },
+                "offset": {
+                    "description": "offset from the hex center along the X & Z axes",
+                    "$ref": "#/$defs/Vec2"
+                },
+                "y_offset": { "type": "number" },
+                "flip": {
+                    "description": "mirror the tile along its X axis",
+                    "type": "boolean"
+                }
+            },
+            "additionalProperties": false
+        }),
+    );
+    let placed = [
+        ("EdgeTile", "Edge", "a tile on the edge between two hexes"),
+        ("VertexTile", "Vertex", "a tile on a corner of three hexes"),
+    ];
+    for (name, key, description) in placed {
+        let field = key.to_lowercase();
+        defs.insert(
+            name.into(),
+            json!({
+                "description": description,
+                "type": "object",
+                "required": [field, "tileset", "tile_id", "rotation"],
+                "properties": {
+                    field: { "$ref": format!("#/$defs/{}", key) },
+                    "tileset": {
+                        "description": "SaveId of the tileset; key in the map `tilesets` object",
+                        "$ref": "#/$defs/SaveId"
+                    },
+                    "tile_id": {
+                        "description": "`id` of a tile within the tileset",
+                        "type": "integer",
+                        "minimum": 0
+                    },
+                    "rotation": { "$ref": "#/$defs/TileRotation" }
+                },
+                "additionalProperties": false
+            }),
+        );
+    }
+    defs.insert(
+        "TileRotation".into(),
+        json!({
+            "enum": [
+                "None",
+                "Clockwise60",
+                "Clockwise120",
+                "Clockwise180",
+                "CounterClockwise120",
+                "CounterClockwise60"
+            ]
+        }),
+    );
+    defs.insert(
+        "Edge".into(),
+        json!({
+            "description": "edge shared by the hex at `location` and its neighbor on `side`",
+            "type": "object",
+            "required": ["location", "side"],
+            "properties": {
+                "location": { "$ref": "#/$defs/Location" },
+                "side": {
+                    "description": "axial offset of the neighbor; (1, 0), (1, -1), or (0, -1)",
+                    "enum": ["PosX", "PosXNegY", "NegY"]
+                }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Vertex".into(),
+        json!({
+            "description": "corner shared by the hex at `location` and two neighbors",
+            "type": "object",
+            "required": ["location", "side"],
+            "properties": {
+                "location": { "$ref": "#/$defs/Location" },
+                "side": {
+                    "description": "neighbors at (1, 0) & (0, 1), or (1, -1) & (1, 0)",
+                    "enum": ["PosXPosY", "PosXNegY"]
+                }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Object".into(),
+        json!({
+            "description": "an object or marker placed on an object layer",
+            "type": "object",
+            "required": ["id", "name", "kind", "position"],
+            "properties": {
+                "id": {
+                    "description": "unique within the map; used by object references",
+                    "$ref": "#/$defs/SaveId"
+                },
+                "name": { "type": "string" },
+                "kind": { "description": "type of the object", "type": "string" },
+                "position": {
+                    "description": "a hex, or a free-form position relative to the layer",
+                    "oneOf": [
+                        enum_variant("Hex", json!({ "$ref": "#/$defs/Location" })),
+                        enum_variant("Free", json!({ "$ref": "#/$defs/Vec3" })),
+                    ]
+                },
+                "model": {
+                    "description": "path to the glTF model, relative to the asset directory",
+                    "type": "string"
+                },
+                "icon": { "type": "string" },
+                "properties": { "$ref": "#/$defs/Properties" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Annotation".into(),
+        json!({
+            "description": "an editor-only note placed on a hex; not included in exports",
+            "type": "object",
+            "required": ["location", "text", "color"],
+            "properties": {
+                "location": { "$ref": "#/$defs/Location" },
+                "text": { "type": "string" },
+                "color": { "$ref": "#/$defs/Color" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Region".into(),
+        json!({
+            "description": "a named set of hexes; regions may overlap",
+            "type": "object",
+            "required": ["name", "color", "locations"],
+            "properties": {
+                "name": { "type": "string" },
+                "color": { "$ref": "#/$defs/Color" },
+                "locations": { "type": "array", "items": { "$ref": "#/$defs/Location" } },
+                "properties": { "$ref": "#/$defs/Properties" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Properties".into(),
+        json!({
+            "description": "user-defined properties, keyed by name",
+            "type": "object",
+            "additionalProperties": { "$ref": "#/$defs/Property" }
+        }),
+    );
+    defs.insert(
+        "Property".into(),
+        json!({
+            "description": "value of an object property",
+            "oneOf": [
+                enum_variant("Bool", json!({ "type": "boolean" })),
+                enum_variant("Int", json!({ "type": "integer" })),
+                enum_variant("Float", json!({ "type": "number" })),
+                enum_variant("String", json!({ "type": "string" })),
+                enum_variant("Object", json!({
+                    "description": "id of another object in the map",
+                    "$ref": "#/$defs/SaveId"
+                })),
+            ]
+        }),
+    );
+    defs.insert("SaveId".into(), json!({ "type": "integer", "minimum": 0 }));
+
+    json!({
+        "$schema": SCHEMA_DRAFT,
+        "title": "hex_sandbox map",
+        "type": "object",
+        "required": ["version", "layout", "tilesets", "layers"],
+        "properties": {
+            "version": { "const": MAP_FORMAT_VERSION },
+            "layout": { "$ref": "#/$defs/HexLayout" },
+            "tilesets": {
+                "description": "tilesets used by the map, keyed by SaveId",
+                "type": "object",
+                "propertyNames": { "pattern": "^[0-9]+$" },
+                "additionalProperties": { "$ref": "#/$defs/Tileset" }
+            },
+            "layers": { "type": "array", "items": { "$ref": "#/$defs/Layer" } },
+            "regions": { "type": "array", "items": { "$ref": "#/$defs/Region" } }
+        },
+        "additionalProperties": false,
+        "$defs": defs,
+    })
+}
+
+/// schema for a `tileset::Tileset` saved as JSON
+pub fn tileset_schema() -> Value {
+    let mut schema = json!({
+        "$schema": SCHEMA_DRAFT,
+        "title": "hex_sandbox tileset",
+    });
+    let root = schema.as_object_mut().unwrap();
+    if let Value::Object(def) = tileset_def() {
+        root.extend(def);
+    }
+    root.insert("$defs".into(), Value::Object(common_defs()));
+    schema
+}
+
+/// the tileset format enforced by `TilesetVisitor`
+fn tileset_def() -> Value {
+    json!({
+        "description": "The keys must appear in the order version, name, property_schema, \
+            tiles.",
+        "type": "object",
+        "required": ["version", "name", "tiles"],
+        "properties": {
+            "version": { "const": TILESET_VERSION },
+            "name": { "type": "string" },
+            "property_schema": {
+                "description": "typed properties every tile in the tileset has",
+                "type": "array",
+                "items": { "$ref": "#/$defs/TilePropertyField" }
+            },
+            "tiles": { "type": "array", "items": { "$ref": "#/$defs/Tile" } }
+        },
+        "additionalProperties": false
+    })
+}
+
+/// definitions used by both the map & the tileset schemas
+fn common_defs() -> Map<String, Value> {
+    let mut defs = Map::new();
+    defs.insert(
+        "Tile".into(),
+        json!({
+            "type": "object",
+            "required": ["id", "name", "path", "transform"],
+            "properties": {
+                "id": { "type": "integer", "minimum": 0 },
+                "name": { "type": "string" },
+                "path": {
+                    "description": "path to the glTF model, relative to the asset directory",
+                    "type": "string"
+                },
+                "transform": { "$ref": "#/$defs/Transform" },
+                "color": { "$ref": "#/$defs/Color" },
+                "footprint": {
+                    "description": "offsets of the other hexes covered by the tile, \
+                        relative to the hex it is placed on",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/Location" }
+                },
+                "tags": {
+                    "description": "free-form tags used to find the tile in the editor",
+                    "type": "array",
+                    "items": { "type": "string" }
+                },
+                "properties": {
+                    "description": "values of the fields in the tileset property_schema",
+                    "type": "object",
+                    "additionalProperties": { "$ref": "#/$defs/TileProperty" }
+                }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "TilePropertyField".into(),
+        json!({
+            "type": "object",
+            "required": ["name", "kind", "default"],
+            "properties": {
+                "name": { "type": "string" },
+                "kind": {
+                    "oneOf": [
+                        { "enum": ["Int", "Float", "Bool", "String"] },
+                        enum_variant("Enum", json!({
+                            "description": "names of the options",
+                            "type": "array",
+                            "items": { "type": "string" }
+                        })),
+                    ]
+                },
+                "default": { "$ref": "#/$defs/TileProperty" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "TileProperty".into(),
+        json!({
+            "description": "value of a tile property",
+            "oneOf": [
+                enum_variant("Int", json!({ "type": "integer" })),
+                enum_variant("Float", json!({ "type": "number" })),
+                enum_variant("Bool", json!({ "type": "boolean" })),
+                enum_variant("String", json!({ "type": "string" })),
+                enum_variant("Enum", json!({ "type": "string" })),
+            ]
+        }),
+    );
+    defs.insert(
+        "Location".into(),
+        json!({
+            "type": "object",
+            "description": "axial hex coordinates",
+            "required": ["x", "y"],
+            "properties": {
+                "x": { "type": "integer" },
+                "y": { "type": "integer" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Transform".into(),
+        json!({
+            "type": "object",
+            "required": ["translation", "rotation", "scale"],
+            "properties": {
+                "translation": { "$ref": "#/$defs/Vec3" },
+                "rotation": {
+                    "description": "quaternion [x, y, z, w]",
+                    "$ref": "#/$defs/Vec4"
+                },
+                "scale": { "$ref": "#/$defs/Vec3" }
+            },
+            "additionalProperties": false
+        }),
+    );
+    defs.insert(
+        "Color".into(),
+        json!({
+            "description": "bevy::render::color::Color",
+            "oneOf": [
+                color_variant("Rgba", &["red", "green", "blue", "alpha"]),
+                color_variant("RgbaLinear", &["red", "green", "blue", "alpha"]),
+                color_variant("Hsla", &["hue", "saturation", "lightness", "alpha"]),
+                color_variant("Lcha", &["lightness", "chroma", "hue", "alpha"]),
+            ]
+        }),
+    );
+    for (name, len) in [("Vec2", 2), ("Vec3", 3), ("Vec4", 4)] {
+        defs.insert(
+            name.into(),
+            json!({
+                "type": "array",
+                "items": { "type": "number" },
+                "minItems": len,
+                "maxItems": len
+            }),
+        );
+    }
+    defs
+}
+
+/// externally tagged enum variant holding `value`
+fn enum_variant(name: &str, value: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": [name],
+        "properties": { name: value },
+        "additionalProperties": false
+    })
+}
+
+fn color_variant(name: &str, fields: &[&str]) -> Value {
+    let properties: Map<String, Value> = fields
+        .iter()
+        .map(|f| (f.to_string(), json!({ "type": "number" })))
+        .collect();
+    json!({
+        "type": "object",
+        "required": [name],
+        "properties": {
+            name: {
+                "type": "object",
+                "required": fields,
+                "properties": properties,
+                "additionalProperties": false
+            }
+        },
+        "additionalProperties": false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn published_schemas_up_to_date() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schema");
+        for (name, schema) in schemas() {
+            let path = dir.join(name);
+            let published =
+                std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("read {:?}: {}", path, e));
+            let published: Value = serde_json::from_str(&published).unwrap();
+            assert_eq!(
+                published, schema,
+                "{} out of date; run `cargo run --bin cli -- schema schema/`",
+                name
+            );
+        }
+    }
+}
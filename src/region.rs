@@ -0,0 +1,210 @@
+//! Named regions of hexes
+//!
+//! A region is a named, colored set of hexes on a map, such as a forest, or
+//! the start zone for a player.  Regions may overlap, and aren't tied to a
+//! layer.  Gameplay code uses `Regions::containing()` to find the regions a
+//! hex belongs to, and the user-defined properties of a region to drive
+//! encounters & scripting.
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use hexx::HexLayout;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{map, map::Location, object::Property, selection};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_region_overlay);
+    }
+}
+
+/// a named set of hexes; spawned as a child of the map root
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    /// color of the region overlay
+    pub color: Color,
+    pub locations: HashSet<Location>,
+    pub properties: BTreeMap<String, Property>,
+}
+
+impl Region {
+    pub fn new(name: impl Into<String>, color: Color) -> Self {
+        Self {
+            name: name.into(),
+            color,
+            locations: HashSet::new(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    pub fn contains(&self, location: Location) -> bool {
+        self.locations.contains(&location)
+    }
+}
+
+/// query the regions of the map
+#[derive(SystemParam)]
+pub struct Regions<'w, 's> {
+    regions: Query<'w, 's, (Entity, &'static Region)>,
+}
+
+impl<'w, 's> Regions<'w, 's> {
+    /// every region that contains `location`
+    pub fn containing(&self, location: Location) -> impl Iterator<Item = (Entity, &Region)> {
+        self.regions
+            .iter()
+            .filter(move |(_, region)| region.contains(location))
+    }
+
+    /// find a region by name
+    pub fn get_by_name(&self, name: &str) -> Option<(Entity, &Region)> {
+        self.regions.iter().find(|(_, region)| region.name == name)
+    }
+}
+
+/// overlay drawn for a region; child of the Region entity
+#[derive(Component)]
+struct RegionOverlay;
+
+/// height of the overlay above the map, so it's drawn over flat tiles
+const OVERLAY_HEIGHT: f32 = 0.04;
+
+/// redraw the overlay of regions that changed
+fn update_region_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    map: Query<&map::Map>,
+    regions: Query<(Entity, &Region, Option<&Children>), Changed<Region>>,
+    overlays: Query<(), With<RegionOverlay>>,
+) {
+    let Ok(map) = map.get_single() else { return };
+    for (entity, region, children) in &regions {
+        for child in children.into_iter().flatten() {
+            if overlays.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        if region.locations.is_empty() {
+            continue;
+        }
+
+        let fill = materials.add(StandardMaterial {
+            base_color: region.color.with_a(0.3),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        let border = materials.add(StandardMaterial {
+            base_color: region.color,
+            unlit: true,
+            ..default()
+        });
+        commands.entity(entity).with_children(|region_entity| {
+            region_entity.spawn((
+                Name::new("region_fill"),
+                RegionOverlay,
+                PbrBundle {
+                    mesh: meshes.add(fill_mesh(&map.layout, &region.locations)),
+                    material: fill,
+                    transform: Transform::from_translation(Vec3::Y * OVERLAY_HEIGHT),
+                    ..default()
+                },
+            ));
+            region_entity.spawn((
+                Name::new("region_border"),
+                RegionOverlay,
+                PbrBundle {
+                    mesh: meshes.add(selection::outline_mesh(
+                        &map.layout,
+                        &region.locations,
+                        0.08,
+                    )),
+                    material: border,
+                    transform: Transform::from_translation(Vec3::Y * (OVERLAY_HEIGHT + 0.005)),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+/// build a flat mesh covering every hex in `locations`, in the XZ plane at
+/// y = 0
+pub fn fill_mesh(layout: &HexLayout, locations: &HashSet<Location>) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for location in locations {
+        let center = layout.hex_to_world_pos((*location).into());
+        let base = positions.len() as u32;
+        positions.push([center.x, 0.0, center.y]);
+        for corner in layout.hex_corners((*location).into()) {
+            positions.push([corner.x, 0.0, corner.y]);
+        }
+        // fan around the center, wound both ways to be visible from below
+        for i in 0..6 {
+            let (a, b) = (base + 1 + i, base + 1 + (i + 1) % 6);
+            indices.extend([base, a, b, base, b, a]);
+        }
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use test_log::test;
+
+    #[test]
+    fn regions_containing() {
+        let mut world = World::new();
+        let mut forest = Region::new("forest", Color::GREEN);
+        forest
+            .locations
+            .extend([Location::from((0, 0)), (1, 0).into()]);
+        let forest = world.spawn(forest).id();
+        let mut start = Region::new("start", Color::BLUE);
+        start
+            .locations
+            .extend([Location::from((1, 0)), (2, 0).into()]);
+        let start = world.spawn(start).id();
+
+        let mut state: SystemState<Regions> = SystemState::new(&mut world);
+        let regions = state.get(&world);
+        let containing = |x, y| {
+            let mut entities: Vec<Entity> =
+                regions.containing((x, y).into()).map(|(e, _)| e).collect();
+            entities.sort();
+            entities
+        };
+        assert_eq!(containing(0, 0), vec![forest]);
+        assert_eq!(containing(1, 0), vec![forest, start]);
+        assert_eq!(containing(3, 0), vec![]);
+        assert_eq!(regions.get_by_name("start").map(|(e, _)| e), Some(start));
+    }
+
+    #[test]
+    fn fill_mesh_covers_each_hex() {
+        let locations: HashSet<Location> = [(0, 0).into(), (1, 0).into()].into();
+        let mesh = fill_mesh(&HexLayout::default(), &locations);
+        assert_eq!(mesh.count_vertices(), 14);
+        let Some(Indices::U32(indices)) = mesh.indices() else { panic!("no indices") };
+        assert_eq!(indices.len(), 2 * 6 * 6);
+    }
+}
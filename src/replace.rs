@@ -0,0 +1,176 @@
+//! Find & replace tiles on a map
+//!
+//! A `TileReplace` maps tiles to their replacements; it is either a single
+//! tile, or every tile in a tileset mapped by name to the tiles in another
+//! tileset.
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::tileset::{TileId, TileRef, TileRotation, TileTransform, Tileset};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileReplace {
+    /// replacement for each tile
+    pub mapping: HashMap<TileRef, TileRef>,
+    /// only replace tiles with this rotation
+    pub rotation: Option<TileRotation>,
+    /// set the rotation of replaced tiles to `TileRotation::None`
+    pub reset_rotation: bool,
+}
+
+impl TileReplace {
+    /// replace a single tile
+    pub fn tile(from: TileRef, to: TileRef) -> Self {
+        Self {
+            mapping: HashMap::from([(from, to)]),
+            ..default()
+        }
+    }
+
+    /// replace every tile in the `from` tileset with the tile of the same
+    /// name in the `to` tileset
+    ///
+    /// Tiles without a match in the `to` tileset are not replaced; names are
+    /// compared ignoring case.
+    pub fn tileset_by_name(world: &World, from: Entity, to: Entity) -> Result<Self> {
+        let tiles: Vec<TileId> = world
+            .get::<Tileset>(from)
+            .context(format!("failed to get source Tileset {:?}", from))?
+            .tile_order
+            .clone();
+        Self::tiles_by_name(world, from, &tiles, to)
+    }
+
+    /// replace some tiles in the `from` tileset with the tiles of the same
+    /// name in the `to` tileset; see `tileset_by_name()`
+    pub fn tiles_by_name(
+        world: &World,
+        from: Entity,
+        tiles: &[TileId],
+        to: Entity,
+    ) -> Result<Self> {
+        let from_tileset = world
+            .get::<Tileset>(from)
+            .context(format!("failed to get source Tileset {:?}", from))?;
+        let to_tileset = world
+            .get::<Tileset>(to)
+            .context(format!("failed to get destination Tileset {:?}", to))?;
+
+        let names: HashMap<String, usize> = to_tileset
+            .tiles
+            .values()
+            .map(|tile| (tile.name.to_lowercase(), tile.id))
+            .collect();
+        let mut mapping = HashMap::new();
+        for tile in tiles.iter().filter_map(|id| from_tileset.tiles.get(id)) {
+            let Some(id) = names.get(&tile.name.to_lowercase()) else { continue };
+            mapping.insert(
+                TileRef {
+                    tileset: from,
+                    tile: tile.id,
+                },
+                TileRef {
+                    tileset: to,
+                    tile: *id,
+                },
+            );
+        }
+        if mapping.is_empty() {
+            bail!(
+                "no tiles in tileset \"{}\" match tiles in \"{}\"",
+                from_tileset.name,
+                to_tileset.name
+            );
+        }
+        Ok(Self {
+            mapping,
+            ..default()
+        })
+    }
+
+    /// the tile & transform to replace the tile with, or None if the tile is
+    /// not replaced
+    pub fn replacement(
+        &self,
+        tile_ref: &TileRef,
+        transform: &TileTransform,
+    ) -> Option<(TileRef, TileTransform)> {
+        if let Some(rotation) = self.rotation {
+            if transform.rotation != rotation {
+                return None;
+            }
+        }
+        let to = self.mapping.get(tile_ref)?;
+        let rotation = if self.reset_rotation {
+            TileRotation::None
+        } else {
+            transform.rotation
+        };
+        Some((*to, TileTransform { rotation }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    fn tileset(world: &mut World, name: &str, tiles: &[&str]) -> Entity {
+        let mut tileset = Tileset::new(name);
+        for tile in tiles {
+            tileset.add_tile(format!("{}.glb", tile).into());
+        }
+        world.spawn(tileset).id()
+    }
+
+    #[test]
+    fn replace_tile() {
+        let from = TileRef {
+            tileset: Entity::PLACEHOLDER,
+            tile: 1,
+        };
+        let to = TileRef {
+            tileset: Entity::PLACEHOLDER,
+            tile: 2,
+        };
+        let cw60 = TileTransform {
+            rotation: TileRotation::Clockwise60,
+        };
+        let mut replace = TileReplace::tile(from, to);
+        assert_eq!(replace.replacement(&from, &cw60), Some((to, cw60.clone())));
+        assert_eq!(replace.replacement(&to, &cw60), None);
+
+        replace.reset_rotation = true;
+        assert_eq!(
+            replace.replacement(&from, &cw60),
+            Some((to, TileTransform::default()))
+        );
+
+        replace.rotation = Some(TileRotation::None);
+        assert_eq!(replace.replacement(&from, &cw60), None);
+        assert!(replace
+            .replacement(&from, &TileTransform::default())
+            .is_some());
+    }
+
+    #[test]
+    fn replace_tileset_by_name() {
+        let mut world = World::new();
+        let a = tileset(&mut world, "a", &["grass", "water", "sand"]);
+        let b = tileset(&mut world, "b", &["Water", "stone", "grass"]);
+        let replace = TileReplace::tileset_by_name(&world, a, b).unwrap();
+        let tile = |tileset, tile| TileRef { tileset, tile };
+        assert_eq!(
+            replace.mapping,
+            HashMap::from([(tile(a, 0), tile(b, 2)), (tile(a, 1), tile(b, 0))])
+        );
+
+        let c = tileset(&mut world, "c", &["lava"]);
+        assert!(TileReplace::tileset_by_name(&world, a, c).is_err());
+
+        let replace = TileReplace::tiles_by_name(&world, a, &[1, 2], b).unwrap();
+        assert_eq!(replace.mapping, HashMap::from([(tile(a, 1), tile(b, 0))]));
+        assert!(TileReplace::tiles_by_name(&world, a, &[2], b).is_err());
+    }
+}
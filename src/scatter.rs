@@ -0,0 +1,707 @@
+//! Scatter decoration tiles onto a layer using rules
+//!
+//! A `Scatter` places decoration tiles onto a target layer based on the tiles
+//! in a source layer, such as trees on 30% of the grass hexes, but never next
+//! to a path.  Tiles are referred to by the names of the tile & its tileset, so
+//! the same rules work on any map; they are saved as presets in the user
+//! library, see `presets_path()`.
+//!
+//! Placements only depend on the seed & the source tiles, so scattering the
+//! same layer with the same seed always gives the same result.
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use hexx::Hex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    brush::Rng,
+    map,
+    persistence::{self, FileFormat, MapFormat},
+    stamp, tileset,
+};
+
+pub const PRESETS_VERSION: usize = 1;
+
+/// a tile referred to by name, so rules can be shared between maps
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TileName {
+    pub tileset: String,
+    pub tile: String,
+}
+
+impl TileName {
+    /// name of a tile in the World
+    pub fn from_ref(world: &World, tile_ref: &tileset::TileRef) -> Option<Self> {
+        let tileset = world.get::<tileset::Tileset>(tile_ref.tileset)?;
+        Some(Self {
+            tileset: tileset.name.clone(),
+            tile: tileset.tiles.get(&tile_ref.tile)?.name.clone(),
+        })
+    }
+}
+
+impl std::fmt::Display for TileName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.tileset, self.tile)
+    }
+}
+
+/// one decoration rule of a `Scatter`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScatterRule {
+    /// source tiles the decoration may be placed on; any tile if empty
+    pub on: Vec<TileName>,
+    /// source tiles the decoration may not be placed next to
+    pub not_adjacent: Vec<TileName>,
+    /// chance of placing a decoration on each matching hex, 0.0 to 1.0
+    pub density: f32,
+    /// decoration tiles; one is picked at random for each placement
+    pub place: Vec<TileName>,
+    /// give each decoration a random rotation
+    pub random_rotation: bool,
+}
+
+impl Default for ScatterRule {
+    fn default() -> Self {
+        Self {
+            on: Vec::new(),
+            not_adjacent: Vec::new(),
+            density: 0.3,
+            place: Vec::new(),
+            random_rotation: true,
+        }
+    }
+}
+
+/// a set of rules for placing decoration tiles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scatter {
+    pub name: String,
+    /// rules are applied in order; earlier rules get the first pick of hexes
+    pub rules: Vec<ScatterRule>,
+    /// smallest distance in hexes between two placed decorations; 0 & 1 allow
+    /// decorations on neighboring hexes
+    pub min_spacing: u32,
+    pub seed: u64,
+}
+
+impl Default for Scatter {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            rules: Vec::new(),
+            min_spacing: 1,
+            seed: 1,
+        }
+    }
+}
+
+/// a decoration tile placed by a `Scatter`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub location: map::Location,
+    pub tile: TileName,
+    pub rotation: tileset::TileRotation,
+}
+
+impl Scatter {
+    /// decorations to place given the source layer tiles, and the hexes
+    /// already covered by tiles in the target layer
+    ///
+    /// `footprint` gives every hex covered by a decoration placed at a
+    /// location, see `tileset::Tile::footprint_at()`; decorations are only
+    /// placed where none of those hexes are covered.
+    pub fn placements(
+        &self,
+        source: &HashMap<map::Location, TileName>,
+        occupied: &HashSet<map::Location>,
+        footprint: impl Fn(&TileName, map::Location, tileset::TileRotation) -> Vec<map::Location>,
+    ) -> Vec<Placement> {
+        // visit the hexes in a fixed order so spacing is reproducible
+        let mut locations: Vec<map::Location> = source.keys().copied().collect();
+        locations.sort_by_key(|l| (l.y, l.x));
+
+        let mut placed = HashSet::new();
+        let mut placements = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.place.is_empty() {
+                continue;
+            }
+            // each rule makes its own picks for a hex
+            let seed = Rng::new(self.seed.wrapping_add(index as u64)).next_u64();
+
+            for location in &locations {
+                let tile = &source[location];
+                if !rule.on.is_empty() && !rule.on.contains(tile) {
+                    continue;
+                }
+                if occupied.contains(location) || placed.contains(location) {
+                    continue;
+                }
+                let hex = Hex::from(*location);
+                let next_to_excluded = hex.all_neighbors().iter().any(|h| {
+                    matches!(source.get(&(*h).into()), Some(t) if rule.not_adjacent.contains(t))
+                });
+                if next_to_excluded {
+                    continue;
+                }
+
+                let mut rng = Rng::for_location(seed, *location);
+                if rng.next_f32() >= rule.density {
+                    continue;
+                }
+                if self.min_spacing > 1
+                    && hex
+                        .range(self.min_spacing - 1)
+                        .any(|h| placed.contains(&map::Location::from(h)))
+                {
+                    continue;
+                }
+
+                let tile = rule.place[rng.below(rule.place.len())].clone();
+                let rotation = match rule.random_rotation {
+                    true => (0..rng.below(6))
+                        .fold(default(), |r: tileset::TileRotation, _| r.clockwise()),
+                    false => tileset::TileRotation::None,
+                };
+                let covered = footprint(&tile, *location, rotation);
+                if covered
+                    .iter()
+                    .any(|l| occupied.contains(l) || placed.contains(l))
+                {
+                    continue;
+                }
+                placed.extend(covered);
+                placements.push(Placement {
+                    location: *location,
+                    tile,
+                    rotation,
+                });
+            }
+        }
+        placements
+    }
+
+    /// scatter decorations onto the `target` layer of a map using the tiles
+    /// of the `source` layer; the target layer is created if it doesn't exist
+    ///
+    /// Multi-hex tiles are handled like `world_placements()`.  Returns the
+    /// number of decorations placed.
+    pub fn apply(&self, map: &mut MapFormat, source: &str, target: &str) -> Result<usize> {
+        let names: HashMap<persistence::SaveId, &tileset::Tileset> =
+            map.tilesets.iter().map(|(id, t)| (*id, t)).collect();
+        let tile_name = |tileset: &persistence::SaveId, tile: &tileset::TileId| {
+            let tileset = names.get(tileset)?;
+            Some(TileName {
+                tileset: tileset.name.clone(),
+                tile: tileset.tiles.get(tile)?.name.clone(),
+            })
+        };
+
+        let source_layer = map
+            .layers
+            .iter()
+            .find(|layer| layer.name == source)
+            .context(format!("source layer not found: {}", source))?;
+        let footprint = |t: &persistence::Tile| match names.get(&t.tileset) {
+            Some(tileset) => tileset.footprint(t.tile_id, t.location, t.rotation),
+            None => vec![t.location],
+        };
+        // every hex covered by a source tile counts as that tile, like the
+        // `map::Layer` index used in the editor
+        let mut source_tiles = HashMap::new();
+        for tile in &source_layer.tiles {
+            let Some(name) = tile_name(&tile.tileset, &tile.tile_id) else { continue };
+            for location in footprint(tile) {
+                source_tiles.insert(location, name.clone());
+            }
+        }
+
+        let occupied: HashSet<map::Location> = match map.layers.iter().find(|l| l.name == target) {
+            Some(layer) => layer.tiles.iter().flat_map(footprint).collect(),
+            None => HashSet::new(),
+        };
+
+        // resolve the decoration tiles before changing the map
+        let decoration_footprint =
+            |name: &TileName, location, rotation| match find_tile(&map.tilesets, name) {
+                Some((id, tile_id)) => map.tilesets[&id].footprint(tile_id, location, rotation),
+                None => vec![location],
+            };
+        let mut tiles = Vec::new();
+        for placement in self.placements(&source_tiles, &occupied, decoration_footprint) {
+            let Some((id, tile_id)) = find_tile(&map.tilesets, &placement.tile) else {
+                bail!("decoration tile not found in map: {}", placement.tile);
+            };
+            tiles.push(persistence::Tile {
+                location: placement.location,
+                tileset: id,
+                tile_id,
+                rotation: placement.rotation,
+                ..default()
+            });
+        }
+
+        let count = tiles.len();
+        let layer = match map.layers.iter().position(|l| l.name == target) {
+            Some(index) => &mut map.layers[index],
+            None => {
+                let order = map.layers.iter().map(|l| l.order + 1).max().unwrap_or(0);
+                map.layers.push(persistence::Layer {
+                    name: target.to_string(),
+                    order,
+                    ..default()
+                });
+                map.layers.last_mut().unwrap()
+            }
+        };
+        if layer.kind != map::LayerKind::Tile {
+            bail!("target layer is not a tile layer: {}", target);
+        }
+        layer.tiles.extend(tiles);
+        Ok(count)
+    }
+}
+
+impl Scatter {
+    /// decorations to place on the `target` layer in the World using the
+    /// tiles of the `source` layer
+    ///
+    /// Every hex covered by a multi-hex tile counts as that tile in the source
+    /// layer, and as occupied in the target layer.
+    pub fn world_placements(
+        &self,
+        world: &mut World,
+        source: Entity,
+        target: Entity,
+    ) -> Result<Vec<(map::Location, tileset::TileRef, tileset::TileTransform)>> {
+        let tiles = world_tiles(world);
+        let names: HashMap<tileset::TileRef, &TileName> = tiles
+            .iter()
+            .map(|(name, tile_ref)| (*tile_ref, name))
+            .collect();
+
+        let source_layer = world
+            .get::<map::Layer>(source)
+            .context(format!("failed to get source Layer {:?}", source))?;
+        let source_tiles: HashMap<map::Location, TileName> = source_layer
+            .tiles
+            .iter()
+            .filter_map(|(location, (_, tile_ref))| {
+                Some((*location, (*names.get(tile_ref)?).clone()))
+            })
+            .collect();
+        let target_layer = world
+            .get::<map::Layer>(target)
+            .context(format!("failed to get target Layer {:?}", target))?;
+        let occupied: HashSet<map::Location> = target_layer.tiles.keys().copied().collect();
+
+        let footprint = |name: &TileName, location, rotation| {
+            let footprint = |tile_ref: &tileset::TileRef| {
+                let tileset = world.get::<tileset::Tileset>(tile_ref.tileset)?;
+                Some(tileset.footprint(tile_ref.tile, location, rotation))
+            };
+            tiles
+                .get(name)
+                .and_then(footprint)
+                .unwrap_or_else(|| vec![location])
+        };
+        self.placements(&source_tiles, &occupied, footprint)
+            .into_iter()
+            .map(|placement| {
+                let tile_ref = tiles
+                    .get(&placement.tile)
+                    .context(format!("decoration tile not found: {}", placement.tile))?;
+                let transform = tileset::TileTransform {
+                    rotation: placement.rotation,
+                };
+                Ok((placement.location, *tile_ref, transform))
+            })
+            .collect()
+    }
+}
+
+/// every tile in the tilesets of the World by name; if names repeat, the tile
+/// in the oldest tileset is used
+pub fn world_tiles(world: &mut World) -> HashMap<TileName, tileset::TileRef> {
+    let mut tilesets: Vec<(Entity, &tileset::Tileset)> = world
+        .query::<(Entity, &tileset::Tileset)>()
+        .iter(world)
+        .collect();
+    tilesets.sort_by_key(|(entity, _)| *entity);
+
+    let mut tiles = HashMap::new();
+    for (entity, tileset) in tilesets {
+        for tile in tileset.tiles.values() {
+            let name = TileName {
+                tileset: tileset.name.clone(),
+                tile: tile.name.clone(),
+            };
+            tiles.entry(name).or_insert(tileset::TileRef {
+                tileset: entity,
+                tile: tile.id,
+            });
+        }
+    }
+    tiles
+}
+
+/// find a tile by name in the tilesets of a map
+fn find_tile(
+    tilesets: &std::collections::BTreeMap<persistence::SaveId, tileset::Tileset>,
+    name: &TileName,
+) -> Option<(persistence::SaveId, tileset::TileId)> {
+    tilesets
+        .iter()
+        .filter(|(_, tileset)| tileset.name == name.tileset)
+        .find_map(|(id, tileset)| {
+            let tile = tileset.tiles.values().find(|tile| tile.name == name.tile)?;
+            Some((*id, tile.id))
+        })
+}
+
+/// saved scatter presets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Presets {
+    pub version: usize,
+    pub presets: Vec<Scatter>,
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        Self {
+            version: PRESETS_VERSION,
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Presets {
+    /// load presets from a RON or JSON file; a missing file has no presets
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let buf = std::fs::read_to_string(path).context(format!("read presets {:?}", path))?;
+        let presets: Self = FileFormat::from_path(path)
+            .deserialize(&buf)
+            .context(format!("parse presets {:?}", path))?;
+        if presets.version != PRESETS_VERSION {
+            bail!(
+                "unsupported scatter presets version {} in {:?}",
+                presets.version,
+                path
+            );
+        }
+        Ok(presets)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).context(format!("create directory {:?}", dir))?;
+        }
+        let buf = FileFormat::from_path(path).serialize(self)?;
+        std::fs::write(path, buf).context(format!("write presets {:?}", path))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Scatter> {
+        self.presets.iter().find(|preset| preset.name == name)
+    }
+
+    /// add a preset, replacing any preset with the same name
+    pub fn insert(&mut self, scatter: Scatter) {
+        match self.presets.iter_mut().find(|p| p.name == scatter.name) {
+            Some(preset) => *preset = scatter,
+            None => self.presets.push(scatter),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.presets.retain(|preset| preset.name != name);
+    }
+}
+
+/// file the scatter presets are saved to in the user library
+pub fn presets_path() -> PathBuf {
+    stamp::library_root().join("scatter.ron")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    fn name(tile: &str) -> TileName {
+        TileName {
+            tileset: "terrain".into(),
+            tile: tile.into(),
+        }
+    }
+
+    /// grass everywhere, with a path along y == 0
+    fn source() -> HashMap<map::Location, TileName> {
+        let mut source = HashMap::new();
+        for location in Hex::ZERO.range(6).map(map::Location::from) {
+            let tile = if location.y == 0 { "path" } else { "grass" };
+            source.insert(location, name(tile));
+        }
+        source
+    }
+
+    fn one_hex(
+        _: &TileName,
+        location: map::Location,
+        _: tileset::TileRotation,
+    ) -> Vec<map::Location> {
+        vec![location]
+    }
+
+    fn trees(density: f32) -> Scatter {
+        Scatter {
+            rules: vec![ScatterRule {
+                on: vec![name("grass")],
+                not_adjacent: vec![name("path")],
+                density,
+                place: vec![name("tree"), name("bush")],
+                random_rotation: true,
+            }],
+            seed: 7,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn placements_follow_rules() {
+        let source = source();
+        let scatter = trees(0.3);
+        let placements = scatter.placements(&source, &HashSet::new(), one_hex);
+        assert!(!placements.is_empty());
+        assert_eq!(
+            placements,
+            scatter.placements(&source, &HashSet::new(), one_hex)
+        );
+
+        for placement in &placements {
+            let location = placement.location;
+            assert_eq!(source[&location], name("grass"));
+            assert!(location.y.abs() > 1, "next to the path: {:?}", location);
+        }
+
+        let candidates = source
+            .keys()
+            .filter(|l| l.y.abs() > 1 && Hex::from(**l).length() < 6)
+            .count();
+        let ratio = placements.len() as f32 / candidates as f32;
+        assert!((0.1..0.5).contains(&ratio), "ratio {}", ratio);
+    }
+
+    #[test]
+    fn placements_spacing_and_occupied() {
+        let source = source();
+        let mut scatter = trees(1.0);
+        let occupied = HashSet::from([map::Location { x: 0, y: 3 }]);
+        let placements = scatter.placements(&source, &occupied, one_hex);
+        assert!(placements
+            .iter()
+            .all(|p| p.location != map::Location { x: 0, y: 3 }));
+
+        scatter.min_spacing = 3;
+        let placements = scatter.placements(&source, &HashSet::new(), one_hex);
+        assert!(placements.len() > 1);
+        for a in &placements {
+            for b in &placements {
+                if a != b {
+                    let distance = Hex::from(a.location).distance_to(b.location.into());
+                    assert!(distance >= 3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn apply_to_map() {
+        let mut terrain = tileset::Tileset::new("terrain");
+        for tile in ["grass", "path", "tree"] {
+            terrain.add_tile(format!("{}.glb", tile).into());
+        }
+        let grass = find_tile_id(&terrain, "grass");
+        let tree = find_tile_id(&terrain, "tree");
+
+        let mut map = MapFormat::default();
+        let id = persistence::SaveId::default();
+        map.tilesets.insert(id, terrain);
+        map.layers.push(persistence::Layer {
+            name: "ground".into(),
+            tiles: Hex::ZERO
+                .range(3)
+                .map(|h| persistence::Tile {
+                    location: h.into(),
+                    tileset: id,
+                    tile_id: grass,
+                    ..default()
+                })
+                .collect(),
+            ..default()
+        });
+
+        let scatter = Scatter {
+            rules: vec![ScatterRule {
+                on: vec![name("grass")],
+                density: 0.5,
+                place: vec![name("tree")],
+                ..default()
+            }],
+            ..default()
+        };
+        let count = scatter.apply(&mut map, "ground", "trees").unwrap();
+        assert!(count > 0);
+        let layer = map.layers.iter().find(|l| l.name == "trees").unwrap();
+        assert_eq!(layer.tiles.len(), count);
+        assert!(layer.tiles.iter().all(|t| t.tile_id == tree));
+
+        // scattering again doesn't place on occupied hexes
+        assert_eq!(scatter.apply(&mut map, "ground", "trees").unwrap(), 0);
+
+        let missing = Scatter {
+            rules: vec![ScatterRule {
+                density: 1.0,
+                place: vec![name("rock")],
+                ..default()
+            }],
+            ..default()
+        };
+        assert!(missing.apply(&mut map, "ground", "rocks").is_err());
+        assert!(scatter.apply(&mut map, "water", "trees").is_err());
+    }
+
+    #[test]
+    fn world_placements() {
+        let mut world = World::new();
+        let mut terrain = tileset::Tileset::new("terrain");
+        for tile in ["grass", "tree"] {
+            terrain.add_tile(format!("{}.glb", tile).into());
+        }
+        let grass = find_tile_id(&terrain, "grass");
+        let tree = find_tile_id(&terrain, "tree");
+        let tileset = world.spawn(terrain).id();
+
+        let grass_ref = tileset::TileRef {
+            tileset,
+            tile: grass,
+        };
+        let mut ground = map::Layer::new("ground".into());
+        for hex in Hex::ZERO.range(3) {
+            ground
+                .tiles
+                .insert(hex.into(), (Entity::PLACEHOLDER, grass_ref));
+        }
+        let ground = world.spawn(ground).id();
+        let mut trees = map::Layer::new("trees".into());
+        let occupied = map::Location { x: 1, y: 1 };
+        trees
+            .tiles
+            .insert(occupied, (Entity::PLACEHOLDER, grass_ref));
+        let trees = world.spawn(trees).id();
+
+        let scatter = Scatter {
+            rules: vec![ScatterRule {
+                on: vec![name("grass")],
+                density: 1.0,
+                place: vec![name("tree")],
+                random_rotation: false,
+                ..default()
+            }],
+            ..default()
+        };
+        let placements = scatter.world_placements(&mut world, ground, trees).unwrap();
+        assert_eq!(placements.len(), Hex::ZERO.range(3).count() - 1);
+        for (location, tile_ref, transform) in placements {
+            assert_ne!(location, occupied);
+            assert_eq!(
+                tile_ref,
+                tileset::TileRef {
+                    tileset,
+                    tile: tree
+                }
+            );
+            assert_eq!(transform.rotation, tileset::TileRotation::None);
+        }
+    }
+
+    #[test]
+    fn footprints() {
+        // a meadow covering three hexes, & boulders covering two
+        let mut terrain = tileset::Tileset::new("terrain");
+        for (tile, footprint) in [("meadow", vec![(1, 0), (0, 1)]), ("boulder", vec![(1, 0)])] {
+            terrain.add_tile(format!("{}.glb", tile).into());
+            let id = find_tile_id(&terrain, tile);
+            terrain.tiles.get_mut(&id).unwrap().footprint =
+                footprint.into_iter().map(map::Location::from).collect();
+        }
+        let meadow = find_tile_id(&terrain, "meadow");
+        let scatter = Scatter {
+            rules: vec![ScatterRule {
+                on: vec![name("meadow")],
+                density: 1.0,
+                place: vec![name("boulder")],
+                random_rotation: false,
+                ..default()
+            }],
+            ..default()
+        };
+
+        let mut map = MapFormat::default();
+        let id = persistence::SaveId::default();
+        map.tilesets.insert(id, terrain.clone());
+        map.layers.push(persistence::Layer {
+            name: "ground".into(),
+            tiles: vec![persistence::Tile {
+                location: (0, 0).into(),
+                tileset: id,
+                tile_id: meadow,
+                ..default()
+            }],
+            ..default()
+        });
+        assert_eq!(scatter.apply(&mut map, "ground", "boulders").unwrap(), 2);
+        let layer = map.layers.iter().find(|l| l.name == "boulders").unwrap();
+        let locations: Vec<(i32, i32)> = layer
+            .tiles
+            .iter()
+            .map(|t| (t.location.x, t.location.y))
+            .collect();
+        // the boulder at the origin also covers (1, 0)
+        assert_eq!(locations, vec![(0, 0), (0, 1)]);
+
+        // the editor scatters the same boulders
+        let mut world = World::new();
+        let tileset = world.spawn(terrain).id();
+        let mut ground = map::Layer::new("ground".into());
+        for location in [(0, 0), (1, 0), (0, 1)] {
+            let tile_ref = tileset::TileRef {
+                tileset,
+                tile: meadow,
+            };
+            ground
+                .tiles
+                .insert(location.into(), (Entity::PLACEHOLDER, tile_ref));
+        }
+        let ground = world.spawn(ground).id();
+        let boulders = world.spawn(map::Layer::new("boulders".into())).id();
+        let placements = scatter
+            .world_placements(&mut world, ground, boulders)
+            .unwrap();
+        let placed: Vec<(i32, i32)> = placements.iter().map(|(l, ..)| (l.x, l.y)).collect();
+        assert_eq!(placed, locations);
+    }
+
+    fn find_tile_id(tileset: &tileset::Tileset, name: &str) -> tileset::TileId {
+        tileset.tiles.values().find(|t| t.name == name).unwrap().id
+    }
+}
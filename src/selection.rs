@@ -0,0 +1,177 @@
+//! Helpers for working with groups of selected hexes
+//!
+//! These are used by the selection tools & stamps to find the center of a
+//! group of hexes, transform them, and draw their outline.
+use bevy::{
+    prelude::*,
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use hexx::{Hex, HexLayout};
+use std::collections::HashSet;
+
+use crate::{map::Location, tileset::TileRotation};
+
+/// the location nearest the center of the locations; used as the pivot when
+/// rotating a group of hexes
+pub fn pivot<'a>(locations: impl IntoIterator<Item = &'a Location>) -> Option<Location> {
+    let locations: Vec<Location> = locations.into_iter().copied().collect();
+    if locations.is_empty() {
+        return None;
+    }
+    let count = locations.len() as f32;
+    let (sum_x, sum_y) = locations
+        .iter()
+        .fold((0.0, 0.0), |(x, y), l| (x + l.x as f32, y + l.y as f32));
+    let center = Vec2::new(sum_x / count, sum_y / count);
+    locations.into_iter().min_by(|a, b| {
+        let a = Vec2::new(a.x as f32, a.y as f32).distance_squared(center);
+        let b = Vec2::new(b.x as f32, b.y as f32).distance_squared(center);
+        a.total_cmp(&b)
+    })
+}
+
+/// rotate `location` around `pivot`
+pub fn rotate_about(location: Location, pivot: Location, rotation: TileRotation) -> Location {
+    let offset = Location {
+        x: location.x - pivot.x,
+        y: location.y - pivot.y,
+    }
+    .rotate(rotation);
+    Location {
+        x: pivot.x + offset.x,
+        y: pivot.y + offset.y,
+    }
+}
+
+/// check if a location is inside the polygon traced by `path`, or on the path
+/// itself
+///
+/// The axial coordinates are used directly; they are an affine transform of
+/// the world positions, so the result is the same as testing the hex centers.
+pub fn in_lasso(path: &[Location], location: Location) -> bool {
+    if path.contains(&location) {
+        return true;
+    }
+    if path.len() < 3 {
+        return false;
+    }
+    let (px, py) = (location.x as f32, location.y as f32);
+    let mut inside = false;
+    let mut prev = path[path.len() - 1];
+    for l in path {
+        let (ax, ay) = (l.x as f32, l.y as f32);
+        let (bx, by) = (prev.x as f32, prev.y as f32);
+        if (ay > py) != (by > py) && px < (bx - ax) * (py - ay) / (by - ay) + ax {
+            inside = !inside;
+        }
+        prev = *l;
+    }
+    inside
+}
+
+/// edges on the border of a group of hexes, as pairs of the hex inside the
+/// group and the neighboring hex outside of it
+pub fn outline(locations: &HashSet<Location>) -> Vec<(Location, Location)> {
+    let mut edges = Vec::new();
+    for location in locations {
+        for neighbor in Hex::from(*location).all_neighbors() {
+            let neighbor: Location = neighbor.into();
+            if !locations.contains(&neighbor) {
+                edges.push((*location, neighbor));
+            }
+        }
+    }
+    edges
+}
+
+/// build a flat mesh of the outline of a group of hexes
+///
+/// Each edge is a strip `width` wide along the inside of the border.  The
+/// mesh is in the XZ plane at y = 0.
+pub fn outline_mesh(layout: &HexLayout, locations: &HashSet<Location>, width: f32) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for (inside, outside) in outline(locations) {
+        let center = layout.hex_to_world_pos(inside.into());
+        let neighbor = layout.hex_to_world_pos(outside.into());
+
+        // the shared edge is made of the two corners closest to the neighbor
+        let mut corners = layout.hex_corners(inside.into());
+        corners.sort_by(|a, b| {
+            a.distance_squared(neighbor)
+                .total_cmp(&b.distance_squared(neighbor))
+        });
+        let (a, b) = (corners[0], corners[1]);
+        let (a_in, b_in) = (a.lerp(center, width), b.lerp(center, width));
+
+        let base = positions.len() as u32;
+        for p in [a, b, b_in, a_in] {
+            positions.push([p.x, 0.0, p.y]);
+        }
+        // wind both ways so the strip is visible regardless of corner order
+        indices.extend([0, 1, 2, 0, 2, 3, 0, 2, 1, 0, 3, 2].map(|i| base + i));
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn pivot_nearest_center() {
+        let locations: Vec<Location> = vec![(0, 0).into(), (1, 0).into(), (2, 0).into()];
+        assert_eq!(pivot(&locations), Some((1, 0).into()));
+        assert_eq!(pivot(&[]), None);
+    }
+
+    #[test]
+    fn rotate_about_pivot() {
+        let pivot = Location { x: 3, y: 3 };
+        assert_eq!(rotate_about(pivot, pivot, TileRotation::Clockwise60), pivot);
+        assert_eq!(
+            rotate_about((5, 3).into(), pivot, TileRotation::Clockwise180),
+            (1, 3).into()
+        );
+        let mut location = Location { x: 4, y: 2 };
+        for _ in 0..6 {
+            location = rotate_about(location, pivot, TileRotation::Clockwise60);
+        }
+        assert_eq!(location, (4, 2).into());
+    }
+
+    #[test]
+    fn lasso() {
+        let path: Vec<Location> = vec![(0, 0).into(), (4, 0).into(), (4, 4).into(), (0, 4).into()];
+        assert!(in_lasso(&path, (2, 2).into()));
+        assert!(in_lasso(&path, (4, 4).into()));
+        assert!(!in_lasso(&path, (5, 2).into()));
+        assert!(!in_lasso(&path, (-1, 2).into()));
+
+        // too short to enclose anything
+        assert!(!in_lasso(&path[..2], (2, 0).into()));
+    }
+
+    #[test]
+    fn outline_edges() {
+        let single = HashSet::from([Location::default()]);
+        assert_eq!(outline(&single).len(), 6);
+
+        // two adjacent hexes share an edge that is not on the outline
+        let pair = HashSet::from([(0, 0).into(), (1, 0).into()]);
+        assert_eq!(outline(&pair).len(), 10);
+
+        let mesh = outline_mesh(&HexLayout::default(), &pair, 0.1);
+        assert_eq!(mesh.count_vertices(), 40);
+    }
+}
@@ -0,0 +1,344 @@
+//! Stamps are groups of tiles saved outside of a map so they can be placed
+//! repeatedly, and shared between maps.
+//!
+//! A stamp stores the tile locations relative to the center of the stamp,
+//! along with copies of the tilesets the tiles reference.  Stamps are kept in
+//! a user library directory; see `library_dir()`.
+use anyhow::{bail, Context, Result};
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    map,
+    persistence::{self, FileFormat, SaveId},
+    selection, tileset,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StampSaved>().add_system(stamp_exporter);
+    }
+}
+
+pub const STAMP_VERSION: usize = 1;
+
+/// file extension used for stamps in the library directory
+pub const STAMP_EXTENSION: &str = "stamp.ron";
+
+/// save file representation of a group of tiles
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Stamp {
+    pub version: usize,
+    pub name: String,
+    pub tilesets: BTreeMap<SaveId, tileset::Tileset>,
+    /// tiles with locations relative to the center of the stamp
+    pub tiles: Vec<persistence::Tile>,
+}
+
+impl Stamp {
+    /// create a stamp from tile entities in the World
+    ///
+    /// The tile closest to the center of the tiles becomes the origin of the
+    /// stamp.
+    pub fn try_new(world: &World, name: &str, tiles: &[Entity]) -> Result<Self> {
+        let mut placed = Vec::new();
+        for entity in tiles {
+            let entity_ref = world
+                .get_entity(*entity)
+                .context(format!("unknown entity: {:?}", entity))?;
+            let (Some(location), Some(tile_ref)) = (
+                entity_ref.get::<map::Location>(),
+                entity_ref.get::<tileset::TileRef>(),
+            ) else {
+                bail!("entity {:?} is not a tile", entity);
+            };
+            let rotation = entity_ref
+                .get::<tileset::TileTransform>()
+                .map(|t| t.rotation)
+                .unwrap_or_default();
+            let overrides = entity_ref
+                .get::<tileset::TileOverrides>()
+                .copied()
+                .unwrap_or_default();
+            placed.push((*location, *tile_ref, rotation, overrides));
+        }
+        if placed.is_empty() {
+            bail!("no tiles in stamp");
+        }
+
+        // use the tile nearest the center as the origin
+        let origin = selection::pivot(placed.iter().map(|(l, ..)| l)).unwrap();
+
+        let mut stamp = Stamp {
+            version: STAMP_VERSION,
+            name: name.to_string(),
+            ..default()
+        };
+        let mut ids: HashMap<Entity, SaveId> = HashMap::new();
+        for (location, tile_ref, rotation, overrides) in placed {
+            let id = match ids.get(&tile_ref.tileset) {
+                Some(id) => *id,
+                None => {
+                    let tileset = world
+                        .get::<tileset::Tileset>(tile_ref.tileset)
+                        .context(format!("failed to get Tileset for tile {:?}", tile_ref))?;
+                    let id = SaveId::default() + ids.len();
+                    ids.insert(tile_ref.tileset, id);
+                    stamp.tilesets.insert(id, tileset.clone());
+                    id
+                }
+            };
+            stamp.tiles.push(persistence::Tile {
+                location: (location.x - origin.x, location.y - origin.y).into(),
+                tileset: id,
+                tile_id: tile_ref.tile,
+                rotation,
+                overrides,
+            });
+        }
+        Ok(stamp)
+    }
+
+    /// load a Stamp from a RON or JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let buf = std::fs::read_to_string(path).context("failed to read file")?;
+        let stamp: Stamp = FileFormat::from_path(path)
+            .deserialize(&buf)
+            .context("failed to parse stamp")?;
+        if stamp.version != STAMP_VERSION {
+            bail!(
+                "unsupported stamp version: {} != {}",
+                stamp.version,
+                STAMP_VERSION
+            );
+        }
+        Ok(stamp)
+    }
+
+    /// save the Stamp to a file; the format is determined by the extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let buf = FileFormat::from_path(path).serialize(self)?;
+        std::fs::write(path, buf).context(format!("writing stamp to {:?}", path))?;
+        Ok(())
+    }
+
+    /// tiles in the stamp rotated around the stamp origin, then moved to
+    /// `location`
+    pub fn placed_tiles(
+        &self,
+        location: map::Location,
+        rotation: tileset::TileRotation,
+    ) -> impl Iterator<Item = (map::Location, &persistence::Tile, tileset::TileRotation)> {
+        self.tiles.iter().map(move |tile| {
+            let offset = tile.location.rotate(rotation);
+            let placed = map::Location {
+                x: location.x + offset.x,
+                y: location.y + offset.y,
+            };
+            (placed, tile, tile.rotation.rotate_by(rotation))
+        })
+    }
+}
+
+/// directory stamps are saved to & loaded from
+pub fn library_dir() -> PathBuf {
+    library_root().join("stamps")
+}
+
+/// directory of the user library; stamps & other shared files are kept in it
+///
+/// `HEX_SANDBOX_LIBRARY` overrides the default of a `hex_sandbox` directory
+/// in the user data directory.
+pub fn library_root() -> PathBuf {
+    if let Ok(dir) = std::env::var("HEX_SANDBOX_LIBRARY") {
+        return PathBuf::from(dir);
+    }
+    let data_dir = if let Ok(dir) = std::env::var("XDG_DATA_HOME") {
+        PathBuf::from(dir)
+    } else if let Ok(dir) = std::env::var("APPDATA") {
+        PathBuf::from(dir)
+    } else if let Ok(home) = std::env::var("HOME") {
+        if cfg!(target_os = "macos") {
+            PathBuf::from(home).join("Library/Application Support")
+        } else {
+            PathBuf::from(home).join(".local/share")
+        }
+    } else {
+        PathBuf::from(".")
+    };
+    data_dir.join("hex_sandbox")
+}
+
+/// path of a stamp with the given name in the library directory
+pub fn library_path(dir: &Path, name: &str) -> PathBuf {
+    let file_name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    dir.join(format!("{}.{}", file_name, STAMP_EXTENSION))
+}
+
+/// list the stamp files in the library directory, sorted by path
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).context(format!("read directory {:?}", dir))? {
+        let path = entry?.path();
+        let is_stamp = path
+            .file_name()
+            .map(|n| n.to_string_lossy().ends_with(STAMP_EXTENSION))
+            .unwrap_or(false);
+        if is_stamp {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Event sent when a `StampExporter` has written a stamp to disk
+#[derive(Debug)]
+pub struct StampSaved(pub PathBuf);
+
+/// Component used to write a stamp in the IoTaskPool
+#[derive(Component, Debug)]
+pub struct StampExporter {
+    path: PathBuf,
+    task: Task<Result<()>>,
+}
+
+impl StampExporter {
+    pub fn new(path: PathBuf, stamp: Stamp) -> Self {
+        let task_pool = IoTaskPool::get();
+        let path_copy = path.clone();
+        let task = task_pool.spawn(async move {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).context(format!("create directory {:?}", dir))?;
+            }
+            stamp.save(&path)
+        });
+        Self {
+            path: path_copy,
+            task,
+        }
+    }
+}
+
+fn stamp_exporter(
+    mut commands: Commands,
+    mut stamp_exporters: Query<(Entity, &mut StampExporter)>,
+    mut events: EventWriter<StampSaved>,
+) {
+    use futures_lite::future;
+    for (entity, mut exporter) in &mut stamp_exporters {
+        let Some(result) = future::block_on(future::poll_once(&mut exporter.task)) else { continue };
+        match result {
+            Err(e) => warn!("failed to save stamp: {:#?}", e),
+            Ok(_) => events.send(StampSaved(exporter.path.clone())),
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+    use tileset::TileRotation;
+
+    fn spawn_tiles(world: &mut World) -> (Entity, Vec<Entity>) {
+        let tileset = world.spawn(tileset::Tileset::new("tileset a")).id();
+        let tiles = [(0, 0), (1, 0), (2, 0)]
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                world
+                    .spawn((
+                        map::Location::from(*l),
+                        tileset::TileRef { tileset, tile: i },
+                        tileset::TileTransform {
+                            rotation: TileRotation::Clockwise60,
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+        (tileset, tiles)
+    }
+
+    #[test]
+    fn try_new() {
+        let mut world = World::new();
+        let (_, tiles) = spawn_tiles(&mut world);
+        let stamp = Stamp::try_new(&world, "row", &tiles).unwrap();
+        assert_eq!(stamp.name, "row");
+        assert_eq!(stamp.tilesets.len(), 1);
+        let locations: Vec<_> = stamp.tiles.iter().map(|t| t.location).collect();
+        assert_eq!(
+            locations,
+            vec![(-1, 0).into(), (0, 0).into(), (1, 0).into()]
+        );
+        assert_eq!(stamp.tiles[2].tile_id, 2);
+        assert_eq!(stamp.tiles[2].rotation, TileRotation::Clockwise60);
+    }
+
+    #[test]
+    fn try_new_empty() {
+        let world = World::new();
+        assert!(Stamp::try_new(&world, "empty", &[]).is_err());
+    }
+
+    #[test]
+    fn placed_tiles() {
+        let mut world = World::new();
+        let (_, tiles) = spawn_tiles(&mut world);
+        let stamp = Stamp::try_new(&world, "row", &tiles).unwrap();
+        let placed: Vec<_> = stamp
+            .placed_tiles((5, 5).into(), TileRotation::Clockwise180)
+            .map(|(l, t, r)| (l, t.tile_id, r))
+            .collect();
+        assert_eq!(
+            placed,
+            vec![
+                ((6, 5).into(), 0, TileRotation::CounterClockwise120),
+                ((5, 5).into(), 1, TileRotation::CounterClockwise120),
+                ((4, 5).into(), 2, TileRotation::CounterClockwise120),
+            ]
+        );
+    }
+
+    #[test]
+    fn save_load_library() {
+        let dir = std::env::temp_dir().join(format!("hex_sandbox_stamps_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut world = World::new();
+        let (_, tiles) = spawn_tiles(&mut world);
+        let stamp = Stamp::try_new(&world, "a/b", &tiles).unwrap();
+        let path = library_path(&dir, &stamp.name);
+        assert_eq!(path, dir.join("a_b.stamp.ron"));
+        stamp.save(&path).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a stamp").unwrap();
+
+        assert_eq!(list(&dir).unwrap(), vec![path.clone()]);
+        let loaded = Stamp::load(&path).unwrap();
+        assert_eq!(loaded.name, "a/b");
+        assert_eq!(loaded.tiles.len(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -54,13 +54,20 @@ fn setup(mut commands: Commands) {
 
 #[derive(Resource, Default, Debug)]
 pub struct RenderQueue {
-    queue: VecDeque<(Handle<Image>, Handle<Scene>)>,
+    queue: VecDeque<(Handle<Image>, Vec<(Handle<Scene>, Transform)>)>,
     scene: Option<Entity>,
 }
 
 impl RenderQueue {
     pub fn push(&mut self, image: Handle<Image>, scene: Handle<Scene>) {
-        self.queue.push_back((image, scene));
+        self.push_scenes(image, vec![(scene, Transform::IDENTITY)]);
+    }
+
+    /// render multiple scenes into a single thumbnail; the camera is centered
+    /// on the origin, and fits a single tile, so the caller must scale the
+    /// scenes to fit.
+    pub fn push_scenes(&mut self, image: Handle<Image>, scenes: Vec<(Handle<Scene>, Transform)>) {
+        self.queue.push_back((image, scenes));
     }
 }
 
@@ -74,7 +81,8 @@ fn render_thumbnails(
     mut commands: Commands,
     mut render_queue: ResMut<RenderQueue>,
     mut camera: Query<(&mut Camera, &RenderLayers), With<ThumbnailCamera>>,
-    scene_instances: Query<&SceneInstance, With<ThumbnailScene>>,
+    thumbnail_scenes: Query<Option<&Children>, With<ThumbnailScene>>,
+    scene_instances: Query<&SceneInstance>,
     scene_manager: Res<SceneSpawner>,
 ) {
     use bevy::render::camera::RenderTarget;
@@ -85,17 +93,27 @@ fn render_thumbnails(
 
     // if we're working on an existing scene, see if it's loaded
     if let Some(scene) = render_queue.scene {
-        if let Ok(instance) = scene_instances.get(scene) {
-            // check if the scene has been loaded
-            if !scene_manager.instance_is_ready(**instance) {
-                debug!("scene not loaded {:?}", scene);
-                return;
+        if let Ok(children) = thumbnail_scenes.get(scene) {
+            // check if all of the scenes have been loaded
+            let mut instances = Vec::new();
+            for child in children.into_iter().flatten() {
+                let Ok(instance) = scene_instances.get(*child) else {
+                    debug!("scene not spawned {:?}", child);
+                    return;
+                };
+                if !scene_manager.instance_is_ready(**instance) {
+                    debug!("scene not loaded {:?}", child);
+                    return;
+                }
+                instances.push(**instance);
             }
 
-            // scene is loaded, update all the child entities to be in the
+            // scenes are loaded, update all the child entities to be in the
             // proper render layer
-            for entity in scene_manager.iter_instance_entities(**instance) {
-                commands.entity(entity).insert(*render_layers);
+            for instance in instances {
+                for entity in scene_manager.iter_instance_entities(instance) {
+                    commands.entity(entity).insert(*render_layers);
+                }
             }
 
             // enable the camera, and clear the tag; we'll render the scene to
@@ -117,22 +135,33 @@ fn render_thumbnails(
     }
 
     // scene has been loaded, so let's pop the request off the queue
-    let Some((image, scene)) = render_queue.queue.pop_front() else { return };
+    let Some((image, scenes)) = render_queue.queue.pop_front() else { return };
 
     // update camera to write to the new image
     camera.target = RenderTarget::Image(image);
 
-    // spawn the new model
+    // spawn the new models
     let entity = commands
         .spawn((
             ThumbnailScene,
-            SceneBundle {
-                scene,
+            SpatialBundle {
                 visibility: Visibility::Hidden,
                 ..default()
             },
             *render_layers,
         ))
+        .with_children(|parent| {
+            for (scene, transform) in scenes {
+                parent.spawn((
+                    SceneBundle {
+                        scene,
+                        transform,
+                        ..default()
+                    },
+                    *render_layers,
+                ));
+            }
+        })
         .id();
     render_queue.scene = Some(entity);
     debug!("spawn thumbnail {:?}", entity);
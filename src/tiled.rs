@@ -0,0 +1,535 @@
+//! Import hexagonal maps created with the Tiled map editor (TMX files)
+//!
+//! Tiled stores hexagonal maps using staggered (offset) coordinates, and
+//! references tiles by global tile ids (GIDs).  As Tiled tiles are 2d images,
+//! and our tiles are 3d models, the caller must provide a `GidMapping` that
+//! describes which model (or existing tile) each GID should be converted to.
+//!
+//! The import produces a `persistence::MapFormat`, so the resulting map is
+//! spawned the same way a saved map is.
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use hexx::{HexLayout, HexOrientation};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    map,
+    persistence::{self, MapFormat, SaveId},
+    tileset,
+};
+
+/// bits set in a GID by Tiled for flipped & rotated tiles
+const GID_FLAGS: u32 = 0xf000_0000;
+
+/// which axis is staggered in the TMX map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaggerAxis {
+    /// columns are staggered; flat-topped hexagons
+    X,
+    /// rows are staggered; pointy-topped hexagons
+    Y,
+}
+
+/// which rows/columns are shifted in the TMX map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaggerIndex {
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stagger {
+    pub axis: StaggerAxis,
+    pub index: StaggerIndex,
+}
+
+impl Stagger {
+    /// convert TMX staggered coordinates to a map Location
+    pub fn to_location(&self, col: i32, row: i32) -> map::Location {
+        match (self.axis, self.index) {
+            (StaggerAxis::Y, StaggerIndex::Odd) => (col - (row - (row & 1)) / 2, row),
+            (StaggerAxis::Y, StaggerIndex::Even) => (col - (row + (row & 1)) / 2, row),
+            (StaggerAxis::X, StaggerIndex::Odd) => (col, row - (col - (col & 1)) / 2),
+            (StaggerAxis::X, StaggerIndex::Even) => (col, row - (col + (col & 1)) / 2),
+        }
+        .into()
+    }
+
+    /// get the HexLayout that matches the TMX hexagon orientation
+    pub fn layout(&self) -> HexLayout {
+        let orientation = match self.axis {
+            StaggerAxis::X => HexOrientation::flat(),
+            StaggerAxis::Y => HexOrientation::pointy(),
+        };
+        HexLayout {
+            orientation,
+            ..default()
+        }
+    }
+}
+
+/// what a Tiled GID should be converted into
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GidTarget {
+    /// add this model to the tileset created for the TMX tileset that
+    /// contains the GID
+    Model(PathBuf),
+    /// use an existing tile from `GidMapping::tilesets[tileset]`
+    Tile {
+        tileset: usize,
+        tile: tileset::TileId,
+    },
+}
+
+/// user-supplied mapping from Tiled GIDs to tiles
+///
+/// Example mapping file:
+/// ```ron
+/// (
+///     tilesets: ["kenney.tileset.ron"],
+///     gids: {
+///         1: Tile(tileset: 0, tile: 12),
+///         2: Model("kenney_hexagon-kit/water.glb"),
+///     },
+/// )
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GidMapping {
+    /// existing tileset files referenced by `GidTarget::Tile`
+    #[serde(default)]
+    pub tilesets: Vec<PathBuf>,
+    pub gids: BTreeMap<u32, GidTarget>,
+}
+
+impl GidMapping {
+    /// load a mapping file along with the tilesets it references.  Relative
+    /// tileset paths are resolved relative to the mapping file.
+    pub fn load(path: &Path) -> Result<(Self, Vec<tileset::Tileset>)> {
+        let buf = std::fs::read_to_string(path).context("failed to read GID mapping")?;
+        let mapping: GidMapping = ron::from_str(&buf).context("failed to parse GID mapping")?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mut tilesets = Vec::new();
+        for tileset_path in &mapping.tilesets {
+            let tileset_path = dir.join(tileset_path);
+            let tileset = tileset::Tileset::load(&tileset_path)
+                .with_context(|| format!("load tileset {:?}", tileset_path))?;
+            tilesets.push(tileset);
+        }
+        Ok((mapping, tilesets))
+    }
+}
+
+/// result of importing a TMX map
+#[derive(Debug)]
+pub struct TmxImport {
+    pub map: MapFormat,
+    /// GIDs that were present in the TMX map, but not in the `GidMapping`,
+    /// along with the number of cells that used them
+    pub unmapped: BTreeMap<u32, usize>,
+    /// number of cells that had flip/rotation flags set; these are ignored
+    pub flagged: usize,
+}
+
+impl TmxImport {
+    /// import a TMX file using the GID mapping file at `mapping`
+    pub fn load(path: &Path, mapping: &Path) -> Result<Self> {
+        let (mapping, tilesets) = GidMapping::load(mapping)?;
+        let buf = std::fs::read_to_string(path).context("failed to read TMX file")?;
+        Self::parse(&buf, &mapping, tilesets)
+    }
+
+    /// import the contents of a TMX file
+    pub fn parse(tmx: &str, mapping: &GidMapping, tilesets: Vec<tileset::Tileset>) -> Result<Self> {
+        let doc = roxmltree::Document::parse(tmx).context("failed to parse TMX")?;
+        let root = doc.root_element();
+        if !root.has_tag_name("map") {
+            bail!(
+                "expected <map> root element, found <{}>",
+                root.tag_name().name()
+            );
+        }
+        if root.attribute("orientation") != Some("hexagonal") {
+            bail!(
+                "unsupported map orientation: {:?}",
+                root.attribute("orientation")
+            );
+        }
+        let stagger = Stagger {
+            axis: match root.attribute("staggeraxis") {
+                Some("x") => StaggerAxis::X,
+                Some("y") | None => StaggerAxis::Y,
+                Some(v) => bail!("unsupported staggeraxis: {}", v),
+            },
+            index: match root.attribute("staggerindex") {
+                Some("even") => StaggerIndex::Even,
+                Some("odd") | None => StaggerIndex::Odd,
+                Some(v) => bail!("unsupported staggerindex: {}", v),
+            },
+        };
+
+        let mut import = TmxImport {
+            map: MapFormat::new(stagger.layout()),
+            unmapped: BTreeMap::new(),
+            flagged: 0,
+        };
+
+        let gids = import.add_tilesets(root, mapping, tilesets)?;
+
+        for layer in root.descendants().filter(|n| n.has_tag_name("layer")) {
+            let name = layer.attribute("name").unwrap_or("layer").to_string();
+            let mut tiles = Vec::new();
+            for (col, row, raw_gid) in
+                layer_cells(layer).with_context(|| format!("failed to read layer \"{}\"", name))?
+            {
+                if raw_gid == 0 {
+                    continue;
+                }
+                if raw_gid & GID_FLAGS != 0 {
+                    import.flagged += 1;
+                }
+                let gid = raw_gid & !GID_FLAGS;
+                let Some((tileset, tile_id)) = gids.get(&gid) else {
+                    *import.unmapped.entry(gid).or_default() += 1;
+                    continue;
+                };
+                tiles.push(persistence::Tile {
+                    location: stagger.to_location(col, row),
+                    tileset: *tileset,
+                    tile_id: *tile_id,
+                    ..default()
+                });
+            }
+            let order = import.map.layers.len();
+            import.map.layers.push(persistence::Layer {
+                name,
+                tiles,
+                order,
+                ..default()
+            });
+        }
+
+        Ok(import)
+    }
+
+    /// create the tilesets needed for the mapped GIDs, and return the GID to
+    /// tile mapping
+    fn add_tilesets(
+        &mut self,
+        root: roxmltree::Node,
+        mapping: &GidMapping,
+        mut tilesets: Vec<tileset::Tileset>,
+    ) -> Result<HashMap<u32, (SaveId, tileset::TileId)>> {
+        // grab the GID ranges for the TMX tilesets
+        let mut tmx_tilesets = Vec::new();
+        for node in root.children().filter(|n| n.has_tag_name("tileset")) {
+            let first_gid: u32 = node
+                .attribute("firstgid")
+                .context("<tileset> missing firstgid")?
+                .parse()
+                .context("invalid firstgid")?;
+            let name = match (node.attribute("name"), node.attribute("source")) {
+                (Some(name), _) => name.to_string(),
+                (None, Some(source)) => Path::new(source)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| source.to_string()),
+                (None, None) => format!("tiled tileset {}", first_gid),
+            };
+            tmx_tilesets.push((first_gid, name));
+        }
+        tmx_tilesets.sort_by_key(|(first_gid, _)| *first_gid);
+
+        let mut next_id = SaveId::default();
+        let mut existing_ids: HashMap<usize, SaveId> = HashMap::new();
+        let mut created: BTreeMap<u32, (SaveId, tileset::Tileset)> = BTreeMap::new();
+        let mut gids = HashMap::new();
+
+        for (gid, target) in &mapping.gids {
+            match target {
+                GidTarget::Tile { tileset, tile } => {
+                    let Some(existing) = tilesets.get(*tileset) else {
+                        bail!("GID {} references unknown tileset index {}", gid, tileset);
+                    };
+                    if !existing.tiles.contains_key(tile) {
+                        bail!(
+                            "GID {} references unknown tile {} in tileset \"{}\"",
+                            gid,
+                            tile,
+                            existing.name
+                        );
+                    }
+                    let id = *existing_ids.entry(*tileset).or_insert_with(|| {
+                        let id = next_id;
+                        next_id += 1;
+                        id
+                    });
+                    gids.insert(*gid, (id, *tile));
+                }
+                GidTarget::Model(path) => {
+                    // find the TMX tileset this GID belongs to
+                    let Some((first_gid, name)) = tmx_tilesets
+                        .iter()
+                        .rev()
+                        .find(|(first_gid, _)| first_gid <= gid) else {
+                        bail!("GID {} is not part of any TMX tileset", gid);
+                    };
+                    let (id, tileset) = created.entry(*first_gid).or_insert_with(|| {
+                        let id = next_id;
+                        next_id += 1;
+                        (id, tileset::Tileset::new(name))
+                    });
+
+                    let tile_id = match tileset.tiles.values().find(|t| t.path == *path) {
+                        Some(tile) => tile.id,
+                        None => tileset.add_tile(path.clone()),
+                    };
+                    gids.insert(*gid, (*id, tile_id));
+                }
+            }
+        }
+
+        for (index, id) in existing_ids {
+            let tileset = std::mem::take(&mut tilesets[index]);
+            self.map.tilesets.insert(id, tileset);
+        }
+        for (_, (id, tileset)) in created {
+            self.map.tilesets.insert(id, tileset);
+        }
+
+        Ok(gids)
+    }
+
+    /// describe any GIDs that could not be mapped, or were flagged
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !self.unmapped.is_empty() {
+            let gids: Vec<String> = self
+                .unmapped
+                .iter()
+                .map(|(gid, count)| format!("{} ({} cells)", gid, count))
+                .collect();
+            warnings.push(format!(
+                "{} unmapped GIDs were not imported: {}",
+                self.unmapped.len(),
+                gids.join(", ")
+            ));
+        }
+        if self.flagged > 0 {
+            warnings.push(format!(
+                "ignored flip/rotation flags on {} cells",
+                self.flagged
+            ));
+        }
+        warnings
+    }
+
+    /// log any GIDs that could not be mapped, or were flagged
+    pub fn warn_unmapped(&self, path: &Path) {
+        for warning in self.warnings() {
+            warn!("{}: {}", path.to_string_lossy(), warning);
+        }
+    }
+}
+
+/// create a `MapImporter` that imports the TMX file at `path` using the GID
+/// mapping file at `mapping`; the unmapped GIDs are added to the map as
+/// `ImportWarnings`
+pub fn importer(path: PathBuf, mapping: PathBuf) -> persistence::MapImporter {
+    persistence::MapImporter::with_loader(path, move |path| {
+        let import = TmxImport::load(&path, &mapping)?;
+        import.warn_unmapped(&path);
+        let warnings = import.warnings();
+        Ok((import.map, warnings))
+    })
+}
+
+/// get the (column, row, gid) for every cell in a TMX `<layer>`
+fn layer_cells(layer: roxmltree::Node) -> Result<Vec<(i32, i32, u32)>> {
+    let data = layer
+        .children()
+        .find(|n| n.has_tag_name("data"))
+        .context("<layer> missing <data>")?;
+
+    // infinite maps store the data in chunks; finite maps are a single chunk
+    // the size of the layer
+    let mut chunks: Vec<roxmltree::Node> = data
+        .children()
+        .filter(|n| n.has_tag_name("chunk"))
+        .collect();
+    if chunks.is_empty() {
+        chunks.push(data);
+    }
+
+    let mut cells = Vec::new();
+    for chunk in chunks {
+        let (x, y, width) = if chunk == data {
+            (0, 0, int_attribute(layer, "width")?)
+        } else {
+            (
+                int_attribute(chunk, "x")?,
+                int_attribute(chunk, "y")?,
+                int_attribute(chunk, "width")?,
+            )
+        };
+        if width <= 0 {
+            bail!("invalid width: {}", width);
+        }
+
+        let gids = match data.attribute("encoding") {
+            Some("csv") => chunk
+                .text()
+                .unwrap_or("")
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<u32>().context("invalid GID"))
+                .collect::<Result<Vec<u32>>>()?,
+            None => chunk
+                .children()
+                .filter(|n| n.has_tag_name("tile"))
+                .map(|n| {
+                    n.attribute("gid")
+                        .map(|v| v.parse::<u32>().context("invalid GID"))
+                        .unwrap_or(Ok(0))
+                })
+                .collect::<Result<Vec<u32>>>()?,
+            Some(encoding) => bail!(
+                "unsupported layer encoding \"{}\"; save the map using CSV layer format",
+                encoding
+            ),
+        };
+
+        for (index, gid) in gids.into_iter().enumerate() {
+            let index = index as i32;
+            cells.push((x + index % width, y + index / width, gid));
+        }
+    }
+    Ok(cells)
+}
+
+fn int_attribute(node: roxmltree::Node, name: &str) -> Result<i32> {
+    node.attribute(name)
+        .with_context(|| format!("<{}> missing {}", node.tag_name().name(), name))?
+        .parse()
+        .with_context(|| format!("invalid {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="hexagonal" renderorder="right-down" width="3" height="2"
+     tilewidth="32" tileheight="32" infinite="0" hexsidelength="16"
+     staggeraxis="y" staggerindex="odd">
+ <tileset firstgid="1" source="terrain.tsx"/>
+ <tileset firstgid="10" name="buildings" tilewidth="32" tileheight="32" tilecount="4" columns="4"/>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,2,0,
+2,7,1
+</data>
+ </layer>
+ <layer id="2" name="buildings" width="3" height="2">
+  <data>
+   <tile/><tile gid="10"/><tile/>
+   <tile/><tile/><tile gid="2147483658"/>
+  </data>
+ </layer>
+</map>"#;
+
+    fn mapping() -> (GidMapping, Vec<tileset::Tileset>) {
+        let mut existing = tileset::Tileset::new("existing");
+        existing.add_tile("grass.glb".into());
+
+        let mut gids = BTreeMap::new();
+        gids.insert(
+            1,
+            GidTarget::Tile {
+                tileset: 0,
+                tile: 0,
+            },
+        );
+        gids.insert(2, GidTarget::Model("water.glb".into()));
+        gids.insert(10, GidTarget::Model("building_castle.glb".into()));
+        (
+            GidMapping {
+                tilesets: vec!["existing.tileset.ron".into()],
+                gids,
+            },
+            vec![existing],
+        )
+    }
+
+    #[test]
+    fn stagger_y_odd() {
+        let stagger = Stagger {
+            axis: StaggerAxis::Y,
+            index: StaggerIndex::Odd,
+        };
+        assert_eq!(stagger.to_location(0, 0), (0, 0).into());
+        assert_eq!(stagger.to_location(0, 1), (0, 1).into());
+        assert_eq!(stagger.to_location(0, 2), (-1, 2).into());
+        assert_eq!(stagger.to_location(2, -1), (3, -1).into());
+    }
+
+    #[test]
+    fn stagger_x_even() {
+        let stagger = Stagger {
+            axis: StaggerAxis::X,
+            index: StaggerIndex::Even,
+        };
+        assert_eq!(stagger.to_location(0, 0), (0, 0).into());
+        assert_eq!(stagger.to_location(1, 0), (1, -1).into());
+        assert_eq!(stagger.to_location(2, 0), (2, -1).into());
+        assert_eq!(stagger.to_location(3, 2), (3, 0).into());
+    }
+
+    #[test]
+    fn parse_tmx() {
+        let (mapping, tilesets) = mapping();
+        let import = TmxImport::parse(TMX, &mapping, tilesets).expect("TMX to parse");
+
+        assert_eq!(import.map.layers.len(), 2);
+        assert_eq!(import.map.layers[0].name, "ground");
+        assert_eq!(import.map.layers[0].tiles.len(), 4);
+        assert_eq!(import.map.layers[1].tiles.len(), 2);
+
+        // existing tileset, "terrain" for water.glb, "buildings" for the castle
+        assert_eq!(import.map.tilesets.len(), 3);
+        let names: Vec<&str> = import
+            .map
+            .tilesets
+            .values()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert!(names.contains(&"existing"));
+        assert!(names.contains(&"terrain"));
+        assert!(names.contains(&"buildings"));
+
+        // gid 7 is not mapped, and one cell had flip flags
+        assert_eq!(import.unmapped.get(&7), Some(&1));
+        assert_eq!(import.unmapped.len(), 1);
+        assert_eq!(import.flagged, 1);
+        assert_eq!(
+            import.warnings(),
+            [
+                "1 unmapped GIDs were not imported: 7 (1 cells)",
+                "ignored flip/rotation flags on 1 cells",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_tmx_not_hexagonal() {
+        let (mapping, tilesets) = mapping();
+        let tmx = TMX.replace("hexagonal", "orthogonal");
+        assert!(TmxImport::parse(&tmx, &mapping, tilesets).is_err());
+    }
+}
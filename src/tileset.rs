@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use bevy::ecs::system::SystemParam;
 use bevy::{
     prelude::*,
     tasks::{IoTaskPool, Task},
@@ -9,9 +10,12 @@ use serde::{
     ser::SerializeMap,
     Deserialize, Serialize,
 };
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
-use crate::map;
+use crate::{map, persistence::FileFormat};
 
 pub struct Plugin;
 
@@ -20,9 +24,11 @@ impl bevy::app::Plugin for Plugin {
         app.register_type::<Tileset>()
             .register_type::<TileRef>()
             .register_type::<TileRotation>()
+            .register_type::<TileOverrides>()
             .register_type::<Tile>()
             .register_type::<TileId>()
             .register_type::<Vec<TileId>>()
+            .register_type::<Vec<map::Location>>()
             .add_system(tile_ref_changed)
             .add_system(update_tile_scene)
             .add_system(update_tile_transform)
@@ -41,6 +47,22 @@ pub struct Tile {
     pub name: String,
     pub path: std::path::PathBuf,
     pub transform: Transform,
+    /// color used for the tile in 2d representations of the map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// offsets of the other hexes covered by the tile, relative to the hex it
+    /// is placed on; they turn with the `TileRotation` of the placed tile
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub footprint: Vec<map::Location>,
+    /// free-form tags used to find the tile in the editor, such as "water" or
+    /// "building"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// values of the fields in the `property_schema` of the tileset; fields
+    /// without a value use the default from the schema
+    #[reflect(ignore)]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, TileProperty>,
     #[reflect(ignore)]
     #[serde(skip)]
     pub scene: Option<Handle<Scene>>,
@@ -49,6 +71,184 @@ pub struct Tile {
     pub egui_texture_id: Option<egui::TextureId>,
 }
 
+impl Tile {
+    /// every hex covered by the tile when placed at `location`, starting with
+    /// `location`
+    pub fn footprint_at(
+        &self,
+        location: map::Location,
+        rotation: TileRotation,
+    ) -> Vec<map::Location> {
+        let mut hexes = vec![location];
+        for offset in &self.footprint {
+            let offset = offset.rotate(rotation);
+            let hex = map::Location {
+                x: location.x + offset.x,
+                y: location.y + offset.y,
+            };
+            if !hexes.contains(&hex) {
+                hexes.push(hex);
+            }
+        }
+        hexes
+    }
+
+    /// start of the tile name up to the first `_`; `building` for
+    /// `building_cabin`, or the whole name if it has no `_`
+    pub fn name_prefix(&self) -> &str {
+        self.name.split('_').next().unwrap_or_default()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// how the tiles of a tileset are grouped in the tile picker
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TileGrouping {
+    #[default]
+    None,
+    /// one group per tag; tiles with several tags are in several groups
+    Tag,
+    /// one group per `Tile::name_prefix()`
+    NamePrefix,
+}
+
+/// tiles shown in the tile picker
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TileFilter {
+    /// only tiles with this text in their name, ignoring case
+    pub search: String,
+    /// only tiles with every one of these tags
+    pub tags: Vec<String>,
+    pub grouping: TileGrouping,
+}
+
+impl TileFilter {
+    pub fn matches(&self, tile: &Tile) -> bool {
+        let search = self.search.trim().to_lowercase();
+        (search.is_empty() || tile.name.to_lowercase().contains(&search))
+            && self.tags.iter().all(|tag| tile.has_tag(tag))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.search.trim().is_empty() && self.tags.is_empty()
+    }
+}
+
+/// type of a field in the property schema of a tileset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TilePropertyKind {
+    Int,
+    Float,
+    Bool,
+    String,
+    /// one of a fixed list of names
+    Enum(Vec<String>),
+}
+
+impl TilePropertyKind {
+    /// name of the type, for display
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            TilePropertyKind::Int => "int",
+            TilePropertyKind::Float => "float",
+            TilePropertyKind::Bool => "bool",
+            TilePropertyKind::String => "string",
+            TilePropertyKind::Enum(_) => "enum",
+        }
+    }
+
+    /// zero value of the type; the first option for enums
+    pub fn zero(&self) -> TileProperty {
+        match self {
+            TilePropertyKind::Int => TileProperty::Int(0),
+            TilePropertyKind::Float => TileProperty::Float(0.0),
+            TilePropertyKind::Bool => TileProperty::Bool(false),
+            TilePropertyKind::String => TileProperty::String(String::new()),
+            TilePropertyKind::Enum(options) => {
+                TileProperty::Enum(options.first().cloned().unwrap_or_default())
+            }
+        }
+    }
+
+    /// check if `value` is of this type; enum values must be one of the
+    /// options
+    pub fn accepts(&self, value: &TileProperty) -> bool {
+        match (self, value) {
+            (TilePropertyKind::Enum(options), TileProperty::Enum(v)) => options.contains(v),
+            (TilePropertyKind::Int, TileProperty::Int(_))
+            | (TilePropertyKind::Float, TileProperty::Float(_))
+            | (TilePropertyKind::Bool, TileProperty::Bool(_))
+            | (TilePropertyKind::String, TileProperty::String(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// value of a tile property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TileProperty {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Enum(String),
+}
+
+impl TileProperty {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            TileProperty::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// the value as a float; ints are converted
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            TileProperty::Float(v) => Some(*v),
+            TileProperty::Int(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TileProperty::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// the value of a string or enum property
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TileProperty::String(v) | TileProperty::Enum(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// a field in the property schema of a tileset, such as "movement_cost"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TilePropertyField {
+    pub name: String,
+    pub kind: TilePropertyKind,
+    /// value of the property for tiles that don't set it
+    pub default: TileProperty,
+}
+
+impl TilePropertyField {
+    pub fn new(name: impl Into<String>, kind: TilePropertyKind) -> Self {
+        Self {
+            name: name.into(),
+            default: kind.zero(),
+            kind,
+        }
+    }
+}
+
 pub type TileSetId = usize;
 
 #[derive(Component, Default, Reflect, Debug, Clone)]
@@ -57,6 +257,10 @@ pub struct Tileset {
     pub name: String,
     pub tiles: HashMap<TileId, Tile>,
     pub tile_order: Vec<TileId>,
+    /// typed properties every tile in the tileset has, such as movement cost
+    /// or defense bonus, used by gameplay code
+    #[reflect(ignore)]
+    pub property_schema: Vec<TilePropertyField>,
     tile_id_max: TileId,
 }
 
@@ -66,23 +270,329 @@ impl Tileset {
             name: name.into(),
             tiles: HashMap::new(),
             tile_order: Vec::new(),
+            property_schema: Vec::new(),
             tile_id_max: 0,
         }
     }
 
-    pub fn add_tile(&mut self, path: std::path::PathBuf) {
+    pub fn add_tile(&mut self, path: std::path::PathBuf) -> TileId {
         let tile = Tile {
             id: self.tile_id_max,
             name: path.file_stem().unwrap().to_string_lossy().into(),
             path,
             transform: Transform::IDENTITY,
+            color: None,
+            footprint: Vec::new(),
+            tags: Vec::new(),
+            properties: BTreeMap::new(),
             scene: None,
             egui_texture_id: None,
         };
-        self.tile_order.push(tile.id);
-        self.tiles.insert(tile.id, tile);
+        let id = tile.id;
+        self.tile_order.push(id);
+        self.tiles.insert(id, tile);
         self.tile_id_max += 1;
+        id
+    }
+
+    /// remove tiles from the tileset; placed tiles still using them are
+    /// despawned by `update_tile_scene`, so replace or delete those first
+    pub fn remove_tiles(&mut self, tiles: &[TileId]) {
+        self.tile_order.retain(|id| !tiles.contains(id));
+        for id in tiles {
+            self.tiles.remove(id);
+        }
+    }
+
+    /// add a tile for every glTF model below `dir` that isn't already in the
+    /// tileset, with `transform`, and find the tiles whose model in `dir` no
+    /// longer exists
+    ///
+    /// Existing tiles are not changed.  Tile paths below `asset_dir` are
+    /// stored relative to it, like the paths loaded by the `AssetServer`.
+    /// Tiles are named after the file stem, or the path within `dir` if the
+    /// name is already used.
+    pub fn scan_folder(
+        &mut self,
+        dir: &Path,
+        asset_dir: &Path,
+        transform: Transform,
+    ) -> Result<FolderScan> {
+        let models = find_models(dir)?;
+        let mut scan = FolderScan::default();
+
+        let resolve = |id: &TileId| crate::export::asset_path(asset_dir, &self.tiles[id].path);
+        let existing: Vec<(TileId, PathBuf)> = self
+            .tile_order
+            .iter()
+            .map(|id| (*id, resolve(id)))
+            .collect();
+        for (id, path) in &existing {
+            if path.starts_with(dir) && !models.contains(path) {
+                scan.missing.push(*id);
+            }
+        }
+
+        for model in models {
+            if existing.iter().any(|(_, path)| *path == model) {
+                continue;
+            }
+            let relative = model.strip_prefix(dir).unwrap_or(&model).with_extension("");
+            let stem = model.file_stem().unwrap_or_default().to_string_lossy();
+            let name = if self.tiles.values().any(|tile| tile.name == stem) {
+                let parts: Vec<_> = relative.iter().map(|p| p.to_string_lossy()).collect();
+                parts.join("_")
+            } else {
+                stem.to_string()
+            };
+            let path = match model.strip_prefix(asset_dir) {
+                Ok(path) => path.to_path_buf(),
+                Err(_) => model.clone(),
+            };
+
+            let id = self.add_tile(path);
+            let tile = self.tiles.get_mut(&id).unwrap();
+            tile.name = name;
+            tile.transform = transform;
+            scan.added.push(id);
+        }
+        Ok(scan)
+    }
+
+    /// every hex covered by a tile in the tileset placed at `location`; just
+    /// `location` for unknown tiles
+    pub fn footprint(
+        &self,
+        tile: TileId,
+        location: map::Location,
+        rotation: TileRotation,
+    ) -> Vec<map::Location> {
+        match self.tiles.get(&tile) {
+            Some(tile) => tile.footprint_at(location, rotation),
+            None => vec![location],
+        }
+    }
+
+    /// every tag used by the tiles of the tileset, sorted
+    pub fn tags(&self) -> Vec<String> {
+        let tags: std::collections::BTreeSet<&String> =
+            self.tiles.values().flat_map(|tile| &tile.tags).collect();
+        tags.into_iter().cloned().collect()
+    }
+
+    /// the tiles matching `filter`, as indexes into `tile_order`, grouped by
+    /// `filter.grouping`
+    ///
+    /// Groups are sorted by name, and the tiles in each group keep the tile
+    /// order.  Tiles without tags are put in an "untagged" group at the end
+    /// when grouping by tag.  Without grouping there is one group named "".
+    pub fn group_tiles(&self, filter: &TileFilter) -> Vec<(String, Vec<usize>)> {
+        let mut groups: std::collections::BTreeMap<String, Vec<usize>> = default();
+        let mut untagged = Vec::new();
+        for (index, tile_id) in self.tile_order.iter().enumerate() {
+            let Some(tile) = self.tiles.get(tile_id) else { continue };
+            if !filter.matches(tile) {
+                continue;
+            }
+            match filter.grouping {
+                TileGrouping::None => groups.entry(String::new()).or_default().push(index),
+                TileGrouping::NamePrefix => groups
+                    .entry(tile.name_prefix().to_string())
+                    .or_default()
+                    .push(index),
+                TileGrouping::Tag if tile.tags.is_empty() => untagged.push(index),
+                TileGrouping::Tag => {
+                    for tag in &tile.tags {
+                        let group = groups.entry(tag.clone()).or_default();
+                        if !group.contains(&index) {
+                            group.push(index);
+                        }
+                    }
+                }
+            }
+        }
+        let mut groups: Vec<(String, Vec<usize>)> = groups.into_iter().collect();
+        if !untagged.is_empty() {
+            groups.push(("untagged".into(), untagged));
+        }
+        groups
+    }
+
+    pub fn property_field(&self, name: &str) -> Option<&TilePropertyField> {
+        self.property_schema.iter().find(|field| field.name == name)
+    }
+
+    /// value of the property `name` for a tile; the default from the schema
+    /// if the tile has no valid value.  Returns None if the tile doesn't exist,
+    /// or the property isn't in the schema.
+    pub fn tile_property(&self, tile: TileId, name: &str) -> Option<&TileProperty> {
+        let field = self.property_field(name)?;
+        let tile = self.tiles.get(&tile)?;
+        match tile.properties.get(name) {
+            Some(value) if field.kind.accepts(value) => Some(value),
+            _ => Some(&field.default),
+        }
+    }
+
+    /// remove tile property values that are not in the property schema, or
+    /// are the wrong type; also fixes defaults of the wrong type.  Call after
+    /// changing the schema.
+    pub fn clean_properties(&mut self) {
+        for field in &mut self.property_schema {
+            if !field.kind.accepts(&field.default) {
+                field.default = field.kind.zero();
+            }
+        }
+        let schema = &self.property_schema;
+        for tile in self.tiles.values_mut() {
+            tile.properties.retain(|name, value| {
+                schema
+                    .iter()
+                    .any(|field| field.name == *name && field.kind.accepts(value))
+            });
+        }
+    }
+
+    /// update the tileset from another version of it, such as the tileset
+    /// file after it was changed in a text editor, keeping the tile ids
+    ///
+    /// Tiles are matched by id.  Tiles missing from `other` are removed,
+    /// unless they are in `in_use`; those are kept so placed tiles don't
+    /// lose their tile.  Tiles whose model path changed have their scene &
+    /// thumbnail reset so `load_tiles` loads them again.
+    pub fn merge(&mut self, other: Tileset, in_use: &HashSet<TileId>) -> TilesetMerge {
+        let mut merge = TilesetMerge::default();
+        let mut tiles = other.tiles;
+        for id in &other.tile_order {
+            let Some(mut tile) = tiles.remove(id) else { continue };
+            match self.tiles.get(id) {
+                None => merge.added.push(*id),
+                Some(old) if old.path == tile.path => {
+                    tile.scene = old.scene.clone();
+                    tile.egui_texture_id = old.egui_texture_id;
+                    if !same_metadata(old, &tile) {
+                        merge.changed.push(*id);
+                    }
+                }
+                Some(_) => {
+                    tile.scene = None;
+                    tile.egui_texture_id = None;
+                    merge.changed.push(*id);
+                    merge.models_changed.push(*id);
+                }
+            }
+            self.tiles.insert(*id, tile);
+        }
+
+        let mut tile_order = other.tile_order;
+        for id in std::mem::take(&mut self.tile_order) {
+            if tile_order.contains(&id) {
+                continue;
+            }
+            if in_use.contains(&id) {
+                merge.kept.push(id);
+                tile_order.push(id);
+            } else {
+                merge.removed.push(id);
+                self.tiles.remove(&id);
+            }
+        }
+        self.tile_order = tile_order;
+        self.tile_id_max = self.tile_id_max.max(other.tile_id_max);
+        self.name = other.name;
+        self.property_schema = other.property_schema;
+        self.clean_properties();
+        merge
+    }
+
+    /// load a Tileset from a RON or JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let buf = std::fs::read_to_string(path).context("failed to read file")?;
+        let tileset = FileFormat::from_path(path)
+            .deserialize(&buf)
+            .context("failed to parse tileset")?;
+        Ok(tileset)
+    }
+
+    /// save the Tileset to a file; the format is determined by the extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let buf = FileFormat::from_path(path).serialize(self)?;
+        std::fs::write(path, buf).context(format!("writing tileset to {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// extensions of the model files found by `Tileset::scan_folder()`
+pub const MODEL_EXTENSIONS: [&str; 2] = ["glb", "gltf"];
+
+/// result of `Tileset::scan_folder()`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FolderScan {
+    /// tiles added for new models
+    pub added: Vec<TileId>,
+    /// tiles whose model is no longer in the folder
+    pub missing: Vec<TileId>,
+}
+
+/// result of `Tileset::merge()`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TilesetMerge {
+    pub added: Vec<TileId>,
+    /// tiles with a different name, model, transform, or other metadata
+    pub changed: Vec<TileId>,
+    /// changed tiles with a different model path; placed tiles need their
+    /// scene replaced
+    pub models_changed: Vec<TileId>,
+    pub removed: Vec<TileId>,
+    /// tiles missing from the new version that were kept as they are in use
+    pub kept: Vec<TileId>,
+}
+
+impl TilesetMerge {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// compare the saved fields of two tiles
+fn same_metadata(a: &Tile, b: &Tile) -> bool {
+    a.name == b.name
+        && a.path == b.path
+        && a.transform == b.transform
+        && a.color == b.color
+        && a.footprint == b.footprint
+        && a.tags == b.tags
+        && a.properties == b.properties
+}
+
+/// file a tileset was imported from or exported to; the editor reloads the
+/// tileset when the file changes
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct TilesetSource(pub PathBuf);
+
+/// every glTF model below `dir`, sorted; symbolic links to directories are
+/// not followed
+fn find_models(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut models = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries =
+            std::fs::read_dir(&dir).context(format!("failed to read directory {:?}", dir))?;
+        for entry in entries {
+            let entry = entry.context(format!("failed to read directory {:?}", dir))?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+            if matches!(extension, Some(e) if MODEL_EXTENSIONS.contains(&e.as_str())) {
+                models.push(path);
+            }
+        }
     }
+    models.sort();
+    Ok(models)
 }
 
 /// version of tileset used during serialize
@@ -93,9 +603,13 @@ impl Serialize for Tileset {
     where
         S: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(3))?;
+        let schema = !self.property_schema.is_empty();
+        let mut map = serializer.serialize_map(Some(if schema { 4 } else { 3 }))?;
         map.serialize_entry("version", &TILESET_VERSION)?;
         map.serialize_entry("name", &self.name)?;
+        if schema {
+            map.serialize_entry("property_schema", &self.property_schema)?;
+        }
 
         let tiles: Vec<Tile> = self
             .tile_order
@@ -113,7 +627,10 @@ impl<'de> Visitor<'de> for TilesetVisitor {
     type Value = Tileset;
 
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("{ \"version\": usize, \"name\": &str, \"tiles\": Vec<Tile> }")
+        formatter.write_str(
+            "{ \"version\": usize, \"name\": &str, \
+            \"property_schema\": Vec<TilePropertyField> (optional), \"tiles\": Vec<Tile> }",
+        )
     }
 
     fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
@@ -123,7 +640,7 @@ impl<'de> Visitor<'de> for TilesetVisitor {
         let mut tileset = Tileset::default();
 
         // version checking
-        if map.next_key::<&str>()? != Some("version") {
+        if map.next_key::<String>()?.as_deref() != Some("version") {
             return Err(de::Error::custom("expected \"version\" key"));
         };
         match map.next_value::<usize>()? {
@@ -137,19 +654,27 @@ impl<'de> Visitor<'de> for TilesetVisitor {
         }
 
         // grab the tileset name
-        if map.next_key::<&str>()? != Some("name") {
+        if map.next_key::<String>()?.as_deref() != Some("name") {
             return Err(de::Error::custom("expected \"name\" key"));
         };
         tileset.name = map.next_value::<String>()?;
 
+        // grab the optional property schema
+        let mut key = map.next_key::<String>()?;
+        if key.as_deref() == Some("property_schema") {
+            tileset.property_schema = map.next_value()?;
+            key = map.next_key::<String>()?;
+        }
+
         // grab the tiles
-        if map.next_key::<&str>()? != Some("tiles") {
+        if key.as_deref() != Some("tiles") {
             return Err(de::Error::custom("expected \"tiles\" key"));
         };
         let tiles = map.next_value::<Vec<Tile>>()?;
 
         // insert tiles into the hashmap and update the tile order
         for tile in tiles {
+            tileset.tile_id_max = tileset.tile_id_max.max(tile.id + 1);
             tileset.tile_order.push(tile.id);
             tileset.tiles.insert(tile.id, tile);
         }
@@ -167,6 +692,39 @@ impl<'de> Deserialize<'de> for Tileset {
     }
 }
 
+/// read the properties of the tiles placed on the map
+#[derive(SystemParam)]
+pub struct MapTileProperties<'w, 's> {
+    layers: Query<'w, 's, &'static map::Layer>,
+    tilesets: Query<'w, 's, &'static Tileset>,
+}
+
+impl<'w, 's> MapTileProperties<'w, 's> {
+    /// value of the property `name` for the tile covering `location` in
+    /// `layer`; None if there is no tile, or its tileset has no such property
+    pub fn get(&self, layer: Entity, location: map::Location, name: &str) -> Option<&TileProperty> {
+        let (_, tile_ref) = self.layers.get(layer).ok()?.tiles.get(&location)?;
+        self.tilesets
+            .get(tile_ref.tileset)
+            .ok()?
+            .tile_property(tile_ref.tile, name)
+    }
+
+    /// value of the property `name` for the top-most tile covering `location`
+    /// that has the property, in any layer
+    pub fn get_top(&self, location: map::Location, name: &str) -> Option<&TileProperty> {
+        let mut layers: Vec<&map::Layer> = self.layers.iter().collect();
+        layers.sort_by_key(|layer| std::cmp::Reverse(layer.order));
+        layers.into_iter().find_map(|layer| {
+            let (_, tile_ref) = layer.tiles.get(&location)?;
+            self.tilesets
+                .get(tile_ref.tileset)
+                .ok()?
+                .tile_property(tile_ref.tile, name)
+        })
+    }
+}
+
 #[derive(Component, Debug, Reflect, FromReflect, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TileRef {
     pub tileset: Entity,
@@ -174,7 +732,7 @@ pub struct TileRef {
 }
 
 #[derive(
-    Component, Default, Debug, Reflect, Clone, Copy, Serialize, Deserialize, PartialEq, Eq,
+    Component, Default, Debug, Reflect, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash,
 )]
 pub enum TileRotation {
     #[default]
@@ -208,6 +766,23 @@ impl TileRotation {
             TileRotation::Clockwise60 => TileRotation::None,
         }
     }
+
+    /// number of 60 degree clockwise steps this rotation is made of
+    pub fn clockwise_steps(self) -> usize {
+        match self {
+            TileRotation::None => 0,
+            TileRotation::Clockwise60 => 1,
+            TileRotation::Clockwise120 => 2,
+            TileRotation::Clockwise180 => 3,
+            TileRotation::CounterClockwise120 => 4,
+            TileRotation::CounterClockwise60 => 5,
+        }
+    }
+
+    /// combine this rotation with another rotation
+    pub fn rotate_by(self, rotation: TileRotation) -> Self {
+        (0..rotation.clockwise_steps()).fold(self, |r, _| r.clockwise())
+    }
 }
 
 impl From<TileRotation> for f32 {
@@ -230,6 +805,65 @@ pub struct TileTransform {
     pub rotation: TileRotation,
 }
 
+/// per-instance changes to a placed tile, combined with the `Tile::transform`
+/// of the tile; only non-default values are saved
+#[derive(Component, Debug, Reflect, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct TileOverrides {
+    /// color multiplied with the colors of the tile materials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tint: Option<Color>,
+    /// uniform scale applied on top of the tile scale
+    #[serde(skip_serializing_if = "is_one")]
+    pub scale: f32,
+    /// offset from the center of the hex along the X & Z axes
+    #[serde(skip_serializing_if = "is_zero")]
+    pub offset: Vec2,
+    /// added to the height of the tile
+    #[serde(skip_serializing_if = "is_zero")]
+    pub y_offset: f32,
+    /// mirror the tile along its X axis
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub flip: bool,
+}
+
+impl Default for TileOverrides {
+    fn default() -> Self {
+        Self {
+            tint: None,
+            scale: 1.0,
+            offset: Vec2::ZERO,
+            y_offset: 0.0,
+            flip: false,
+        }
+    }
+}
+
+impl TileOverrides {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// turn the offset with the tile when it is rotated around the map
+    pub fn rotate(self, rotation: TileRotation) -> Self {
+        let offset =
+            Quat::from_rotation_y(rotation.into()) * Vec3::new(self.offset.x, 0.0, self.offset.y);
+        Self {
+            offset: Vec2::new(offset.x, offset.z),
+            ..self
+        }
+    }
+}
+
+fn is_one(v: &f32) -> bool {
+    *v == 1.0
+}
+
+fn is_zero<T: Default + PartialEq>(v: &T) -> bool {
+    *v == T::default()
+}
+
 #[derive(Bundle)]
 pub struct TileBundle {
     tile_ref: TileRef,
@@ -253,7 +887,8 @@ impl TileBundle {
             .get(&tile_id)
             .unwrap_or_else(|| panic!("TileId {} in Tileset {}", tile_id, tileset.name));
 
-        let transform = map.tile_transform(tile, location, &tile_transform);
+        let transform =
+            map.tile_transform(tile, location, &tile_transform, &TileOverrides::default());
         let scene = tile.scene.as_ref().unwrap().clone();
 
         TileBundle {
@@ -309,13 +944,25 @@ fn update_tile_transform(
     mut commands: Commands,
     map: Query<&map::Map>,
     tile_transforms: Query<
-        (Entity, &TileRef, &TileTransform, &map::Location),
-        Or<(Changed<TileTransform>, Changed<map::Location>)>,
+        (
+            Entity,
+            &TileRef,
+            &TileTransform,
+            AnyOf<(&map::Location, &map::Edge, &map::Vertex)>,
+            Option<&TileOverrides>,
+        ),
+        Or<(
+            Changed<TileTransform>,
+            Changed<TileOverrides>,
+            Changed<map::Location>,
+            Changed<map::Edge>,
+            Changed<map::Vertex>,
+        )>,
     >,
     tilesets: Query<&mut Tileset>,
 ) {
     let Ok(map) = map.get_single() else { return; };
-    for (entity, tile_ref, tile_transform, location) in &tile_transforms {
+    for (entity, tile_ref, tile_transform, position, overrides) in &tile_transforms {
         let Ok(tileset) = tilesets.get(tile_ref.tileset) else {
             warn!("unknown tileset for tile {:?}: {:?}; removing entity", entity, tile_ref);
             commands.entity(entity).despawn_recursive();
@@ -326,7 +973,17 @@ fn update_tile_transform(
             commands.entity(entity).despawn_recursive();
             continue;
         };
-        let transform = map.tile_transform(tile, *location, tile_transform);
+        // the editor cursor keeps its location while snapped to an edge or
+        // vertex, so those take priority
+        let transform = match position {
+            (_, Some(edge), _) => map.edge_transform(tile, *edge, tile_transform),
+            (_, _, Some(vertex)) => map.vertex_transform(tile, *vertex, tile_transform),
+            (Some(location), _, _) => {
+                let overrides = overrides.copied().unwrap_or_default();
+                map.tile_transform(tile, *location, tile_transform, &overrides)
+            }
+            (None, None, None) => continue,
+        };
         commands.entity(entity).insert(transform);
     }
 }
@@ -365,7 +1022,7 @@ fn load_tiles(
 }
 
 /// allocate an image to use as a render target
-fn alloc_render_image(width: u32, height: u32) -> Image {
+pub fn alloc_render_image(width: u32, height: u32) -> Image {
     use bevy::render::render_resource::{
         Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
     };
@@ -404,14 +1061,9 @@ pub struct TilesetImporter {
 
 impl TilesetImporter {
     pub fn new(path: std::path::PathBuf) -> Self {
-        use ron::de::from_reader;
         let task_pool = IoTaskPool::get();
         let path_copy = path.clone();
-        let task = task_pool.spawn(async move {
-            let f = std::fs::File::open(path).context("failed to open file")?;
-            let tileset: Tileset = from_reader(f).context("failed to parse tileset")?;
-            Ok::<Tileset, anyhow::Error>(tileset)
-        });
+        let task = task_pool.spawn(async move { Tileset::load(&path) });
         Self {
             path: path_copy,
             task,
@@ -437,10 +1089,11 @@ fn tileset_importer(
             }
             Ok(tileset) => {
                 let name = importer.path.file_stem().unwrap().to_string_lossy();
-                commands
-                    .entity(entity)
-                    .remove::<TilesetImporter>()
-                    .insert((Name::new(format!("tileset: {}", name)), tileset));
+                commands.entity(entity).remove::<TilesetImporter>().insert((
+                    Name::new(format!("tileset: {}", name)),
+                    TilesetSource(importer.path.clone()),
+                    tileset,
+                ));
             }
         };
     }
@@ -453,16 +1106,8 @@ pub struct TilesetExporter {
 
 impl TilesetExporter {
     pub fn new(path: std::path::PathBuf, tileset: Tileset) -> Self {
-        use ron::ser::{to_writer_pretty, PrettyConfig};
         let task_pool = IoTaskPool::get();
-        let task = task_pool.spawn(async move {
-            let f =
-                std::fs::File::create(path.clone()).context(format!("open tileset {:?}", path))?;
-            to_writer_pretty(f, &tileset, PrettyConfig::default())
-                .context(format!("writing tileset to {:?}", path))?;
-
-            Ok::<(), anyhow::Error>(())
-        });
+        let task = task_pool.spawn(async move { tileset.save(&path) });
         Self { task }
     }
 }
@@ -480,3 +1125,268 @@ fn tileset_exporter(
         commands.entity(entity).despawn();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+    use test_log::test;
+
+    fn tileset() -> Tileset {
+        let mut tileset = Tileset::new("test");
+        for (name, tags) in [
+            ("grass", vec!["ground"]),
+            ("water_rocks", vec!["water", "ground"]),
+            ("building_cabin", vec!["building"]),
+            ("water", vec!["water"]),
+            ("building_mill", vec![]),
+        ] {
+            tileset.add_tile(format!("{}.glb", name).into());
+            let id = *tileset.tile_order.last().unwrap();
+            tileset.tiles.get_mut(&id).unwrap().tags = tags.into_iter().map(String::from).collect();
+        }
+        tileset
+    }
+
+    #[test]
+    fn tags_roundtrip() {
+        let tileset = tileset();
+        let buf = FileFormat::Ron.serialize(&tileset).unwrap();
+        let loaded: Tileset = FileFormat::Ron.deserialize(&buf).unwrap();
+        assert_eq!(loaded.tiles[&1].tags, vec!["water", "ground"]);
+        assert!(loaded.tiles[&4].tags.is_empty());
+        assert_eq!(loaded.tags(), vec!["building", "ground", "water"]);
+    }
+
+    #[test]
+    fn add_tile_after_load() {
+        let buf = FileFormat::Ron.serialize(&tileset()).unwrap();
+        let mut loaded: Tileset = FileFormat::Ron.deserialize(&buf).unwrap();
+        loaded.add_tile("rock.glb".into());
+        assert_eq!(loaded.tile_order, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(loaded.tiles[&0].name, "grass");
+        assert_eq!(loaded.tiles[&5].name, "rock");
+    }
+
+    #[test]
+    fn group_tiles() {
+        let tileset = tileset();
+        let mut filter = TileFilter::default();
+        assert_eq!(
+            tileset.group_tiles(&filter),
+            vec![("".into(), vec![0, 1, 2, 3, 4])]
+        );
+
+        filter.grouping = TileGrouping::NamePrefix;
+        assert_eq!(
+            tileset.group_tiles(&filter),
+            vec![
+                ("building".into(), vec![2, 4]),
+                ("grass".into(), vec![0]),
+                ("water".into(), vec![1, 3]),
+            ]
+        );
+
+        filter.grouping = TileGrouping::Tag;
+        assert_eq!(
+            tileset.group_tiles(&filter),
+            vec![
+                ("building".into(), vec![2]),
+                ("ground".into(), vec![0, 1]),
+                ("water".into(), vec![1, 3]),
+                ("untagged".into(), vec![4]),
+            ]
+        );
+
+        filter.grouping = TileGrouping::None;
+        filter.search = " WATER".into();
+        assert_eq!(tileset.group_tiles(&filter), vec![("".into(), vec![1, 3])]);
+        filter.tags = vec!["ground".into()];
+        assert_eq!(tileset.group_tiles(&filter), vec![("".into(), vec![1])]);
+        filter.search.clear();
+        filter.tags.push("building".into());
+        assert!(tileset.group_tiles(&filter).is_empty());
+    }
+
+    fn property_tileset() -> Tileset {
+        let mut tileset = tileset();
+        let mut cost = TilePropertyField::new("movement_cost", TilePropertyKind::Int);
+        cost.default = TileProperty::Int(1);
+        let terrain = TilePropertyKind::Enum(vec!["land".into(), "sea".into()]);
+        tileset.property_schema = vec![
+            cost,
+            TilePropertyField::new("terrain", terrain),
+            TilePropertyField::new("sound", TilePropertyKind::String),
+        ];
+        let water = tileset.tiles.get_mut(&3).unwrap();
+        water
+            .properties
+            .insert("movement_cost".into(), TileProperty::Int(3));
+        water
+            .properties
+            .insert("terrain".into(), TileProperty::Enum("sea".into()));
+        tileset
+    }
+
+    #[test]
+    fn tile_properties() {
+        let tileset = property_tileset();
+        for format in [FileFormat::Ron, FileFormat::Json] {
+            let buf = format.serialize(&tileset).unwrap();
+            let loaded: Tileset = format.deserialize(&buf).unwrap();
+            assert_eq!(loaded.property_schema, tileset.property_schema);
+            assert_eq!(loaded.tiles[&3].properties, tileset.tiles[&3].properties);
+        }
+
+        let get = |tile, name| tileset.tile_property(tile, name).cloned();
+        assert_eq!(get(3, "movement_cost"), Some(TileProperty::Int(3)));
+        assert_eq!(get(0, "movement_cost"), Some(TileProperty::Int(1)));
+        assert_eq!(get(3, "terrain"), Some(TileProperty::Enum("sea".into())));
+        assert_eq!(get(0, "terrain"), Some(TileProperty::Enum("land".into())));
+        assert_eq!(get(0, "sound"), Some(TileProperty::String("".into())));
+        assert_eq!(get(0, "unknown"), None);
+        assert_eq!(get(99, "sound"), None);
+    }
+
+    #[test]
+    fn clean_properties() {
+        let mut tileset = property_tileset();
+        tileset.property_schema[0].kind = TilePropertyKind::Float;
+        tileset.property_schema[1].kind = TilePropertyKind::Enum(vec!["land".into()]);
+        tileset.property_schema.pop();
+        tileset
+            .tiles
+            .get_mut(&0)
+            .unwrap()
+            .properties
+            .insert("sound".into(), TileProperty::String("splash".into()));
+        tileset.clean_properties();
+
+        assert_eq!(tileset.property_schema[0].default, TileProperty::Float(0.0));
+        let tiles = tileset.tiles.values();
+        assert!(tiles.clone().all(|tile| tile.properties.is_empty()));
+        assert_eq!(
+            tileset.tile_property(3, "terrain"),
+            Some(&TileProperty::Enum("land".into()))
+        );
+    }
+
+    #[test]
+    fn map_tile_properties() {
+        let mut world = World::new();
+        let tileset = world.spawn(property_tileset()).id();
+        let tile = |tile| (Entity::PLACEHOLDER, TileRef { tileset, tile });
+        let location = map::Location { x: 1, y: 2 };
+
+        let mut ground = map::Layer::new("ground".into());
+        ground.tiles.insert(location, tile(3));
+        let ground = world.spawn(ground).id();
+        let mut top = map::Layer::new("top".into());
+        top.order = 1;
+        top.tiles.insert(location, tile(0));
+        world.spawn(top);
+
+        let mut state: SystemState<MapTileProperties> = SystemState::new(&mut world);
+        let properties = state.get(&world);
+        assert_eq!(
+            properties.get(ground, location, "movement_cost"),
+            Some(&TileProperty::Int(3))
+        );
+        assert_eq!(properties.get(ground, default(), "movement_cost"), None);
+        assert_eq!(
+            properties.get_top(location, "movement_cost"),
+            Some(&TileProperty::Int(1))
+        );
+        assert_eq!(properties.get_top(location, "unknown"), None);
+    }
+
+    #[test]
+    fn scan_folder() {
+        let assets = std::env::temp_dir().join(format!("hex_sandbox_scan_{}", std::process::id()));
+        let dir = assets.join("models");
+        std::fs::create_dir_all(dir.join("trees")).unwrap();
+        for file in ["grass.glb", "water.GLTF", "notes.txt", "trees/grass.glb"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(dir.join("trees/oak.glb"), "").unwrap();
+
+        let mut tileset = Tileset::new("models");
+        tileset.add_tile("other/rock.glb".into());
+        let transform = Transform::from_scale(Vec3::splat(0.5));
+        let scan = tileset.scan_folder(&dir, &assets, transform).unwrap();
+        assert_eq!(scan.added, vec![1, 2, 3, 4]);
+        assert!(scan.missing.is_empty());
+        let tile = |id: TileId| {
+            let tile = &tileset.tiles[&id];
+            let path = tile.path.to_string_lossy().replace('\\', "/");
+            (tile.name.as_str(), path)
+        };
+        assert_eq!(tile(1), ("grass", "models/grass.glb".into()));
+        assert_eq!(tile(2), ("trees_grass", "models/trees/grass.glb".into()));
+        assert_eq!(tile(3), ("oak", "models/trees/oak.glb".into()));
+        assert_eq!(tile(4), ("water", "models/water.GLTF".into()));
+        assert_eq!(tileset.tiles[&4].transform, transform);
+
+        // rescanning a saved tileset keeps the existing tiles & ids
+        let buf = FileFormat::Ron.serialize(&tileset).unwrap();
+        let mut tileset: Tileset = FileFormat::Ron.deserialize(&buf).unwrap();
+        std::fs::remove_file(dir.join("trees/oak.glb")).unwrap();
+        std::fs::write(dir.join("sand.glb"), "").unwrap();
+        let scan = tileset.scan_folder(&dir, &assets, default()).unwrap();
+        assert_eq!(scan.added, vec![5]);
+        assert_eq!(scan.missing, vec![3]);
+        assert_eq!(tileset.tiles[&5].name, "sand");
+        assert_eq!(tileset.tile_order, vec![0, 1, 2, 3, 4, 5]);
+
+        std::fs::remove_dir_all(&assets).unwrap();
+    }
+
+    #[test]
+    fn merge() {
+        let mut tileset = tileset();
+        let scene = Handle::<Scene>::weak(bevy::asset::HandleId::random::<Scene>());
+        for tile in tileset.tiles.values_mut() {
+            tile.scene = Some(scene.clone());
+        }
+
+        let mut other = tileset.clone();
+        other.name = "edited".into();
+        other.tiles.get_mut(&0).unwrap().tags.push("green".into());
+        other.tiles.get_mut(&1).unwrap().path = "water_stones.glb".into();
+        for id in [2, 4] {
+            other.tiles.remove(&id);
+            other.tile_order.retain(|i| *i != id);
+        }
+        other.add_tile("sand.glb".into());
+
+        let merge = tileset.merge(other, &HashSet::from([4]));
+        assert_eq!(
+            merge,
+            TilesetMerge {
+                added: vec![5],
+                changed: vec![0, 1],
+                models_changed: vec![1],
+                removed: vec![2],
+                kept: vec![4],
+            }
+        );
+        assert_eq!(tileset.name, "edited");
+        assert_eq!(tileset.tile_order, vec![0, 1, 3, 5, 4]);
+        assert_eq!(tileset.tiles[&0].tags, vec!["ground", "green"]);
+        assert_eq!(tileset.tiles[&0].scene, Some(scene));
+        assert_eq!(tileset.tiles[&1].scene, None);
+        assert!(!tileset.tiles.contains_key(&2));
+        assert_eq!(tileset.add_tile("new.glb".into()), 6);
+
+        assert!(tileset.merge(tileset.clone(), &default()).is_empty());
+    }
+
+    #[test]
+    fn remove_tiles() {
+        let mut tileset = tileset();
+        tileset.remove_tiles(&[1, 3, 99]);
+        assert_eq!(tileset.tile_order, vec![0, 2, 4]);
+        assert_eq!(tileset.tiles.len(), 3);
+        assert_eq!(tileset.add_tile("new.glb".into()), 5);
+    }
+}
@@ -0,0 +1,779 @@
+//! Tools used to edit the active layer of a map
+//!
+//! A tool turns the mouse input on the map into a list of `ToolEdit`s.  The
+//! editor previews the edits, and applies them to the active layer when the
+//! tool commits.  Tools are registered in the `ToolRegistry`; the built-in
+//! tools are registered by `Plugin`, and others can be added using
+//! `AppToolExt::register_tool()`.
+//!
+//! Selection tools produce `ToolEdit::Select` edits for the tiles they select,
+//! and the `Move` tool moves the selected tiles.
+//!
+//! Tools that place tiles on the edges or vertices between hexes return a
+//! `ToolSnap` other than `Hex` from `Tool::snap()`; the editor cursor then
+//! snaps to the nearest edge or vertex, which is passed in the `ToolInput`.
+use bevy::prelude::*;
+use hexx::Hex;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    map::{Edge, Location, Vertex},
+    selection,
+    tileset::TileRef,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.register_tool(Paint)
+            .register_tool(Erase)
+            .register_tool(Line)
+            .register_tool(Rectangle)
+            .register_tool(Radius)
+            .register_tool(Fill)
+            .register_tool(SelectBox)
+            .register_tool(SelectLasso)
+            .register_tool(SelectRadius)
+            .register_tool(SelectSame)
+            .register_tool(Move)
+            .register_tool(PaintEdge)
+            .register_tool(PaintVertex);
+    }
+}
+
+/// maximum number of tiles changed by a single flood fill
+pub const FILL_LIMIT: usize = 4096;
+
+/// change made to the active layer by a tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToolEdit {
+    /// place the brush tile at the location
+    Paint(Location),
+    /// remove the tile at the location
+    Erase(Location),
+    /// add the tile at the location to the selection
+    Select(Location),
+    /// move the selected tile at `from` to `to`
+    Move { from: Location, to: Location },
+    /// place the brush tile on the edge
+    PaintEdge(Edge),
+    /// remove the tile on the edge
+    EraseEdge(Edge),
+    /// place the brush tile on the vertex
+    PaintVertex(Vertex),
+    /// remove the tile on the vertex
+    EraseVertex(Vertex),
+}
+
+impl ToolEdit {
+    /// location changed by the edit; the destination of a move, or the
+    /// canonical location of an edge or vertex
+    pub fn location(&self) -> Location {
+        match self {
+            ToolEdit::Paint(l) | ToolEdit::Erase(l) | ToolEdit::Select(l) => *l,
+            ToolEdit::Move { to, .. } => *to,
+            ToolEdit::PaintEdge(e) | ToolEdit::EraseEdge(e) => e.location,
+            ToolEdit::PaintVertex(v) | ToolEdit::EraseVertex(v) => v.location,
+        }
+    }
+
+    /// the matching erase edit for a paint edit
+    pub fn to_erase(self) -> ToolEdit {
+        match self {
+            ToolEdit::Paint(l) => ToolEdit::Erase(l),
+            ToolEdit::PaintEdge(e) => ToolEdit::EraseEdge(e),
+            ToolEdit::PaintVertex(v) => ToolEdit::EraseVertex(v),
+            edit => edit,
+        }
+    }
+}
+
+/// what part of the map the cursor snaps to for a tool
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ToolSnap {
+    /// the center of a hex
+    #[default]
+    Hex,
+    /// the edge between two hexes
+    Edge,
+    /// the corner shared by three hexes
+    Vertex,
+}
+
+/// when the edits made by a tool are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolMode {
+    /// apply the edits every time the cursor moves while the button is held
+    Continuous,
+    /// apply the edits when the button is released
+    Drag,
+}
+
+/// state of the map & mouse passed to a `Tool`
+#[derive(Debug)]
+pub struct ToolInput<'a> {
+    /// location the button was pressed at; the cursor location when no button
+    /// is pressed
+    pub start: Location,
+    /// current cursor location
+    pub cursor: Location,
+    /// brush radius set in the toolbar
+    pub radius: u32,
+    /// tiles in the active layer
+    pub tiles: &'a HashMap<Location, TileRef>,
+    /// tile that will be painted
+    pub brush: Option<TileRef>,
+    /// locations the cursor has passed through since the button was pressed
+    pub path: &'a [Location],
+    /// locations of the selected tiles
+    pub selection: &'a HashSet<Location>,
+    /// edge nearest to the cursor, when the tool snaps to edges
+    pub edge: Option<Edge>,
+    /// vertex nearest to the cursor, when the tool snaps to vertices
+    pub vertex: Option<Vertex>,
+}
+
+/// a tool that can be selected in the toolbar
+pub trait Tool: Send + Sync + 'static {
+    /// name shown in the toolbar tooltip; also used to select the tool
+    fn name(&self) -> &'static str;
+
+    /// label of the toolbar button
+    fn icon(&self) -> &'static str;
+
+    fn mode(&self) -> ToolMode {
+        ToolMode::Continuous
+    }
+
+    /// true if the toolbar should show the radius setting for this tool
+    fn uses_radius(&self) -> bool {
+        false
+    }
+
+    /// what the cursor snaps to while the tool is active
+    fn snap(&self) -> ToolSnap {
+        ToolSnap::Hex
+    }
+
+    /// true if the `ToolEdit::Select` edits made by this tool replace the
+    /// selection; selecting nothing clears it
+    fn selects(&self) -> bool {
+        false
+    }
+
+    /// edits the tool will make for the input
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit>;
+}
+
+/// all tools available in the toolbar, and the active tool
+#[derive(Resource)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+    active: usize,
+    /// brush radius used by tools that support it
+    pub radius: u32,
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self {
+            tools: Vec::new(),
+            active: 0,
+            radius: 1,
+        }
+    }
+}
+
+impl ToolRegistry {
+    pub fn register(&mut self, tool: impl Tool) -> &mut Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    pub fn tools(&self) -> impl Iterator<Item = &dyn Tool> {
+        self.tools.iter().map(|t| t.as_ref())
+    }
+
+    pub fn active(&self) -> Option<&dyn Tool> {
+        self.tools.get(self.active).map(|t| t.as_ref())
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.tools.len() {
+            self.active = index;
+        }
+    }
+
+    /// select a tool by name; returns false if no tool has that name
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.tools.iter().position(|t| t.name() == name) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// trait to add tool registration to App
+pub trait AppToolExt {
+    fn register_tool(&mut self, tool: impl Tool) -> &mut Self;
+}
+
+impl AppToolExt for App {
+    fn register_tool(&mut self, tool: impl Tool) -> &mut Self {
+        self.init_resource::<ToolRegistry>();
+        self.world.resource_mut::<ToolRegistry>().register(tool);
+        self
+    }
+}
+
+/// paint the cursor location
+pub struct Paint;
+
+impl Tool for Paint {
+    fn name(&self) -> &'static str {
+        "Paint"
+    }
+    fn icon(&self) -> &'static str {
+        "✏"
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        vec![ToolEdit::Paint(input.cursor)]
+    }
+}
+
+/// erase the cursor location
+pub struct Erase;
+
+impl Tool for Erase {
+    fn name(&self) -> &'static str {
+        "Erase"
+    }
+    fn icon(&self) -> &'static str {
+        "🗑"
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        vec![ToolEdit::Erase(input.cursor)]
+    }
+}
+
+/// paint a straight line of hexes
+pub struct Line;
+
+impl Tool for Line {
+    fn name(&self) -> &'static str {
+        "Line"
+    }
+    fn icon(&self) -> &'static str {
+        "📏"
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input
+            .start
+            .hex()
+            .line_to(input.cursor.hex())
+            .map(|h| ToolEdit::Paint(h.into()))
+            .collect()
+    }
+}
+
+/// paint the parallelogram of hexes with the start & cursor as opposite
+/// corners
+pub struct Rectangle;
+
+impl Tool for Rectangle {
+    fn name(&self) -> &'static str {
+        "Rectangle"
+    }
+    fn icon(&self) -> &'static str {
+        "⬜"
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        let (start, end) = (input.start, input.cursor);
+        let xs = start.x.min(end.x)..=start.x.max(end.x);
+        let ys = start.y.min(end.y)..=start.y.max(end.y);
+        xs.flat_map(|x| ys.clone().map(move |y| ToolEdit::Paint((x, y).into())))
+            .collect()
+    }
+}
+
+/// paint every hex within the radius of the cursor
+pub struct Radius;
+
+impl Tool for Radius {
+    fn name(&self) -> &'static str {
+        "Radius"
+    }
+    fn icon(&self) -> &'static str {
+        "⭕"
+    }
+    fn uses_radius(&self) -> bool {
+        true
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input
+            .cursor
+            .hex()
+            .range(input.radius)
+            .map(|h| ToolEdit::Paint(h.into()))
+            .collect()
+    }
+}
+
+/// paint the contiguous region of hexes containing the same tile as the
+/// cursor location
+///
+/// When the cursor is over an empty hex, the fill is limited to the bounds of
+/// the tiles in the layer.
+pub struct Fill;
+
+impl Tool for Fill {
+    fn name(&self) -> &'static str {
+        "Fill"
+    }
+    fn icon(&self) -> &'static str {
+        "🌊"
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        flood_fill(input.tiles, input.cursor)
+            .into_iter()
+            .map(ToolEdit::Paint)
+            .collect()
+    }
+}
+
+/// select the tiles in the parallelogram with the start & cursor as opposite
+/// corners
+pub struct SelectBox;
+
+impl Tool for SelectBox {
+    fn name(&self) -> &'static str {
+        "Select Box"
+    }
+    fn icon(&self) -> &'static str {
+        "⬚"
+    }
+    fn selects(&self) -> bool {
+        true
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        Rectangle
+            .edits(input)
+            .into_iter()
+            .map(|e| e.location())
+            .filter(|l| input.tiles.contains_key(l))
+            .map(ToolEdit::Select)
+            .collect()
+    }
+}
+
+/// select the tiles inside the path traced by the cursor
+pub struct SelectLasso;
+
+impl Tool for SelectLasso {
+    fn name(&self) -> &'static str {
+        "Lasso"
+    }
+    fn icon(&self) -> &'static str {
+        "➰"
+    }
+    fn selects(&self) -> bool {
+        true
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input
+            .tiles
+            .keys()
+            .filter(|l| selection::in_lasso(input.path, **l))
+            .map(|l| ToolEdit::Select(*l))
+            .collect()
+    }
+}
+
+/// select the tiles within the radius of the cursor
+pub struct SelectRadius;
+
+impl Tool for SelectRadius {
+    fn name(&self) -> &'static str {
+        "Select Radius"
+    }
+    fn icon(&self) -> &'static str {
+        "◎"
+    }
+    fn selects(&self) -> bool {
+        true
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn uses_radius(&self) -> bool {
+        true
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input
+            .cursor
+            .hex()
+            .range(input.radius)
+            .map(Location::from)
+            .filter(|l| input.tiles.contains_key(l))
+            .map(ToolEdit::Select)
+            .collect()
+    }
+}
+
+/// select every tile in the layer that is the same as the tile under the
+/// cursor
+pub struct SelectSame;
+
+impl Tool for SelectSame {
+    fn name(&self) -> &'static str {
+        "Select Same"
+    }
+    fn icon(&self) -> &'static str {
+        "🎯"
+    }
+    fn selects(&self) -> bool {
+        true
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        let Some(target) = input.tiles.get(&input.cursor) else { return Vec::new() };
+        input
+            .tiles
+            .iter()
+            .filter(|(_, tile)| *tile == target)
+            .map(|(l, _)| ToolEdit::Select(*l))
+            .collect()
+    }
+}
+
+/// drag the selected tiles to a new location
+pub struct Move;
+
+impl Tool for Move {
+    fn name(&self) -> &'static str {
+        "Move"
+    }
+    fn icon(&self) -> &'static str {
+        "✋"
+    }
+    fn mode(&self) -> ToolMode {
+        ToolMode::Drag
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        let (dx, dy) = (
+            input.cursor.x - input.start.x,
+            input.cursor.y - input.start.y,
+        );
+        if (dx, dy) == (0, 0) {
+            return Vec::new();
+        }
+        input
+            .selection
+            .iter()
+            .map(|from| ToolEdit::Move {
+                from: *from,
+                to: (from.x + dx, from.y + dy).into(),
+            })
+            .collect()
+    }
+}
+
+/// paint the edge nearest to the cursor
+pub struct PaintEdge;
+
+impl Tool for PaintEdge {
+    fn name(&self) -> &'static str {
+        "Edge"
+    }
+    fn icon(&self) -> &'static str {
+        "▭"
+    }
+    fn snap(&self) -> ToolSnap {
+        ToolSnap::Edge
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input.edge.map(ToolEdit::PaintEdge).into_iter().collect()
+    }
+}
+
+/// paint the vertex nearest to the cursor
+pub struct PaintVertex;
+
+impl Tool for PaintVertex {
+    fn name(&self) -> &'static str {
+        "Vertex"
+    }
+    fn icon(&self) -> &'static str {
+        "▪"
+    }
+    fn snap(&self) -> ToolSnap {
+        ToolSnap::Vertex
+    }
+    fn edits(&self, input: &ToolInput) -> Vec<ToolEdit> {
+        input
+            .vertex
+            .map(ToolEdit::PaintVertex)
+            .into_iter()
+            .collect()
+    }
+}
+
+/// find the locations connected to `start` containing the same tile
+pub fn flood_fill(tiles: &HashMap<Location, TileRef>, start: Location) -> Vec<Location> {
+    let target = tiles.get(&start);
+
+    // bounds used when filling empty hexes
+    let (mut min, mut max) = (start, start);
+    for location in tiles.keys() {
+        min = (min.x.min(location.x), min.y.min(location.y)).into();
+        max = (max.x.max(location.x), max.y.max(location.y)).into();
+    }
+    let in_bounds = |l: &Location| (min.x..=max.x).contains(&l.x) && (min.y..=max.y).contains(&l.y);
+
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut region = Vec::new();
+    while let Some(location) = queue.pop_front() {
+        region.push(location);
+        if region.len() >= FILL_LIMIT {
+            warn!("flood fill limited to {} tiles", FILL_LIMIT);
+            break;
+        }
+        for neighbor in Hex::from(location).all_neighbors() {
+            let neighbor: Location = neighbor.into();
+            if tiles.get(&neighbor) != target || !in_bounds(&neighbor) {
+                continue;
+            }
+            if seen.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    region
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    fn input<'a>(
+        start: (i32, i32),
+        cursor: (i32, i32),
+        tiles: &'a HashMap<Location, TileRef>,
+        selection: &'a HashSet<Location>,
+    ) -> ToolInput<'a> {
+        ToolInput {
+            start: start.into(),
+            cursor: cursor.into(),
+            radius: 1,
+            tiles,
+            brush: None,
+            path: &[],
+            selection,
+            edge: None,
+            vertex: None,
+        }
+    }
+
+    fn locations(edits: Vec<ToolEdit>) -> HashSet<(i32, i32)> {
+        edits
+            .iter()
+            .map(|e| {
+                let l = e.location();
+                (l.x, l.y)
+            })
+            .collect()
+    }
+
+    fn tile(tile: usize) -> TileRef {
+        TileRef {
+            tileset: Entity::PLACEHOLDER,
+            tile,
+        }
+    }
+
+    #[test]
+    fn line() {
+        let tiles = HashMap::new();
+        let edits = Line.edits(&input((0, 0), (3, 0), &tiles, &HashSet::new()));
+        assert_eq!(
+            locations(edits),
+            HashSet::from([(0, 0), (1, 0), (2, 0), (3, 0)])
+        );
+        let edits = Line.edits(&input((0, 0), (2, -4), &tiles, &HashSet::new()));
+        assert_eq!(edits.len(), 5);
+        assert_eq!(edits.first(), Some(&ToolEdit::Paint((0, 0).into())));
+        assert_eq!(edits.last(), Some(&ToolEdit::Paint((2, -4).into())));
+    }
+
+    #[test]
+    fn rectangle() {
+        let tiles = HashMap::new();
+        let edits = Rectangle.edits(&input((1, 1), (0, -1), &tiles, &HashSet::new()));
+        assert_eq!(
+            locations(edits),
+            HashSet::from([(0, -1), (0, 0), (0, 1), (1, -1), (1, 0), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn radius() {
+        let tiles = HashMap::new();
+        let edits = Radius.edits(&input((0, 0), (5, 5), &tiles, &HashSet::new()));
+        assert_eq!(edits.len(), 7);
+        assert!(edits.contains(&ToolEdit::Paint((5, 5).into())));
+        assert!(edits.contains(&ToolEdit::Paint((6, 4).into())));
+    }
+
+    #[test]
+    fn fill_same_tile() {
+        let tiles = HashMap::from([
+            ((0, 0).into(), tile(1)),
+            ((1, 0).into(), tile(1)),
+            ((2, 0).into(), tile(2)),
+            ((3, 0).into(), tile(1)),
+        ]);
+        let edits = Fill.edits(&input((0, 0), (1, 0), &tiles, &HashSet::new()));
+        assert_eq!(locations(edits), HashSet::from([(0, 0), (1, 0)]));
+    }
+
+    #[test]
+    fn fill_empty_bounded() {
+        // ring of tiles around the origin; the fill stays inside of it
+        let tiles: HashMap<Location, TileRef> =
+            Hex::ZERO.ring(2).map(|h| (h.into(), tile(1))).collect();
+        let edits = Fill.edits(&input((0, 0), (0, 0), &tiles, &HashSet::new()));
+        assert_eq!(edits.len(), 7);
+
+        // empty layer fills only the start
+        let tiles = HashMap::new();
+        let edits = Fill.edits(&input((0, 0), (4, 4), &tiles, &HashSet::new()));
+        assert_eq!(locations(edits), HashSet::from([(4, 4)]));
+    }
+
+    #[test]
+    fn registry_select() {
+        let mut app = App::new();
+        app.add_plugin(Plugin);
+        let mut registry = app.world.resource_mut::<ToolRegistry>();
+        assert_eq!(registry.active().unwrap().name(), "Paint");
+        assert!(registry.select("Fill"));
+        assert_eq!(registry.active().unwrap().name(), "Fill");
+        assert!(!registry.select("missing"));
+        assert_eq!(registry.tools().count(), 13);
+    }
+
+    #[test]
+    fn select_box() {
+        let tiles = HashMap::from([
+            ((0, 0).into(), tile(1)),
+            ((1, 1).into(), tile(1)),
+            ((3, 0).into(), tile(1)),
+        ]);
+        let edits = SelectBox.edits(&input((0, 0), (2, 2), &tiles, &HashSet::new()));
+        assert_eq!(locations(edits), HashSet::from([(0, 0), (1, 1)]));
+    }
+
+    #[test]
+    fn select_lasso() {
+        let tiles = HashMap::from([((2, 2).into(), tile(1)), ((6, 2).into(), tile(1))]);
+        let path: Vec<Location> = vec![(0, 0).into(), (4, 0).into(), (4, 4).into(), (0, 4).into()];
+        let selection = HashSet::new();
+        let input = ToolInput {
+            path: &path,
+            ..input((0, 0), (0, 4), &tiles, &selection)
+        };
+        assert_eq!(
+            SelectLasso.edits(&input),
+            vec![ToolEdit::Select((2, 2).into())]
+        );
+    }
+
+    #[test]
+    fn select_same() {
+        let tiles = HashMap::from([
+            ((0, 0).into(), tile(1)),
+            ((5, 5).into(), tile(1)),
+            ((1, 0).into(), tile(2)),
+        ]);
+        let selection = HashSet::new();
+        let edits = SelectSame.edits(&input((0, 0), (0, 0), &tiles, &selection));
+        assert_eq!(locations(edits), HashSet::from([(0, 0), (5, 5)]));
+        assert!(SelectSame
+            .edits(&input((0, 0), (9, 9), &tiles, &selection))
+            .is_empty());
+    }
+
+    #[test]
+    fn move_selection() {
+        let tiles = HashMap::new();
+        let selection = HashSet::from([(0, 0).into(), (1, 0).into()]);
+        let input = input((1, 0), (1, 2), &tiles, &selection);
+        let edits: HashSet<ToolEdit> = Move.edits(&input).into_iter().collect();
+        assert_eq!(
+            edits,
+            HashSet::from([
+                ToolEdit::Move {
+                    from: (0, 0).into(),
+                    to: (0, 2).into()
+                },
+                ToolEdit::Move {
+                    from: (1, 0).into(),
+                    to: (1, 2).into()
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn paint_edge_and_vertex() {
+        let tiles = HashMap::new();
+        let edge = Edge::new((0, 0).into(), (1, 0).into()).unwrap();
+        let vertex = Vertex::new((0, 0).into(), (1, 0).into(), (0, 1).into()).unwrap();
+        assert_eq!(PaintEdge.snap(), ToolSnap::Edge);
+        let selection = HashSet::new();
+        assert!(PaintEdge
+            .edits(&input((0, 0), (0, 0), &tiles, &selection))
+            .is_empty());
+        let input = ToolInput {
+            edge: Some(edge),
+            vertex: Some(vertex),
+            ..input((0, 0), (0, 0), &tiles, &selection)
+        };
+        assert_eq!(PaintEdge.edits(&input), vec![ToolEdit::PaintEdge(edge)]);
+        assert_eq!(
+            PaintVertex.edits(&input),
+            vec![ToolEdit::PaintVertex(vertex)]
+        );
+        assert_eq!(
+            ToolEdit::PaintEdge(edge).to_erase(),
+            ToolEdit::EraseEdge(edge)
+        );
+    }
+}
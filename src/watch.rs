@@ -0,0 +1,354 @@
+//! Reload tilesets & tile models when their files are changed by other
+//! programs, such as a GLB re-exported by an artist, or a tileset edited in a
+//! text editor
+//!
+//! Files are polled for changes to their modification time & size instead of
+//! using OS file notifications, as only a few hundred files are watched.
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+
+use crate::{export, map, tileset};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FileWatcher>()
+            .add_event::<FileChanged>()
+            .add_event::<TilesetReloaded>()
+            .add_system(poll_files)
+            .add_system(watch_tileset_files)
+            .add_system(reload_tilesets)
+            .add_system(reload_models)
+            .add_system(reload_scenes);
+    }
+}
+
+/// sent when a watched file was changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChanged(pub PathBuf);
+
+/// sent when a tileset was updated from its `tileset::TilesetSource`
+#[derive(Debug, Clone)]
+pub struct TilesetReloaded {
+    pub tileset: Entity,
+    pub merge: tileset::TilesetMerge,
+}
+
+/// modification time & size of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileState {
+    /// None if the file doesn't exist
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// files checked for changes once every `interval`
+#[derive(Resource, Debug)]
+pub struct FileWatcher {
+    files: HashMap<PathBuf, Option<FileState>>,
+    timer: Timer,
+}
+
+impl Default for FileWatcher {
+    fn default() -> Self {
+        Self::new(std::time::Duration::from_secs(1))
+    }
+}
+
+impl FileWatcher {
+    pub fn new(interval: std::time::Duration) -> Self {
+        Self {
+            files: HashMap::new(),
+            timer: Timer::new(interval, TimerMode::Repeating),
+        }
+    }
+
+    /// start watching a file; changes made before this call are not
+    /// reported.  Files that don't exist yet are reported once created.
+    pub fn watch(&mut self, path: impl Into<PathBuf>) {
+        self.files
+            .entry(path.into())
+            .or_insert_with_key(|path| FileState::new(path));
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        self.files.remove(path);
+    }
+
+    /// forget changes made to a watched file so far, such as the map saved
+    /// by the editor itself
+    pub fn touch(&mut self, path: &Path) {
+        if let Some(state) = self.files.get_mut(path) {
+            *state = FileState::new(path);
+        }
+    }
+
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// check the files for changes since the last poll, sorted
+    ///
+    /// Removed files are not reported, as some programs save files by
+    /// removing & replacing them; the new file is reported instead.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, state) in &mut self.files {
+            let current = FileState::new(path);
+            if current != *state && current.is_some() {
+                changed.push(path.clone());
+            }
+            *state = current;
+        }
+        changed.sort();
+        changed
+    }
+}
+
+fn poll_files(
+    time: Res<Time>,
+    mut watcher: ResMut<FileWatcher>,
+    mut events: EventWriter<FileChanged>,
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    for path in watcher.poll() {
+        debug!("file changed: {:?}", path);
+        events.send(FileChanged(path));
+    }
+}
+
+/// watch the source file & models of every tileset, and stop watching the
+/// files no longer used by any tileset
+fn watch_tileset_files(
+    mut watcher: ResMut<FileWatcher>,
+    mut watched: Local<HashSet<PathBuf>>,
+    tilesets: Query<(&tileset::Tileset, Option<&tileset::TilesetSource>)>,
+    changed: Query<(), Or<(Changed<tileset::Tileset>, Changed<tileset::TilesetSource>)>>,
+    mut removed_tilesets: RemovedComponents<tileset::Tileset>,
+    mut removed_sources: RemovedComponents<tileset::TilesetSource>,
+) {
+    let removed = removed_tilesets.iter().count() + removed_sources.iter().count();
+    if changed.is_empty() && removed == 0 {
+        return;
+    }
+    let asset_dir = export::asset_dir();
+    let mut paths = HashSet::new();
+    for (tileset, source) in &tilesets {
+        if let Some(source) = source {
+            paths.insert(source.0.clone());
+        }
+        for tile in tileset.tiles.values() {
+            paths.insert(export::asset_path(&asset_dir, &tile.path));
+        }
+    }
+    for path in watched.difference(&paths) {
+        watcher.unwatch(path);
+    }
+    for path in &paths {
+        watcher.watch(path);
+    }
+    *watched = paths;
+}
+
+/// merge changes to tileset files into the tilesets loaded from them
+fn reload_tilesets(
+    mut events: EventReader<FileChanged>,
+    mut reloaded: EventWriter<TilesetReloaded>,
+    mut tilesets: Query<(Entity, &mut tileset::Tileset, &tileset::TilesetSource)>,
+    mut placed: Query<(
+        Entity,
+        &tileset::TileRef,
+        &mut tileset::TileTransform,
+        Option<&Parent>,
+    )>,
+    layers: Query<(), With<map::Layer>>,
+    mut commands: Commands,
+) {
+    for FileChanged(path) in events.iter() {
+        for (entity, mut tileset, source) in &mut tilesets {
+            if source.0 != *path {
+                continue;
+            }
+            let loaded = match tileset::Tileset::load(path) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    warn!("failed to reload tileset {:?}: {:?}", path, e);
+                    continue;
+                }
+            };
+            // tiles on the map layers, ignoring the cursor & brush previews
+            let in_use: HashSet<tileset::TileId> = placed
+                .iter()
+                .filter(|(_, tile_ref, _, parent)| {
+                    tile_ref.tileset == entity
+                        && matches!(parent, Some(parent) if layers.contains(parent.get()))
+                })
+                .map(|(_, tile_ref, _, _)| tile_ref.tile)
+                .collect();
+            let merge = tileset.merge(loaded, &in_use);
+            if merge.is_empty() {
+                continue;
+            }
+            info!(
+                "reloaded tileset {:?}: {} added, {} changed, {} removed",
+                path,
+                merge.added.len(),
+                merge.changed.len(),
+                merge.removed.len()
+            );
+            if !merge.kept.is_empty() {
+                warn!(
+                    "tiles {:?} were removed from {:?} but are placed on the map; keeping them",
+                    merge.kept, path
+                );
+            }
+
+            // placed tiles pick up the new transform & model
+            for (tile_entity, tile_ref, mut transform, _) in &mut placed {
+                if tile_ref.tileset != entity || !merge.changed.contains(&tile_ref.tile) {
+                    continue;
+                }
+                transform.set_changed();
+                if merge.models_changed.contains(&tile_ref.tile) {
+                    commands.entity(tile_entity).remove::<Handle<Scene>>();
+                }
+            }
+            reloaded.send(TilesetReloaded {
+                tileset: entity,
+                merge,
+            });
+        }
+    }
+}
+
+/// reload the scenes of changed tile models
+fn reload_models(
+    mut events: EventReader<FileChanged>,
+    asset_server: Res<AssetServer>,
+    tilesets: Query<&tileset::Tileset>,
+) {
+    let mut asset_dir = None;
+    for FileChanged(path) in events.iter() {
+        let asset_dir = asset_dir.get_or_insert_with(export::asset_dir);
+        let mut models = HashSet::new();
+        for tile in tilesets.iter().flat_map(|tileset| tileset.tiles.values()) {
+            if export::asset_path(asset_dir, &tile.path) == *path {
+                models.insert(tile.path.clone());
+            }
+        }
+        for model in models {
+            info!("reloading model {:?}", model);
+            asset_server.reload_asset(model.as_path());
+        }
+    }
+}
+
+/// render new thumbnails for reloaded scenes, & respawn the placed tiles
+/// using them
+fn reload_scenes(
+    mut events: EventReader<AssetEvent<Scene>>,
+    mut tilesets: Query<&mut tileset::Tileset>,
+    mut placed: Query<&mut Handle<Scene>, With<tileset::TileRef>>,
+) {
+    for event in events.iter() {
+        let AssetEvent::Modified { handle } = event else { continue };
+        for mut tileset in &mut tilesets {
+            let uses_scene = |tile: &tileset::Tile| tile.scene.as_ref() == Some(handle);
+            if !tileset.tiles.values().any(uses_scene) {
+                continue;
+            }
+            // load_tiles loads the scene again & renders the thumbnail
+            for tile in tileset.tiles.values_mut() {
+                if tile.scene.as_ref() == Some(handle) {
+                    tile.scene = None;
+                    tile.egui_texture_id = None;
+                }
+            }
+        }
+        for mut scene in &mut placed {
+            if *scene == *handle {
+                scene.set_changed();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn poll() {
+        let dir = std::env::temp_dir().join(format!("hex_sandbox_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("tileset.ron");
+        let new_file = dir.join("map.ron");
+        std::fs::write(&file, "a").unwrap();
+
+        let mut watcher = FileWatcher::default();
+        watcher.watch(&file);
+        watcher.watch(&new_file);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(&file, "ab").unwrap();
+        assert_eq!(watcher.poll(), vec![file.clone()]);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(&new_file, "").unwrap();
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(watcher.poll(), vec![new_file.clone()]);
+        std::fs::write(&file, "abc").unwrap();
+        assert_eq!(watcher.poll(), vec![file.clone()]);
+
+        std::fs::write(&file, "abcd").unwrap();
+        watcher.touch(&file);
+        assert!(watcher.poll().is_empty());
+
+        watcher.unwatch(&file);
+        assert!(!watcher.is_watched(&file));
+        std::fs::write(&file, "abcde").unwrap();
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unwatch_removed_tilesets() {
+        let mut app = App::new();
+        app.init_resource::<FileWatcher>()
+            .add_system(watch_tileset_files);
+        let path = PathBuf::from("tilesets/forest.ron");
+        let tileset = app
+            .world
+            .spawn((
+                tileset::Tileset::new("forest"),
+                tileset::TilesetSource(path.clone()),
+            ))
+            .id();
+        app.update();
+        assert!(app.world.resource::<FileWatcher>().is_watched(&path));
+
+        app.world.despawn(tileset);
+        app.update();
+        assert!(!app.world.resource::<FileWatcher>().is_watched(&path));
+    }
+}